@@ -0,0 +1,166 @@
+//! Compare the ways of loading, and of updating, the documents on a
+//! generated dataset: `cargo bench --bench load`.
+
+use std::fs::File;
+use std::hint::black_box;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tempfile::NamedTempFile;
+
+use read_json_in_rust::in_memory;
+use read_json_in_rust::json_iter::{self, ArrayWriter, Loader, RawArrayWriter};
+use read_json_in_rust::transcode::Transcoder;
+use read_json_in_rust::transform::{AffectedKeys, DocumentTransform, Remove, Rename};
+
+/// Number of documents in the generated dataset.
+const DOCS: usize = 20_000;
+
+/// Capacities of the `BufReader` compared when streaming.
+const BUFFER_SIZES: [usize; 4] = [1 << 10, 8 << 10, 64 << 10, 1 << 20];
+
+/// A generated entry with every field typed.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+struct Entry {
+    #[serde(rename = "_id")]
+    id: String,
+    lexicon_name: String,
+    lexicon_order: u32,
+    name: Name,
+    occupation: Vec<Occupation>,
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Name {
+    firstname: String,
+    lastname: String,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Occupation {
+    #[serde(rename = "type")]
+    kind: String,
+    start: u32,
+}
+
+/// Write a JSON array of `DOCS` entries shaped like those of SKBL.
+fn generate() -> NamedTempFile {
+    let file = NamedTempFile::new().expect("a temporary file");
+    let docs = (0..DOCS).map(|i| {
+        json!({
+            "_id": format!("skbl-{}", i),
+            "lexiconName": "skbl",
+            "lexiconOrder": 47,
+            "name": {"firstname": "Anna Maria", "lastname": format!("Lindqvist{}", i)},
+            "occupation": [{"type": "Författare", "start": 1890 + i % 50}],
+            "text": "Hon var född i Stockholm och verkade som författare. ".repeat(4),
+        })
+    });
+    json_iter::dump_to_writer(BufWriter::new(file.reopen().expect("to reopen")), docs)
+        .expect("to write the dataset");
+    file
+}
+
+fn stream<T: serde::de::DeserializeOwned>(path: &Path, buffer_size: usize) {
+    let reader = BufReader::with_capacity(buffer_size, File::open(path).expect("the dataset"));
+    for doc in json_iter::load_from_reader::<T, _>(reader).expect("an array") {
+        black_box(doc.expect("a document"));
+    }
+}
+
+fn open(path: &Path) -> BufReader<File> {
+    BufReader::new(File::open(path).expect("the dataset"))
+}
+
+/// The updates compared, that `--raw` supports too.
+fn updates() -> (Remove, Rename) {
+    let removal = "_id".parse().expect("a path");
+    let rename = "name.lastname=name.surname".parse().expect("a rename");
+    (removal, rename)
+}
+
+/// Update every document as a `Value`.
+fn update_values(path: &Path) {
+    let (removal, rename) = updates();
+    let mut writer = ArrayWriter::new(io::sink()).expect("to write");
+    for doc in json_iter::load_from_reader::<Value, _>(open(path)).expect("an array") {
+        let mut doc = doc.expect("a document");
+        removal.apply(&mut doc).expect("to update");
+        rename.apply(&mut doc).expect("to update");
+        writer.write(&doc).expect("to write");
+    }
+    writer.finish().expect("to write");
+}
+
+/// Update the documents as raw JSON, parsing the ones that may change.
+fn update_raw(path: &Path) {
+    let (removal, rename) = updates();
+    let keys = AffectedKeys::new([&removal.path, &rename.from]).expect("top-level keys");
+    let mut writer = RawArrayWriter::new(io::sink()).expect("to write");
+    for raw in Loader::new().raw_from_reader(open(path)).expect("an array") {
+        let raw = raw.expect("a document");
+        if !keys.may_affect(raw.get()) {
+            writer.write_raw(&raw).expect("to write");
+            continue;
+        }
+        let mut doc: Value = serde_json::from_str(raw.get()).expect("a document");
+        removal.apply(&mut doc).expect("to update");
+        rename.apply(&mut doc).expect("to update");
+        writer.write(&doc).expect("to write");
+    }
+    writer.finish().expect("to write");
+}
+
+/// Update the documents token by token.
+fn update_transcoded(path: &Path) {
+    let (removal, rename) = updates();
+    let transcoder = Transcoder::new().remove(removal).rename(rename);
+    black_box(transcoder.transcode_array(open(path), io::sink())).expect("to transcode");
+}
+
+fn load(c: &mut Criterion) {
+    let file = generate();
+    let path = file.path();
+    let size = path.metadata().expect("the dataset").len();
+
+    let mut group = c.benchmark_group("load");
+    group.sample_size(10).throughput(Throughput::Bytes(size));
+    group.bench_function("in_memory", |b| {
+        let path = path.to_str().expect("a UTF-8 path");
+        b.iter(|| black_box(in_memory::load_from_file(path).expect("the dataset")))
+    });
+    group.bench_function("streaming", |b| b.iter(|| stream::<Value>(path, 8 << 10)));
+    group.bench_function("streaming_typed", |b| {
+        b.iter(|| stream::<Entry>(path, 8 << 10))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("buffer_size");
+    group.sample_size(10).throughput(Throughput::Bytes(size));
+    for buffer_size in BUFFER_SIZES {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(buffer_size),
+            &buffer_size,
+            |b, &buffer_size| b.iter(|| stream::<Value>(path, buffer_size)),
+        );
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("update");
+    group.sample_size(10).throughput(Throughput::Bytes(size));
+    group.bench_function("value", |b| b.iter(|| update_values(path)));
+    group.bench_function("raw", |b| b.iter(|| update_raw(path)));
+    group.bench_function("transcode", |b| b.iter(|| update_transcoded(path)));
+    group.finish();
+}
+
+criterion_group!(benches, load);
+criterion_main!(benches);
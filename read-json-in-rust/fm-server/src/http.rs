@@ -0,0 +1,169 @@
+//! The lookup endpoints, served over HTTP/1.1 by the server of
+//! `read_json_in_rust::serve::http`:
+//!
+//! - `GET /fl/WORD`, the analyses of the word form WORD, an empty array for
+//!   an unknown word.
+//! - `GET /lid/ID`, the word forms of the lemma ID, `404 Not Found` for an
+//!   unknown lemma.
+//!
+//! Both answer with a JSON array of entries.
+
+use std::io;
+use std::net::TcpListener;
+
+use serde::Serialize;
+
+use read_json_in_rust::serve::http::{self, percent_decode, HttpOptions, Request};
+
+use crate::lexicon::Lexicon;
+
+/// Answer the requests of the connections to `listener`, until accepting a
+/// connection fails.
+pub fn serve(listener: TcpListener, options: &HttpOptions, lexicon: &Lexicon) -> io::Result<()> {
+    http::serve(listener, options, |request| handle(lexicon, request))
+}
+
+/// A response, with its status and JSON or plain text body.
+#[derive(Debug)]
+enum Response {
+    Entries(Vec<u8>),
+    Error(&'static str, String),
+}
+
+fn handle(lexicon: &Lexicon, request: &mut Request) -> io::Result<()> {
+    let response = match request.method() {
+        "GET" => route(lexicon, request.path()),
+        _ => Response::Error("405 Method Not Allowed", "use GET".to_string()),
+    };
+    match response {
+        Response::Entries(body) => request.respond("200 OK", "application/json", &body),
+        Response::Error(status, message) => request.respond_error(status, &message),
+    }
+}
+
+fn route(lexicon: &Lexicon, path: &str) -> Response {
+    let Some((endpoint, key)) = path.strip_prefix('/').and_then(|path| path.split_once('/')) else {
+        return Response::Error("404 Not Found", "not found".to_string());
+    };
+    let Some(key) = percent_decode(key) else {
+        return Response::Error("400 Bad Request", "invalid percent-encoding".to_string());
+    };
+    match endpoint {
+        "fl" => entries(lexicon.analyses(&key)),
+        "lid" => match lexicon.inflection(&key) {
+            forms if forms.is_empty() => {
+                Response::Error("404 Not Found", format!("no lemma {}", key))
+            }
+            forms => entries(&forms),
+        },
+        _ => Response::Error("404 Not Found", "not found".to_string()),
+    }
+}
+
+fn entries(entries: &[impl Serialize]) -> Response {
+    match serde_json::to_vec(entries) {
+        Ok(body) => Response::Entries(body),
+        Err(err) => Response::Error("500 Internal Server Error", err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::OnceLock;
+    use std::thread;
+
+    use super::*;
+    use crate::lexicon::Entry;
+
+    fn lexicon() -> &'static Lexicon {
+        static LEXICON: OnceLock<Lexicon> = OnceLock::new();
+        LEXICON.get_or_init(|| {
+            Lexicon::load(concat!(env!("CARGO_MANIFEST_DIR"), "/saldom-sample.json")).unwrap()
+        })
+    }
+
+    /// The words of the entries of a `200 OK` response.
+    fn words(response: Response) -> Vec<String> {
+        match response {
+            Response::Entries(body) => serde_json::from_slice::<Vec<Entry>>(&body)
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.word)
+                .collect(),
+            Response::Error(status, message) => panic!("{}: {}", status, message),
+        }
+    }
+
+    fn error(response: Response) -> (&'static str, String) {
+        match response {
+            Response::Error(status, message) => (status, message),
+            Response::Entries(body) => panic!("{}", String::from_utf8_lossy(&body)),
+        }
+    }
+
+    #[test]
+    fn looks_up_word_forms() {
+        assert_eq!(words(route(lexicon(), "/fl/hundarna")), ["hundarna"]);
+        assert_eq!(words(route(lexicon(), "/fl/h%C3%B6et")), ["höet"]);
+        assert_eq!(words(route(lexicon(), "/fl/h%c3%b6s")), ["hös"]);
+        assert!(words(route(lexicon(), "/fl/katt")).is_empty());
+        assert!(words(route(lexicon(), "/fl/")).is_empty());
+    }
+
+    #[test]
+    fn looks_up_lemmas() {
+        assert_eq!(
+            words(route(lexicon(), "/lid/h%C3%B6..nn.1")),
+            ["hö", "hös", "höet", "höets"]
+        );
+        assert_eq!(words(route(lexicon(), "/lid/bil..nn.1")).len(), 8);
+        assert_eq!(
+            error(route(lexicon(), "/lid/katt..nn.1")),
+            ("404 Not Found", "no lemma katt..nn.1".to_string())
+        );
+        assert_eq!(
+            error(route(lexicon(), "/lid/h%C3%B6..nn.2")),
+            ("404 Not Found", "no lemma hö..nn.2".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_other_paths() {
+        for path in ["/fl/%C3", "/fl/%zz", "/lid/h%C3%B"] {
+            assert_eq!(
+                error(route(lexicon(), path)),
+                ("400 Bad Request", "invalid percent-encoding".to_string()),
+                "{}",
+                path
+            );
+        }
+        for path in ["/", "/fl", "/lemma/hund..nn.1"] {
+            assert_eq!(error(route(lexicon(), path)).0, "404 Not Found", "{}", path);
+        }
+    }
+
+    #[test]
+    fn serves_lookups() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener, &HttpOptions::new(), lexicon()).ok());
+        let exchange = |request: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = exchange("GET /fl/h%C3%B6?pretty HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("Content-Type: application/json\r\n"));
+        assert!(response.ends_with(r#""id":"hö..nn.1","p":"nn_0n_hö"}]"#));
+
+        let response = exchange("POST /fl/hund HTTP/1.1\r\nContent-Length: 0\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(response.ends_with("use GET\n"));
+    }
+}
@@ -0,0 +1,81 @@
+//! The word forms of a morphology lexicon, indexed by form and by lemma.
+
+use serde::{Deserialize, Serialize};
+
+use read_json_in_rust::json_iter::JsonIterError;
+use read_json_in_rust::lookup::{Index, IndexBuilder};
+
+/// A word form and its analysis, a document of the SALDO morphology in JSON,
+/// e.g.
+///
+/// ```json
+/// {"word": "hundarna", "head": "hund", "pos": "nn", "param": "pl def nom",
+///  "inhs": ["u"], "id": "hund..nn.1", "p": "nn_2u_stol"}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// The word form.
+    pub word: String,
+    /// The base form of the lemma.
+    pub head: String,
+    /// The part of speech.
+    pub pos: String,
+    /// The morphosyntactic description of the form.
+    pub param: String,
+    /// Inherent features of the lemma, such as the gender of a noun.
+    #[serde(default)]
+    pub inhs: Vec<String>,
+    /// The lemma, e.g. `hund..nn.1`.
+    pub id: String,
+    /// The inflection paradigm.
+    pub p: String,
+}
+
+/// All word forms of a lexicon.
+#[derive(Debug)]
+pub struct Lexicon {
+    /// The entries of every word form.
+    forms: Index<Entry>,
+    /// The positions in `forms` of the entries of every lemma.
+    lemmas: Index<u32>,
+}
+
+impl Lexicon {
+    /// Stream the entries of the JSON array at `path` into a lexicon.
+    pub fn load(path: &str) -> Result<Self, JsonIterError> {
+        let forms = Index::from_file(path, |entry: &Entry| &entry.word)?;
+        let mut lemmas = IndexBuilder::new();
+        for (position, entry) in forms.values().iter().enumerate() {
+            lemmas.insert(&entry.id, position as u32);
+        }
+        Ok(Self {
+            forms,
+            lemmas: lemmas.build(),
+        })
+    }
+
+    /// The number of entries.
+    pub fn len(&self) -> usize {
+        self.forms.len()
+    }
+
+    /// The number of lemmas.
+    pub fn lemmas(&self) -> usize {
+        self.lemmas.keys().count()
+    }
+
+    /// The analyses of the word form `word`, in the order of the lexicon.
+    pub fn analyses(&self, word: &str) -> &[Entry] {
+        self.forms.lookup(word)
+    }
+
+    /// The word forms of the lemma `id`, its inflection table.
+    pub fn inflection(&self, id: &str) -> Vec<&Entry> {
+        let forms = self.forms.values();
+        self.lemmas
+            .lookup(id)
+            .iter()
+            .map(|&position| &forms[position as usize])
+            .collect()
+    }
+}
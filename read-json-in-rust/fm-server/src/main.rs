@@ -0,0 +1,61 @@
+//! A morphology lookup service: the analyses of word forms and the
+//! inflection tables of lemmas of a lexicon in the SALDO morphology format,
+//! over HTTP.
+
+use std::error::Error;
+use std::net::TcpListener;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use clap::Parser;
+
+use read_json_in_rust::serve::http::HttpOptions;
+
+use lexicon::Lexicon;
+
+mod http;
+mod lexicon;
+
+/// Look up word forms and lemmas of a morphology lexicon over HTTP.
+#[derive(Debug, Parser)]
+#[command(name = "fm-server", version)]
+struct Cli {
+    /// JSON array of word forms and their analyses, such as saldom.json,
+    /// `-` reads from stdin
+    lexicon: String,
+
+    /// Address to listen on
+    #[arg(long, value_name = "HOST:PORT", default_value = "127.0.0.1:8081")]
+    listen: String,
+
+    /// Number of connections served at a time, the others wait their turn
+    #[arg(long, value_name = "N", default_value_t = 8)]
+    workers: usize,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if let Err(err) = run(&cli) {
+        eprintln!("error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn run(cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let lexicon = Lexicon::load(&cli.lexicon).map_err(|err| format!("{}: {}", cli.lexicon, err))?;
+    eprintln!(
+        "loaded {} word forms of {} lemmas in {:.2?}",
+        lexicon.len(),
+        lexicon.lemmas(),
+        start.elapsed()
+    );
+    let listener =
+        TcpListener::bind(&cli.listen).map_err(|err| format!("{}: {}", cli.listen, err))?;
+    eprintln!("listening on http://{}", listener.local_addr()?);
+    // Lookups have no body.
+    let options = HttpOptions::new().workers(cli.workers).max_body(0);
+    http::serve(listener, &options, &lexicon)?;
+    Ok(())
+}
@@ -0,0 +1,52 @@
+/*
+ * C API of read-json-in-rust, built with
+ *
+ *     cargo rustc --release --features ffi --lib --crate-type cdylib
+ *
+ * into target/release/libread_json_in_rust.so (.dylib on macOS,
+ * read_json_in_rust.dll on Windows).
+ *
+ * Documents are UTF-8 JSON buffers with a length, not NUL-terminated. On
+ * failure the functions return NULL or -1, and json_iter_last_error describes
+ * the error.
+ */
+
+#ifndef JSON_ITER_H
+#define JSON_ITER_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct JsonIterReader JsonIterReader;
+typedef struct JsonIterWriter JsonIterWriter;
+
+/* Open the JSON array in the file path, decompressing it if needed. */
+JsonIterReader *json_iter_open(const char *path);
+
+/* Point doc and len at the next document and return 1, or return 0 at the end
+ * of the array and -1 on failure. The document is valid until the next call. */
+int json_iter_next(JsonIterReader *reader, const char **doc, size_t *len);
+
+/* Close reader, NULL is ignored. */
+void json_iter_close(JsonIterReader *reader);
+
+/* Create the file path for a JSON array, compressed if the extension asks for it. */
+JsonIterWriter *json_iter_create(const char *path);
+
+/* Write the len bytes of JSON at doc as the next document, 0 or -1. */
+int json_iter_write(JsonIterWriter *writer, const char *doc, size_t len);
+
+/* Close the array and the file and free writer, 0 or -1. */
+int json_iter_finish(JsonIterWriter *writer);
+
+/* The last error on this thread, or NULL; valid until the next error. */
+const char *json_iter_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
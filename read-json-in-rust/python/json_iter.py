@@ -0,0 +1,139 @@
+"""Streaming JSON arrays from Python with the reader and writer of
+read-json-in-rust, through its C API (include/json_iter.h).
+
+Build the library first with
+
+    cargo rustc --release --features ffi --lib --crate-type cdylib
+
+which gives target/release/libread_json_in_rust.so, where this module looks
+for it unless JSON_ITER_LIBRARY names another file. Then, with this directory
+on the path:
+
+    import orjson
+    import json_iter
+
+    docs = (orjson.loads(raw) for raw in json_iter.load_stream("data/skbl.json"))
+    docs = (orjson.dumps(update(doc)) for doc in docs)
+    json_iter.dump_stream(docs, "data/skbl2.json.gz")
+
+The documents are passed as bytes of JSON, which the library has already
+checked, so they are parsed once more in Python by whichever JSON library is
+at hand, or not at all if they are only passed on. `raw=False` parses and
+serializes them with `json` instead, for convenience, at the cost of parsing
+every document twice.
+"""
+
+import ctypes
+import json
+import os
+import sys
+import typing
+from pathlib import Path
+
+
+class JsonIterError(Exception):
+    """An error reading or writing a JSON array, with the message of the
+    library."""
+
+
+def _library_path() -> str:
+    if "JSON_ITER_LIBRARY" in os.environ:
+        return os.environ["JSON_ITER_LIBRARY"]
+    if sys.platform == "darwin":
+        name = "libread_json_in_rust.dylib"
+    elif sys.platform == "win32":
+        name = "read_json_in_rust.dll"
+    else:
+        name = "libread_json_in_rust.so"
+    return str(Path(__file__).resolve().parent.parent / "target" / "release" / name)
+
+
+_lib = None
+
+
+def _library() -> ctypes.CDLL:
+    """The library, loaded the first time it's needed."""
+    global _lib
+    if _lib is None:
+        lib = ctypes.CDLL(_library_path())
+        lib.json_iter_open.argtypes = [ctypes.c_char_p]
+        lib.json_iter_open.restype = ctypes.c_void_p
+        lib.json_iter_next.argtypes = [
+            ctypes.c_void_p,
+            ctypes.POINTER(ctypes.c_void_p),
+            ctypes.POINTER(ctypes.c_size_t),
+        ]
+        lib.json_iter_next.restype = ctypes.c_int
+        lib.json_iter_close.argtypes = [ctypes.c_void_p]
+        lib.json_iter_close.restype = None
+        lib.json_iter_create.argtypes = [ctypes.c_char_p]
+        lib.json_iter_create.restype = ctypes.c_void_p
+        lib.json_iter_write.argtypes = [ctypes.c_void_p, ctypes.c_char_p, ctypes.c_size_t]
+        lib.json_iter_write.restype = ctypes.c_int
+        lib.json_iter_finish.argtypes = [ctypes.c_void_p]
+        lib.json_iter_finish.restype = ctypes.c_int
+        lib.json_iter_last_error.argtypes = []
+        lib.json_iter_last_error.restype = ctypes.c_char_p
+        _lib = lib
+    return _lib
+
+
+def _error(lib: ctypes.CDLL) -> JsonIterError:
+    message = lib.json_iter_last_error()
+    return JsonIterError(message.decode("utf-8", "replace") if message else "unknown error")
+
+
+def load_stream(path: str, raw: bool = True) -> typing.Iterator[typing.Any]:
+    """Yield the documents of the JSON array in the file `path` one at a
+    time as bytes of compact JSON, decompressing it if needed. With
+    `raw=False`, yield them parsed with `json` instead.
+    """
+    lib = _library()
+    reader = lib.json_iter_open(os.fsencode(path))
+    if not reader:
+        raise _error(lib)
+    doc = ctypes.c_void_p()
+    length = ctypes.c_size_t()
+    try:
+        while True:
+            status = lib.json_iter_next(reader, ctypes.byref(doc), ctypes.byref(length))
+            if status == 0:
+                return
+            if status < 0:
+                raise _error(lib)
+            # The buffer is only valid until the next call, so it's copied.
+            buffer = ctypes.string_at(doc.value, length.value) if length.value else b""
+            yield buffer if raw else json.loads(buffer)
+    finally:
+        lib.json_iter_close(reader)
+
+
+def dump_stream(docs: typing.Iterable[typing.Any], path: str, raw: bool = True) -> int:
+    """Write the documents of `docs`, each its JSON as bytes or str, as a
+    JSON array to the file `path`, one at a time, compressed if its extension
+    asks for it, and return how many there were. With `raw=False`, the
+    documents are serialized with `json` first.
+
+    If `docs` raises or a document can't be written, the array written so
+    far is closed before the error is raised.
+    """
+    lib = _library()
+    writer = lib.json_iter_create(os.fsencode(path))
+    if not writer:
+        raise _error(lib)
+    count = 0
+    try:
+        for doc in docs:
+            if not raw:
+                doc = json.dumps(doc, ensure_ascii=False)
+            if isinstance(doc, str):
+                doc = doc.encode("utf-8")
+            if lib.json_iter_write(writer, doc, len(doc)) < 0:
+                raise _error(lib)
+            count += 1
+    except BaseException:
+        lib.json_iter_finish(writer)
+        raise
+    if lib.json_iter_finish(writer) < 0:
+        raise _error(lib)
+    return count
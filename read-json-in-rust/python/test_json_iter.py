@@ -0,0 +1,88 @@
+"""Tests of json_iter, run from this directory with
+
+    python -m unittest
+
+after building the library with
+
+    cargo rustc --release --features ffi --lib --crate-type cdylib
+"""
+
+import gzip
+import json
+import os
+import tempfile
+import unittest
+
+import json_iter
+
+
+def setUpModule():
+    if not os.path.exists(json_iter._library_path()):
+        raise unittest.SkipTest(
+            "build the library with cargo rustc --release --features ffi --lib --crate-type cdylib"
+        )
+
+
+class JsonIterTest(unittest.TestCase):
+    def setUp(self):
+        directory = tempfile.TemporaryDirectory()
+        self.addCleanup(directory.cleanup)
+        self.directory = directory.name
+
+    def path(self, name: str) -> str:
+        return os.path.join(self.directory, name)
+
+    def write(self, name: str, text: str) -> str:
+        path = self.path(name)
+        with open(path, "w", encoding="utf-8") as file:
+            file.write(text)
+        return path
+
+    def test_loads_documents(self):
+        path = self.write("in.json", '[{"word": "hö", "n": 1}, [], 2.5, "x", null]')
+        self.assertEqual(
+            list(json_iter.load_stream(path)),
+            [b'{"word":"h\xc3\xb6","n":1}', b"[]", b"2.5", b'"x"', b"null"],
+        )
+        self.assertEqual(
+            list(json_iter.load_stream(path, raw=False)),
+            [{"word": "hö", "n": 1}, [], 2.5, "x", None],
+        )
+        self.assertEqual(list(json_iter.load_stream(self.write("empty.json", "[]"))), [])
+
+    def test_round_trips_documents(self):
+        docs = [{"word": "hö", "inhs": ["n"]}, {"word": "hus", "n": 12345678901234567890}]
+        for name in ["out.json", "out.json.gz"]:
+            path = self.path(name)
+            self.assertEqual(json_iter.dump_stream(iter(docs), path, raw=False), 2)
+            self.assertEqual(list(json_iter.load_stream(path, raw=False)), docs)
+        with gzip.open(self.path("out.json.gz"), "rt", encoding="utf-8") as file:
+            self.assertEqual(json.load(file), docs)
+
+        # Raw documents are copied as the library writes them, compact.
+        path = self.path("raw.json")
+        self.assertEqual(json_iter.dump_stream(['{"a": 1}', b"[2]"], path), 2)
+        self.assertEqual(list(json_iter.load_stream(path)), [b'{"a":1}', b"[2]"])
+
+    def test_reports_errors(self):
+        with self.assertRaises(json_iter.JsonIterError) as error:
+            list(json_iter.load_stream(self.path("missing.json")))
+        self.assertIn("missing.json", str(error.exception))
+
+        docs = json_iter.load_stream(self.write("broken.json", '[{"a": 1}, {"a": }]'))
+        self.assertEqual(next(docs), b'{"a":1}')
+        with self.assertRaises(json_iter.JsonIterError):
+            next(docs)
+
+        path = self.path("invalid.json")
+        with self.assertRaises(json_iter.JsonIterError):
+            json_iter.dump_stream([b'{"a": 1}', b"{"], path)
+        # What was written before the error is a complete array.
+        self.assertEqual(list(json_iter.load_stream(path)), [b'{"a":1}'])
+
+        with self.assertRaises(json_iter.JsonIterError):
+            json_iter.dump_stream([], self.path("no/such/directory.json"))
+
+
+if __name__ == "__main__":
+    unittest.main()
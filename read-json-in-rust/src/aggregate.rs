@@ -0,0 +1,120 @@
+//! Grouping documents by fields, for frequency tables over lexicon
+//! attributes.
+//!
+//! The documents are streamed, only the groups are kept in memory: their
+//! keys, counts and collected values.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use crate::path::FieldPath;
+use crate::sort::compare_keys;
+
+/// The name of the count in the documents of [`Aggregation::documents`].
+pub const COUNT_FIELD: &str = "count";
+
+/// Groups documents by the values of some fields, counting them and
+/// collecting the values of other fields.
+///
+/// Documents without a field are grouped under `null` for it. Collected
+/// values are kept in input order, without the missing ones and `null`s.
+#[derive(Debug, Clone, Default)]
+pub struct Aggregation {
+    by: Vec<FieldPath>,
+    count: bool,
+    collect: Vec<FieldPath>,
+    /// The groups by their key as JSON.
+    groups: HashMap<String, Group>,
+}
+
+#[derive(Debug, Clone)]
+struct Group {
+    key: Vec<Value>,
+    count: u64,
+    collected: Vec<Vec<Value>>,
+}
+
+impl Aggregation {
+    pub fn new(by: Vec<FieldPath>) -> Self {
+        Self {
+            by,
+            ..Default::default()
+        }
+    }
+
+    /// Count the documents of each group, in the field `count`.
+    pub fn count(mut self, count: bool) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Collect the values of this field for each group, in an array at the
+    /// same path.
+    pub fn collect(mut self, path: FieldPath) -> Self {
+        self.collect.push(path);
+        self
+    }
+
+    pub fn add(&mut self, doc: &Value) {
+        let key: Vec<_> = self
+            .by
+            .iter()
+            .map(|path| path.get(doc).cloned().unwrap_or_default())
+            .collect();
+        let group = self
+            .groups
+            .entry(Value::from(key.clone()).to_string())
+            .or_insert_with(|| Group {
+                key,
+                count: 0,
+                collected: vec![Vec::new(); self.collect.len()],
+            });
+        group.count += 1;
+        for (path, values) in self.collect.iter().zip(&mut group.collected) {
+            if let Some(value) = path.get(doc).filter(|value| !value.is_null()) {
+                values.push(value.clone());
+            }
+        }
+    }
+
+    /// The number of groups so far.
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// One document per group, sorted by the key as in [`crate::sort`], with
+    /// the grouping fields at their paths, the count and the collected values.
+    pub fn documents(self) -> Vec<Value> {
+        let mut groups: Vec<_> = self.groups.into_values().collect();
+        groups.sort_by(|a, b| {
+            a.key
+                .iter()
+                .zip(&b.key)
+                .map(|(a, b)| compare_keys(Some(a), Some(b)))
+                .find(|order| order.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        groups
+            .into_iter()
+            .map(|group| {
+                let mut doc = Value::Object(Map::new());
+                for (path, value) in self.by.iter().zip(group.key) {
+                    // Paths through a grouping field set before are skipped.
+                    let _ = path.set(&mut doc, value);
+                }
+                if self.count {
+                    doc[COUNT_FIELD] = group.count.into();
+                }
+                for (path, values) in self.collect.iter().zip(group.collected) {
+                    let _ = path.set(&mut doc, values.into());
+                }
+                doc
+            })
+            .collect()
+    }
+}
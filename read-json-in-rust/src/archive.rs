@@ -0,0 +1,878 @@
+//! The files of tar and zip archives, e.g. backups with a JSON file per
+//! lexicon, read one after another without extracting them.
+//!
+//! Tar archives are streamed, also compressed ones such as `.tar.gz`, so they
+//! can be read from stdin or a URL. Zip archives are read by their central
+//! directory at the end and have to be files; their files may be stored or
+//! deflated, and archives and files larger than 4 GiB are read from their
+//! zip64 records.
+//!
+//! ```no_run
+//! # use read_json_in_rust::archive::Archive;
+//! # use read_json_in_rust::io::ReadOptions;
+//! # use read_json_in_rust::json_iter;
+//! # use serde_json::Value;
+//! for member in Archive::open("backup.tar.gz", &ReadOptions::new())? {
+//!     let member = member?;
+//!     for doc in json_iter::load_from_reader::<Value, _>(member.reader)? {
+//!         println!("{}: {}", member.name, doc?);
+//!     }
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+
+use flate2::read::DeflateDecoder;
+
+use crate::io::{is_s3, is_url, Input, ReadOptions, BUFFER_SIZE, STDIO};
+
+/// The size of the blocks of a tar archive.
+const BLOCK: u64 = 512;
+
+/// The kind of an archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// The format implied by the extension of `path`: `.tar`, also
+    /// compressed as `.tar.gz`, `.tgz` or `.tar.zst`, or `.zip`.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let uncompressed = path
+            .strip_suffix(".gz")
+            .or_else(|| path.strip_suffix(".zst"))
+            .unwrap_or(path);
+        if uncompressed.ends_with(".tar") || path.ends_with(".tgz") {
+            Some(ArchiveFormat::Tar)
+        } else if path.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// A file of an archive.
+pub struct Member {
+    /// The path of the file in the archive.
+    pub name: String,
+    /// The contents of the file, decompressed and decoded as with
+    /// [`ReadOptions::wrap`]. The reader of a file of a tar archive ends when
+    /// the next file is read.
+    pub reader: Input,
+}
+
+/// Iterator over the regular files of an archive, in the order they are
+/// stored. Directories, links and other entries are skipped.
+///
+/// Yields `Err` at most once, after which the iterator is exhausted.
+pub struct Archive {
+    options: ReadOptions,
+    inner: Inner,
+    done: bool,
+}
+
+enum Inner {
+    Tar(Arc<Mutex<Tar>>),
+    Zip(Zip),
+}
+
+impl Archive {
+    /// Open the archive at `path`, of the [`ArchiveFormat`] of its
+    /// extension, reading it and its files with `options`.
+    pub fn open(path: &str, options: &ReadOptions) -> io::Result<Self> {
+        match ArchiveFormat::from_path(path) {
+            Some(ArchiveFormat::Tar) => Ok(Self::tar(options.open_compressed(path)?, options)),
+            Some(ArchiveFormat::Zip) => Self::zip(path, options),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} isn't a tar or zip archive", path),
+            )),
+        }
+    }
+
+    /// Read the tar archive from `reader`, already decompressed.
+    pub fn tar(reader: Input, options: &ReadOptions) -> Self {
+        let tar = Tar {
+            reader,
+            remaining: 0,
+            padding: 0,
+            file: 0,
+        };
+        Self {
+            options: options.clone(),
+            inner: Inner::Tar(Arc::new(Mutex::new(tar))),
+            done: false,
+        }
+    }
+
+    /// Read the directory of the zip archive at `path`.
+    pub fn zip(path: &str, options: &ReadOptions) -> io::Result<Self> {
+        if path == STDIO || is_url(path) || is_s3(path) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{}: zip archives can only be read from files", path),
+            ));
+        }
+        let files = Zip::directory(&mut File::open(path)?)?;
+        Ok(Self {
+            options: options.clone(),
+            inner: Inner::Zip(Zip {
+                path: path.to_string(),
+                files: files.into_iter(),
+            }),
+            done: false,
+        })
+    }
+
+    fn next_member(&mut self) -> io::Result<Option<Member>> {
+        let (name, reader): (_, Input) = match &mut self.inner {
+            Inner::Tar(tar) => {
+                let mut locked = tar.lock().expect("no panic holding the lock");
+                let Some(name) = locked.next_file()? else {
+                    return Ok(None);
+                };
+                let file = TarFile {
+                    tar: tar.clone(),
+                    file: locked.file,
+                };
+                (name, Box::new(BufReader::with_capacity(BUFFER_SIZE, file)))
+            }
+            Inner::Zip(zip) => {
+                let Some(file) = zip.files.next() else {
+                    return Ok(None);
+                };
+                let reader = zip.open(&file, &self.options)?;
+                (file.name, reader)
+            }
+        };
+        let reader = self.options.wrap(&name, reader)?;
+        Ok(Some(Member { name, reader }))
+    }
+}
+
+impl Iterator for Archive {
+    type Item = io::Result<Member>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.next_member();
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+        result.transpose()
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// A tar archive, at the data of its current file.
+struct Tar {
+    reader: Input,
+    /// The bytes of the current file not yet read, and the padding after
+    /// them up to the next block.
+    remaining: u64,
+    padding: u64,
+    /// The number of the current file, so that the reader of a file ends
+    /// when the next one is read.
+    file: u64,
+}
+
+impl Tar {
+    /// Skip the rest of the current file and read the headers up to the
+    /// next regular file, returning its name.
+    fn next_file(&mut self) -> io::Result<Option<String>> {
+        let mut block = [0; BLOCK as usize];
+        // Names of the next file from GNU and pax headers, longer than the
+        // 100 bytes of the name in the header.
+        let mut long_name = None;
+        loop {
+            self.skip()?;
+            if !self.read_block(&mut block)? || block.iter().all(|&byte| byte == 0) {
+                return Ok(None);
+            }
+            check_header(&block)?;
+            let size = parse_size(&block[124..136])?;
+            self.remaining = size;
+            self.padding = (BLOCK - size % BLOCK) % BLOCK;
+            match block[156] {
+                b'0' | b'\0' | b'7' => {
+                    self.file += 1;
+                    return Ok(Some(long_name.unwrap_or_else(|| header_name(&block))));
+                }
+                b'L' => long_name = Some(c_string(&self.read_data()?)),
+                b'x' => long_name = pax_path(&self.read_data()?).or(long_name),
+                // Directories, links, global pax headers and others.
+                _ => long_name = None,
+            }
+        }
+    }
+
+    /// Read a whole block, `false` at the end of the input.
+    fn read_block(&mut self, block: &mut [u8]) -> io::Result<bool> {
+        let mut read = 0;
+        while read < block.len() {
+            match self.reader.read(&mut block[read..])? {
+                0 if read == 0 => return Ok(false),
+                0 => return Err(invalid("the tar archive ends within a header".to_string())),
+                n => read += n,
+            }
+        }
+        Ok(true)
+    }
+
+    /// The data of the current entry, for the headers with a name.
+    fn read_data(&mut self) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        (&mut self.reader)
+            .take(self.remaining)
+            .read_to_end(&mut data)?;
+        self.remaining -= data.len() as u64;
+        Ok(data)
+    }
+
+    fn skip(&mut self) -> io::Result<()> {
+        let length = self.remaining + self.padding;
+        let skipped = io::copy(&mut (&mut self.reader).take(length), &mut io::sink())?;
+        if skipped < length {
+            return Err(truncated());
+        }
+        self.remaining = 0;
+        self.padding = 0;
+        Ok(())
+    }
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "the tar archive ends within a file",
+    )
+}
+
+/// Check the checksum of a header, the sum of its bytes with the checksum
+/// field as spaces.
+fn check_header(block: &[u8]) -> io::Result<()> {
+    let sum: u64 = block
+        .iter()
+        .enumerate()
+        .map(|(index, &byte)| match index {
+            148..=155 => u64::from(b' '),
+            _ => u64::from(byte),
+        })
+        .sum();
+    if parse_size(&block[148..156]).ok() != Some(sum) {
+        return Err(invalid(
+            "not a tar archive, or a corrupt header".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// A number of a header, in octal digits or in base 256 after a byte with
+/// the high bit set, as GNU tar writes large sizes.
+fn parse_size(field: &[u8]) -> io::Result<u64> {
+    if field[0] & 0x80 != 0 {
+        return Ok(field[1..]
+            .iter()
+            .fold(0, |size, &byte| size << 8 | u64::from(byte)));
+    }
+    let digits = String::from_utf8_lossy(field);
+    let digits = digits.trim_matches(|c: char| c == '\0' || c == ' ');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(digits, 8)
+        .map_err(|_| invalid(format!("invalid number `{}` in a tar header", digits)))
+}
+
+/// The text up to the first NUL byte.
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// The name in a header, with the prefix of ustar headers.
+fn header_name(block: &[u8]) -> String {
+    let name = c_string(&block[..100]);
+    if &block[257..262] != b"ustar" {
+        return name;
+    }
+    match c_string(&block[345..500]) {
+        prefix if prefix.is_empty() => name,
+        prefix => format!("{}/{}", prefix, name),
+    }
+}
+
+/// The `path` of the records of a pax header, `LENGTH path=VALUE\n`.
+fn pax_path(mut records: &[u8]) -> Option<String> {
+    let mut path = None;
+    while let Some(space) = records.iter().position(|&byte| byte == b' ') {
+        let length: usize = std::str::from_utf8(&records[..space]).ok()?.parse().ok()?;
+        let record = records.get(space + 1..length)?;
+        if let Some(value) = record.strip_prefix(b"path=") {
+            let value = value.strip_suffix(b"\n").unwrap_or(value);
+            path = Some(String::from_utf8_lossy(value).into_owned());
+        }
+        records = &records[length..];
+    }
+    path
+}
+
+/// The reader of the current file of a [`Tar`].
+struct TarFile {
+    tar: Arc<Mutex<Tar>>,
+    file: u64,
+}
+
+impl Read for TarFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut tar = self.tar.lock().expect("no panic holding the lock");
+        if tar.file != self.file || tar.remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let length = buf
+            .len()
+            .min(tar.remaining.try_into().unwrap_or(usize::MAX));
+        let read = tar.reader.read(&mut buf[..length])?;
+        if read == 0 {
+            return Err(truncated());
+        }
+        tar.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// A file in the directory of a zip archive.
+struct ZipFile {
+    name: String,
+    method: u16,
+    size: u64,
+    /// The offset of the local header before the data.
+    offset: u64,
+}
+
+/// A zip archive, at its next file.
+struct Zip {
+    path: String,
+    files: std::vec::IntoIter<ZipFile>,
+}
+
+/// The signatures of the records of zip archives.
+const END_OF_DIRECTORY: &[u8] = b"PK\x05\x06";
+const ZIP64_END_OF_DIRECTORY: &[u8] = b"PK\x06\x06";
+const ZIP64_LOCATOR: &[u8] = b"PK\x06\x07";
+const DIRECTORY_ENTRY: &[u8] = b"PK\x01\x02";
+const LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().expect("four bytes"))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().expect("eight bytes"))
+}
+
+fn corrupt_directory() -> io::Error {
+    invalid("a corrupt zip directory".to_string())
+}
+
+/// The data of the zip64 field among the `extra` fields of a directory
+/// entry.
+fn zip64_field(mut extra: &[u8]) -> Option<&[u8]> {
+    while extra.len() >= 4 {
+        let (id, length) = (u16_at(extra, 0), usize::from(u16_at(extra, 2)));
+        let data = extra.get(4..4 + length)?;
+        if id == 1 {
+            return Some(data);
+        }
+        extra = &extra[4 + length..];
+    }
+    None
+}
+
+impl Zip {
+    /// The regular files of the central directory at the end of `file`.
+    fn directory(file: &mut File) -> io::Result<Vec<ZipFile>> {
+        // The end record is 22 bytes and a comment of up to 64 KiB.
+        let length = file.metadata()?.len();
+        let tail_length = length.min(22 + 0xffff);
+        file.seek(SeekFrom::Start(length - tail_length))?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)?;
+        let end = (0..tail.len().saturating_sub(21))
+            .rev()
+            .find(|&start| tail[start..].starts_with(END_OF_DIRECTORY))
+            .ok_or_else(|| invalid("not a zip archive".to_string()))?;
+        let record = &tail[end..];
+        let (mut entries, mut size, mut offset) = (
+            u64::from(u16_at(record, 10)),
+            u64::from(u32_at(record, 12)),
+            u64::from(u32_at(record, 16)),
+        );
+        if entries == 0xffff || size == 0xffff_ffff || offset == 0xffff_ffff {
+            // The zip64 end record, at the offset in the locator just before
+            // the end record.
+            let locator = end
+                .checked_sub(20)
+                .map(|start| &tail[start..end])
+                .filter(|locator| locator.starts_with(ZIP64_LOCATOR))
+                .ok_or_else(|| invalid("a zip64 archive without a zip64 locator".to_string()))?;
+            file.seek(SeekFrom::Start(u64_at(locator, 8)))?;
+            let mut record = [0; 56];
+            file.read_exact(&mut record)?;
+            if !record.starts_with(ZIP64_END_OF_DIRECTORY) {
+                return Err(invalid("a corrupt zip64 end record".to_string()));
+            }
+            (entries, size, offset) = (
+                u64_at(&record, 32),
+                u64_at(&record, 40),
+                u64_at(&record, 48),
+            );
+        }
+        if offset.checked_add(size).is_none_or(|end| end > length) {
+            return Err(corrupt_directory());
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        let mut directory = vec![0; size as usize];
+        file.read_exact(&mut directory)?;
+        let mut files = Vec::with_capacity(entries.min(size / 46) as usize);
+        let mut entry = directory.as_slice();
+        while entry.starts_with(DIRECTORY_ENTRY) && entry.len() >= 46 {
+            let name_length = usize::from(u16_at(entry, 28));
+            let extra_length = usize::from(u16_at(entry, 30));
+            let comment_length = usize::from(u16_at(entry, 32));
+            let end = 46 + name_length + extra_length + comment_length;
+            let name = entry
+                .get(46..46 + name_length)
+                .ok_or_else(corrupt_directory)?;
+            let name = String::from_utf8_lossy(name).into_owned();
+            if u16_at(entry, 8) & 1 != 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("{} is encrypted", name),
+                ));
+            }
+            let mut size = u64::from(u32_at(entry, 20));
+            let mut offset = u64::from(u32_at(entry, 42));
+            let uncompressed = u32_at(entry, 24);
+            if [size, offset].contains(&0xffff_ffff) || uncompressed == 0xffff_ffff {
+                // The 64-bit values of the fields that are at their maximum,
+                // in the order uncompressed size, size and offset.
+                let extra = entry
+                    .get(46 + name_length..46 + name_length + extra_length)
+                    .and_then(zip64_field)
+                    .ok_or_else(corrupt_directory)?;
+                let mut values = extra.chunks_exact(8).map(|value| u64_at(value, 0));
+                if uncompressed == 0xffff_ffff {
+                    values.next().ok_or_else(corrupt_directory)?;
+                }
+                if size == 0xffff_ffff {
+                    size = values.next().ok_or_else(corrupt_directory)?;
+                }
+                if offset == 0xffff_ffff {
+                    offset = values.next().ok_or_else(corrupt_directory)?;
+                }
+            }
+            if !name.ends_with('/') {
+                files.push(ZipFile {
+                    name,
+                    method: u16_at(entry, 10),
+                    size,
+                    offset,
+                });
+            }
+            entry = entry.get(end..).unwrap_or_default();
+        }
+        Ok(files)
+    }
+
+    /// A reader of the data of `file`, decompressed.
+    fn open(&self, file: &ZipFile, options: &ReadOptions) -> io::Result<Input> {
+        let mut archive = File::open(&self.path)?;
+        archive.seek(SeekFrom::Start(file.offset))?;
+        let mut header = [0; 30];
+        archive.read_exact(&mut header)?;
+        if !header.starts_with(LOCAL_HEADER) {
+            return Err(invalid(format!("a corrupt zip header of {}", file.name)));
+        }
+        let extra = i64::from(u16_at(&header, 26)) + i64::from(u16_at(&header, 28));
+        archive.seek(SeekFrom::Current(extra))?;
+        let data = options.count(Box::new(BufReader::new(archive.take(file.size))));
+        Ok(match file.method {
+            0 => data,
+            8 => Box::new(BufReader::with_capacity(
+                BUFFER_SIZE,
+                DeflateDecoder::new(data),
+            )),
+            method => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!(
+                        "{} is compressed with method {}, only stored and deflated files \
+                         are supported",
+                        file.name, method
+                    ),
+                ))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    /// A tar header of an entry of type `kind`, with a ustar `prefix` if
+    /// there is one.
+    fn tar_header(name: &str, prefix: Option<&str>, kind: u8, size: usize) -> Vec<u8> {
+        let mut header = vec![0; BLOCK as usize];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000644");
+        header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+        header[156] = kind;
+        if let Some(prefix) = prefix {
+            header[257..263].copy_from_slice(b"ustar\0");
+            header[263..265].copy_from_slice(b"00");
+            header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+        }
+        let sum: u32 = header.iter().map(|&byte| u32::from(byte)).sum::<u32>() + 8 * 32;
+        header[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+        header
+    }
+
+    /// Append an entry with `data`, padded to a block, to `tar`.
+    fn tar_entry(tar: &mut Vec<u8>, header: Vec<u8>, data: &[u8]) {
+        tar.extend(header);
+        tar.extend(data);
+        tar.resize(tar.len().next_multiple_of(BLOCK as usize), 0);
+    }
+
+    fn tar_end(tar: &mut Vec<u8>) {
+        tar.extend([0; 2 * BLOCK as usize]);
+    }
+
+    /// The names and contents of the members of `archive`, read from a
+    /// file named `name`.
+    fn members(name: &str, archive: &[u8]) -> io::Result<Vec<(String, String)>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(name);
+        std::fs::write(&path, archive)?;
+        let mut members = Vec::new();
+        for member in Archive::open(path.to_str().unwrap(), &ReadOptions::new())? {
+            let mut member = member?;
+            let mut text = String::new();
+            member.reader.read_to_string(&mut text)?;
+            members.push((member.name, text));
+        }
+        Ok(members)
+    }
+
+    fn pairs(members: &[(&str, &str)]) -> Vec<(String, String)> {
+        members
+            .iter()
+            .map(|&(name, text)| (name.to_string(), text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_tar_files_with_their_names() {
+        let long = format!("lexicons/{}.json", "a".repeat(120));
+        let mut tar = Vec::new();
+        tar_entry(&mut tar, tar_header("old.json", None, b'\0', 3), b"[1]");
+        tar_entry(&mut tar, tar_header("dir/", None, b'5', 0), b"");
+        tar_entry(
+            &mut tar,
+            tar_header("b.json", Some("ustar/dir"), b'0', 3),
+            b"[2]",
+        );
+        // The length of the record includes its own three digits.
+        let record = format!(" path={}\n", long);
+        let records = format!("{}{}", record.len() + 3, record);
+        tar_entry(
+            &mut tar,
+            tar_header("PaxHeader", None, b'x', records.len()),
+            records.as_bytes(),
+        );
+        tar_entry(&mut tar, tar_header("short.json", None, b'0', 3), b"[3]");
+        let gnu = format!("{}\0", long.replace("lexicons", "gnu"));
+        tar_entry(
+            &mut tar,
+            tar_header("././@LongLink", None, b'L', gnu.len()),
+            gnu.as_bytes(),
+        );
+        tar_entry(&mut tar, tar_header("trunc.json", None, b'0', 3), b"[4]");
+        tar_entry(&mut tar, tar_header("link.json", None, b'2', 0), b"");
+        tar_end(&mut tar);
+        assert_eq!(
+            members("backup.tar", &tar).unwrap(),
+            pairs(&[
+                ("old.json", "[1]"),
+                ("ustar/dir/b.json", "[2]"),
+                (&long, "[3]"),
+                (&long.replace("lexicons", "gnu"), "[4]"),
+            ])
+        );
+    }
+
+    #[test]
+    fn reads_large_tar_files() {
+        let data = "x".repeat(1500);
+        let mut tar = Vec::new();
+        tar_entry(
+            &mut tar,
+            tar_header("large.json", None, b'0', data.len()),
+            data.as_bytes(),
+        );
+        tar_entry(&mut tar, tar_header("next.json", None, b'0', 2), b"[]");
+        tar_end(&mut tar);
+        let members = members("backup.tar", &tar).unwrap();
+        assert_eq!(
+            members,
+            pairs(&[("large.json", &data), ("next.json", "[]")])
+        );
+    }
+
+    #[test]
+    fn fails_on_truncated_tar_archives() {
+        let mut tar = Vec::new();
+        tar_entry(
+            &mut tar,
+            tar_header("a.json", None, b'0', 700),
+            &[b' '; 700],
+        );
+        let err = members("backup.tar", &tar[..BLOCK as usize + 100]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(err.to_string(), "the tar archive ends within a file");
+        let err = members("backup.tar", &tar[..300]).unwrap_err();
+        assert_eq!(err.to_string(), "the tar archive ends within a header");
+        let mut corrupt = tar.clone();
+        corrupt[0] = b'b';
+        let err = members("backup.tar", &corrupt).unwrap_err();
+        assert_eq!(err.to_string(), "not a tar archive, or a corrupt header");
+    }
+
+    /// A file to put in a zip archive.
+    struct Entry {
+        name: &'static str,
+        data: &'static str,
+        deflate: bool,
+        /// Write the sizes in a data descriptor after the data, rather than
+        /// in the local header.
+        descriptor: bool,
+    }
+
+    impl Entry {
+        fn new(name: &'static str, data: &'static str) -> Self {
+            Self {
+                name,
+                data,
+                deflate: false,
+                descriptor: false,
+            }
+        }
+    }
+
+    /// A zip archive of `entries`, with zip64 records for all sizes and
+    /// offsets if `zip64`. CRCs are left zero, as they aren't checked.
+    fn zip(entries: &[Entry], zip64: bool) -> Vec<u8> {
+        let mut zip = Vec::new();
+        let mut directory = Vec::new();
+        for entry in entries {
+            let data = match entry.deflate {
+                true => {
+                    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                    encoder.write_all(entry.data.as_bytes()).unwrap();
+                    encoder.finish().unwrap()
+                }
+                false => entry.data.as_bytes().to_vec(),
+            };
+            let offset = zip.len() as u64;
+            let (flags, method) = (
+                u16::from(entry.descriptor) << 3,
+                u16::from(entry.deflate) * 8,
+            );
+            let (size, data_size) = (entry.data.len() as u32, data.len() as u32);
+            zip.extend(LOCAL_HEADER);
+            zip.extend(20u16.to_le_bytes());
+            zip.extend(flags.to_le_bytes());
+            zip.extend(method.to_le_bytes());
+            zip.extend([0; 8]);
+            match entry.descriptor {
+                true => zip.extend([0; 8]),
+                false => {
+                    zip.extend(data_size.to_le_bytes());
+                    zip.extend(size.to_le_bytes());
+                }
+            }
+            zip.extend((entry.name.len() as u16).to_le_bytes());
+            zip.extend(0u16.to_le_bytes());
+            zip.extend(entry.name.as_bytes());
+            zip.extend(&data);
+            if entry.descriptor {
+                zip.extend(b"PK\x07\x08");
+                zip.extend([0; 4]);
+                zip.extend(data_size.to_le_bytes());
+                zip.extend(size.to_le_bytes());
+            }
+            directory.extend(DIRECTORY_ENTRY);
+            directory.extend([20, 0, 20, 0]);
+            directory.extend(flags.to_le_bytes());
+            directory.extend(method.to_le_bytes());
+            directory.extend([0; 8]);
+            let mut extra = Vec::new();
+            match zip64 {
+                true => {
+                    directory.extend([0xff; 8]);
+                    extra.extend(1u16.to_le_bytes());
+                    extra.extend(24u16.to_le_bytes());
+                    extra.extend(u64::from(size).to_le_bytes());
+                    extra.extend(u64::from(data_size).to_le_bytes());
+                    extra.extend(offset.to_le_bytes());
+                }
+                false => {
+                    directory.extend(data_size.to_le_bytes());
+                    directory.extend(size.to_le_bytes());
+                }
+            }
+            directory.extend((entry.name.len() as u16).to_le_bytes());
+            directory.extend((extra.len() as u16).to_le_bytes());
+            // The comment length, disk, and internal and external attributes.
+            directory.extend([0; 10]);
+            match zip64 {
+                true => directory.extend([0xff; 4]),
+                false => directory.extend((offset as u32).to_le_bytes()),
+            }
+            directory.extend(entry.name.as_bytes());
+            directory.extend(extra);
+        }
+        let offset = zip.len() as u64;
+        zip.extend(&directory);
+        let count = entries.len() as u64;
+        if zip64 {
+            let record = zip.len() as u64;
+            zip.extend(ZIP64_END_OF_DIRECTORY);
+            zip.extend(44u64.to_le_bytes());
+            zip.extend([45, 0, 45, 0]);
+            zip.extend([0; 8]);
+            zip.extend(count.to_le_bytes());
+            zip.extend(count.to_le_bytes());
+            zip.extend((directory.len() as u64).to_le_bytes());
+            zip.extend(offset.to_le_bytes());
+            zip.extend(ZIP64_LOCATOR);
+            zip.extend([0; 4]);
+            zip.extend(record.to_le_bytes());
+            zip.extend(1u32.to_le_bytes());
+        }
+        zip.extend(END_OF_DIRECTORY);
+        zip.extend([0; 4]);
+        match zip64 {
+            true => zip.extend([0xff; 12]),
+            false => {
+                zip.extend((count as u16).to_le_bytes());
+                zip.extend((count as u16).to_le_bytes());
+                zip.extend((directory.len() as u32).to_le_bytes());
+                zip.extend((offset as u32).to_le_bytes());
+            }
+        }
+        let comment = b"backup";
+        zip.extend((comment.len() as u16).to_le_bytes());
+        zip.extend(comment);
+        zip
+    }
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            Entry::new("stored.json", "[1]"),
+            Entry::new("dir/", ""),
+            Entry {
+                deflate: true,
+                ..Entry::new("dir/deflated.json", "[\"hund\", \"hund\", \"hund\"]")
+            },
+            Entry {
+                descriptor: true,
+                ..Entry::new("descriptor.json", "[2]")
+            },
+            Entry {
+                deflate: true,
+                descriptor: true,
+                ..Entry::new("both.json", "[3]")
+            },
+        ]
+    }
+
+    fn expected() -> Vec<(String, String)> {
+        pairs(&[
+            ("stored.json", "[1]"),
+            ("dir/deflated.json", "[\"hund\", \"hund\", \"hund\"]"),
+            ("descriptor.json", "[2]"),
+            ("both.json", "[3]"),
+        ])
+    }
+
+    #[test]
+    fn reads_zip_files() {
+        assert_eq!(
+            members("backup.zip", &zip(&entries(), false)).unwrap(),
+            expected()
+        );
+    }
+
+    #[test]
+    fn reads_zip64_files() {
+        assert_eq!(
+            members("backup.zip", &zip(&entries(), true)).unwrap(),
+            expected()
+        );
+    }
+
+    #[test]
+    fn fails_on_truncated_zip_archives() {
+        let zip = zip(&entries(), false);
+        let err = members("backup.zip", &zip[..zip.len() - 30]).unwrap_err();
+        assert_eq!(err.to_string(), "not a zip archive");
+        let mut corrupt = zip.clone();
+        // The offset of the directory in the end record.
+        let end = corrupt.len() - 6 - 6;
+        corrupt[end..end + 4].copy_from_slice(&u32::MAX.to_le_bytes()[..]);
+        corrupt[end - 4..end].copy_from_slice(&[0xff; 4]);
+        let err = members("backup.zip", &corrupt).unwrap_err();
+        assert_eq!(err.to_string(), "a zip64 archive without a zip64 locator");
+        let err = members("backup.zip", &zip[..0]).unwrap_err();
+        assert_eq!(err.to_string(), "not a zip archive");
+    }
+
+    #[test]
+    fn rejects_encrypted_zip_files() {
+        let mut zip = zip(&[Entry::new("secret.json", "[]")], false);
+        let directory = zip
+            .windows(4)
+            .position(|bytes| bytes == DIRECTORY_ENTRY)
+            .unwrap();
+        zip[directory + 8] |= 1;
+        let err = members("backup.zip", &zip).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert_eq!(err.to_string(), "secret.json is encrypted");
+    }
+}
@@ -0,0 +1,35 @@
+use std::error::Error;
+
+use serde_json::Value;
+
+use read_json_in_rust::aggregate::Aggregation;
+use read_json_in_rust::io;
+use read_json_in_rust::json_iter::{self, ArrayWriter};
+
+use crate::cli::AggregateArgs;
+
+pub fn aggregate(args: &AggregateArgs) -> Result<(), Box<dyn Error>> {
+    let mut aggregation = args
+        .collect
+        .iter()
+        .fold(Aggregation::new(args.by.clone()), |aggregation, path| {
+            aggregation.collect(path.clone())
+        })
+        .count(args.count);
+    for path in &args.inputs {
+        for doc in json_iter::load_from_file::<Value>(path)? {
+            aggregation.add(&doc?);
+        }
+    }
+    let output = io::create(&args.output)?;
+    let mut writer = if args.pretty {
+        ArrayWriter::pretty(output, 2)?
+    } else {
+        ArrayWriter::new(output)?
+    };
+    for doc in aggregation.documents() {
+        writer.write(&doc)?;
+    }
+    writer.finish()?.finish()?;
+    Ok(())
+}
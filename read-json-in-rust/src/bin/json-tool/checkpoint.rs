@@ -0,0 +1,92 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Seek, Write};
+
+use serde::{Deserialize, Serialize};
+
+use read_json_in_rust::io::Replacement;
+use read_json_in_rust::json_iter::RawArrayWriter;
+
+use crate::cli::ConvertArgs;
+
+/// Documents between the checkpoints of `--checkpoint`.
+pub const INTERVAL: u64 = 10_000;
+
+/// How far a conversion with `--checkpoint` got, where everything before
+/// it is in the output.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    inputs: Vec<String>,
+    output: String,
+    /// Documents read from the inputs, all updated and written.
+    pub documents: u64,
+    /// Documents written to the output.
+    pub written: u64,
+    /// Length of the output, without the closing `]`.
+    output_bytes: u64,
+}
+
+impl Checkpoint {
+    pub fn new(args: &ConvertArgs) -> Self {
+        Self {
+            inputs: args.inputs.clone(),
+            output: args.output.clone(),
+            documents: 0,
+            written: 0,
+            output_bytes: 0,
+        }
+    }
+
+    /// Read the checkpoint at `path`, `None` if there is none.
+    pub fn load(args: &ConvertArgs, path: &str) -> Result<Option<Self>, Box<dyn Error>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(format!("{}: {}", path, err).into()),
+        };
+        let checkpoint: Self =
+            serde_json::from_str(&text).map_err(|err| format!("{}: {}", path, err))?;
+        if checkpoint.inputs != args.inputs || checkpoint.output != args.output {
+            return Err(format!("{} is the checkpoint of another conversion", path).into());
+        }
+        Ok(Some(checkpoint))
+    }
+
+    /// Open the output to continue after the checkpoint, dropping what was
+    /// written after it, or create it for a new conversion.
+    pub fn open_output(
+        checkpoint: Option<&Self>,
+        path: &str,
+    ) -> Result<RawArrayWriter<BufWriter<File>>, Box<dyn Error>> {
+        let Some(checkpoint) = checkpoint else {
+            return Ok(RawArrayWriter::new(BufWriter::new(File::create(path)?))?);
+        };
+        let mut file = File::options().write(true).open(path)?;
+        if file.metadata()?.len() < checkpoint.output_bytes {
+            return Err("the output is shorter than at the checkpoint".into());
+        }
+        file.set_len(checkpoint.output_bytes)?;
+        file.seek(std::io::SeekFrom::End(0))?;
+        Ok(RawArrayWriter::resume(
+            BufWriter::new(file),
+            checkpoint.written == 0,
+        ))
+    }
+
+    /// Flush the output to disk and save the checkpoint at `path`, replacing
+    /// the previous one at once so a crash leaves one or the other.
+    pub fn save(
+        &mut self,
+        writer: &mut RawArrayWriter<BufWriter<File>>,
+        path: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let output = writer.get_mut();
+        output.flush()?;
+        output.get_ref().sync_data()?;
+        self.output_bytes = output.stream_position()?;
+        let replacement = Replacement::new(path)?;
+        fs::write(replacement.temp_path(), serde_json::to_vec(self)?)?;
+        replacement.persist()?;
+        Ok(())
+    }
+}
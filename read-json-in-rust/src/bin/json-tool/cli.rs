@@ -0,0 +1,1157 @@
+use std::path::PathBuf;
+#[cfg(feature = "http")]
+use std::time::Duration;
+
+#[cfg(feature = "arrow")]
+use arrow_schema::SchemaRef;
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+#[cfg(feature = "http")]
+use reqwest::header::{HeaderName, HeaderValue};
+
+#[cfg(feature = "arrow")]
+use read_json_in_rust::columnar;
+#[cfg(feature = "serve")]
+use read_json_in_rust::serve::http;
+#[cfg(feature = "sqlite")]
+use read_json_in_rust::sqlite;
+#[cfg(feature = "http")]
+use read_json_in_rust::upload;
+
+use read_json_in_rust::dedup::Keep;
+use read_json_in_rust::filter::Filter;
+use read_json_in_rust::glob;
+use read_json_in_rust::io::{self, Encoding};
+use read_json_in_rust::json_iter;
+use read_json_in_rust::number::NumberFormat;
+use read_json_in_rust::path::FieldPath;
+use read_json_in_rust::rules::Rules;
+use read_json_in_rust::shuffle::Shuffle;
+use read_json_in_rust::sort::{self, ExternalSort};
+use read_json_in_rust::table::{Column, Quote, TableSchema};
+use read_json_in_rust::transform::{
+    Derive, FlattenArrays, Jq, MergePatch, NormalizationForm, Patch, Remove, Rename, Select,
+    SetField,
+};
+use read_json_in_rust::validate::Schema;
+
+/// Read a JSON array of documents, update every document and write the result.
+#[derive(Debug, Parser)]
+#[command(name = "json-tool", version, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Arguments for `stream`, used when no subcommand is given.
+    #[command(flatten)]
+    pub convert: Option<ConvertArgs>,
+
+    /// Report the elapsed time and peak memory use on stderr as JSON, e.g.
+    /// {"elapsed_seconds":0.68,"peak_rss_bytes":338165760}
+    #[arg(long, global = true)]
+    pub report_json: bool,
+}
+
+impl Cli {
+    /// The arguments of a conversion, with or without the `stream` or
+    /// `in-memory` subcommand.
+    pub fn conversion(&self) -> Option<&ConvertArgs> {
+        match (&self.command, &self.convert) {
+            (Some(Command::Stream(args)), _) | (None, Some(args)) => Some(args),
+            (Some(Command::InMemory(args)), _) => Some(&args.convert),
+            _ => None,
+        }
+    }
+
+    pub fn conversion_mut(&mut self) -> Option<&mut ConvertArgs> {
+        match (&mut self.command, &mut self.convert) {
+            (Some(Command::Stream(args)), _) | (None, Some(args)) => Some(args),
+            (Some(Command::InMemory(args)), _) => Some(&mut args.convert),
+            _ => None,
+        }
+    }
+
+    /// Replace glob patterns and directories among the inputs by the files
+    /// they stand for, see [`glob::expand`].
+    pub fn expand_inputs(&mut self) -> std::io::Result<()> {
+        let (inputs, suffix) = match (&mut self.command, &mut self.convert) {
+            (Some(Command::Stream(args)), _) | (None, Some(args)) => {
+                (&mut args.inputs, args.members.as_str())
+            }
+            (Some(Command::InMemory(args)), _) => {
+                (&mut args.convert.inputs, args.convert.members.as_str())
+            }
+            (Some(Command::Validate(args)), _) => (&mut args.inputs, ".json"),
+            (Some(Command::InferSchema(args)), _) => (&mut args.inputs, ".json"),
+            (Some(Command::Stats(args)), _) => (&mut args.inputs, ".json"),
+            (Some(Command::Aggregate(args)), _) => (&mut args.inputs, ".json"),
+            (Some(Command::ToCsv(args)), _) => (&mut args.inputs, ".json"),
+            _ => return Ok(()),
+        };
+        *inputs = expand_all(inputs, suffix)?;
+        Ok(())
+    }
+}
+
+/// The files that the glob patterns and directories among `inputs` stand
+/// for, and the other inputs as they are.
+pub fn expand_all(inputs: &[String], suffix: &str) -> std::io::Result<Vec<String>> {
+    let mut expanded = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        expanded.extend(glob::expand(input, suffix)?);
+    }
+    Ok(expanded)
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Stream one document at a time (the default)
+    Stream(ConvertArgs),
+    /// Load the whole file into memory before updating it
+    InMemory(InMemoryArgs),
+    /// Compare two dumps, matching documents by a key field
+    Diff(DiffArgs),
+    /// Join two dumps sorted by a key field, streaming both
+    Join(JoinArgs),
+    /// Check that all documents match a JSON Schema
+    Validate(ValidateArgs),
+    /// Write a JSON Schema of the fields and types seen in the documents
+    InferSchema(InferSchemaArgs),
+    /// Count the documents and the values of every field
+    Stats(StatsArgs),
+    /// Write one document per group of documents with the same values of
+    /// some fields, with their count and collected values
+    Aggregate(AggregateArgs),
+    /// Write the first documents
+    Head(PickArgs),
+    /// Write the last documents
+    Tail(PickArgs),
+    /// Write a random sample of the documents
+    Sample(SampleArgs),
+    /// Write fields of the documents as rows of a CSV or TSV table
+    ToCsv(ToCsvArgs),
+    /// Update uploaded documents over HTTP with the rules of a lexicon
+    #[cfg(feature = "serve")]
+    Serve(ServeArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ToCsvArgs {
+    /// JSON files with arrays of documents, `-` reads from stdin
+    #[arg(value_name = "INPUT", required = true, num_args = 1..)]
+    pub inputs: Vec<String>,
+
+    /// Where to write the table, `-` writes to stdout
+    #[arg(long, short, default_value = "-")]
+    pub output: String,
+
+    /// A column with the values of a field, given as NAME=FIELD or FIELD,
+    /// where FIELD is a dotted path or a JSON pointer (repeatable, in order)
+    #[arg(long = "column", value_name = "COLUMN", required = true)]
+    pub columns: Vec<Column>,
+
+    /// Delimiter between cells, a single character or `tab`
+    #[arg(long, value_name = "CHAR", default_value = ",", value_parser = parse_delimiter)]
+    pub delimiter: u8,
+
+    /// When to quote cells
+    #[arg(long, value_enum, default_value_t = QuoteArg::Necessary)]
+    pub quote: QuoteArg,
+
+    /// Leave out the header row with the column names
+    #[arg(long)]
+    pub no_header: bool,
+
+    /// Start the table with a UTF-8 byte order mark, so that Excel reads it
+    /// as UTF-8
+    #[arg(long)]
+    pub bom: bool,
+}
+
+fn parse_delimiter(s: &str) -> Result<u8, String> {
+    match s {
+        "tab" | "\\t" => Ok(b'\t'),
+        _ if s.len() == 1 => Ok(s.as_bytes()[0]),
+        _ => Err("the delimiter must be a single ASCII character or `tab`".to_string()),
+    }
+}
+
+/// Parse the key of `--enrich` or `join`, FIELD or A=B, as the paths in the
+/// two inputs.
+fn parse_join_key(s: &str) -> Result<(FieldPath, FieldPath), String> {
+    match s.split_once('=') {
+        Some((doc, lookup)) => Ok((doc.parse()?, lookup.parse()?)),
+        None => Ok((s.parse()?, s.parse()?)),
+    }
+}
+
+/// Parse a positive number of things a second.
+#[cfg(feature = "http")]
+fn parse_rate(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+        Ok(_) => Err("the rate must be positive".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Parse a positive number of seconds.
+#[cfg(feature = "http")]
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    match s.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 && seconds.is_finite() => Ok(Duration::from_secs_f64(seconds)),
+        Ok(_) => Err("the number of seconds must be positive".to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Parse a size in bytes, with an optional suffix `K` (KiB), `M` (MiB) or
+/// `G` (GiB).
+fn parse_size(s: &str) -> Result<usize, String> {
+    let (number, unit) = match s.strip_suffix(['K', 'k']) {
+        Some(number) => (number, 1 << 10),
+        None => match s.strip_suffix(['M', 'm']) {
+            Some(number) => (number, 1 << 20),
+            None => match s.strip_suffix(['G', 'g']) {
+                Some(number) => (number, 1 << 30),
+                None => (s, 1),
+            },
+        },
+    };
+    let number: usize = number
+        .parse()
+        .map_err(|_| format!("expected a size like 65536, 64K or 1M, got '{}'", s))?;
+    match number.checked_mul(unit) {
+        Some(0) => Err("the size must be at least 1 byte".to_string()),
+        Some(size) => Ok(size),
+        None => Err(format!("the size '{}' is too large", s)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum QuoteArg {
+    /// Only cells with the delimiter, a quote or a line break
+    Necessary,
+    Always,
+    /// All cells that aren't a number
+    NonNumeric,
+    Never,
+}
+
+impl From<QuoteArg> for Quote {
+    fn from(quote: QuoteArg) -> Self {
+        match quote {
+            QuoteArg::Necessary => Quote::Necessary,
+            QuoteArg::Always => Quote::Always,
+            QuoteArg::NonNumeric => Quote::NonNumeric,
+            QuoteArg::Never => Quote::Never,
+        }
+    }
+}
+
+#[cfg(feature = "serve")]
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// TOML or YAML file with rules per lexicon, each lexicon is a pipeline
+    /// at `/pipelines/NAME`
+    #[arg(long, value_name = "FILE", value_parser = Rules::from_file)]
+    pub rules: Rules,
+
+    /// Address to listen on
+    #[arg(long, value_name = "HOST:PORT", default_value = "127.0.0.1:8080")]
+    pub listen: String,
+
+    /// Number of connections served at a time, the others wait their turn
+    #[arg(long, value_name = "N", default_value_t = 8)]
+    pub workers: usize,
+
+    /// Refuse uploads of more than SIZE bytes, e.g. 500M
+    #[arg(long, value_name = "SIZE", default_value_t = http::MAX_BODY as usize, value_parser = parse_size)]
+    pub max_body: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct PickArgs {
+    /// JSON file with an array of documents, `-` reads from stdin
+    pub input: String,
+
+    /// Number of documents to write
+    #[arg(long, short = 'n', default_value_t = 10)]
+    pub count: usize,
+
+    /// Where to write the documents, `-` writes to stdout
+    #[arg(long, short, default_value = "-")]
+    pub output: String,
+
+    /// Pretty print the output
+    #[arg(long)]
+    pub pretty: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct SampleArgs {
+    #[command(flatten)]
+    pub pick: PickArgs,
+
+    /// Seed of the random choice, so the same seed picks the same documents
+    #[arg(long, value_name = "S")]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    /// JSON files with arrays of documents, `-` reads from stdin
+    #[arg(value_name = "INPUT", required = true, num_args = 1..)]
+    pub inputs: Vec<String>,
+
+    /// Format of the report
+    #[arg(long, value_enum, default_value_t = StatsFormat::Text)]
+    pub format: StatsFormat,
+
+    /// Also profile the values of every field: the share of nulls, the
+    /// range and mean of the numbers, and the lengths and most common
+    /// values of the strings
+    #[arg(long)]
+    pub profile: bool,
+
+    /// Number of most common strings in a profile
+    #[arg(long, value_name = "N", default_value_t = 10, requires = "profile")]
+    pub top: usize,
+
+    /// Where to write the report, `-` writes to stdout
+    #[arg(long, short, default_value = "-")]
+    pub output: String,
+}
+
+#[derive(Debug, Args)]
+pub struct AggregateArgs {
+    /// JSON files with arrays of documents, `-` reads from stdin
+    #[arg(value_name = "INPUT", required = true, num_args = 1..)]
+    pub inputs: Vec<String>,
+
+    /// Group the documents by the value of this field, a dotted path or a
+    /// JSON pointer (repeatable)
+    #[arg(long, value_name = "FIELD", required = true)]
+    pub by: Vec<FieldPath>,
+
+    /// Count the documents of each group, in the field `count`
+    #[arg(long)]
+    pub count: bool,
+
+    /// Collect the values of this field in each group into an array
+    /// (repeatable)
+    #[arg(long, value_name = "FIELD")]
+    pub collect: Vec<FieldPath>,
+
+    /// Where to write the groups, `-` writes to stdout
+    #[arg(long, short, default_value = "-")]
+    pub output: String,
+
+    /// Pretty print the output
+    #[arg(long)]
+    pub pretty: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StatsFormat {
+    /// A table with one line per field
+    Text,
+    /// A JSON object
+    Json,
+}
+
+#[derive(Debug, Args)]
+pub struct ValidateArgs {
+    /// JSON files with arrays of documents, `-` reads from stdin
+    #[arg(value_name = "INPUT", required = true, num_args = 1..)]
+    pub inputs: Vec<String>,
+
+    /// JSON file with the schema
+    #[arg(long, value_name = "FILE", value_parser = Schema::from_file)]
+    pub schema: Schema,
+}
+
+#[derive(Debug, Args)]
+pub struct InferSchemaArgs {
+    /// JSON files with arrays of documents, `-` reads from stdin
+    #[arg(value_name = "INPUT", required = true, num_args = 1..)]
+    pub inputs: Vec<String>,
+
+    /// Forbid fields that weren't seen, with `additionalProperties: false`
+    #[arg(long)]
+    pub closed: bool,
+
+    /// Where to write the schema, `-` writes to stdout
+    #[arg(long, short, default_value = "-")]
+    pub output: String,
+}
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// The old JSON array of documents
+    pub old: String,
+
+    /// The new JSON array of documents
+    pub new: String,
+
+    /// Field identifying a document in both dumps (dotted path or JSON pointer)
+    #[arg(long, value_name = "FIELD")]
+    pub key: FieldPath,
+
+    /// Format of the report
+    #[arg(long, value_enum, default_value_t = DiffFormat::Text)]
+    pub format: DiffFormat,
+
+    #[command(flatten)]
+    pub sorting: SortArgs,
+
+    /// Where to write the report, `-` writes to stdout
+    #[arg(long, short, default_value = "-")]
+    pub output: String,
+}
+
+/// How large inputs are sorted or shuffled in temporary files.
+#[derive(Debug, Clone, Args)]
+pub struct SortArgs {
+    /// Keep at most about SIZE bytes of documents in memory when sorting or
+    /// shuffling large inputs, the rest in temporary files, e.g. 2G
+    #[arg(long, value_name = "SIZE", default_value_t = sort::DEFAULT_MEMORY, value_parser = parse_size)]
+    pub sort_memory: usize,
+
+    /// Write the temporary files of sorting or shuffling to this directory
+    /// instead of $TMPDIR or /tmp
+    #[arg(long, value_name = "DIR")]
+    pub temp_dir: Option<PathBuf>,
+}
+
+impl SortArgs {
+    pub fn external_sort(&self) -> ExternalSort {
+        let sort = ExternalSort::new().memory(self.sort_memory);
+        match &self.temp_dir {
+            Some(dir) => sort.temp_dir(dir),
+            None => sort,
+        }
+    }
+
+    pub fn shuffle(&self) -> Shuffle {
+        let shuffle = Shuffle::new().memory(self.sort_memory);
+        match &self.temp_dir {
+            Some(dir) => shuffle.temp_dir(dir),
+            None => shuffle,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct JoinArgs {
+    /// The left JSON array of documents, whose order the output keeps
+    pub left: String,
+
+    /// The right JSON array of documents, joined into the left ones
+    pub right: String,
+
+    /// The key, a field (dotted path or JSON pointer) of the documents of
+    /// both inputs, or LEFT=RIGHT if they differ
+    #[arg(long, value_name = "KEY", value_parser = parse_join_key)]
+    pub on: (FieldPath, FieldPath),
+
+    /// Also write the left documents without a match
+    #[arg(long)]
+    pub left_join: bool,
+
+    /// Put the matching right document in this top-level field of the left
+    /// one (null if there is none), instead of adding the fields it lacks
+    #[arg(long, value_name = "FIELD")]
+    pub into: Option<String>,
+
+    /// Sort both inputs by the key first, in temporary files if they are
+    /// large, instead of expecting them sorted
+    #[arg(long)]
+    pub sort: bool,
+
+    #[command(flatten)]
+    pub sorting: SortArgs,
+
+    /// Where to write the documents, `-` writes to stdout
+    #[arg(long, short, default_value = "-")]
+    pub output: String,
+
+    /// Pretty print the output
+    #[arg(long)]
+    pub pretty: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DiffFormat {
+    /// One line per added or removed document, and per changed field
+    Text,
+    /// A JSON array of changes
+    Json,
+}
+
+#[derive(Debug, Args)]
+pub struct InMemoryArgs {
+    #[command(flatten)]
+    pub convert: ConvertArgs,
+
+    /// Parse directly from a memory map of the input instead of reading it
+    /// to a string first (uncompressed arrays only)
+    #[arg(long, conflicts_with_all = ["progress", "input_format"])]
+    pub mmap: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+#[command(group(ArgGroup::new("skipping").args(["lenient", "check"]).multiple(true)))]
+#[command(group(ArgGroup::new("flattening").args(["flatten", "unflatten"]).multiple(true)))]
+#[command(group(ArgGroup::new("randomizing").args(["sample", "shuffle"]).multiple(true)))]
+pub struct ConvertArgs {
+    /// JSON files with arrays of documents, written in order to a single
+    /// array, `-` reads from stdin
+    #[arg(value_name = "INPUT", required = true, num_args = 1..)]
+    pub inputs: Vec<String>,
+
+    /// Where to write the updated documents, `-` writes to stdout (with the
+    /// `http` feature, a URL posts them in batches)
+    pub output: String,
+
+    /// Format of the inputs
+    #[arg(long, visible_alias = "from", value_enum, default_value_t = InputFormat::Array)]
+    pub input_format: InputFormat,
+
+    /// Read the files of directories and of `.tar`, `.tar.gz`, `.tgz`,
+    /// `.tar.zst` and `.zip` inputs whose names end with SUFFIX (before `.gz`
+    /// or `.zst`)
+    #[arg(long, value_name = "SUFFIX", default_value = ".json")]
+    pub members: String,
+
+    /// Delimiter between the cells of `--input-format csv`, a single
+    /// character or `tab`
+    #[arg(long, value_name = "CHAR", default_value = ",", value_parser = parse_delimiter)]
+    pub input_delimiter: u8,
+
+    /// TOML file with the names and types of the columns of
+    /// `--input-format csv`, instead of the names in the header
+    #[arg(long, value_name = "FILE", value_parser = TableSchema::from_file)]
+    pub csv_schema: Option<TableSchema>,
+
+    /// The CSV input has no header row
+    #[arg(long, requires = "csv_schema")]
+    pub no_input_header: bool,
+
+    /// Set this top-level field in every document to the path of the input
+    /// file it was read from
+    #[arg(long, value_name = "FIELD")]
+    pub source_field: Option<String>,
+
+    /// Set a top-level field in every document, the value is parsed as JSON
+    /// if possible and used as a string otherwise (repeatable)
+    #[arg(long = "set", value_name = "FIELD=VALUE")]
+    pub updates: Vec<SetField>,
+
+    /// Set a field (dotted path or JSON pointer) to the value of a jq
+    /// expression over the document after `--set`, e.g.
+    /// 'displayName=.name.firstname + " " + .name.lastname' (repeatable, in
+    /// order)
+    #[arg(long = "derive", value_name = "FIELD=EXPR")]
+    pub derives: Vec<Derive>,
+
+    /// Move a field to a new path, e.g. lexiconId=id or entry.pos=entry.partOfSpeech,
+    /// where both are dotted paths or JSON pointers (repeatable)
+    #[arg(long = "rename", value_name = "OLD=NEW")]
+    pub renames: Vec<Rename>,
+
+    /// TOML or YAML file with remove, rename and set rules per lexicon, with
+    /// optional `when` conditions, applied before the other updates
+    #[arg(long, value_name = "FILE", value_parser = Rules::from_file)]
+    pub rules: Option<Rules>,
+
+    /// Apply the rules of this lexicon to all documents, instead of the rules
+    /// of the lexicon named by their `lexiconName`
+    #[arg(long, value_name = "NAME", requires = "rules")]
+    pub lexicon: Option<String>,
+
+    /// JSON array of lookup records, read into memory, to copy `--fields`
+    /// from into the documents with the same `--on` key, after the rules
+    #[arg(long, value_name = "FILE", requires_all = ["on", "enrich_fields"])]
+    pub enrich: Option<String>,
+
+    /// The key of `--enrich`, a field (dotted path or JSON pointer) of both
+    /// the documents and the records, or DOC=LOOKUP if they differ
+    #[arg(long, value_name = "KEY", requires = "enrich", value_parser = parse_join_key)]
+    pub on: Option<(FieldPath, FieldPath)>,
+
+    /// Fields of the records of `--enrich` to copy to the same paths in the
+    /// documents, separated by commas
+    #[arg(
+        long = "fields",
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        requires = "enrich"
+    )]
+    pub enrich_fields: Vec<FieldPath>,
+
+    /// Only apply `--remove`, `--rename`, `--set`, `--derive`, `--merge`,
+    /// `--patch` and `--transform` to the documents this jq condition
+    /// matches, e.g. '.lexiconName == "skbl"'; other documents are written
+    /// as they are
+    #[arg(long, value_name = "EXPR")]
+    pub when: Option<Jq>,
+
+    /// Delete a field, e.g. _id or senses.*.id, where * stands for every
+    /// element of an array (repeatable)
+    #[arg(long = "remove", value_name = "FIELD")]
+    pub removals: Vec<Remove>,
+
+    /// Normalize the strings to the Unicode normalization form nfc or nfd,
+    /// before the other updates
+    #[arg(long, value_name = "FORM")]
+    pub normalize: Option<NormalizationForm>,
+
+    /// Only normalize the strings in this field, e.g. senses.*.gloss
+    /// (repeatable)
+    #[arg(long = "normalize-field", value_name = "FIELD", requires = "normalize")]
+    pub normalize_fields: Vec<FieldPath>,
+
+    /// Only keep documents matching FIELD==VALUE, FIELD!=VALUE or FIELD
+    /// (present and not null), where FIELD is a dotted path or a JSON pointer
+    /// (repeatable, all must match)
+    #[arg(long = "filter", value_name = "EXPR")]
+    pub filters: Vec<Filter>,
+
+    /// JSON Merge Patch (RFC 7386) merged into every document after `--set`,
+    /// given as JSON or as @FILE, e.g. '{"lexiconName": "skbl2", "_id": null}'
+    /// (repeatable)
+    #[arg(long = "merge", value_name = "JSON")]
+    pub merges: Vec<MergePatch>,
+
+    /// JSON file with a JSON Patch (RFC 6902) applied to every document after
+    /// `--merge`; a document the patch fails on stops the conversion (repeatable)
+    #[arg(long = "patch", value_name = "FILE", value_parser = Patch::from_file)]
+    pub patches: Vec<Patch>,
+
+    /// jq filter run on every document after `--patch`, e.g.
+    /// '.lexiconName = "skbl2" | .lexiconOrder = 48'; each document it yields
+    /// is written
+    #[arg(long, value_name = "JQ")]
+    pub transform: Option<Jq>,
+
+    /// Set this top-level field of every document to the provenance of the
+    /// output: the time, the version of json-tool and its arguments, e.g.
+    /// _provenance
+    #[arg(long, value_name = "FIELD")]
+    pub provenance: Option<String>,
+
+    /// Set this field (dotted path or JSON pointer) of every document to a
+    /// UUID, a random one or, with `--id-from`, one from a hash, after
+    /// `--transform`
+    #[arg(long, value_name = "FIELD")]
+    pub assign_id: Option<FieldPath>,
+
+    /// Make the ids of `--assign-id` from the SHA-256 hash of these fields,
+    /// separated by commas, so the same values always give the same id, e.g.
+    /// lexiconName,_id
+    #[arg(
+        long,
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        requires = "assign_id"
+    )]
+    pub id_from: Vec<FieldPath>,
+
+    /// Only keep these fields of every document, separated by commas, e.g.
+    /// _id,entry.pos,senses.*.gloss, after `--transform`
+    #[arg(long, value_name = "FIELDS")]
+    pub select: Option<Select>,
+
+    /// Flatten nested objects into one object with joined keys, e.g.
+    /// {"entry": {"pos": "nn"}} into {"entry.pos": "nn"}, after all other
+    /// updates
+    #[arg(long)]
+    pub flatten: bool,
+
+    /// Split the keys of flat documents into nested objects, e.g.
+    /// {"entry.pos": "nn"} into {"entry": {"pos": "nn"}}, before all other
+    /// updates
+    #[arg(long)]
+    pub unflatten: bool,
+
+    /// Join the keys of `--flatten`, and split those of `--unflatten`, with
+    /// this separator
+    #[arg(long, value_name = "SEP", default_value = ".", requires = "flattening",
+        value_parser = clap::builder::NonEmptyStringValueParser::new())]
+    pub flatten_separator: String,
+
+    /// What `--flatten` and `--unflatten` do with arrays
+    #[arg(long, value_enum, default_value_t = FlattenArraysArg::Index, requires = "flattening")]
+    pub flatten_arrays: FlattenArraysArg,
+
+    /// Only keep one document per value of a field (dotted path or JSON
+    /// pointer), documents without the field are all kept
+    #[arg(long, value_name = "FIELD")]
+    pub dedup_by: Option<FieldPath>,
+
+    /// Which document to keep per value of `--dedup-by`
+    #[arg(long, value_enum, default_value_t = KeepArg::First, requires = "dedup_by")]
+    pub keep: KeepArg,
+
+    /// Sort the output by a field (dotted path or JSON pointer), keeping the
+    /// order of documents with equal values; when streaming, large inputs are
+    /// sorted in chunks in temporary files
+    #[arg(long, value_name = "FIELD")]
+    pub sort_by: Option<FieldPath>,
+
+    // The options of `SortArgs`, not flattened: clap leaves the group of
+    // arguments empty for a struct with flattened fields, so the top-level
+    // `Cli::convert` would never be set.
+    /// Keep at most about SIZE bytes of documents in memory when sorting or
+    /// shuffling large inputs, the rest in temporary files, e.g. 2G
+    #[arg(long, value_name = "SIZE", default_value_t = sort::DEFAULT_MEMORY, value_parser = parse_size)]
+    pub sort_memory: usize,
+
+    /// Write the temporary files of sorting or shuffling to this directory
+    /// instead of $TMPDIR or /tmp
+    #[arg(long, value_name = "DIR")]
+    pub temp_dir: Option<PathBuf>,
+
+    /// Keep only the N documents with the largest numbers at `--by`, largest
+    /// first, and of equal numbers the first; documents without a number
+    /// there are dropped
+    #[arg(long, value_name = "N", requires = "by")]
+    pub top: Option<usize>,
+
+    /// The field of `--top` (dotted path or JSON pointer)
+    #[arg(long, value_name = "FIELD", requires = "top")]
+    pub by: Option<FieldPath>,
+
+    /// Keep the documents with the smallest numbers with `--top`, smallest
+    /// first
+    #[arg(long, requires = "top")]
+    pub smallest: bool,
+
+    /// Keep only a uniform random sample of N documents, in input order
+    #[arg(long, value_name = "N")]
+    pub sample: Option<usize>,
+
+    /// Write the documents in a random order, in temporary files if they
+    /// are large
+    #[arg(long, conflicts_with_all = ["sort_by", "top"])]
+    pub shuffle: bool,
+
+    /// Seed of `--sample` and `--shuffle`, so the same seed picks the same
+    /// documents of the same input, in the same order
+    #[arg(long, value_name = "S", requires = "randomizing")]
+    pub seed: Option<u64>,
+
+    /// Fail on the first output document that doesn't match the JSON Schema
+    /// in this file
+    #[arg(long, value_name = "FILE", value_parser = Schema::from_file)]
+    pub schema: Option<Schema>,
+
+    /// Write the output to files of at most N documents each, named like
+    /// `out_0001.json` for the output `out.json`
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub split_size: Option<u64>,
+
+    /// Format of the output
+    #[arg(long, visible_alias = "to", value_enum, default_value_t = OutputFormat::Array)]
+    pub format: OutputFormat,
+
+    /// Elasticsearch index to write the documents to with `--format bulk`
+    #[arg(long, value_name = "NAME", required_if_eq("format", "bulk"))]
+    pub index: Option<String>,
+
+    /// Field with the id of each document for `--format bulk` (dotted path or
+    /// JSON pointer), ids are generated by Elasticsearch without it
+    #[arg(long, value_name = "FIELD", requires = "index")]
+    pub id_field: Option<FieldPath>,
+
+    /// Leave the `--id-field` out of the documents, as Elasticsearch doesn't
+    /// accept `_id` in a document
+    #[arg(long, requires = "id_field")]
+    pub remove_id: bool,
+
+    /// TOML file with the Arrow schema of `--format parquet` or `arrow`,
+    /// instead of the schema inferred from the first row group
+    #[cfg(feature = "arrow")]
+    #[arg(long, value_name = "FILE", value_parser = columnar::schema_from_file)]
+    pub arrow_schema: Option<SchemaRef>,
+
+    /// Number of documents per row group of `--format parquet`, or record
+    /// batch of `--format arrow`
+    #[cfg(feature = "arrow")]
+    #[arg(long, value_name = "N", default_value_t = columnar::ROW_GROUP_SIZE)]
+    pub row_group_size: usize,
+
+    /// Table of `--format sqlite` and `--input-format sqlite`
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "NAME", default_value = sqlite::TABLE)]
+    pub sqlite_table: String,
+
+    /// Column of `--format sqlite` with the values of a field, as NAME=FIELD
+    /// or FIELD (can be repeated)
+    #[cfg(feature = "sqlite")]
+    #[arg(long = "sqlite-column", value_name = "COLUMN")]
+    pub sqlite_columns: Vec<Column>,
+
+    /// Table (and column) to write a `COPY` statement for with `--format
+    /// copy`, e.g. 'lexicon (entry)', instead of only the rows
+    #[arg(long, value_name = "TABLE")]
+    pub copy_table: Option<String>,
+
+    /// Number of documents per request when the output is a URL
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "N", default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    pub batch_size: u64,
+
+    /// Header sent with every request when the output is a URL, as
+    /// 'NAME: VALUE' (can be repeated)
+    #[cfg(feature = "http")]
+    #[arg(long = "header", value_name = "HEADER", value_parser = upload::parse_header)]
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+
+    /// Number of requests sent at a time when the output is a URL
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub concurrency: usize,
+
+    /// Post at most N documents a second when the output is a URL
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "N", value_parser = parse_rate)]
+    pub rate: Option<f64>,
+
+    /// Post at most SIZE bytes a second when the output is a URL, e.g. 5M
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub byte_rate: Option<usize>,
+
+    /// Seconds to wait for a connection when the output is a URL
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "SECONDS", default_value = "10", value_parser = parse_seconds)]
+    pub connect_timeout: Duration,
+
+    /// Seconds a request may take when the output is a URL, from connecting
+    /// to reading the response
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "SECONDS", default_value = "300", value_parser = parse_seconds)]
+    pub timeout: Duration,
+
+    /// JSON Merge Patch merged into the metadata of `--format karp`, given as
+    /// JSON or as @FILE, e.g. '{"resource_id": "skbl2"}'
+    #[arg(long, value_name = "JSON")]
+    pub metadata: Option<MergePatch>,
+
+    /// Set this member of the metadata of `--format karp` to the provenance
+    /// of the output: the time, the version of json-tool and its arguments
+    #[arg(long, value_name = "NAME")]
+    pub metadata_provenance: Option<String>,
+
+    /// Pretty print the output
+    #[arg(long)]
+    pub pretty: bool,
+
+    /// Spaces per level when pretty printing
+    #[arg(long, value_name = "N", default_value_t = 2, requires = "pretty")]
+    pub indent: usize,
+
+    /// Write the floating point numbers of JSON outputs in this format,
+    /// whichever writer writes them
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with_all = ["raw", "transcode"])]
+    pub numbers: Option<NumberArg>,
+
+    /// Character encoding of the inputs, decoded to UTF-8 as they are read
+    #[arg(long, value_enum, value_name = "ENCODING", default_value_t = EncodingArg::Utf8)]
+    pub input_encoding: EncodingArg,
+
+    /// Bytes to read from the inputs at a time, e.g. 1M on a network
+    /// filesystem
+    #[arg(long, value_name = "SIZE", default_value_t = io::BUFFER_SIZE, value_parser = parse_size)]
+    pub read_buffer: usize,
+
+    /// Bytes to buffer before writing to the output
+    #[arg(long, value_name = "SIZE", default_value_t = io::BUFFER_SIZE, value_parser = parse_size)]
+    pub write_buffer: usize,
+
+    /// Compression level for `.gz` (0-9) and `.zst` (1-22) output
+    #[arg(long, value_name = "LEVEL")]
+    pub compression_level: Option<i32>,
+
+    /// Start the outputs with a UTF-8 byte order mark, for Windows tools that
+    /// expect one (text formats only)
+    #[arg(long)]
+    pub bom: bool,
+
+    /// Pass the documents through as raw JSON, only parsing the ones that
+    /// `--rename` and `--remove` may change (JSON arrays only)
+    #[arg(long, conflicts_with_all = [
+        "input_format", "source_field", "updates", "rules", "filters", "merges", "patches",
+        "transform", "dedup_by", "sort_by", "schema", "split_size", "format", "pretty", "parser",
+        "lenient", "check", "dry_run", "normalize", "flatten",
+        "unflatten", "select", "derives", "when",
+        "assign_id", "provenance", "enrich", "top", "sample", "shuffle",
+    ])]
+    pub raw: bool,
+
+    /// Copy the documents token by token, only reading the objects that
+    /// `--rename` moves fields within into memory (JSON arrays only)
+    #[arg(long, conflicts_with_all = [
+        "input_format", "source_field", "rules", "filters", "merges", "patches", "transform",
+        "dedup_by", "sort_by", "schema", "split_size", "format", "pretty", "parser", "lenient",
+        "check", "dry_run", "raw", "normalize", "flatten",
+        "unflatten", "select", "derives", "when",
+        "assign_id", "provenance", "enrich", "top", "sample", "shuffle",
+    ])]
+    pub transcode: bool,
+
+    /// Skip the documents that can't be deserialized or updated, with a
+    /// warning for each, instead of stopping at the first (JSON arrays and
+    /// scrolls only)
+    #[arg(long)]
+    pub lenient: bool,
+
+    /// Fail on a document with the same key twice in an object, naming the
+    /// document and the key, instead of keeping the last value; with
+    /// `--lenient` or `--check` such documents are skipped (JSON arrays and
+    /// scrolls only)
+    #[arg(long, conflicts_with_all = ["raw", "transcode"])]
+    pub strict: bool,
+
+    /// Write the output to a temporary file next to it, renamed over it when
+    /// done, so it can also be an input and is never left half written
+    #[arg(long, conflicts_with_all = ["split_size", "check", "dry_run"])]
+    pub in_place: bool,
+
+    /// With `--in-place`, keep the original output with SUFFIX added to its
+    /// name (`.bak` by default), where `{time}` is replaced by the UTC time,
+    /// e.g. `--backup=.{time}.bak`
+    #[arg(
+        long,
+        value_name = "SUFFIX",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ".bak",
+        requires = "in_place"
+    )]
+    pub backup: Option<String>,
+
+    /// Save how far the conversion got to FILE every 10 000 documents, to
+    /// continue from there with `--resume` (JSON array output files only)
+    #[arg(long, value_name = "FILE", conflicts_with_all = [
+        "split_size", "in_place", "dedup_by", "sort_by", "top", "sample", "shuffle", "format",
+        "pretty", "check", "dry_run", "raw", "transcode", "errors",
+    ])]
+    pub checkpoint: Option<String>,
+
+    /// Continue from the `--checkpoint` of an interrupted run, keeping the
+    /// output written up to it, or start from the beginning if there is none
+    #[arg(long, requires = "checkpoint")]
+    pub resume: bool,
+
+    /// Print the changes of the updates to the first N documents (10 by
+    /// default) instead of writing them
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "10", conflicts_with = "check")]
+    pub dry_run: Option<usize>,
+
+    /// Read and update all documents without writing them (the output is
+    /// not created, give it as `-`), and fail with a summary of the problems
+    #[arg(long)]
+    pub check: bool,
+
+    /// Write each document skipped with `--lenient` or `--check` to FILE, as
+    /// a line of JSON with its text and error
+    #[arg(long, value_name = "FILE", requires = "skipping")]
+    pub errors: Option<String>,
+
+    /// Parser for the documents
+    #[arg(long, value_enum, default_value_t = ParserArg::Serde)]
+    pub parser: ParserArg,
+
+    /// Show a progress bar on stderr
+    #[arg(long)]
+    pub progress: bool,
+
+    /// Number of threads for updating documents, 0 uses one per CPU
+    #[arg(long, default_value_t = 0)]
+    pub threads: usize,
+
+    /// Write the documents in the order their updates finish, instead of
+    /// holding back the ones done before those preceding them
+    #[arg(long, conflicts_with_all = ["raw", "transcode", "checkpoint"])]
+    pub unordered: bool,
+
+    /// Convert the inputs N at a time, each to a file of the same name in
+    /// the output directory, instead of all of them to one output
+    #[arg(long, value_name = "N", conflicts_with_all = [
+        "split_size", "in_place", "check", "dry_run", "raw", "transcode", "checkpoint", "errors",
+    ])]
+    pub jobs: Option<usize>,
+
+    /// Keep running and convert again whenever an input changes, checked
+    /// twice a second, until interrupted
+    #[arg(long, conflicts_with_all = ["in_place", "checkpoint", "dry_run"])]
+    pub watch: bool,
+}
+
+impl ConvertArgs {
+    /// The options of sorting and shuffling in temporary files.
+    pub fn sorting(&self) -> SortArgs {
+        SortArgs {
+            sort_memory: self.sort_memory,
+            temp_dir: self.temp_dir.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// A JSON array of documents
+    Array,
+    /// A JSON5 array of documents, JSON with comments, trailing commas,
+    /// unquoted keys and single-quoted strings
+    Json5,
+    /// JSON values one after another, with or without whitespace between
+    /// them, such as newline-delimited JSON; every value is a document
+    Concatenated,
+    /// An Elasticsearch search or scroll response, the documents are the
+    /// `_source` of every hit
+    Scroll,
+    /// An Elasticsearch bulk file, the documents of its index and create
+    /// actions
+    Bulk,
+    /// A Karp export, the documents are its entries
+    Karp,
+    /// A CSV or TSV table with a header, every row is a document
+    Csv,
+    /// MessagePack documents, one after another
+    Msgpack,
+    /// A CBOR sequence of documents
+    Cbor,
+    /// A YAML sequence of documents, read at once
+    Yaml,
+    /// An LMF XML lexicon, every LexicalEntry element is a document
+    Lmf,
+    /// The documents of an SQLite table written with `--format sqlite`
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// A JSON array of documents
+    Array,
+    /// Newline-delimited action and document lines for the Elasticsearch
+    /// bulk API
+    Bulk,
+    /// A Karp export, with the metadata of the first input if it is one
+    Karp,
+    /// An RFC 7464 JSON text sequence, every document after a record
+    /// separator and followed by a line feed
+    JsonSeq,
+    /// MessagePack documents, one after another
+    Msgpack,
+    /// A CBOR sequence of documents
+    Cbor,
+    /// A YAML sequence of documents
+    Yaml,
+    /// Rows for PostgreSQL `COPY ... FROM STDIN`, with the document as the
+    /// only column
+    Copy,
+    /// A Parquet file
+    #[cfg(feature = "parquet")]
+    Parquet,
+    /// An Arrow IPC file (Feather version 2)
+    #[cfg(feature = "arrow")]
+    Arrow,
+    /// A table of an SQLite database, a row per document
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ParserArg {
+    /// serde_json, or struson when streaming
+    Serde,
+    /// simd-json
+    #[cfg(feature = "simd")]
+    Simd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum NumberArg {
+    /// The shortest digits, as serde_json writes them, e.g. 1.0 and 1e-7
+    Shortest,
+    /// As in JavaScript and RFC 8785, e.g. 1 and 1e-7
+    Canonical,
+}
+
+impl From<NumberArg> for NumberFormat {
+    fn from(numbers: NumberArg) -> Self {
+        match numbers {
+            NumberArg::Shortest => NumberFormat::Shortest,
+            NumberArg::Canonical => NumberFormat::Canonical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EncodingArg {
+    #[value(name = "utf-8", alias = "utf8")]
+    Utf8,
+    /// ISO-8859-1
+    #[value(name = "latin1", alias = "iso-8859-1")]
+    Latin1,
+    /// Latin-1 with `€`, `“` and other characters for the C1 control codes,
+    /// as exported on Windows
+    #[value(name = "windows-1252", alias = "cp1252")]
+    Windows1252,
+}
+
+impl From<EncodingArg> for Encoding {
+    fn from(encoding: EncodingArg) -> Self {
+        match encoding {
+            EncodingArg::Utf8 => Encoding::Utf8,
+            EncodingArg::Latin1 => Encoding::Latin1,
+            EncodingArg::Windows1252 => Encoding::Windows1252,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FlattenArraysArg {
+    /// Elements keyed by their index, e.g. senses.0.gloss
+    Index,
+    /// Arrays as values, and numbers as object keys
+    Keep,
+}
+
+impl From<FlattenArraysArg> for FlattenArrays {
+    fn from(arrays: FlattenArraysArg) -> Self {
+        match arrays {
+            FlattenArraysArg::Index => FlattenArrays::Index,
+            FlattenArraysArg::Keep => FlattenArrays::Keep,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KeepArg {
+    First,
+    Last,
+}
+
+impl From<KeepArg> for Keep {
+    fn from(keep: KeepArg) -> Self {
+        match keep {
+            KeepArg::First => Keep::First,
+            KeepArg::Last => Keep::Last,
+        }
+    }
+}
+
+impl From<ParserArg> for json_iter::Parser {
+    fn from(parser: ParserArg) -> Self {
+        match parser {
+            ParserArg::Serde => json_iter::Parser::Serde,
+            #[cfg(feature = "simd")]
+            ParserArg::Simd => json_iter::Parser::Simd,
+        }
+    }
+}
@@ -0,0 +1,1670 @@
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::SystemTime;
+
+use rand::seq::SliceRandom;
+use rayon::prelude::*;
+use serde_json::value::RawValue;
+use serde_json::{json, Value};
+use struson::reader::JsonReader;
+use struson::writer::{JsonStreamWriter, JsonWriter};
+
+use read_json_in_rust::archive::{Archive, ArchiveFormat};
+use read_json_in_rust::bulk::{self, BulkWriter};
+use read_json_in_rust::cbor::{self, CborWriter};
+#[cfg(feature = "arrow")]
+use read_json_in_rust::columnar::IpcWriter;
+#[cfg(feature = "parquet")]
+use read_json_in_rust::columnar::ParquetWriter;
+use read_json_in_rust::copy::CopyWriter;
+use read_json_in_rust::dedup::{self, Dedup, Keep};
+use read_json_in_rust::diff;
+use read_json_in_rust::in_memory;
+#[cfg(feature = "http")]
+use read_json_in_rust::io::Compression;
+use read_json_in_rust::io::{
+    self, ByteCounter, Input, Output, ReadOptions, Replacement, WriteOptions,
+};
+use read_json_in_rust::json5::Json5Reader;
+use read_json_in_rust::json_iter::{
+    self, ArrayWriter, JsonIterError, LinesWriter, Loader, RawArrayWriter, SeqWriter,
+};
+use read_json_in_rust::karp::{self, ExportWriter, Metadata};
+use read_json_in_rust::lmf;
+use read_json_in_rust::msgpack::{self, MessagePackWriter};
+use read_json_in_rust::number::NumberFormat;
+use read_json_in_rust::provenance::{self, Provenance};
+use read_json_in_rust::rules::RulesError;
+use read_json_in_rust::sample::{self, Extreme};
+use read_json_in_rust::scroll;
+use read_json_in_rust::sort;
+#[cfg(feature = "sqlite")]
+use read_json_in_rust::sqlite::{self, SqliteWriter};
+use read_json_in_rust::table::TableOptions;
+use read_json_in_rust::transcode::Transcoder;
+use read_json_in_rust::transform::{
+    AffectedKeys, AssignId, DocumentTransform, Enrich, Flatten, IdKind, Normalize, TransformError,
+    Transforms, Unflatten,
+};
+#[cfg(feature = "http")]
+use read_json_in_rust::upload::{RateLimit, UploadOptions, Uploader};
+use read_json_in_rust::yaml::{self, YamlWriter};
+
+use crate::checkpoint::{self, Checkpoint};
+use crate::cli::{ConvertArgs, EncodingArg, InMemoryArgs, InputFormat, OutputFormat};
+use crate::diff::write_fields;
+use crate::progress::Progress;
+
+/// Which documents `--top` keeps.
+fn extreme(args: &ConvertArgs) -> Extreme {
+    match args.smallest {
+        true => Extreme::Smallest,
+        false => Extreme::Largest,
+    }
+}
+
+fn keep(args: &ConvertArgs, doc: &Value) -> bool {
+    args.filters.iter().all(|filter| filter.matches(doc))
+}
+
+fn check_lexicon(args: &ConvertArgs) -> Result<(), RulesError> {
+    if let (Some(rules), Some(name)) = (&args.rules, &args.lexicon) {
+        rules.get(name)?;
+    }
+    Ok(())
+}
+
+/// The updates of a run that are the same for every document, built once
+/// before the documents are read.
+struct Updates {
+    /// The updates from `--unflatten` to `--enrich`, of every document.
+    before: Transforms,
+    /// The updates from `--remove` to `--patch`, of the documents that
+    /// `--when` matches, which are then run through `--transform`.
+    updates: Transforms,
+    /// The updates from `--assign-id` to `--flatten`, of every output
+    /// document.
+    after: Transforms,
+    /// The provenance of this run, for `--provenance` and
+    /// `--metadata-provenance`.
+    provenance: Value,
+}
+
+impl Updates {
+    /// The updates of `args`, with the lookup records of `--enrich` loaded.
+    fn new(args: &ConvertArgs) -> Result<Self, Box<dyn Error>> {
+        let mut before = Transforms::new();
+        if args.unflatten {
+            before.push(
+                Unflatten::new()
+                    .separator(args.flatten_separator.as_str())
+                    .arrays(args.flatten_arrays.into()),
+            );
+        }
+        if let Some(form) = args.normalize {
+            before.push(
+                args.normalize_fields
+                    .iter()
+                    .fold(Normalize::new(form), |normalize, path| {
+                        normalize.field(path.clone())
+                    }),
+            );
+        }
+        if let Some(rules) = &args.rules {
+            match &args.lexicon {
+                Some(name) => before.push(rules.get(name)?.clone()),
+                None => before.push(rules.clone()),
+            }
+        }
+        if let Some(enrich) = load_enrich(args)? {
+            before.push(enrich);
+        }
+
+        let mut updates = Transforms::new();
+        updates.extend(args.removals.iter().cloned());
+        updates.extend(args.renames.iter().cloned());
+        updates.extend(args.updates.iter().cloned());
+        updates.extend(args.derives.iter().cloned());
+        updates.extend(args.merges.iter().cloned());
+        updates.extend(args.patches.iter().cloned());
+
+        let mut after = Transforms::new();
+        if let Some(field) = &args.assign_id {
+            let kind = match args.id_from.is_empty() {
+                true => IdKind::Random,
+                false => IdKind::Hash(args.id_from.clone()),
+            };
+            after.push(AssignId::new(field.clone(), kind));
+        }
+        if let Some(select) = &args.select {
+            after.push(select.clone());
+        }
+        let mut provenance = Provenance::new("json-tool", std::env::args().skip(1));
+        if let Some(rules) = &args.rules {
+            provenance = match &args.lexicon {
+                Some(name) => provenance.rules(json!({ name: rules.get(name)?.to_value() })),
+                None => provenance.rules(rules.to_value()),
+            };
+        }
+        let provenance = provenance.to_value();
+        if let Some(field) = args.provenance.clone() {
+            let provenance = provenance.clone();
+            after.push(move |doc: &mut Value| {
+                if let Some(doc) = doc.as_object_mut() {
+                    doc.insert(field.clone(), provenance.clone());
+                }
+                Ok(())
+            });
+        }
+        if args.flatten {
+            after.push(
+                Flatten::new()
+                    .separator(args.flatten_separator.as_str())
+                    .arrays(args.flatten_arrays.into()),
+            );
+        }
+        Ok(Self {
+            before,
+            updates,
+            after,
+            provenance,
+        })
+    }
+
+    fn apply(&self, args: &ConvertArgs, mut doc: Value) -> Result<Vec<Value>, TransformError> {
+        self.before.apply(&mut doc)?;
+        let matches = match &args.when {
+            Some(when) => when.matches(&doc)?,
+            None => true,
+        };
+        let mut docs = match matches {
+            true => {
+                self.updates.apply(&mut doc)?;
+                match &args.transform {
+                    Some(jq) => jq.apply(doc)?,
+                    None => vec![doc],
+                }
+            }
+            false => vec![doc],
+        };
+        for doc in &mut docs {
+            self.after.apply(doc)?;
+        }
+        Ok(docs)
+    }
+}
+
+/// Load the lookup records of `--enrich`, if any, before the documents.
+fn load_enrich(args: &ConvertArgs) -> Result<Option<Enrich>, Box<dyn Error>> {
+    let (Some(path), Some((on, lookup_on))) = (&args.enrich, &args.on) else {
+        return Ok(None);
+    };
+    let enrich = Enrich::new(on.clone(), args.enrich_fields.clone())
+        .lookup_on(lookup_on.clone())
+        .load(path)
+        .map_err(|err| format!("{}: {}", path, err))?;
+    if enrich.is_empty() {
+        eprintln!("warning: no records with the key {} in {}", lookup_on, path);
+    }
+    Ok(Some(enrich))
+}
+
+fn tag_source(args: &ConvertArgs, path: &str, doc: &mut Value) {
+    if let (Some(field), Some(object)) = (&args.source_field, doc.as_object_mut()) {
+        object.insert(field.clone(), path.into());
+    }
+}
+
+/// Start the progress bar if asked for, with the bytes read from all inputs
+/// counted by the returned counter.
+fn start_progress(args: &ConvertArgs) -> std::io::Result<(Option<ByteCounter>, Option<Progress>)> {
+    if !args.progress {
+        return Ok((None, None));
+    }
+    let mut total = Some(0);
+    for path in &args.inputs {
+        total = match (total, io::input_size(path)?) {
+            (Some(total), Some(size)) => Some(total + size),
+            _ => None,
+        };
+    }
+    let counter = ByteCounter::new();
+    let progress = Progress::start(counter.clone(), total);
+    Ok((Some(counter), Some(progress)))
+}
+
+fn read_options(args: &ConvertArgs, counter: Option<&ByteCounter>) -> ReadOptions {
+    let options = ReadOptions::new()
+        .buffer_size(args.read_buffer)
+        .encoding(args.input_encoding.into());
+    match counter {
+        Some(counter) => options.counter(counter.clone()),
+        None => options,
+    }
+}
+
+/// `err` with the name of the file, as there may be many inputs.
+fn named(path: &str, err: std::io::Error) -> std::io::Error {
+    std::io::Error::new(err.kind(), format!("{}: {}", path, err))
+}
+
+fn open_input(
+    args: &ConvertArgs,
+    path: &str,
+    counter: Option<&ByteCounter>,
+) -> std::io::Result<Input> {
+    read_options(args, counter)
+        .open(path)
+        .map_err(|err| named(path, err))
+}
+
+/// The files of the archive at `path` whose names end with `--members`,
+/// named `ARCHIVE:FILE` for `--source-field` and errors.
+fn archive_members(
+    args: &ConvertArgs,
+    path: &str,
+    counter: Option<&ByteCounter>,
+) -> Result<impl Iterator<Item = Result<(String, Input), JsonIterError>>, JsonIterError> {
+    #[cfg(feature = "sqlite")]
+    if args.input_format == InputFormat::Sqlite {
+        return Err(std::io::Error::other("can't read SQLite databases from archives").into());
+    }
+    let archive =
+        Archive::open(path, &read_options(args, counter)).map_err(|err| named(path, err))?;
+    let suffix = args.members.clone();
+    let path = path.to_string();
+    Ok(archive.filter_map(move |member| match member {
+        Ok(member) => {
+            let name = member.name.strip_suffix(".gz").unwrap_or(&member.name);
+            let name = name.strip_suffix(".zst").unwrap_or(name);
+            name.ends_with(&suffix)
+                .then(|| Ok((format!("{}:{}", path, member.name), member.reader)))
+        }
+        Err(err) => Some(Err(named(&path, err).into())),
+    }))
+}
+
+type DocumentResults<'a> = Box<dyn Iterator<Item = Result<Value, JsonIterError>> + 'a>;
+
+/// The documents of the input at `path` in the format of `--input-format`.
+fn load(
+    args: &ConvertArgs,
+    loader: &Loader,
+    path: &str,
+    counter: Option<&ByteCounter>,
+) -> Result<DocumentResults<'static>, JsonIterError> {
+    #[cfg(feature = "sqlite")]
+    if args.input_format == InputFormat::Sqlite {
+        if path == io::STDIO {
+            return Err(std::io::Error::other("can't read an SQLite database from stdin").into());
+        }
+        if io::is_url(path) || io::is_s3(path) {
+            let message = format!("can't read an SQLite database from {}, only files", path);
+            return Err(std::io::Error::other(message).into());
+        }
+        return Ok(Box::new(sqlite::load(path, &args.sqlite_table)?));
+    }
+    load_from(args, loader, open_input(args, path, counter)?)
+}
+
+/// The documents read from `input` in the format of `--input-format`.
+fn load_from(
+    args: &ConvertArgs,
+    loader: &Loader,
+    input: Input,
+) -> Result<DocumentResults<'static>, JsonIterError> {
+    Ok(match args.input_format {
+        InputFormat::Array => Box::new(loader.from_reader(input)?),
+        InputFormat::Json5 => Box::new(loader.from_reader(Json5Reader::new(input))?),
+        InputFormat::Concatenated => Box::new(json_iter::load_concatenated_from_reader(input)),
+        InputFormat::Scroll => Box::new(scroll::load_sources_from_reader(loader, input)?),
+        InputFormat::Bulk => Box::new(bulk::load_bulk_from_reader(input)),
+        InputFormat::Karp => Box::new(karp::load_from_reader(input)?),
+        InputFormat::Csv => {
+            let mut options = TableOptions::new()
+                .delimiter(args.input_delimiter)
+                .header(!args.no_input_header);
+            if let Some(schema) = &args.csv_schema {
+                options = options.schema(schema.clone());
+            }
+            Box::new(options.reader(input)?)
+        }
+        InputFormat::Msgpack => Box::new(msgpack::load_from_reader(input)),
+        InputFormat::Cbor => Box::new(cbor::load_from_reader(input)),
+        InputFormat::Lmf => Box::new(lmf::load_from_reader(input)),
+        #[cfg(feature = "sqlite")]
+        InputFormat::Sqlite => unreachable!("SQLite to be opened by path"),
+        InputFormat::Yaml => Box::new(yaml::load_from_reader(input)?.into_iter().map(Ok)),
+    })
+}
+
+/// Add the documents of the input `name` to `docs`, tagged with
+/// `--source-field`.
+fn load_in_memory(
+    args: &ConvertArgs,
+    name: &str,
+    input: Input,
+    docs: &mut Vec<Value>,
+) -> Result<(), Box<dyn Error>> {
+    let start = docs.len();
+    if args.input_format == InputFormat::Array && !args.strict {
+        docs.extend(in_memory::load_from_reader_with(input, args.parser.into())?);
+    } else {
+        // A strict loader streams the array, to check each document.
+        let loader = Loader::new().parser(args.parser.into()).strict(args.strict);
+        for doc in load_from(args, &loader, input)? {
+            docs.push(doc?);
+        }
+    }
+    for doc in &mut docs[start..] {
+        tag_source(args, name, doc);
+    }
+    Ok(())
+}
+
+/// The documents of all inputs, each input is opened when the previous one
+/// is done.
+/// The files of archives are read as inputs of their own.
+///
+/// The documents skipped with `--lenient` or `--check` are left out and
+/// handed to `skipped`.
+fn documents<'a>(
+    args: &'a ConvertArgs,
+    counter: Option<&'a ByteCounter>,
+    skipped: &'a Skipped,
+) -> DocumentResults<'a> {
+    let loader = Loader::new()
+        .parser(args.parser.into())
+        .lenient(args.lenient || args.check)
+        .strict(args.strict);
+    Box::new(
+        args.inputs
+            .iter()
+            .flat_map(move |path| -> DocumentResults<'a> {
+                if ArchiveFormat::from_path(path).is_none() {
+                    let docs = load(args, &loader, path, counter);
+                    return input_documents(args, path.clone(), docs, skipped);
+                }
+                let loader = loader.clone();
+                match archive_members(args, path, counter) {
+                    Ok(members) => Box::new(members.flat_map(move |member| match member {
+                        Ok((name, input)) => {
+                            input_documents(args, name, load_from(args, &loader, input), skipped)
+                        }
+                        Err(err) => Box::new(std::iter::once(Err(err))),
+                    })),
+                    Err(err) => Box::new(std::iter::once(Err(err))),
+                }
+            }),
+    )
+}
+
+/// The documents of the input `name`, tagged with `--source-field`.
+fn input_documents<'a>(
+    args: &'a ConvertArgs,
+    name: String,
+    docs: Result<DocumentResults<'static>, JsonIterError>,
+    skipped: &'a Skipped,
+) -> DocumentResults<'a> {
+    match docs {
+        Ok(docs) => Box::new(docs.filter_map(move |doc| match doc {
+            Ok(mut doc) => {
+                tag_source(args, &name, &mut doc);
+                Some(Ok(doc))
+            }
+            Err(err @ JsonIterError::Skipped { .. }) => {
+                skipped.skip_document(&name, err);
+                None
+            }
+            Err(err @ JsonIterError::MissingComma { .. }) => {
+                skipped.missing_comma(&name, err);
+                None
+            }
+            Err(err) => Some(Err(err)),
+        })),
+        Err(err) => Box::new(std::iter::once(Err(err))),
+    }
+}
+
+/// The documents skipped with `--lenient` or `--check`, counted and written
+/// to `--errors`.
+struct Skipped {
+    /// Documents that couldn't be read.
+    read: AtomicU64,
+    /// Documents that couldn't be updated.
+    updated: AtomicU64,
+    /// Commas missing between documents, which are read nonetheless.
+    commas: AtomicU64,
+    errors: Mutex<Option<LinesWriter<Output>>>,
+    /// The first error writing to `--errors`, after which nothing more is
+    /// written.
+    failed: Mutex<Option<JsonIterError>>,
+}
+
+impl Skipped {
+    fn new(args: &ConvertArgs) -> std::io::Result<Self> {
+        let errors = match &args.errors {
+            Some(path) => Some(LinesWriter::new(write_options(args).create(path)?)),
+            None => None,
+        };
+        Ok(Self {
+            read: AtomicU64::new(0),
+            updated: AtomicU64::new(0),
+            commas: AtomicU64::new(0),
+            errors: Mutex::new(errors),
+            failed: Mutex::new(None),
+        })
+    }
+
+    fn total(&self) -> u64 {
+        self.read.load(Ordering::Relaxed) + self.updated.load(Ordering::Relaxed)
+    }
+
+    /// Warn about a skipped document and write `record` to `--errors`.
+    fn skip(&self, message: impl fmt::Display, record: Value) {
+        eprintln!("warning: {}", message);
+        let mut errors = self.errors.lock().expect("no panic while writing");
+        if let Some(Err(err)) = errors.as_mut().map(|errors| errors.write(&record)) {
+            *errors = None;
+            *self.failed.lock().expect("no panic while writing") = Some(err);
+        }
+    }
+
+    /// Skip the document of `err`, read from the input `path`.
+    fn skip_document(&self, path: &str, err: JsonIterError) {
+        let JsonIterError::Skipped {
+            document,
+            offset,
+            text,
+            source,
+        } = &err
+        else {
+            unreachable!("a skipped document");
+        };
+        let record = json!({
+            "input": path,
+            "document": document,
+            "offset": offset,
+            "error": source.to_string(),
+            "text": text,
+        });
+        self.read.fetch_add(1, Ordering::Relaxed);
+        self.skip(format_args!("{}: {}", path, err), record);
+    }
+
+    /// Warn about the missing comma of `err`, read from the input `path`.
+    fn missing_comma(&self, path: &str, err: JsonIterError) {
+        let JsonIterError::MissingComma { document, offset } = &err else {
+            unreachable!("a missing comma");
+        };
+        let record = json!({
+            "input": path,
+            "document": document,
+            "offset": offset,
+            "error": "missing comma",
+        });
+        self.commas.fetch_add(1, Ordering::Relaxed);
+        self.skip(format_args!("{}: {}", path, err), record);
+    }
+
+    /// Skip the document `doc` that couldn't be updated.
+    fn skip_update(&self, err: TransformError, doc: Option<Value>) {
+        let mut record = json!({ "error": err.to_string() });
+        if let Some(doc) = doc {
+            record["text"] = doc.to_string().into();
+        }
+        self.updated.fetch_add(1, Ordering::Relaxed);
+        self.skip(format_args!("skipped a document: {}", err), record);
+    }
+
+    /// Finish writing to `--errors`.
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        if let Some(err) = self.failed.into_inner().expect("no panic while writing") {
+            return Err(format!("failed to write the skipped documents: {}", err).into());
+        }
+        if let Some(errors) = self.errors.into_inner().expect("no panic while writing") {
+            errors.finish()?.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Documents waiting between the threads that read, update and write them.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Fail on the first output document that doesn't match `--schema`.
+fn check_schema(args: &ConvertArgs, index: usize, doc: &Value) -> Result<(), Box<dyn Error>> {
+    let Some(schema) = &args.schema else {
+        return Ok(());
+    };
+    if schema.is_valid(doc) {
+        return Ok(());
+    }
+    let mut message = format!("output document {} doesn't match the schema", index + 1);
+    for violation in schema.violations(doc) {
+        message.push_str(&format!("\n  {}", violation));
+    }
+    Err(message.into())
+}
+
+fn check_format(args: &ConvertArgs) -> Result<(), Box<dyn Error>> {
+    if args.pretty && args.format != OutputFormat::Array {
+        return Err("--pretty only works with --format array".into());
+    }
+    let text = matches!(
+        args.format,
+        OutputFormat::Array
+            | OutputFormat::Bulk
+            | OutputFormat::Karp
+            | OutputFormat::Yaml
+            | OutputFormat::Copy
+    );
+    if args.bom && !text {
+        return Err("--bom only works with --format array, bulk, karp, yaml and copy".into());
+    }
+    #[cfg(feature = "http")]
+    if io::is_url(&args.output) && args.split_size.is_some() {
+        return Err("--split-size needs an output file, use --batch-size for a URL".into());
+    }
+    #[cfg(all(feature = "http", feature = "sqlite"))]
+    if io::is_url(&args.output) && args.format == OutputFormat::Sqlite {
+        return Err("--format sqlite needs an output file, not a URL".into());
+    }
+    #[cfg(feature = "sqlite")]
+    if io::is_s3(&args.output) && args.format == OutputFormat::Sqlite {
+        return Err("--format sqlite needs an output file, not an object".into());
+    }
+    check_in_place(args)
+}
+
+fn check_in_place(args: &ConvertArgs) -> Result<(), Box<dyn Error>> {
+    let output = &args.output;
+    if args.in_place && (output == io::STDIO || io::is_url(output) || io::is_s3(output)) {
+        return Err("--in-place needs an output file".into());
+    }
+    Ok(())
+}
+
+/// `time` in UTC as `20261014T053900Z`, for `{time}` in `--backup`.
+fn timestamp(time: SystemTime) -> String {
+    provenance::rfc3339(time).replace(['-', ':'], "")
+}
+
+/// Write the file `path` with `write`, or with `--in-place` write a
+/// temporary file and rename it over `path` if that succeeds.
+fn write_file<F>(args: &ConvertArgs, path: &str, write: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&str) -> Result<(), Box<dyn Error>>,
+{
+    if !args.in_place {
+        return write(path);
+    }
+    let mut replacement = Replacement::new(path)?;
+    if let Some(suffix) = &args.backup {
+        let suffix = suffix.replace("{time}", &timestamp(SystemTime::now()));
+        replacement = replacement.backup(format!("{}{}", path, suffix));
+    }
+    write(replacement.temp_path())?;
+    Ok(replacement.persist()?)
+}
+
+fn check_split(args: &ConvertArgs) -> Result<(), Box<dyn Error>> {
+    if args.output == io::STDIO {
+        return Err("--split-size needs an output file, not stdout".into());
+    }
+    Ok(())
+}
+
+enum Writer {
+    Array(ArrayWriter<Output>),
+    Bulk(BulkWriter<Output>),
+    Seq(SeqWriter<Output>),
+    Karp(ExportWriter<Output>),
+    MessagePack(MessagePackWriter<Output>),
+    Cbor(CborWriter<Output>),
+    Yaml(YamlWriter<Output>),
+    Copy(CopyWriter<Output>),
+    #[cfg(feature = "parquet")]
+    Parquet(ParquetWriter<Output>),
+    #[cfg(feature = "arrow")]
+    Arrow(IpcWriter<Output>),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteWriter),
+}
+
+impl Writer {
+    fn write(&mut self, doc: &Value) -> Result<(), Box<dyn Error>> {
+        match self {
+            Writer::Array(writer) => writer.write(doc)?,
+            Writer::Bulk(writer) => writer.write(doc)?,
+            Writer::Seq(writer) => writer.write(doc)?,
+            Writer::Karp(writer) => writer.write(doc)?,
+            Writer::MessagePack(writer) => writer.write(doc)?,
+            Writer::Cbor(writer) => writer.write(doc)?,
+            Writer::Yaml(writer) => writer.write(doc)?,
+            Writer::Copy(writer) => writer.write(doc)?,
+            #[cfg(feature = "parquet")]
+            Writer::Parquet(writer) => writer.write(doc)?,
+            #[cfg(feature = "arrow")]
+            Writer::Arrow(writer) => writer.write(doc)?,
+            #[cfg(feature = "sqlite")]
+            Writer::Sqlite(writer) => writer.write(doc)?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        match self {
+            Writer::Array(writer) => writer.finish()?.finish()?,
+            Writer::Bulk(writer) => writer.finish()?.finish()?,
+            Writer::Seq(writer) => writer.finish()?.finish()?,
+            Writer::Karp(writer) => writer.finish()?.finish()?,
+            Writer::MessagePack(writer) => writer.finish()?.finish()?,
+            Writer::Cbor(writer) => writer.finish()?.finish()?,
+            Writer::Yaml(writer) => writer.finish()?.finish()?,
+            Writer::Copy(writer) => writer.finish()?.finish()?,
+            #[cfg(feature = "parquet")]
+            Writer::Parquet(writer) => writer.finish()?.finish()?,
+            #[cfg(feature = "arrow")]
+            Writer::Arrow(writer) => writer.finish()?.finish()?,
+            #[cfg(feature = "sqlite")]
+            Writer::Sqlite(writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
+
+/// The metadata for `--format karp`: that of the first input if it is a Karp
+/// export, with `--metadata` merged in.
+fn export_metadata(args: &ConvertArgs, updates: &Updates) -> Result<Metadata, Box<dyn Error>> {
+    if args.format != OutputFormat::Karp {
+        return Ok(Metadata::new());
+    }
+    let mut metadata = Value::Object(Metadata::new());
+    if args.input_format == InputFormat::Karp {
+        let path = &args.inputs[0];
+        if path == io::STDIO {
+            return Err("can't read the metadata of a Karp export twice from stdin".into());
+        }
+        metadata = Value::Object(karp::load_metadata(path)?);
+    }
+    if let Some(patch) = &args.metadata {
+        patch.apply(&mut metadata)?;
+    }
+    let Value::Object(mut metadata) = metadata else {
+        return Err("--metadata must leave the metadata an object".into());
+    };
+    if let Some(field) = &args.metadata_provenance {
+        metadata.insert(field.clone(), updates.provenance.clone());
+    }
+    Ok(metadata)
+}
+
+fn write_options(args: &ConvertArgs) -> WriteOptions {
+    WriteOptions::new()
+        .buffer_size(args.write_buffer)
+        .compression_level(args.compression_level)
+}
+
+/// Create an output for the documents, starting with a BOM for `--bom`.
+fn create_output(args: &ConvertArgs, path: &str) -> std::io::Result<Output> {
+    write_options(args).bom(args.bom).create(path)
+}
+
+fn create_writer(
+    args: &ConvertArgs,
+    metadata: &Metadata,
+    path: &str,
+) -> Result<Writer, Box<dyn Error>> {
+    #[cfg(feature = "sqlite")]
+    if args.format == OutputFormat::Sqlite {
+        if path == io::STDIO {
+            return Err("--format sqlite needs an output file, not stdout".into());
+        }
+        let columns = args.sqlite_columns.clone();
+        return Ok(Writer::Sqlite(SqliteWriter::create(
+            path,
+            &args.sqlite_table,
+            columns,
+        )?));
+    }
+    let output = create_output(args, path)?;
+    output_writer(args, metadata, output)
+}
+
+/// A writer of the documents in the format of `--format` to `output`.
+fn output_writer(
+    args: &ConvertArgs,
+    metadata: &Metadata,
+    output: Output,
+) -> Result<Writer, Box<dyn Error>> {
+    let writer = match (args.format, &args.index) {
+        (OutputFormat::Karp, _) => Writer::Karp(ExportWriter::new(output, metadata)?),
+        (OutputFormat::JsonSeq, _) => Writer::Seq(SeqWriter::new(output)),
+        (OutputFormat::Msgpack, _) => Writer::MessagePack(MessagePackWriter::new(output)),
+        (OutputFormat::Cbor, _) => Writer::Cbor(CborWriter::new(output)),
+        (OutputFormat::Yaml, _) => Writer::Yaml(YamlWriter::new(output)),
+        (OutputFormat::Copy, _) => Writer::Copy(match &args.copy_table {
+            Some(table) => CopyWriter::with_statement(output, table)?,
+            None => CopyWriter::new(output),
+        }),
+        #[cfg(feature = "parquet")]
+        (OutputFormat::Parquet, _) => Writer::Parquet(ParquetWriter::new(
+            output,
+            args.arrow_schema.clone(),
+            args.row_group_size,
+        )?),
+        #[cfg(feature = "arrow")]
+        (OutputFormat::Arrow, _) => Writer::Arrow(IpcWriter::new(
+            output,
+            args.arrow_schema.clone(),
+            args.row_group_size,
+        )?),
+        (OutputFormat::Bulk, Some(index)) => {
+            let mut writer = BulkWriter::new(output, index).remove_id(args.remove_id);
+            if let Some(path) = &args.id_field {
+                writer = writer.id_field(path.clone());
+            }
+            Writer::Bulk(writer)
+        }
+        _ if args.pretty => Writer::Array(ArrayWriter::pretty(output, args.indent)?),
+        _ => Writer::Array(ArrayWriter::new(output)?),
+    };
+    let Some(format) = args.numbers.map(NumberFormat::from) else {
+        return Ok(writer);
+    };
+    Ok(match writer {
+        Writer::Array(writer) => Writer::Array(writer.numbers(format)),
+        Writer::Bulk(writer) => Writer::Bulk(writer.numbers(format)),
+        Writer::Seq(writer) => Writer::Seq(writer.numbers(format)),
+        Writer::Karp(writer) => Writer::Karp(writer.numbers(format)),
+        Writer::Copy(writer) => Writer::Copy(writer.numbers(format)),
+        writer => writer,
+    })
+}
+
+/// Write the documents to writers of at most `size` documents each, opened
+/// with the number of the part (none for no documents).
+fn write_parts<I, F>(docs: I, size: u64, mut open: F) -> Result<(), Box<dyn Error>>
+where
+    I: Iterator<Item = Result<Value, Box<dyn Error>>>,
+    F: FnMut(usize) -> Result<Writer, Box<dyn Error>>,
+{
+    let mut part = 0;
+    let mut written = 0;
+    let mut writer = None;
+    for doc in docs {
+        let doc = doc?;
+        if writer.is_none() {
+            part += 1;
+            writer = Some(open(part)?);
+        }
+        if let Some(writer) = &mut writer {
+            writer.write(&doc)?;
+        }
+        written += 1;
+        if written == size {
+            if let Some(writer) = writer.take() {
+                writer.finish()?;
+            }
+            written = 0;
+        }
+    }
+    if let Some(writer) = writer {
+        writer.finish()?;
+    }
+    Ok(())
+}
+
+/// Start posting batches to the output URL.
+#[cfg(feature = "http")]
+fn start_upload(args: &ConvertArgs) -> Result<Uploader, Box<dyn Error>> {
+    let mut options = UploadOptions::new()
+        .concurrency(args.concurrency)
+        .connect_timeout(args.connect_timeout)
+        .timeout(args.timeout);
+    if let Some(rate) = args.byte_rate {
+        options = options.bytes_per_second(rate as f64);
+    }
+    for (name, value) in &args.headers {
+        options = options.header(name.clone(), value.clone());
+    }
+    let content_type = match args.format {
+        OutputFormat::Array | OutputFormat::Karp => "application/json",
+        OutputFormat::Bulk => "application/x-ndjson",
+        OutputFormat::JsonSeq => "application/json-seq",
+        OutputFormat::Yaml => "application/yaml",
+        OutputFormat::Copy => "text/plain",
+        _ => "application/octet-stream",
+    };
+    Ok(options
+        .default_content_type(content_type)
+        .start(&args.output)?)
+}
+
+/// The output for the next batch of `uploader`.
+#[cfg(feature = "http")]
+fn batch_output(uploader: &mut Uploader) -> Result<Output, Box<dyn Error>> {
+    Ok(Output::new(uploader.batch()?, Compression::None, None)?)
+}
+
+/// Write the documents to the output, or with `--split-size` to as many
+/// parts as needed (none for no documents).
+fn write_stream<I>(args: &ConvertArgs, metadata: &Metadata, docs: I) -> Result<(), Box<dyn Error>>
+where
+    I: Iterator<Item = Result<Value, Box<dyn Error>>>,
+{
+    #[cfg(feature = "http")]
+    if io::is_url(&args.output) {
+        let mut uploader = start_upload(args)?;
+        let mut rate = args.rate.map(RateLimit::new);
+        let docs = docs.inspect(|_| {
+            if let Some(rate) = &mut rate {
+                rate.wait(1);
+            }
+        });
+        write_parts(docs, args.batch_size, |_| {
+            output_writer(args, metadata, batch_output(&mut uploader)?)
+        })?;
+        return Ok(uploader.finish()?);
+    }
+    let Some(size) = args.split_size else {
+        return write_file(args, &args.output, |path| {
+            let mut writer = create_writer(args, metadata, path)?;
+            for doc in docs {
+                writer.write(&doc?)?;
+            }
+            writer.finish()
+        });
+    };
+    check_split(args)?;
+    write_parts(docs, size, |part| {
+        create_writer(args, metadata, &io::part_path(&args.output, part))
+    })
+}
+
+fn dump(
+    args: &ConvertArgs,
+    metadata: &Metadata,
+    docs: &[Value],
+    path: &str,
+) -> Result<(), Box<dyn Error>> {
+    if args.format != OutputFormat::Array {
+        let mut writer = create_writer(args, metadata, path)?;
+        for doc in docs {
+            writer.write(doc)?;
+        }
+        return writer.finish();
+    }
+    dump_to(args, metadata, docs, create_output(args, path)?)
+}
+
+fn dump_to(
+    args: &ConvertArgs,
+    metadata: &Metadata,
+    docs: &[Value],
+    output: Output,
+) -> Result<(), Box<dyn Error>> {
+    if args.format != OutputFormat::Array || args.numbers.is_some() {
+        let mut writer = output_writer(args, metadata, output)?;
+        for doc in docs {
+            writer.write(doc)?;
+        }
+        return writer.finish();
+    }
+    let output = if args.pretty {
+        in_memory::dump_to_writer_pretty(docs, output, args.indent)?
+    } else {
+        in_memory::dump_to_writer(docs, output)?
+    };
+    output.finish()?;
+    Ok(())
+}
+
+/// The raw documents of all inputs, like [`documents`].
+fn raw_documents<'a>(
+    args: &'a ConvertArgs,
+    counter: Option<&'a ByteCounter>,
+) -> impl Iterator<Item = Result<Box<RawValue>, JsonIterError>> + 'a {
+    let loader = Loader::new();
+    args.inputs.iter().flat_map(move |path| {
+        let docs: Box<dyn Iterator<Item = _>> =
+            match open_input(args, path, counter).map(|input| loader.raw_from_reader(input)) {
+                Ok(Ok(docs)) => Box::new(docs),
+                Ok(Err(err)) => Box::new(std::iter::once(Err(err))),
+                Err(err) => Box::new(std::iter::once(Err(err.into()))),
+            };
+        docs
+    })
+}
+
+/// A document of `--raw`, passed through or updated.
+enum Passed {
+    Raw(Box<RawValue>),
+    Updated(Value),
+}
+
+/// Parse and update the document `raw` with `updates` if `--rename` or
+/// `--remove` may change it.
+fn pass(
+    updates: &Transforms,
+    keys: Option<&AffectedKeys>,
+    raw: Box<RawValue>,
+) -> Result<Passed, Box<dyn Error + Send + Sync>> {
+    if keys.is_some_and(|keys| !keys.may_affect(raw.get())) {
+        return Ok(Passed::Raw(raw));
+    }
+    let mut doc = serde_json::from_str(raw.get())?;
+    updates.apply(&mut doc)?;
+    Ok(Passed::Updated(doc))
+}
+
+/// Stream the documents with `--raw`, only parsing the ones that may change.
+fn stream_raw(args: &ConvertArgs) -> Result<(), Box<dyn Error>> {
+    check_in_place(args)?;
+    let paths = args.renames.iter().map(|rename| &rename.from);
+    let keys = AffectedKeys::new(paths.chain(args.removals.iter().map(|removal| &removal.path)));
+    let mut updates = Transforms::new();
+    updates.extend(args.removals.iter().cloned());
+    updates.extend(args.renames.iter().cloned());
+    let (counter, progress) = start_progress(args)?;
+    let docs = json_iter::par_map(raw_documents(args, counter.as_ref()), args.threads, |raw| {
+        let doc = pass(&updates, keys.as_ref(), raw);
+        if let Some(progress) = &progress {
+            progress.document_done();
+        }
+        doc
+    })?;
+    write_file(args, &args.output, |path| {
+        let mut writer = RawArrayWriter::new(create_output(args, path)?)?;
+        for doc in docs {
+            match doc?.map_err(|err| err as Box<dyn Error>)? {
+                Passed::Raw(raw) => writer.write_raw(&raw)?,
+                Passed::Updated(doc) => writer.write(&doc)?,
+            }
+        }
+        Ok(writer.finish()?.finish()?)
+    })?;
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    Ok(())
+}
+
+/// Stream the documents with `--transcode`, copying what isn't updated.
+fn stream_transcoded(args: &ConvertArgs) -> Result<(), Box<dyn Error>> {
+    check_in_place(args)?;
+    let mut transcoder = Transcoder::new();
+    for removal in &args.removals {
+        transcoder = transcoder.remove(removal.clone());
+    }
+    for rename in &args.renames {
+        transcoder = transcoder.rename(rename.clone());
+    }
+    for set_field in &args.updates {
+        transcoder = transcoder.set(set_field.clone());
+    }
+    let (counter, progress) = start_progress(args)?;
+    write_file(args, &args.output, |path| {
+        let mut writer = JsonStreamWriter::new(create_output(args, path)?);
+        writer.begin_array()?;
+        for path in &args.inputs {
+            let mut reader = json_iter::stream_reader(open_input(args, path, counter.as_ref())?);
+            reader.begin_array()?;
+            while reader.has_next()? {
+                transcoder.transcode(&mut reader, &mut writer)?;
+                if let Some(progress) = &progress {
+                    progress.document_done();
+                }
+            }
+            reader.end_array()?;
+            reader.consume_trailing_whitespace()?;
+        }
+        writer.end_array()?;
+        Ok(writer.finish_document()?.finish()?)
+    })?;
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    Ok(())
+}
+
+/// Stream the documents with `--checkpoint`, saving how far it got every
+/// [`checkpoint::INTERVAL`] documents, and with `--resume` continue from the
+/// last checkpoint.
+fn stream_checkpointed(
+    args: &ConvertArgs,
+    updates: &Updates,
+    path: &str,
+) -> Result<(), Box<dyn Error>> {
+    check_lexicon(args)?;
+    let output = &args.output;
+    if output == io::STDIO || io::is_url(output) || io::is_s3(output) {
+        return Err("--checkpoint needs an output file".into());
+    }
+    if io::Compression::from_path(output) != io::Compression::None {
+        return Err("--checkpoint can't append to a compressed output".into());
+    }
+    let resumed = match args.resume {
+        true => Checkpoint::load(args, path)?,
+        false => None,
+    };
+    match &resumed {
+        Some(checkpoint) => eprintln!(
+            "resuming after {} documents read and {} written",
+            checkpoint.documents, checkpoint.written
+        ),
+        None if args.resume => eprintln!("no checkpoint in {}, starting from the beginning", path),
+        None => {}
+    }
+    let mut writer = Checkpoint::open_output(resumed.as_ref(), output)
+        .map_err(|err| format!("{}: {}", output, err))?;
+    let mut checkpoint = resumed.unwrap_or_else(|| Checkpoint::new(args));
+    let (counter, progress) = start_progress(args)?;
+    let skipped = Skipped::new(args)?;
+    let docs = documents(args, counter.as_ref(), &skipped).skip(checkpoint.documents as usize);
+    // The documents updated from each input document, so a checkpoint is
+    // only saved once all of them are written.
+    let docs = json_iter::par_map(docs, args.threads, |doc: Value| {
+        let docs = match keep(args, &doc) {
+            true => updates.apply(args, doc),
+            false => Ok(Vec::new()),
+        };
+        if let Some(progress) = &progress {
+            progress.document_done();
+        }
+        docs
+    })?;
+    for docs in docs {
+        let docs = match docs? {
+            Ok(docs) => docs,
+            Err(err) if args.lenient => {
+                skipped.skip_update(err, None);
+                Vec::new()
+            }
+            Err(err) => return Err(err.into()),
+        };
+        for doc in docs {
+            writer.write(&doc)?;
+            checkpoint.written += 1;
+        }
+        checkpoint.documents += 1;
+        if checkpoint.documents % checkpoint::INTERVAL == 0 {
+            checkpoint.save(&mut writer, path)?;
+        }
+    }
+    writer.finish()?.into_inner()?.sync_data()?;
+    std::fs::remove_file(path).or_else(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => Ok(()),
+        _ => Err(err),
+    })?;
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    if skipped.total() > 0 {
+        eprintln!("skipped {} documents", skipped.total());
+    }
+    skipped.finish()
+}
+
+/// Print the changes of the updates to the first `count` documents, for
+/// `--dry-run`, with colors on a terminal.
+fn dry_run(args: &ConvertArgs, updates: &Updates, count: usize) -> Result<(), Box<dyn Error>> {
+    check_lexicon(args)?;
+    let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let mut output = std::io::stdout().lock();
+    let skipped = Skipped::new(args)?;
+    let mut changed = 0;
+    let mut read = 0;
+    for doc in documents(args, None, &skipped).take(count) {
+        let doc = doc?;
+        read += 1;
+        if !keep(args, &doc) {
+            writeln!(output, "- document {} is filtered out", read)?;
+            continue;
+        }
+        let docs = match updates.apply(args, doc.clone()) {
+            Ok(docs) => docs,
+            Err(err) if args.lenient => {
+                skipped.skip_update(err, Some(doc));
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if docs.is_empty() {
+            writeln!(output, "- document {} has no output", read)?;
+            changed += 1;
+        }
+        let outputs = docs.len();
+        let mut doc_changed = false;
+        for (index, new) in docs.iter().enumerate() {
+            let name = match outputs {
+                1 => format!("document {}", read),
+                _ => format!("document {}, output {}", read, index + 1),
+            };
+            let fields = diff::field_changes(&doc, new);
+            if fields.is_empty() {
+                writeln!(output, "= {} is unchanged", name)?;
+                continue;
+            }
+            writeln!(output, "~ {}", name)?;
+            write_fields(&mut output, &fields, color)?;
+            doc_changed = true;
+        }
+        changed += doc_changed as usize;
+    }
+    skipped.finish()?;
+    eprintln!("{} of {} documents would change", changed, read);
+    Ok(())
+}
+
+/// Run the documents through for `--check`, and fail with a summary if
+/// any of the `read` documents couldn't be read or updated, or an output
+/// document doesn't match `--schema`.
+fn check<I>(
+    args: &ConvertArgs,
+    docs: I,
+    read: &AtomicU64,
+    skipped: &Skipped,
+) -> Result<(), Box<dyn Error>>
+where
+    I: Iterator<Item = Result<Value, Box<dyn Error>>>,
+{
+    let mut mismatches = 0;
+    for (index, doc) in docs.enumerate() {
+        if let Err(err) = check_schema(args, index, &doc?) {
+            eprintln!("warning: {}", err);
+            mismatches += 1;
+        }
+    }
+    let documents = read.load(Ordering::Relaxed) + skipped.read.load(Ordering::Relaxed);
+    let commas = skipped.commas.load(Ordering::Relaxed);
+    if skipped.total() + commas + mismatches == 0 {
+        eprintln!("checked {} documents, no problems found", documents);
+        return Ok(());
+    }
+    let mut problems = vec![
+        format!("{} couldn't be read", skipped.read.load(Ordering::Relaxed)),
+        format!(
+            "{} couldn't be updated",
+            skipped.updated.load(Ordering::Relaxed)
+        ),
+    ];
+    if commas > 0 {
+        problems.push(format!("{} commas are missing", commas));
+    }
+    if args.schema.is_some() {
+        problems.push(format!(
+            "{} output documents don't match the schema",
+            mismatches
+        ));
+    }
+    Err(format!("checked {} documents: {}", documents, problems.join(", ")).into())
+}
+
+pub fn stream(args: &ConvertArgs) -> Result<(), Box<dyn Error>> {
+    let updates = Updates::new(args)?;
+    if let Some(count) = args.dry_run {
+        return dry_run(args, &updates, count);
+    }
+    if args.raw {
+        return stream_raw(args);
+    }
+    if args.transcode {
+        return stream_transcoded(args);
+    }
+    if let Some(path) = &args.checkpoint {
+        return stream_checkpointed(args, &updates, path);
+    }
+    if let Some(jobs) = args.jobs {
+        return stream_files(args, &updates, jobs);
+    }
+    let (counter, progress) = start_progress(args)?;
+    let skipped = stream_with(args, &updates, counter.as_ref(), progress.as_ref());
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    let skipped = skipped?;
+    if skipped > 0 {
+        eprintln!("skipped {} documents", skipped);
+    }
+    Ok(())
+}
+
+/// The file in the directory `output` that `--jobs` writes `input` to.
+fn job_output(output: &str, input: &str) -> Result<String, Box<dyn Error>> {
+    let name = std::path::Path::new(input)
+        .file_name()
+        .ok_or_else(|| format!("{}: --jobs needs input files", input))?;
+    Ok(std::path::Path::new(output)
+        .join(name)
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// Convert each input to a file of the same name in the directory of the
+/// output with `--jobs`, `jobs` files at a time, with one progress bar for
+/// all of them.
+fn stream_files(args: &ConvertArgs, updates: &Updates, jobs: usize) -> Result<(), Box<dyn Error>> {
+    check_lexicon(args)?;
+    let mut outputs = Vec::new();
+    for input in &args.inputs {
+        let output = job_output(&args.output, input)?;
+        if outputs.contains(&output) {
+            return Err(format!("two inputs would be written to {}", output).into());
+        }
+        outputs.push(output);
+    }
+    std::fs::create_dir_all(&args.output).map_err(|err| format!("{}: {}", args.output, err))?;
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    let (counter, progress) = start_progress(args)?;
+    let results: Vec<_> = pool.install(|| {
+        args.inputs
+            .par_iter()
+            .zip(&outputs)
+            .map(|(input, output)| {
+                let args = ConvertArgs {
+                    inputs: vec![input.clone()],
+                    output: output.clone(),
+                    jobs: None,
+                    ..args.clone()
+                };
+                stream_with(&args, updates, counter.as_ref(), progress.as_ref())
+                    .map_err(|err| err.to_string())
+            })
+            .collect()
+    });
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    let mut failed = 0;
+    for (input, result) in args.inputs.iter().zip(results) {
+        match result {
+            Ok(0) => {}
+            Ok(skipped) => eprintln!("{}: skipped {} documents", input, skipped),
+            Err(err) => {
+                eprintln!("error: {}: {}", input, err);
+                failed += 1;
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{} of {} inputs failed", failed, args.inputs.len()).into()),
+    }
+}
+
+/// Stream the documents of all inputs to the output, counting the bytes read
+/// with `counter` and showing the documents done on `progress`, and return
+/// the number of skipped documents.
+fn stream_with(
+    args: &ConvertArgs,
+    updates: &Updates,
+    counter: Option<&ByteCounter>,
+    progress: Option<&Progress>,
+) -> Result<u64, Box<dyn Error>> {
+    check_lexicon(args)?;
+    check_format(args)?;
+    let metadata = export_metadata(args, updates)?;
+    let skipped = Skipped::new(args)?;
+    let read = AtomicU64::new(0);
+    let counted = |_: &_| {
+        read.fetch_add(1, Ordering::Relaxed);
+    };
+    let wanted = |doc: &Result<Value, _>| doc.as_ref().map_or(true, |doc| keep(args, doc));
+    let updated = |doc: Value| {
+        // The document before the updates, for `--errors`.
+        let original = args.errors.is_some().then(|| doc.clone());
+        let docs = updates.apply(args, doc).map_err(|err| (err, original));
+        if let Some(progress) = progress {
+            progress.document_done();
+        }
+        docs
+    };
+    // With more than one CPU, the documents are read, updated and written on
+    // threads of their own, on one they could only take turns.
+    let staged = thread::available_parallelism().is_ok_and(|cpus| cpus.get() > 1);
+    let streamed = thread::scope(|scope| {
+        let docs: Box<dyn Iterator<Item = Result<_, JsonIterError>>> = match staged {
+            true => {
+                let docs = json_iter::read_ahead(scope, CHANNEL_CAPACITY, || {
+                    documents(args, counter, &skipped)
+                });
+                let order = match args.unordered {
+                    true => json_iter::Order::Completion,
+                    false => json_iter::Order::Input,
+                };
+                Box::new(json_iter::par_map_window(
+                    scope,
+                    docs.inspect(counted).filter(wanted),
+                    args.threads,
+                    CHANNEL_CAPACITY,
+                    order,
+                    updated,
+                ))
+            }
+            false => {
+                let docs = documents(args, counter, &skipped);
+                Box::new(json_iter::par_map(
+                    docs.inspect(counted).filter(wanted),
+                    args.threads,
+                    updated,
+                )?)
+            }
+        };
+        let docs = docs.flat_map(|docs| {
+            let (docs, err) = match docs.map_err(Box::from) {
+                Ok(Ok(docs)) => (docs, None),
+                Ok(Err((err, doc))) if args.lenient || args.check => {
+                    skipped.skip_update(err, doc);
+                    (Vec::new(), None)
+                }
+                Ok(Err((err, _))) => (Vec::new(), Some(Err(err.into()))),
+                Err(err) => (Vec::new(), Some(Err(err))),
+            };
+            docs.into_iter().map(Ok).chain(err)
+        });
+        if args.check {
+            return check(args, docs, &read, &skipped);
+        }
+        let docs: Box<dyn Iterator<Item = Result<Value, Box<dyn Error>>>> =
+            match (&args.dedup_by, args.keep.into()) {
+                (Some(path), Keep::First) => {
+                    let mut dedup = Dedup::new(path.clone());
+                    Box::new(
+                        docs.filter(move |doc| doc.as_ref().map_or(true, |doc| dedup.is_new(doc))),
+                    )
+                }
+                (Some(path), Keep::Last) => {
+                    Box::new(dedup::dedup_last(docs, path)?.map(|doc| Ok(doc?)))
+                }
+                (None, _) => Box::new(docs),
+            };
+        let docs = match &args.sort_by {
+            Some(path) => Box::new(
+                args.sorting()
+                    .external_sort()
+                    .sort(docs, path)?
+                    .map(|doc| Ok(doc?)),
+            ),
+            None => docs,
+        };
+        let docs = match (args.top, &args.by) {
+            (Some(n), Some(path)) => Box::new(
+                sample::top(docs, n, path, extreme(args))?
+                    .into_iter()
+                    .map(Ok),
+            ),
+            _ => docs,
+        };
+        let mut rng = sample::rng(args.seed);
+        let docs = match args.sample {
+            Some(n) => Box::new(sample::sample(docs, n, &mut rng)?.into_iter().map(Ok)),
+            None => docs,
+        };
+        let docs = match args.shuffle {
+            true => Box::new(
+                args.sorting()
+                    .shuffle()
+                    .shuffle(docs, rng)?
+                    .map(|doc| Ok(doc?)),
+            ),
+            false => docs,
+        };
+        let docs = docs.enumerate().map(|(index, doc)| {
+            let doc = doc?;
+            check_schema(args, index, &doc)?;
+            Ok(doc)
+        });
+        if !staged {
+            return write_stream(args, &metadata, docs);
+        }
+        // `None` stops the writer, leaving the output unfinished as on an
+        // error of its own.
+        let mut writer = json_iter::write_behind(scope, CHANNEL_CAPACITY, |docs| {
+            let docs = docs.map(|doc: Option<Value>| doc.ok_or("the conversion stopped".into()));
+            write_stream(args, &metadata, docs).map_err(|err| err.to_string())
+        });
+        for doc in docs {
+            match doc {
+                Ok(doc) => {
+                    if writer.send(Some(doc)).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    writer.send(None).ok();
+                    writer.finish().ok();
+                    return Err(err);
+                }
+            }
+        }
+        Ok(writer.finish()?)
+    });
+    if args.check {
+        skipped.finish()?;
+        return streamed.map(|()| 0);
+    }
+    streamed?;
+    let total = skipped.total();
+    skipped.finish()?;
+    Ok(total)
+}
+
+pub fn in_memory(
+    InMemoryArgs {
+        convert: args,
+        mmap,
+    }: &InMemoryArgs,
+) -> Result<(), Box<dyn Error>> {
+    if args.raw || args.transcode || args.checkpoint.is_some() {
+        return Err("--raw, --transcode and --checkpoint only work when streaming".into());
+    }
+    let updates = Updates::new(args)?;
+    if let Some(count) = args.dry_run {
+        return dry_run(args, &updates, count);
+    }
+    if args.lenient || args.check {
+        return Err("--lenient and --check only work when streaming".into());
+    }
+    check_lexicon(args)?;
+    check_format(args)?;
+    let metadata = export_metadata(args, &updates)?;
+    let (counter, progress) = start_progress(args)?;
+    let mut docs = Vec::new();
+    for path in &args.inputs {
+        let archive = ArchiveFormat::from_path(path).is_some();
+        if *mmap && args.strict {
+            return Err("--strict doesn't work with --mmap".into());
+        }
+        if *mmap && args.input_encoding != EncodingArg::Utf8 {
+            return Err("--input-encoding doesn't work with --mmap".into());
+        }
+        if *mmap && archive {
+            return Err("archives can't be memory mapped".into());
+        }
+        if *mmap {
+            let start = docs.len();
+            docs.extend(in_memory::load_from_file_mmap_with(
+                path,
+                args.parser.into(),
+            )?);
+            for doc in &mut docs[start..] {
+                tag_source(args, path, doc);
+            }
+        } else if archive {
+            for member in archive_members(args, path, counter.as_ref())? {
+                let (name, input) = member?;
+                load_in_memory(args, &name, input, &mut docs)?;
+            }
+        } else {
+            #[cfg(feature = "sqlite")]
+            if args.input_format == InputFormat::Sqlite {
+                for doc in load(args, &Loader::new(), path, counter.as_ref())? {
+                    let mut doc = doc?;
+                    tag_source(args, path, &mut doc);
+                    docs.push(doc);
+                }
+                continue;
+            }
+            let input = open_input(args, path, counter.as_ref())?;
+            load_in_memory(args, path, input, &mut docs)?;
+        }
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.threads)
+        .build()?;
+    let docs = pool.install(|| {
+        docs.into_par_iter()
+            .filter(|doc| keep(args, doc))
+            .map(|doc| {
+                let docs = updates.apply(args, doc);
+                if let Some(progress) = &progress {
+                    progress.document_done();
+                }
+                docs
+            })
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    let mut docs: Vec<Value> = docs.into_iter().flatten().collect();
+    if let Some(path) = &args.dedup_by {
+        dedup::dedup_in_memory(&mut docs, path, args.keep.into());
+    }
+    if let Some(path) = &args.sort_by {
+        sort::sort_by(&mut docs, path);
+    }
+    if let (Some(n), Some(path)) = (args.top, &args.by) {
+        let all = docs.into_iter().map(Ok::<_, Infallible>);
+        docs = sample::top(all, n, path, extreme(args))?;
+    }
+    let mut rng = sample::rng(args.seed);
+    if let Some(n) = args.sample {
+        let all = docs.into_iter().map(Ok::<_, Infallible>);
+        docs = sample::sample(all, n, &mut rng)?;
+    }
+    if args.shuffle {
+        docs.shuffle(&mut rng);
+    }
+    for (index, doc) in docs.iter().enumerate() {
+        check_schema(args, index, doc)?;
+    }
+    #[cfg(feature = "http")]
+    if io::is_url(&args.output) {
+        let mut uploader = start_upload(args)?;
+        let mut rate = args.rate.map(RateLimit::new);
+        for docs in docs.chunks(args.batch_size as usize) {
+            if let Some(rate) = &mut rate {
+                rate.wait(docs.len() as u64);
+            }
+            dump_to(args, &metadata, docs, batch_output(&mut uploader)?)?;
+        }
+        uploader.finish()?;
+        if let Some(progress) = progress {
+            progress.finish();
+        }
+        return Ok(());
+    }
+    match args.split_size {
+        Some(size) => {
+            check_split(args)?;
+            for (part, docs) in docs.chunks(size as usize).enumerate() {
+                dump(
+                    args,
+                    &metadata,
+                    docs,
+                    &io::part_path(&args.output, part + 1),
+                )?;
+            }
+        }
+        None => write_file(args, &args.output, |path| {
+            dump(args, &metadata, &docs, path)
+        })?,
+    }
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::cli::Cli;
+
+    /// A JSON array of `count` documents, with the document at `broken` not
+    /// valid JSON.
+    fn array(count: u64, broken: Option<u64>) -> String {
+        let docs: Vec<String> = (0..count)
+            .map(|i| match Some(i) == broken {
+                true => "{\"i\": }".to_owned(),
+                false => json!({"i": i, "word": format!("w{}", i)}).to_string(),
+            })
+            .collect();
+        format!("[{}]", docs.join(",\n"))
+    }
+
+    fn convert(input: &Path, output: &Path, checkpoint: &Path, resume: bool) -> Result<(), String> {
+        let mut argv = vec![
+            "json-tool".to_owned(),
+            "stream".to_owned(),
+            input.display().to_string(),
+            output.display().to_string(),
+            "--checkpoint".to_owned(),
+            checkpoint.display().to_string(),
+            "--set".to_owned(),
+            "source=skbl".to_owned(),
+            "--threads".to_owned(),
+            "2".to_owned(),
+        ];
+        if resume {
+            argv.push("--resume".to_owned());
+        }
+        let cli = Cli::parse_from(argv);
+        stream(cli.conversion().unwrap()).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn resumes_from_the_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let count = 2 * checkpoint::INTERVAL + 500;
+        let input = dir.path().join("in.json");
+        let checkpoint = dir.path().join("checkpoint.json");
+
+        fs::write(&input, array(count, None)).unwrap();
+        let uninterrupted = dir.path().join("uninterrupted.json");
+        convert(&input, &uninterrupted, &checkpoint, false).unwrap();
+        assert!(!checkpoint.exists());
+
+        // Interrupted by a broken document after the first checkpoint...
+        let output = dir.path().join("out.json");
+        fs::write(&input, array(count, Some(checkpoint::INTERVAL + 100))).unwrap();
+        assert!(convert(&input, &output, &checkpoint, false).is_err());
+        let saved: Value = serde_json::from_slice(&fs::read(&checkpoint).unwrap()).unwrap();
+        assert_eq!(saved["documents"], checkpoint::INTERVAL);
+
+        // ...and resumed once it's fixed.
+        fs::write(&input, array(count, None)).unwrap();
+        convert(&input, &output, &checkpoint, true).unwrap();
+        assert!(!checkpoint.exists());
+        assert_eq!(
+            fs::read(&output).unwrap(),
+            fs::read(&uninterrupted).unwrap()
+        );
+        let docs: Vec<Value> = serde_json::from_slice(&fs::read(&output).unwrap()).unwrap();
+        assert_eq!(docs.len() as u64, count);
+        assert_eq!(
+            docs[12345],
+            json!({"i": 12345, "word": "w12345", "source": "skbl"})
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use std::error::Error;
+use std::io::Write;
+
+use serde_json::Value;
+
+use read_json_in_rust::diff::{self, Change, FieldChange};
+use read_json_in_rust::io;
+use read_json_in_rust::json_iter::{self, ArrayWriter};
+
+use crate::cli::{DiffArgs, DiffFormat};
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+/// Write the changed fields one per line, with the old values in red and the
+/// new ones in green if `color`.
+pub fn write_fields<W: Write>(
+    writer: &mut W,
+    fields: &[FieldChange],
+    color: bool,
+) -> std::io::Result<()> {
+    let paint = |value: &Value, code| match color {
+        true => format!("{}{}{}", code, value, RESET),
+        false => value.to_string(),
+    };
+    for field in fields {
+        match (&field.old, &field.new) {
+            (Some(old), Some(new)) => writeln!(
+                writer,
+                "    {}: {} -> {}",
+                field.path,
+                paint(old, RED),
+                paint(new, GREEN)
+            )?,
+            (Some(old), None) => writeln!(writer, "    {}: - {}", field.path, paint(old, RED))?,
+            (None, Some(new)) => writeln!(writer, "    {}: + {}", field.path, paint(new, GREEN))?,
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
+fn write_text<W: Write>(writer: &mut W, change: &Change) -> std::io::Result<()> {
+    match change {
+        Change::Added { key, .. } => writeln!(writer, "+ {}", key),
+        Change::Removed { key, .. } => writeln!(writer, "- {}", key),
+        Change::Changed { key, fields } => {
+            writeln!(writer, "~ {}", key)?;
+            write_fields(writer, fields, false)
+        }
+    }
+}
+
+pub fn diff(args: &DiffArgs) -> Result<(), Box<dyn Error>> {
+    let old = json_iter::load_from_file(&args.old)?;
+    let new = json_iter::load_from_file(&args.new)?;
+    let changes = diff::diff_with(old, new, &args.key, &args.sorting.external_sort())?;
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+    let mut count = |change: &Change| match change {
+        Change::Added { .. } => added += 1,
+        Change::Removed { .. } => removed += 1,
+        Change::Changed { .. } => changed += 1,
+    };
+    let output = io::create(&args.output)?;
+    match args.format {
+        DiffFormat::Text => {
+            let mut output = output;
+            for change in changes {
+                let change = change?;
+                count(&change);
+                write_text(&mut output, &change)?;
+            }
+            output.finish()?;
+        }
+        DiffFormat::Json => {
+            let mut writer = ArrayWriter::new(output)?;
+            for change in changes {
+                let change = change?;
+                count(&change);
+                writer.write(&change)?;
+            }
+            writer.finish()?.finish()?;
+        }
+    }
+    eprintln!("{} added, {} removed, {} changed", added, removed, changed);
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use std::error::Error;
+
+use serde_json::Value;
+
+use read_json_in_rust::io;
+use read_json_in_rust::join::{Join, JoinKind};
+use read_json_in_rust::json_iter::{self, ArrayWriter, JsonIterError};
+use read_json_in_rust::path::FieldPath;
+use read_json_in_rust::sort::ExternalSort;
+
+use crate::cli::JoinArgs;
+
+type DocumentResults = Box<dyn Iterator<Item = Result<Value, JsonIterError>>>;
+
+/// The documents at `path`, sorted by `key` if there is a `sort`.
+fn open(
+    path: &str,
+    key: &FieldPath,
+    sort: Option<&ExternalSort>,
+) -> Result<DocumentResults, JsonIterError> {
+    let docs = json_iter::load_from_file::<Value>(path)?;
+    Ok(match sort {
+        Some(sort) => Box::new(sort.sort(docs, key)?),
+        None => Box::new(docs),
+    })
+}
+
+pub fn join(args: &JoinArgs) -> Result<(), Box<dyn Error>> {
+    let (left_on, right_on) = &args.on;
+    let kind = match args.left_join {
+        true => JoinKind::Left,
+        false => JoinKind::Inner,
+    };
+    let mut join = Join::new(left_on.clone())
+        .right_on(right_on.clone())
+        .kind(kind);
+    if let Some(field) = &args.into {
+        join = join.into_field(field.as_str());
+    }
+    let sort = args.sort.then(|| args.sorting.external_sort());
+    let left = open(&args.left, left_on, sort.as_ref())?;
+    let right = open(&args.right, right_on, sort.as_ref())?;
+
+    let output = io::create(&args.output)?;
+    let mut writer = if args.pretty {
+        ArrayWriter::pretty(output, 2)?
+    } else {
+        ArrayWriter::new(output)?
+    };
+    let mut written = 0;
+    for doc in join.join(left, right) {
+        writer.write(&doc?)?;
+        written += 1;
+    }
+    writer.finish()?.finish()?;
+    eprintln!("{} joined documents", written);
+    Ok(())
+}
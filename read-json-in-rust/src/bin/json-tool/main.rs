@@ -0,0 +1,76 @@
+use std::process::ExitCode;
+use std::time::Instant;
+
+use clap::Parser;
+
+use read_json_in_rust::report::Report;
+
+use cli::{Cli, Command};
+
+mod aggregate;
+mod checkpoint;
+mod cli;
+mod convert;
+mod diff;
+mod join;
+mod pick;
+mod progress;
+#[cfg(feature = "serve")]
+mod serve;
+mod stats;
+mod table;
+mod validate;
+mod watch;
+
+fn main() -> ExitCode {
+    let mut cli = Cli::parse();
+    if cli.conversion().is_some_and(|args| args.watch) {
+        let Err(err) = watch::watch(&mut cli, |cli| {
+            run(cli);
+        });
+        eprintln!("error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    if let Err(err) = cli.expand_inputs() {
+        eprintln!("error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    if run(&cli) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Run the command and report on stderr, returning whether it succeeded.
+fn run(cli: &Cli) -> bool {
+    let start = Instant::now();
+    let result = match (&cli.command, &cli.convert) {
+        (Some(Command::Stream(args)), _) | (None, Some(args)) => convert::stream(args),
+        (Some(Command::InMemory(args)), _) => convert::in_memory(args),
+        (Some(Command::Diff(args)), _) => diff::diff(args),
+        (Some(Command::Join(args)), _) => join::join(args),
+        (Some(Command::Validate(args)), _) => validate::validate(args),
+        (Some(Command::InferSchema(args)), _) => validate::infer_schema(args),
+        (Some(Command::Stats(args)), _) => stats::stats(args),
+        (Some(Command::Aggregate(args)), _) => aggregate::aggregate(args),
+        (Some(Command::Head(args)), _) => pick::head(args),
+        (Some(Command::Tail(args)), _) => pick::tail(args),
+        (Some(Command::Sample(args)), _) => pick::sample(args),
+        (Some(Command::ToCsv(args)), _) => table::to_csv(args),
+        #[cfg(feature = "serve")]
+        (Some(Command::Serve(args)), _) => serve::serve(args),
+        (None, None) => unreachable!("clap requires either a subcommand or the arguments"),
+    };
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        return false;
+    }
+    let report = Report::new(start.elapsed());
+    if cli.report_json {
+        eprintln!("{}", report.to_json());
+    } else {
+        eprintln!("{}", report);
+    }
+    true
+}
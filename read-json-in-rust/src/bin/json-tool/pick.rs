@@ -0,0 +1,49 @@
+use std::error::Error;
+
+use serde_json::Value;
+
+use read_json_in_rust::io;
+use read_json_in_rust::json_iter::{self, ArrayWriter, Documents, JsonIterError};
+use read_json_in_rust::sample;
+
+use crate::cli::{PickArgs, SampleArgs};
+
+fn open(args: &PickArgs) -> Result<Documents<Value, io::Input>, JsonIterError> {
+    json_iter::load_from_file(&args.input)
+}
+
+fn write<I>(args: &PickArgs, docs: I) -> Result<(), JsonIterError>
+where
+    I: IntoIterator<Item = Result<Value, JsonIterError>>,
+{
+    let output = io::create(&args.output)?;
+    let mut writer = if args.pretty {
+        ArrayWriter::pretty(output, 2)?
+    } else {
+        ArrayWriter::new(output)?
+    };
+    for doc in docs {
+        writer.write(&doc?)?;
+    }
+    writer.finish()?.finish()?;
+    Ok(())
+}
+
+/// Write the first documents, without reading the rest of the input.
+pub fn head(args: &PickArgs) -> Result<(), Box<dyn Error>> {
+    write(args, open(args)?.take(args.count))?;
+    Ok(())
+}
+
+pub fn tail(args: &PickArgs) -> Result<(), Box<dyn Error>> {
+    let docs = sample::tail(open(args)?, args.count)?;
+    write(args, docs.into_iter().map(Ok))?;
+    Ok(())
+}
+
+pub fn sample(args: &SampleArgs) -> Result<(), Box<dyn Error>> {
+    let pick = &args.pick;
+    let docs = sample::sample(open(pick)?, pick.count, &mut sample::rng(args.seed))?;
+    write(pick, docs.into_iter().map(Ok))?;
+    Ok(())
+}
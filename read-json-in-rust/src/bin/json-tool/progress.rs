@@ -0,0 +1,73 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+use read_json_in_rust::io::ByteCounter;
+
+const TICK: Duration = Duration::from_millis(100);
+
+/// A progress bar on stderr, showing the bytes read from the input and the
+/// number of documents processed.
+///
+/// The bar is redrawn from a background thread, so counting stays cheap.
+pub struct Progress {
+    docs: Arc<AtomicU64>,
+    stop: mpsc::Sender<()>,
+    updater: JoinHandle<()>,
+}
+
+impl Progress {
+    /// Start showing progress for an input of `total` bytes (unknown for stdin).
+    pub fn start(bytes: ByteCounter, total: Option<u64>) -> Self {
+        let bar = match total {
+            Some(total) => ProgressBar::new(total).with_style(
+                ProgressStyle::with_template(
+                    "[{elapsed_precise}] [{wide_bar}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta}) {msg}",
+                )
+                .expect("a valid template"),
+            ),
+            None => ProgressBar::new_spinner().with_style(
+                ProgressStyle::with_template(
+                    "{spinner} [{elapsed_precise}] {bytes} ({bytes_per_sec}) {msg}",
+                )
+                .expect("a valid template"),
+            ),
+        };
+        let docs = Arc::new(AtomicU64::new(0));
+        let (stop, stopped) = mpsc::channel();
+        let updater = {
+            let docs = docs.clone();
+            let start = Instant::now();
+            thread::spawn(move || loop {
+                let stopping = stopped.recv_timeout(TICK) != Err(RecvTimeoutError::Timeout);
+                let docs = docs.load(Ordering::Relaxed);
+                let docs_per_sec = docs as f64 / start.elapsed().as_secs_f64();
+                bar.set_position(bytes.get());
+                bar.set_message(format!("{} documents ({:.0}/s)", docs, docs_per_sec));
+                if stopping {
+                    bar.finish();
+                    break;
+                }
+            })
+        };
+        Self {
+            docs,
+            stop,
+            updater,
+        }
+    }
+
+    pub fn document_done(&self) {
+        self.docs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Draw the final state and stop updating.
+    pub fn finish(self) {
+        drop(self.stop);
+        let _ = self.updater.join();
+    }
+}
@@ -0,0 +1,18 @@
+use std::error::Error;
+use std::net::TcpListener;
+
+use read_json_in_rust::serve::http::HttpOptions;
+use read_json_in_rust::serve::Server;
+
+use crate::cli::ServeArgs;
+
+pub fn serve(args: &ServeArgs) -> Result<(), Box<dyn Error>> {
+    let listener =
+        TcpListener::bind(&args.listen).map_err(|err| format!("{}: {}", args.listen, err))?;
+    eprintln!("listening on http://{}", listener.local_addr()?);
+    let options = HttpOptions::new()
+        .workers(args.workers)
+        .max_body(args.max_body as u64);
+    Server::new(args.rules.clone(), options).serve(listener)?;
+    Ok(())
+}
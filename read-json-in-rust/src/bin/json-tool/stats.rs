@@ -0,0 +1,147 @@
+use std::error::Error;
+use std::io::Write;
+
+use serde_json::Value;
+
+use read_json_in_rust::io;
+use read_json_in_rust::json_iter;
+use read_json_in_rust::stats::{FieldProfile, Profiles, Stats, TypeCounts};
+
+use crate::cli::{StatsArgs, StatsFormat};
+
+/// Write the path, the count and the types of a field, padded to `width`.
+fn write_types<W: Write>(
+    writer: &mut W,
+    path: &str,
+    types: &TypeCounts,
+    width: usize,
+) -> std::io::Result<()> {
+    write!(
+        writer,
+        "{:width$}  {:>8}",
+        path,
+        types.total(),
+        width = width
+    )?;
+    for (name, count) in types.iter() {
+        write!(writer, "  {} {}", name, count)?;
+    }
+    Ok(())
+}
+
+fn write_text<W: Write>(writer: &mut W, stats: &Stats) -> std::io::Result<()> {
+    writeln!(writer, "documents: {}", stats.documents)?;
+    writeln!(writer, "bytes: {}", stats.bytes)?;
+    let width = stats
+        .fields
+        .keys()
+        .map(|path| path.len())
+        .max()
+        .unwrap_or(0);
+    for (path, types) in &stats.fields {
+        write_types(writer, path, types, width)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Characters of a common string shown in the text report.
+const MAX_VALUE_CHARS: usize = 40;
+
+/// Write the profile of a field after its types, with the count of a common
+/// string as a range if it isn't exact.
+fn write_profile<W: Write>(writer: &mut W, profile: &FieldProfile) -> std::io::Result<()> {
+    if profile.types.null > 0 {
+        write!(writer, "  nulls {:.1}%", profile.null_rate * 100.0)?;
+    }
+    if let Some(numbers) = &profile.numbers {
+        write!(
+            writer,
+            "  min {} max {} mean {:.3}",
+            numbers.min, numbers.max, numbers.mean
+        )?;
+    }
+    if let Some(strings) = &profile.strings {
+        write!(
+            writer,
+            "  length {}-{}",
+            strings.min_length, strings.max_length
+        )?;
+        let top: Vec<_> = strings
+            .top
+            .top()
+            .into_iter()
+            .map(|top| {
+                let value = match top.value.char_indices().nth(MAX_VALUE_CHARS) {
+                    Some((end, _)) => format!("{:?}...", &top.value[..end]),
+                    None => format!("{:?}", top.value),
+                };
+                match top.error {
+                    0 => format!("{} {}", value, top.count),
+                    error => format!("{} {}-{}", value, top.count - error, top.count),
+                }
+            })
+            .collect();
+        if !top.is_empty() {
+            write!(writer, "  top {}", top.join(", "))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_profiles_text<W: Write>(writer: &mut W, profiles: &Profiles) -> std::io::Result<()> {
+    writeln!(writer, "documents: {}", profiles.documents)?;
+    let width = profiles
+        .fields
+        .keys()
+        .map(|path| path.len())
+        .max()
+        .unwrap_or(0);
+    for (path, profile) in &profiles.fields {
+        write_types(writer, path, &profile.types, width)?;
+        write_profile(writer, profile)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+pub fn stats(args: &StatsArgs) -> Result<(), Box<dyn Error>> {
+    if args.profile {
+        return profile(args);
+    }
+    let mut stats = Stats::new();
+    for path in &args.inputs {
+        for doc in json_iter::load_from_file::<Value>(path)? {
+            stats.add(&doc?);
+        }
+    }
+    let mut output = io::create(&args.output)?;
+    match args.format {
+        StatsFormat::Text => write_text(&mut output, &stats)?,
+        StatsFormat::Json => {
+            serde_json::to_writer_pretty(&mut output, &stats)?;
+            writeln!(output)?;
+        }
+    }
+    output.finish()?;
+    Ok(())
+}
+
+fn profile(args: &StatsArgs) -> Result<(), Box<dyn Error>> {
+    let mut profiles = Profiles::new(args.top);
+    for path in &args.inputs {
+        for doc in json_iter::load_from_file::<Value>(path)? {
+            profiles.add(&doc?);
+        }
+    }
+    let mut output = io::create(&args.output)?;
+    match args.format {
+        StatsFormat::Text => write_profiles_text(&mut output, &profiles)?,
+        StatsFormat::Json => {
+            serde_json::to_writer_pretty(&mut output, &profiles)?;
+            writeln!(output)?;
+        }
+    }
+    output.finish()?;
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use std::error::Error;
+
+use serde_json::Value;
+
+use read_json_in_rust::io;
+use read_json_in_rust::json_iter;
+use read_json_in_rust::table::TableOptions;
+
+use crate::cli::ToCsvArgs;
+
+pub fn to_csv(args: &ToCsvArgs) -> Result<(), Box<dyn Error>> {
+    let output = io::WriteOptions::new().bom(args.bom).create(&args.output)?;
+    let mut writer = TableOptions::new()
+        .delimiter(args.delimiter)
+        .quote(args.quote.into())
+        .header(!args.no_header)
+        .writer(output, args.columns.clone())?;
+    for path in &args.inputs {
+        for doc in json_iter::load_from_file::<Value>(path)? {
+            writer.write(&doc?)?;
+        }
+    }
+    writer.finish()?.finish()?;
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use std::error::Error;
+use std::io::Write;
+
+use serde_json::Value;
+
+use read_json_in_rust::infer::SchemaInference;
+use read_json_in_rust::{io, json_iter};
+
+use crate::cli::{InferSchemaArgs, ValidateArgs};
+
+/// Print every violation with the input and the number of the document,
+/// starting at 1, and fail if there were any.
+pub fn validate(args: &ValidateArgs) -> Result<(), Box<dyn Error>> {
+    let (mut total, mut invalid) = (0, 0);
+    for path in &args.inputs {
+        for (index, doc) in json_iter::load_from_file::<Value>(path)?.enumerate() {
+            let doc = doc?;
+            total += 1;
+            if args.schema.is_valid(&doc) {
+                continue;
+            }
+            invalid += 1;
+            for violation in args.schema.violations(&doc) {
+                println!("{}: document {}: {}", path, index + 1, violation);
+            }
+        }
+    }
+    if invalid > 0 {
+        return Err(format!("{} of {} documents don't match the schema", invalid, total).into());
+    }
+    eprintln!("All {} documents match the schema", total);
+    Ok(())
+}
+
+pub fn infer_schema(args: &InferSchemaArgs) -> Result<(), Box<dyn Error>> {
+    let mut inference = SchemaInference::new().closed(args.closed);
+    for path in &args.inputs {
+        for doc in json_iter::load_from_file::<Value>(path)? {
+            inference.add(&doc?);
+        }
+    }
+    let mut output = io::create(&args.output)?;
+    serde_json::to_writer_pretty(&mut output, &inference.schema())?;
+    writeln!(output)?;
+    output.finish()?;
+    Ok(())
+}
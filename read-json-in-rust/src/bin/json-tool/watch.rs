@@ -0,0 +1,82 @@
+//! `--watch`: converting again whenever an input changes.
+//!
+//! The inputs are polled for their modification time and size rather than
+//! watched with inotify or FSEvents (e.g. through the notify crate): those
+//! don't see changes made on a network mount by another machine, and
+//! checking a few files twice a second costs next to nothing. Patterns and
+//! directories are expanded again on every poll, so files that appear or
+//! disappear count as changes.
+
+use std::convert::Infallible;
+use std::error::Error;
+use std::fs;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::cli::{self, Cli};
+
+const POLL: Duration = Duration::from_millis(500);
+
+/// The files the inputs stand for with the modification time and size of
+/// each, `None` for one that doesn't exist, e.g. while an editor replaces
+/// it, or the error expanding the inputs, e.g. a pattern that no longer
+/// matches anything.
+type Snapshot = Result<Vec<(String, Option<(SystemTime, u64)>)>, String>;
+
+fn snapshot(patterns: &[String], suffix: &str) -> Snapshot {
+    let inputs = cli::expand_all(patterns, suffix).map_err(|err| err.to_string())?;
+    Ok(inputs
+        .into_iter()
+        .map(|input| {
+            let metadata = fs::metadata(&input).ok();
+            let state =
+                metadata.and_then(|metadata| Some((metadata.modified().ok()?, metadata.len())));
+            (input, state)
+        })
+        .collect())
+}
+
+/// Call `run` with the inputs of the conversion of `cli` expanded, then
+/// again every time the files they stand for have changed and stayed the
+/// same for one poll, so that a file is read once it's written. A failed run
+/// is reported by `run` and waits for the next change like any other.
+pub fn watch(cli: &mut Cli, mut run: impl FnMut(&Cli)) -> Result<Infallible, Box<dyn Error>> {
+    let args = cli
+        .conversion()
+        .expect("--watch to be an option of conversions");
+    let (patterns, suffix) = (args.inputs.clone(), args.members.clone());
+    if patterns.iter().any(|input| input == "-") {
+        return Err("--watch needs input files, not stdin".into());
+    }
+    let mut last = snapshot(&patterns, &suffix);
+    loop {
+        match cli::expand_all(&patterns, &suffix) {
+            Ok(inputs) => {
+                let args = cli.conversion_mut().expect("a conversion");
+                args.inputs = inputs;
+                run(cli);
+            }
+            Err(err) => eprintln!("error: {}", err),
+        }
+        match patterns.as_slice() {
+            [input] => eprintln!("watching {} for changes", input),
+            _ => eprintln!("watching {} inputs for changes", patterns.len()),
+        }
+        loop {
+            thread::sleep(POLL);
+            let now = snapshot(&patterns, &suffix);
+            if now != last {
+                last = now;
+                break;
+            }
+        }
+        loop {
+            thread::sleep(POLL);
+            let now = snapshot(&patterns, &suffix);
+            if now == last {
+                break;
+            }
+            last = now;
+        }
+    }
+}
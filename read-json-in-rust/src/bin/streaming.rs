@@ -0,0 +1,67 @@
+use std::process::ExitCode;
+use std::time::Instant;
+
+use serde_json::Value;
+
+use read_json_in_rust::json_iter::{self, JsonIterError};
+use read_json_in_rust::report::Report;
+use read_json_in_rust::transform::{DocumentTransform, SetField, Transforms};
+
+const USAGE: &str = "usage: streaming [--threads N]";
+
+fn main() -> ExitCode {
+    let start = Instant::now();
+
+    // 0 lets rayon use one thread per CPU.
+    let threads = match parse_threads(std::env::args().skip(1)) {
+        Some(threads) => threads,
+        None => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(err) = run(threads) {
+        match err.byte_offset() {
+            Some(offset) => eprintln!("error at byte {}: {}", offset, err),
+            None => eprintln!("error: {}", err),
+        }
+        return ExitCode::FAILURE;
+    }
+    println!("{}", Report::new(start.elapsed()));
+    ExitCode::SUCCESS
+}
+
+fn parse_threads(mut args: impl Iterator<Item = String>) -> Option<usize> {
+    match args.next().as_deref() {
+        None => Some(0),
+        Some("--threads") => {
+            let threads = args.next()?.parse().ok()?;
+            args.next().is_none().then_some(threads)
+        }
+        Some(_) => None,
+    }
+}
+
+fn run(threads: usize) -> Result<(), JsonIterError> {
+    let transforms = Transforms::new()
+        .with(SetField {
+            field: "lexiconName".to_string(),
+            value: "skbl2".into(),
+        })
+        .with(SetField {
+            field: "lexiconOrder".to_string(),
+            value: 48.into(),
+        });
+    let doc_update = |mut doc: Value| {
+        // Setting fields can't fail.
+        transforms.apply(&mut doc).ok();
+        doc
+    };
+
+    let data_source = json_iter::load_from_file("data/skbl.json")?;
+    json_iter::try_dump_to_file(
+        "data/skbl2_rust_streaming.json",
+        json_iter::par_map(data_source, threads, doc_update)?,
+    )
+}
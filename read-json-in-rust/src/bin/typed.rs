@@ -0,0 +1,71 @@
+use std::process::ExitCode;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use read_json_in_rust::json_iter::{self, JsonIterError};
+use read_json_in_rust::report::Report;
+
+const USAGE: &str = "usage: typed [--threads N]";
+
+/// An entry of SKBL with the fields we update as typed fields, and the rest
+/// kept as they are.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SkblEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lexicon_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lexicon_order: Option<u32>,
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+fn main() -> ExitCode {
+    let start = Instant::now();
+
+    // 0 lets rayon use one thread per CPU.
+    let threads = match parse_threads(std::env::args().skip(1)) {
+        Some(threads) => threads,
+        None => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(err) = run(threads) {
+        match err.byte_offset() {
+            Some(offset) => eprintln!("error at byte {}: {}", offset, err),
+            None => eprintln!("error: {}", err),
+        }
+        return ExitCode::FAILURE;
+    }
+    println!("{}", Report::new(start.elapsed()));
+    ExitCode::SUCCESS
+}
+
+fn parse_threads(mut args: impl Iterator<Item = String>) -> Option<usize> {
+    match args.next().as_deref() {
+        None => Some(0),
+        Some("--threads") => {
+            let threads = args.next()?.parse().ok()?;
+            args.next().is_none().then_some(threads)
+        }
+        Some(_) => None,
+    }
+}
+
+fn run(threads: usize) -> Result<(), JsonIterError> {
+    fn doc_update(mut doc: SkblEntry) -> SkblEntry {
+        doc.lexicon_name = Some("skbl2".to_string());
+        doc.lexicon_order = Some(48);
+        doc
+    }
+
+    let data_source = json_iter::load_from_file::<SkblEntry>("data/skbl.json")?;
+    json_iter::try_dump_to_file(
+        "data/skbl2_rust_typed.json",
+        json_iter::par_map(data_source, threads, doc_update)?,
+    )
+}
@@ -0,0 +1,228 @@
+//! The newline-delimited format of the Elasticsearch bulk API.
+//!
+//! Every document is written on its own line after an action line
+//! `{"index":{"_index":...,"_id":...}}`, so the output can be posted to
+//! `_bulk` as is (in parts, for large outputs).
+//!
+//! [`load_bulk_from_reader`] reads the documents of such a file back.
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::io::Input;
+use crate::json_iter::{self, JsonIterError, Lines};
+use crate::number::{self, NumberFormat};
+use crate::path::FieldPath;
+
+#[derive(Serialize)]
+struct Action<'a> {
+    index: Metadata<'a>,
+}
+
+#[derive(Serialize)]
+struct Metadata<'a> {
+    #[serde(rename = "_index")]
+    index: &'a str,
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+}
+
+/// Writes documents with `index` actions for the bulk API.
+pub struct BulkWriter<W: Write> {
+    writer: W,
+    index: String,
+    id_field: Option<FieldPath>,
+    remove_id: bool,
+    numbers: Option<NumberFormat>,
+}
+
+impl<W: Write> BulkWriter<W> {
+    /// Index the documents in `index`, with ids generated by Elasticsearch.
+    pub fn new(writer: W, index: &str) -> Self {
+        Self {
+            writer,
+            index: index.to_string(),
+            id_field: None,
+            remove_id: false,
+            numbers: None,
+        }
+    }
+
+    /// Use the value of this field (a string or a number) as the id of each
+    /// document.
+    pub fn id_field(mut self, path: FieldPath) -> Self {
+        self.id_field = Some(path);
+        self
+    }
+
+    /// Leave the id field out of the documents written, e.g. for `_id`, which
+    /// Elasticsearch doesn't accept in a document.
+    pub fn remove_id(mut self, remove: bool) -> Self {
+        self.remove_id = remove;
+        self
+    }
+
+    /// Write the floats of the documents in `format`, see [`crate::number`].
+    pub fn numbers(mut self, format: NumberFormat) -> Self {
+        self.numbers = Some(format);
+        self
+    }
+
+    pub fn write(&mut self, doc: &Value) -> Result<(), BulkError> {
+        let mut action = Action {
+            index: Metadata {
+                index: &self.index,
+                id: None,
+            },
+        };
+        if let Some(path) = &self.id_field {
+            action.index.id = match path.get(doc) {
+                Some(Value::String(id)) => Some(id.clone()),
+                Some(Value::Number(id)) => Some(id.to_string()),
+                Some(Value::Null) | None => {
+                    return Err(BulkError::MissingId {
+                        field: path.to_string(),
+                    })
+                }
+                Some(id) => return Err(BulkError::InvalidId { id: id.clone() }),
+            };
+        }
+        serde_json::to_writer(&mut self.writer, &action)?;
+        self.writer.write_all(b"\n")?;
+        let removed;
+        let doc = match &self.id_field {
+            Some(path) if self.remove_id => {
+                let mut doc = doc.clone();
+                path.remove(&mut doc);
+                removed = doc;
+                &removed
+            }
+            _ => doc,
+        };
+        match self.numbers {
+            Some(format) => number::to_writer(&mut self.writer, doc, format)?,
+            None => serde_json::to_writer(&mut self.writer, doc)?,
+        }
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Returns the underlying writer, flushed.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Iterator over the documents of the `index` and `create` actions of a bulk
+/// file, `delete` actions are skipped.
+///
+/// Yields `Err` at most once, after which the iterator is exhausted.
+pub struct BulkDocuments<R: BufRead> {
+    lines: Option<Lines<Value, R>>,
+    action: u64,
+}
+
+impl<R: BufRead> BulkDocuments<R> {
+    fn next_document(&mut self) -> Option<Result<Value, JsonIterError>> {
+        let lines = self.lines.as_mut()?;
+        loop {
+            let action = match lines.next()? {
+                Ok(action) => action,
+                Err(err) => return Some(Err(err)),
+            };
+            self.action += 1;
+            let name = action
+                .as_object()
+                .filter(|action| action.len() == 1)
+                .and_then(|action| action.keys().next());
+            let message = match name.map(String::as_str) {
+                Some("index" | "create") => match lines.next() {
+                    Some(doc) => return Some(doc),
+                    None => "has no document",
+                },
+                Some("delete") => continue,
+                _ => "is not an index, create or delete action",
+            };
+            return Some(Err(JsonIterError::Bulk {
+                action: self.action,
+                message,
+            }));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for BulkDocuments<R> {
+    type Item = Result<Value, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.next_document();
+        if !matches!(result, Some(Ok(_))) {
+            self.lines = None;
+        }
+        result
+    }
+}
+
+/// Iterate over the documents of the bulk file read from `reader`.
+pub fn load_bulk_from_reader<R: BufRead>(reader: R) -> BulkDocuments<R> {
+    BulkDocuments {
+        lines: Some(json_iter::load_ndjson_from_reader(reader)),
+        action: 0,
+    }
+}
+
+/// Open `path` and iterate over the documents of the bulk file it contains.
+pub fn load_bulk(path: &str) -> Result<BulkDocuments<Input>, JsonIterError> {
+    Ok(load_bulk_from_reader(crate::io::open(path)?))
+}
+
+/// Errors from writing the bulk format.
+#[derive(Debug)]
+pub enum BulkError {
+    Io(io::Error),
+    /// A document has no value (or `null`) for the id field.
+    MissingId {
+        field: String,
+    },
+    /// The id of a document is neither a string nor a number.
+    InvalidId {
+        id: Value,
+    },
+}
+
+impl fmt::Display for BulkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BulkError::Io(err) => write!(f, "io error: {}", err),
+            BulkError::MissingId { field } => write!(f, "document without id field '{}'", field),
+            BulkError::InvalidId { id } => {
+                write!(f, "id must be a string or a number, not {}", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BulkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BulkError::Io(err) => Some(err),
+            BulkError::MissingId { .. } | BulkError::InvalidId { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for BulkError {
+    fn from(err: io::Error) -> Self {
+        BulkError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for BulkError {
+    fn from(err: serde_json::Error) -> Self {
+        BulkError::Io(err.into())
+    }
+}
@@ -0,0 +1,85 @@
+//! CBOR, with [ciborium](https://crates.io/crates/ciborium): like
+//! [`msgpack`](crate::msgpack), the documents are written one value after
+//! another (a CBOR sequence) and streamed one at a time.
+
+use std::io::{BufRead, Write};
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::io::{self, Input};
+use crate::json_iter::JsonIterError;
+
+/// Iterator over the documents of a CBOR sequence.
+///
+/// Yields `Err` at most once, after which the iterator is exhausted.
+pub struct Items<T, R: BufRead> {
+    reader: Option<R>,
+    document: u64,
+    _doc: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned, R: BufRead> Iterator for Items<T, R> {
+    type Item = Result<T, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reader = self.reader.as_mut()?;
+        let result = match reader.fill_buf() {
+            Ok([]) => {
+                self.reader = None;
+                return None;
+            }
+            Ok(_) => {
+                self.document += 1;
+                ciborium::from_reader(&mut *reader).map_err(|source| JsonIterError::Cbor {
+                    document: self.document,
+                    source,
+                })
+            }
+            Err(err) => Err(err.into()),
+        };
+        if result.is_err() {
+            self.reader = None;
+        }
+        Some(result)
+    }
+}
+
+/// Iterate over the CBOR documents read from `reader`.
+pub fn load_from_reader<T: DeserializeOwned, R: BufRead>(reader: R) -> Items<T, R> {
+    Items {
+        reader: Some(reader),
+        document: 0,
+        _doc: PhantomData,
+    }
+}
+
+/// Open `path` and iterate over the CBOR documents it contains.
+pub fn load_from_file<T: DeserializeOwned>(path: &str) -> Result<Items<T, Input>, JsonIterError> {
+    Ok(load_from_reader(io::open(path)?))
+}
+
+/// Writes documents as a CBOR sequence.
+pub struct CborWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CborWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write<T: Serialize>(
+        &mut self,
+        doc: &T,
+    ) -> Result<(), ciborium::ser::Error<std::io::Error>> {
+        ciborium::into_writer(doc, &mut self.writer)
+    }
+
+    /// Returns the underlying writer, flushed.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
@@ -0,0 +1,338 @@
+//! Columnar output: Arrow IPC (Feather) files with the `arrow` feature, and
+//! Parquet files with the `parquet` feature.
+//!
+//! Documents are converted to Arrow record batches with
+//! [arrow-json](https://crates.io/crates/arrow-json), one per row group. The
+//! schema is given, or inferred from the documents of the first row group;
+//! fields that aren't in the schema are left out.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use arrow_array::RecordBatch;
+use arrow_ipc::writer::FileWriter;
+use arrow_json::reader::{infer_json_schema_from_iterator, Decoder, ReaderBuilder};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+#[cfg(feature = "parquet")]
+use parquet::arrow::ArrowWriter;
+#[cfg(feature = "parquet")]
+use parquet::errors::ParquetError;
+#[cfg(feature = "parquet")]
+use parquet::file::properties::WriterProperties;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Number of documents per row group (or record batch) by default.
+pub const ROW_GROUP_SIZE: usize = 100_000;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SchemaFile {
+    fields: Vec<FieldEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FieldEntry {
+    name: String,
+    #[serde(rename = "type")]
+    data_type: String,
+    #[serde(default = "nullable")]
+    nullable: bool,
+}
+
+fn nullable() -> bool {
+    true
+}
+
+/// Read an Arrow schema from a TOML file with the fields in order, where the
+/// type is written as Arrow displays it (e.g. `Int64`, `Utf8` or
+/// `List(Utf8)`) and fields are nullable by default:
+///
+/// ```toml
+/// [[fields]]
+/// name = "lexiconOrder"
+/// type = "Int64"
+/// nullable = false
+/// ```
+pub fn schema_from_file(path: &str) -> io::Result<SchemaRef> {
+    let invalid = |err: String| io::Error::new(io::ErrorKind::InvalidData, err);
+    let text = fs::read_to_string(path)?;
+    let file: SchemaFile = toml::from_str(&text).map_err(|err| invalid(err.to_string()))?;
+    let fields = file
+        .fields
+        .into_iter()
+        .map(|field| {
+            let data_type: DataType = field
+                .data_type
+                .parse()
+                .map_err(|err: ArrowError| invalid(format!("field {}: {}", field.name, err)))?;
+            Ok(Field::new(field.name, data_type, field.nullable))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    Ok(Arc::new(Schema::new(fields)))
+}
+
+/// A file format the record batches are written to.
+trait Sink<W>: Sized {
+    fn create(writer: W, schema: SchemaRef, row_group_size: usize) -> Result<Self, ColumnarError>;
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), ColumnarError>;
+
+    fn finish(self) -> Result<W, ColumnarError>;
+}
+
+enum State<W, S> {
+    /// The documents of the first row group, before the schema is inferred.
+    Pending { writer: W, docs: Vec<Value> },
+    Writing {
+        sink: Box<S>,
+        decoder: Decoder,
+        rows: usize,
+    },
+}
+
+/// Collects documents into record batches for a [`Sink`].
+struct Batches<W, S> {
+    state: Option<State<W, S>>,
+    row_group_size: usize,
+}
+
+impl<W: Write, S: Sink<W>> Batches<W, S> {
+    fn new(
+        writer: W,
+        schema: Option<SchemaRef>,
+        row_group_size: usize,
+    ) -> Result<Self, ColumnarError> {
+        let row_group_size = row_group_size.max(1);
+        let state = match schema {
+            Some(schema) => start(writer, schema, row_group_size)?,
+            None => State::Pending {
+                writer,
+                docs: Vec::new(),
+            },
+        };
+        Ok(Self {
+            state: Some(state),
+            row_group_size,
+        })
+    }
+
+    /// Infer the schema from the pending documents and start writing.
+    fn infer(&mut self) -> Result<(), ColumnarError> {
+        if !matches!(self.state, Some(State::Pending { .. })) {
+            return Ok(());
+        }
+        let Some(State::Pending { writer, docs }) = self.state.take() else {
+            unreachable!("the state to be pending")
+        };
+        let schema = infer_json_schema_from_iterator(docs.iter().map(Ok))?;
+        let mut state = start(writer, Arc::new(schema), self.row_group_size)?;
+        if let State::Writing { decoder, rows, .. } = &mut state {
+            decoder.serialize(&docs)?;
+            *rows = docs.len();
+        }
+        self.state = Some(state);
+        Ok(())
+    }
+
+    fn write(&mut self, doc: &Value) -> Result<(), ColumnarError> {
+        match self.state.as_mut().expect("writer to be open") {
+            State::Pending { docs, .. } => {
+                docs.push(doc.clone());
+                if docs.len() == self.row_group_size {
+                    self.infer()?;
+                }
+            }
+            State::Writing { decoder, rows, .. } => {
+                decoder.serialize(std::slice::from_ref(doc))?;
+                *rows += 1;
+            }
+        }
+        if let Some(State::Writing {
+            sink,
+            decoder,
+            rows,
+        }) = &mut self.state
+        {
+            if *rows >= self.row_group_size {
+                write_batch(sink.as_mut(), decoder)?;
+                *rows = 0;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<W, ColumnarError> {
+        self.infer()?;
+        let Some(State::Writing {
+            mut sink,
+            mut decoder,
+            ..
+        }) = self.state.take()
+        else {
+            unreachable!("the schema to be inferred")
+        };
+        write_batch(sink.as_mut(), &mut decoder)?;
+        sink.finish()
+    }
+}
+
+fn start<W, S: Sink<W>>(
+    writer: W,
+    schema: SchemaRef,
+    row_group_size: usize,
+) -> Result<State<W, S>, ColumnarError> {
+    let decoder = ReaderBuilder::new(schema.clone())
+        .with_batch_size(row_group_size)
+        .build_decoder()?;
+    Ok(State::Writing {
+        sink: Box::new(S::create(writer, schema, row_group_size)?),
+        decoder,
+        rows: 0,
+    })
+}
+
+fn write_batch<W, S: Sink<W>>(sink: &mut S, decoder: &mut Decoder) -> Result<(), ColumnarError> {
+    if let Some(batch) = decoder.flush()? {
+        sink.write(&batch)?;
+    }
+    Ok(())
+}
+
+macro_rules! columnar_writer {
+    ($(#[$attr:meta])* $name:ident, $sink:ty) => {
+        $(#[$attr])*
+        pub struct $name<W: Write + Send> {
+            batches: Batches<W, $sink>,
+        }
+
+        impl<W: Write + Send> $name<W> {
+            /// Writes row groups of `row_group_size` documents (at least 1),
+            /// with `schema` or the schema inferred from the first row group.
+            pub fn new(
+                writer: W,
+                schema: Option<SchemaRef>,
+                row_group_size: usize,
+            ) -> Result<Self, ColumnarError> {
+                Ok(Self {
+                    batches: Batches::new(writer, schema, row_group_size)?,
+                })
+            }
+
+            pub fn write(&mut self, doc: &Value) -> Result<(), ColumnarError> {
+                self.batches.write(doc)
+            }
+
+            /// Writes the last row group and the footer, and returns the
+            /// underlying writer, flushed.
+            pub fn finish(self) -> Result<W, ColumnarError> {
+                let mut writer = self.batches.finish()?;
+                writer.flush()?;
+                Ok(writer)
+            }
+        }
+    };
+}
+
+impl<W: Write> Sink<W> for FileWriter<W> {
+    fn create(writer: W, schema: SchemaRef, _: usize) -> Result<Self, ColumnarError> {
+        Ok(FileWriter::try_new(writer, &schema)?)
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), ColumnarError> {
+        Ok(FileWriter::write(self, batch)?)
+    }
+
+    fn finish(mut self) -> Result<W, ColumnarError> {
+        FileWriter::finish(&mut self)?;
+        Ok(self.into_inner()?)
+    }
+}
+
+columnar_writer!(
+    /// Writes documents to an Arrow IPC file (Feather version 2), with a
+    /// record batch per row group.
+    IpcWriter,
+    FileWriter<W>
+);
+
+#[cfg(feature = "parquet")]
+impl<W: Write + Send> Sink<W> for ArrowWriter<W> {
+    fn create(writer: W, schema: SchemaRef, row_group_size: usize) -> Result<Self, ColumnarError> {
+        let properties = WriterProperties::builder()
+            .set_max_row_group_row_count(Some(row_group_size))
+            .build();
+        Ok(ArrowWriter::try_new(writer, schema, Some(properties))?)
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), ColumnarError> {
+        ArrowWriter::write(self, batch)?;
+        Ok(self.flush()?)
+    }
+
+    fn finish(self) -> Result<W, ColumnarError> {
+        Ok(self.into_inner()?)
+    }
+}
+
+#[cfg(feature = "parquet")]
+columnar_writer!(
+    /// Writes documents to a Parquet file.
+    ParquetWriter,
+    ArrowWriter<W>
+);
+
+/// Errors from writing columnar files.
+#[derive(Debug)]
+pub enum ColumnarError {
+    /// A document doesn't match the schema, or the IPC file couldn't be
+    /// written.
+    Arrow(ArrowError),
+    #[cfg(feature = "parquet")]
+    Parquet(ParquetError),
+    Io(io::Error),
+}
+
+impl fmt::Display for ColumnarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnarError::Arrow(err) => write!(f, "failed to convert documents: {}", err),
+            #[cfg(feature = "parquet")]
+            ColumnarError::Parquet(err) => write!(f, "failed to write Parquet: {}", err),
+            ColumnarError::Io(err) => write!(f, "io error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ColumnarError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ColumnarError::Arrow(err) => Some(err),
+            #[cfg(feature = "parquet")]
+            ColumnarError::Parquet(err) => Some(err),
+            ColumnarError::Io(err) => Some(err),
+        }
+    }
+}
+
+impl From<ArrowError> for ColumnarError {
+    fn from(err: ArrowError) -> Self {
+        ColumnarError::Arrow(err)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<ParquetError> for ColumnarError {
+    fn from(err: ParquetError) -> Self {
+        ColumnarError::Parquet(err)
+    }
+}
+
+impl From<io::Error> for ColumnarError {
+    fn from(err: io::Error) -> Self {
+        ColumnarError::Io(err)
+    }
+}
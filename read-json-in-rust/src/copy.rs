@@ -0,0 +1,86 @@
+//! The text format of PostgreSQL `COPY ... FROM STDIN`, with a row per
+//! document and the document as its only column (e.g. `jsonb`).
+//!
+//! With a table, the rows are wrapped in the `COPY` statement so the output
+//! can be piped to `psql` as is:
+//!
+//! ```text
+//! COPY lexicon (entry) FROM STDIN;
+//! {"_id":"1","entry":{"lemma":"a\\b"}}
+//! \.
+//! ```
+
+use std::io::{self, Write};
+
+use serde_json::Value;
+
+use crate::number::{self, NumberFormat};
+
+/// Writes documents as rows of `COPY` data.
+pub struct CopyWriter<W: Write> {
+    writer: W,
+    buffer: String,
+    statement: bool,
+    numbers: Option<NumberFormat>,
+}
+
+impl<W: Write> CopyWriter<W> {
+    /// Writes only the rows, e.g. for `\copy lexicon (entry) FROM 'file'`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            buffer: String::new(),
+            statement: false,
+            numbers: None,
+        }
+    }
+
+    /// Writes the rows in a `COPY` statement for `table`, which may name the
+    /// column as in `lexicon (entry)`.
+    pub fn with_statement(mut writer: W, table: &str) -> io::Result<Self> {
+        writeln!(writer, "COPY {} FROM STDIN;", table)?;
+        Ok(Self {
+            statement: true,
+            ..Self::new(writer)
+        })
+    }
+
+    /// Write the floats of the documents in `format`, see [`crate::number`].
+    pub fn numbers(mut self, format: NumberFormat) -> Self {
+        self.numbers = Some(format);
+        self
+    }
+
+    pub fn write(&mut self, doc: &Value) -> io::Result<()> {
+        self.buffer.clear();
+        let json = match self.numbers {
+            Some(format) => {
+                let mut json = Vec::new();
+                number::to_writer(&mut json, doc, format)?;
+                String::from_utf8(json).expect("JSON is UTF-8")
+            }
+            None => doc.to_string(),
+        };
+        for c in json.chars() {
+            match c {
+                '\\' => self.buffer.push_str("\\\\"),
+                '\n' => self.buffer.push_str("\\n"),
+                '\r' => self.buffer.push_str("\\r"),
+                '\t' => self.buffer.push_str("\\t"),
+                c => self.buffer.push(c),
+            }
+        }
+        self.buffer.push('\n');
+        self.writer.write_all(self.buffer.as_bytes())
+    }
+
+    /// Ends the data if there is a statement, and returns the underlying
+    /// writer, flushed.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.statement {
+            self.writer.write_all(b"\\.\n")?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
@@ -0,0 +1,148 @@
+//! Dropping documents with the same value of a key field.
+//!
+//! Only a 64-bit hash of each key is kept in memory, not the key itself, so
+//! two different keys are taken for the same with a probability of about
+//! `n² / 2⁶⁵` for `n` distinct keys.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{self, BufReader, BufWriter, Seek, SeekFrom, Write};
+
+use serde_json::Value;
+
+use crate::json_iter::{self, JsonIterError, Lines};
+use crate::path::FieldPath;
+
+/// Which of the documents with the same key to keep.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Keep {
+    #[default]
+    First,
+    Last,
+}
+
+/// Feeds the serialized key to the hasher, without allocating a string.
+struct HashWriter<'a>(&'a mut DefaultHasher);
+
+impl Write for HashWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The hash of the key of `doc`, `None` if it has no key (or a `null` key).
+pub fn key_hash(doc: &Value, path: &FieldPath) -> Option<u64> {
+    let key = path.get(doc).filter(|key| !key.is_null())?;
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_writer(HashWriter(&mut hasher), key).ok()?;
+    Some(hasher.finish())
+}
+
+/// Remembers the keys seen so far.
+#[derive(Debug, Clone)]
+pub struct Dedup {
+    path: FieldPath,
+    seen: HashSet<u64>,
+}
+
+impl Dedup {
+    pub fn new(path: FieldPath) -> Self {
+        Self {
+            path,
+            seen: HashSet::new(),
+        }
+    }
+
+    /// `true` if the key of `doc` hasn't been seen before. Documents without
+    /// the key are always new.
+    pub fn is_new(&mut self, doc: &Value) -> bool {
+        match key_hash(doc, &self.path) {
+            Some(hash) => self.seen.insert(hash),
+            None => true,
+        }
+    }
+}
+
+/// Keep one document per key in `docs`.
+pub fn dedup_in_memory(docs: &mut Vec<Value>, path: &FieldPath, keep: Keep) {
+    let mut dedup = Dedup::new(path.clone());
+    match keep {
+        Keep::First => docs.retain(|doc| dedup.is_new(doc)),
+        Keep::Last => {
+            docs.reverse();
+            docs.retain(|doc| dedup.is_new(doc));
+            docs.reverse();
+        }
+    }
+}
+
+/// Iterator over the documents kept by [`dedup_last`].
+pub struct DedupLast {
+    path: FieldPath,
+    docs: Lines<Value, BufReader<File>>,
+    last: HashMap<u64, u64>,
+    index: u64,
+}
+
+impl Iterator for DedupLast {
+    type Item = Result<Value, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let doc = match self.docs.next()? {
+                Ok(doc) => doc,
+                Err(err) => return Some(Err(err)),
+            };
+            let index = self.index;
+            self.index += 1;
+            let kept = match key_hash(&doc, &self.path) {
+                Some(hash) => self.last.get(&hash) == Some(&index),
+                None => true,
+            };
+            if kept {
+                return Some(Ok(doc));
+            }
+        }
+    }
+}
+
+/// Keep the last document per key, in the order of the input.
+///
+/// The documents are written to a temporary file as newline-delimited JSON
+/// while the index of the last document of each key is recorded, and then
+/// read back. The first error from `docs` is returned before anything else.
+pub fn dedup_last<I, E>(docs: I, path: &FieldPath) -> Result<DedupLast, E>
+where
+    I: IntoIterator<Item = Result<Value, E>>,
+    E: From<JsonIterError>,
+{
+    let mut last = HashMap::new();
+    let mut writer = json_iter::LinesWriter::new(BufWriter::new(
+        tempfile::tempfile().map_err(JsonIterError::from)?,
+    ));
+    for (index, doc) in docs.into_iter().enumerate() {
+        let doc = doc?;
+        if let Some(hash) = key_hash(&doc, path) {
+            last.insert(hash, index as u64);
+        }
+        writer.write(&doc)?;
+    }
+    let mut file = writer
+        .finish()?
+        .into_inner()
+        .map_err(|err| JsonIterError::from(err.into_error()))?;
+    file.seek(SeekFrom::Start(0)).map_err(JsonIterError::from)?;
+    Ok(DedupLast {
+        path: path.clone(),
+        docs: json_iter::load_ndjson_from_reader(BufReader::new(file)),
+        last,
+        index: 0,
+    })
+}
@@ -0,0 +1,191 @@
+//! Differences between two dumps, matching documents by a key field.
+//!
+//! Both inputs are sorted by the key with a [`crate::sort::ExternalSort`] and
+//! then merged, so neither has to fit in memory.
+
+use std::cmp::Ordering;
+use std::iter::Peekable;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::json_iter::JsonIterError;
+use crate::path::{push_token, FieldPath};
+use crate::sort::{compare_keys, ExternalSort, Sorted};
+
+/// A difference between the old and the new dump.
+///
+/// Documents without the key (or with a `null` key) can't be matched, and are
+/// always reported as removed or added.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Change {
+    /// The document is only in the new dump.
+    Added { key: Value, doc: Value },
+    /// The document is only in the old dump.
+    Removed { key: Value, doc: Value },
+    /// The document is in both dumps, with different fields.
+    Changed {
+        key: Value,
+        fields: Vec<FieldChange>,
+    },
+}
+
+/// A field that differs between two documents. `old` is missing for an added
+/// field and `new` for a removed one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    /// JSON pointer to the field.
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+/// The fields that differ between `old` and `new`. Objects are compared field
+/// by field, other values (including arrays) as a whole.
+pub fn field_changes(old: &Value, new: &Value) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    compare(&mut String::new(), old, new, &mut changes);
+    changes
+}
+
+fn compare(path: &mut String, old: &Value, new: &Value, changes: &mut Vec<FieldChange>) {
+    let (Value::Object(old), Value::Object(new)) = (old, new) else {
+        if old != new {
+            changes.push(FieldChange {
+                path: path.clone(),
+                old: Some(old.clone()),
+                new: Some(new.clone()),
+            });
+        }
+        return;
+    };
+    let len = path.len();
+    for (key, old_value) in old {
+        push_token(path, key);
+        match new.get(key) {
+            Some(new_value) => compare(path, old_value, new_value, changes),
+            None => changes.push(FieldChange {
+                path: path.clone(),
+                old: Some(old_value.clone()),
+                new: None,
+            }),
+        }
+        path.truncate(len);
+    }
+    for (key, new_value) in new {
+        if !old.contains_key(key) {
+            push_token(path, key);
+            changes.push(FieldChange {
+                path: path.clone(),
+                old: None,
+                new: Some(new_value.clone()),
+            });
+            path.truncate(len);
+        }
+    }
+}
+
+/// Iterator over the changes between two dumps, ordered by key.
+pub struct Diff {
+    key: FieldPath,
+    old: Peekable<Sorted>,
+    new: Peekable<Sorted>,
+}
+
+fn key_of(key: &FieldPath, doc: &Value) -> Option<Value> {
+    key.get(doc).filter(|key| !key.is_null()).cloned()
+}
+
+impl Diff {
+    fn removed(&self, doc: Value) -> Change {
+        Change::Removed {
+            key: key_of(&self.key, &doc).unwrap_or_default(),
+            doc,
+        }
+    }
+
+    fn added(&self, doc: Value) -> Change {
+        Change::Added {
+            key: key_of(&self.key, &doc).unwrap_or_default(),
+            doc,
+        }
+    }
+}
+
+impl Iterator for Diff {
+    type Item = Result<Change, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let order = match (self.old.peek(), self.new.peek()) {
+                (None, None) => return None,
+                (Some(Err(_)), _) | (Some(_), None) => Ordering::Less,
+                (_, Some(Err(_))) | (None, Some(_)) => Ordering::Greater,
+                (Some(Ok(old)), Some(Ok(new))) => {
+                    match (key_of(&self.key, old), key_of(&self.key, new)) {
+                        (None, _) => Ordering::Less,
+                        (_, None) => Ordering::Greater,
+                        (old, new) => compare_keys(old.as_ref(), new.as_ref()),
+                    }
+                }
+            };
+            let change = match order {
+                Ordering::Less => match self.old.next()? {
+                    Ok(doc) => self.removed(doc),
+                    Err(err) => return Some(Err(err)),
+                },
+                Ordering::Greater => match self.new.next()? {
+                    Ok(doc) => self.added(doc),
+                    Err(err) => return Some(Err(err)),
+                },
+                Ordering::Equal => {
+                    let (Some(Ok(old)), Some(Ok(new))) = (self.old.next(), self.new.next()) else {
+                        unreachable!("both documents were peeked");
+                    };
+                    let fields = field_changes(&old, &new);
+                    if fields.is_empty() {
+                        continue;
+                    }
+                    Change::Changed {
+                        key: key_of(&self.key, &old).unwrap_or_default(),
+                        fields,
+                    }
+                }
+            };
+            return Some(Ok(change));
+        }
+    }
+}
+
+/// Compare the documents of `old` and `new`, matched by the value at `key`.
+///
+/// Documents with the same key in one dump are matched in order with the
+/// documents with that key in the other.
+pub fn diff<A, B>(old: A, new: B, key: &FieldPath) -> Result<Diff, JsonIterError>
+where
+    A: IntoIterator<Item = Result<Value, JsonIterError>>,
+    B: IntoIterator<Item = Result<Value, JsonIterError>>,
+{
+    diff_with(old, new, key, &ExternalSort::new())
+}
+
+/// Like [`diff`], sorting the dumps with `sort`.
+pub fn diff_with<A, B>(
+    old: A,
+    new: B,
+    key: &FieldPath,
+    sort: &ExternalSort,
+) -> Result<Diff, JsonIterError>
+where
+    A: IntoIterator<Item = Result<Value, JsonIterError>>,
+    B: IntoIterator<Item = Result<Value, JsonIterError>>,
+{
+    Ok(Diff {
+        key: key.clone(),
+        old: sort.sort(old, key)?.peekable(),
+        new: sort.sort(new, key)?.peekable(),
+    })
+}
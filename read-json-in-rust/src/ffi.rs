@@ -0,0 +1,300 @@
+//! A C API for streaming documents, with the `ffi` feature. The C library is
+//! built with `cargo rustc --release --features ffi --lib --crate-type cdylib`,
+//! and the declarations are in `include/json_iter.h`.
+//!
+//! Documents are passed as UTF-8 JSON buffers with a length, they are not
+//! NUL-terminated. The functions that can fail return `NULL` or `-1`, and
+//! [`json_iter_last_error`] describes the last error on the calling thread.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::fmt;
+use std::ptr;
+
+use serde_json::value::RawValue;
+
+use crate::io::{self, Input, Output};
+use crate::json_iter::{Loader, RawArrayWriter, RawDocuments};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(err: impl fmt::Display) {
+    // A NUL would end the message early in C.
+    let message = err.to_string().replace('\0', " ");
+    let message = CString::new(message).expect("no NUL in the message");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// A JSON array read one document at a time.
+pub struct JsonIterReader {
+    docs: RawDocuments<Input>,
+    /// The last document returned, valid until the next call.
+    current: Option<Box<RawValue>>,
+}
+
+/// A JSON array written one document at a time.
+pub struct JsonIterWriter {
+    writer: RawArrayWriter<Output>,
+}
+
+/// The path `path` as a `&str`, or `None` with the error set.
+///
+/// # Safety
+///
+/// `path` must be `NULL` or a NUL-terminated string.
+unsafe fn path_str<'a>(path: *const c_char) -> Option<&'a str> {
+    if path.is_null() {
+        set_error("the path is NULL");
+        return None;
+    }
+    match CStr::from_ptr(path).to_str() {
+        Ok(path) => Some(path),
+        Err(err) => {
+            set_error(format_args!("the path isn't UTF-8: {}", err));
+            None
+        }
+    }
+}
+
+/// Open the JSON array in the file `path`, see [`crate::io::open`], or
+/// return `NULL` on failure.
+///
+/// # Safety
+///
+/// `path` must be `NULL` or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn json_iter_open(path: *const c_char) -> *mut JsonIterReader {
+    let Some(path) = path_str(path) else {
+        return ptr::null_mut();
+    };
+    let docs = io::open(path)
+        .map_err(Into::into)
+        .and_then(|input| Loader::new().raw_from_reader(input));
+    match docs {
+        Ok(docs) => Box::into_raw(Box::new(JsonIterReader {
+            docs,
+            current: None,
+        })),
+        Err(err) => {
+            set_error(format_args!("{}: {}", path, err));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Point `doc` and `len` at the next document and return 1, or return 0 at
+/// the end of the array and -1 on failure.
+///
+/// The document stays valid until the next call with `reader`.
+///
+/// # Safety
+///
+/// `reader` must come from [`json_iter_open`] and not be closed, and `doc`
+/// and `len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn json_iter_next(
+    reader: *mut JsonIterReader,
+    doc: *mut *const c_char,
+    len: *mut usize,
+) -> c_int {
+    let Some(reader) = reader.as_mut() else {
+        set_error("the reader is NULL");
+        return -1;
+    };
+    reader.current = None;
+    match reader.docs.next() {
+        Some(Ok(raw)) => {
+            let json = reader.current.insert(raw).get();
+            *doc = json.as_ptr().cast();
+            *len = json.len();
+            1
+        }
+        Some(Err(err)) => {
+            set_error(err);
+            -1
+        }
+        None => 0,
+    }
+}
+
+/// Close `reader`, `NULL` is ignored.
+///
+/// # Safety
+///
+/// `reader` must come from [`json_iter_open`] and not be closed already.
+#[no_mangle]
+pub unsafe extern "C" fn json_iter_close(reader: *mut JsonIterReader) {
+    if !reader.is_null() {
+        drop(Box::from_raw(reader));
+    }
+}
+
+/// Create the file `path` for a JSON array, see [`crate::io::create`], or
+/// return `NULL` on failure.
+///
+/// # Safety
+///
+/// `path` must be `NULL` or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn json_iter_create(path: *const c_char) -> *mut JsonIterWriter {
+    let Some(path) = path_str(path) else {
+        return ptr::null_mut();
+    };
+    let writer = io::create(path)
+        .map_err(Into::into)
+        .and_then(RawArrayWriter::new);
+    match writer {
+        Ok(writer) => Box::into_raw(Box::new(JsonIterWriter { writer })),
+        Err(err) => {
+            set_error(format_args!("{}: {}", path, err));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Write the `len` bytes of JSON at `doc` as the next document and return 0,
+/// or -1 if it isn't valid JSON or writing fails.
+///
+/// # Safety
+///
+/// `writer` must come from [`json_iter_create`] and not be finished, and
+/// `doc` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn json_iter_write(
+    writer: *mut JsonIterWriter,
+    doc: *const c_char,
+    len: usize,
+) -> c_int {
+    let Some(writer) = writer.as_mut() else {
+        set_error("the writer is NULL");
+        return -1;
+    };
+    if doc.is_null() {
+        set_error("the document is NULL");
+        return -1;
+    }
+    let bytes = std::slice::from_raw_parts(doc.cast::<u8>(), len);
+    let raw = match std::str::from_utf8(bytes) {
+        Ok(json) => serde_json::from_str::<&RawValue>(json).map_err(|err| err.to_string()),
+        Err(err) => Err(format!("the document isn't UTF-8: {}", err)),
+    };
+    match raw.map(|raw| writer.writer.write_raw(raw).map_err(|err| err.to_string())) {
+        Ok(Ok(())) => 0,
+        Ok(Err(err)) | Err(err) => {
+            set_error(err);
+            -1
+        }
+    }
+}
+
+/// Close the array, flush and close the file, and free `writer`. Returns 0,
+/// or -1 on failure, when the file may be incomplete.
+///
+/// # Safety
+///
+/// `writer` must come from [`json_iter_create`] and not be finished already.
+#[no_mangle]
+pub unsafe extern "C" fn json_iter_finish(writer: *mut JsonIterWriter) -> c_int {
+    if writer.is_null() {
+        set_error("the writer is NULL");
+        return -1;
+    }
+    let writer = Box::from_raw(writer).writer;
+    let finished = writer
+        .finish()
+        .map_err(|err| err.to_string())
+        .and_then(|output| output.finish().map_err(|err| err.to_string()));
+    match finished {
+        Ok(()) => 0,
+        Err(err) => {
+            set_error(err);
+            -1
+        }
+    }
+}
+
+/// The message of the last error on this thread as a NUL-terminated string,
+/// or `NULL` if there was none. It stays valid until the next error.
+#[no_mangle]
+pub extern "C" fn json_iter_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |err| err.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c_path(path: &std::path::Path) -> CString {
+        CString::new(path.to_str().unwrap()).unwrap()
+    }
+
+    fn last_error() -> String {
+        let err = json_iter_last_error();
+        assert!(!err.is_null());
+        unsafe { CStr::from_ptr(err) }.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn rejects_a_null_path() {
+        unsafe {
+            assert!(json_iter_open(ptr::null()).is_null());
+            assert_eq!(last_error(), "the path is NULL");
+            assert!(json_iter_create(ptr::null()).is_null());
+            assert_eq!(last_error(), "the path is NULL");
+        }
+    }
+
+    #[test]
+    fn sets_the_last_error_on_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.json");
+        unsafe {
+            assert!(json_iter_open(c_path(&missing).as_ptr()).is_null());
+        }
+        let err = last_error();
+        assert!(err.starts_with(missing.to_str().unwrap()), "{}", err);
+
+        let path = c_path(&dir.path().join("out.json"));
+        unsafe {
+            let writer = json_iter_create(path.as_ptr());
+            assert!(!writer.is_null());
+            let doc = b"{\"a\": ";
+            assert_eq!(json_iter_write(writer, doc.as_ptr().cast(), doc.len()), -1);
+            assert!(last_error().contains("EOF"), "{}", last_error());
+            assert_eq!(json_iter_finish(writer), 0);
+        }
+    }
+
+    #[test]
+    fn returns_0_after_the_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = c_path(&dir.path().join("out.json.gz"));
+        let docs = [&b"{\"a\": 1}"[..], b"[2]"];
+        unsafe {
+            let writer = json_iter_create(path.as_ptr());
+            for doc in docs {
+                assert_eq!(json_iter_write(writer, doc.as_ptr().cast(), doc.len()), 0);
+            }
+            assert_eq!(json_iter_finish(writer), 0);
+
+            let reader = json_iter_open(path.as_ptr());
+            assert!(!reader.is_null());
+            let mut doc = ptr::null();
+            let mut len = 0;
+            let mut read = Vec::new();
+            while json_iter_next(reader, &mut doc, &mut len) == 1 {
+                read.push(std::slice::from_raw_parts(doc.cast::<u8>(), len).to_vec());
+            }
+            assert_eq!(read, [&b"{\"a\":1}"[..], b"[2]"]);
+            assert_eq!(json_iter_next(reader, &mut doc, &mut len), 0);
+            assert_eq!(json_iter_next(reader, &mut doc, &mut len), 0);
+            json_iter_close(reader);
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Predicates for selecting documents.
+
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::path::FieldPath;
+use crate::transform::parse_value;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Exists,
+    Equals(Value),
+    NotEquals(Value),
+}
+
+/// Matches documents by the value of a field: `entry.pos==nn` (or
+/// `entry.pos=nn`), `entry.pos!=nn`, or just `entry.pos` for documents where
+/// the field is present and not null.
+///
+/// The value is parsed as in [`crate::transform::SetField`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    path: FieldPath,
+    condition: Condition,
+}
+
+impl Filter {
+    pub fn matches(&self, doc: &Value) -> bool {
+        let value = self.path.get(doc);
+        match &self.condition {
+            Condition::Exists => !matches!(value, None | Some(Value::Null)),
+            Condition::Equals(expected) => value == Some(expected),
+            Condition::NotEquals(expected) => value != Some(expected),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, condition) = if let Some((path, value)) = s.split_once("!=") {
+            (path, Condition::NotEquals(parse_value(value)))
+        } else if let Some((path, value)) = s.split_once("==") {
+            (path, Condition::Equals(parse_value(value)))
+        } else if let Some((path, value)) = s.split_once('=') {
+            (path, Condition::Equals(parse_value(value)))
+        } else {
+            (s, Condition::Exists)
+        };
+        Ok(Self {
+            path: path.parse()?,
+            condition,
+        })
+    }
+}
@@ -0,0 +1,407 @@
+//! Expanding glob patterns such as `data/**/*.json` and directories to the
+//! files in them, so that inputs don't depend on the shell or on the limits
+//! of the command line.
+//!
+//! In a pattern, `*` matches any characters within a file name, `?` one
+//! character, `[abc]`, `[a-z]` and `[!abc]` one of a set of characters, and a
+//! `**` component any number of directories, none included. A `\` makes the
+//! next character match itself, as in `data/\[draft\]*.json`. As in the
+//! shell, a wildcard doesn't match a `.` at the start of a name. The files
+//! are in the order of their paths, compared by component, so that the same
+//! files always come in the same order.
+//!
+//! The matching is done here rather than with the glob or globset crates,
+//! since it takes little code and the rules needed for inputs, such as not
+//! following symbolic links with `**` and the order of the files, then don't
+//! depend on the defaults of another crate.
+//!
+//! ```no_run
+//! # use read_json_in_rust::glob;
+//! for path in glob::expand("data/**/*.json", ".json")? {
+//!     println!("{}", path);
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Whether `path` has wildcards and is a pattern rather than a file name.
+pub fn is_pattern(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// The files that the input `input` stands for: the files matching it if it
+/// is a pattern, the files under it whose names end with `suffix` (before
+/// `.gz` or `.zst`) if it is a directory, and otherwise `input` itself, such
+/// as a file or `-` for stdin.
+///
+/// A pattern that matches no files, or a directory without such files, is an
+/// error.
+pub fn expand(input: &str, suffix: &str) -> io::Result<Vec<String>> {
+    let mut paths = Vec::new();
+    if is_pattern(input) {
+        let mut root = PathBuf::new();
+        let mut components = Vec::new();
+        for component in Path::new(input).components() {
+            match component {
+                Component::Normal(name) => {
+                    let name = name.to_str().ok_or_else(|| not_utf8(Path::new(name)))?;
+                    if components.is_empty() && !has_wildcard(name) {
+                        root.push(unescape(name));
+                    } else {
+                        components.push(name);
+                    }
+                }
+                _ if components.is_empty() => root.push(component),
+                // `..` and `.` after a wildcard.
+                _ => components.push(component.as_os_str().to_str().unwrap_or_default()),
+            }
+        }
+        if components.is_empty() {
+            // Only escaped wildcards.
+            if root.is_file() {
+                paths.push(root);
+            }
+        } else {
+            walk(&root, &components, &mut paths)?;
+        }
+        if paths.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{}: no files match the pattern", input),
+            ));
+        }
+    } else if Path::new(input).is_dir() {
+        files(Path::new(input), suffix, &mut paths)
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", input, err)))?;
+        if paths.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{}: no files in the directory end with {}", input, suffix),
+            ));
+        }
+    } else {
+        return Ok(vec![input.to_string()]);
+    }
+    paths.sort();
+    paths.dedup();
+    paths
+        .into_iter()
+        .map(|path| {
+            path.into_os_string()
+                .into_string()
+                .map_err(|path| not_utf8(Path::new(&path)))
+        })
+        .collect()
+}
+
+/// Whether the file name pattern `name` has a wildcard that isn't escaped.
+fn has_wildcard(name: &str) -> bool {
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '*' | '?' | '[' => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// The file name pattern `name` without its escapes.
+fn unescape(name: &str) -> String {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+fn not_utf8(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: the path isn't UTF-8", path.display()),
+    )
+}
+
+/// The entries of the directory `dir`, where an empty path is the current
+/// directory.
+fn entries(dir: &Path) -> io::Result<Vec<fs::DirEntry>> {
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    fs::read_dir(dir)?.collect()
+}
+
+/// Add the files under `dir` that match the pattern `components` to `paths`.
+/// Directories that can't be read are skipped, as in the shell.
+fn walk(dir: &Path, components: &[&str], paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    let Some((&component, rest)) = components.split_first() else {
+        return Ok(());
+    };
+    let Ok(entries) = entries(dir) else {
+        return Ok(());
+    };
+    if component == "**" {
+        walk(dir, rest, paths)?;
+    }
+    for entry in entries {
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        let path = dir.join(entry.file_name());
+        // Symbolic links to directories aren't followed by `**`, which could
+        // loop.
+        let is_dir = entry.file_type()?.is_dir();
+        if component == "**" {
+            if is_dir && !name.starts_with('.') {
+                walk(&path, components, paths)?;
+            }
+        } else if matches(component, &name) {
+            if rest.is_empty() {
+                if path.is_file() {
+                    paths.push(path);
+                }
+            } else if path.is_dir() {
+                walk(&path, rest, paths)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Add the files under the directory `dir` whose names end with `suffix`,
+/// possibly followed by `.gz` or `.zst`, to `paths`. Hidden files and
+/// directories, whose names start with `.`, are skipped.
+fn files(dir: &Path, suffix: &str, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in entries(dir)? {
+        let name = entry.file_name();
+        let Some(name) = name.to_str().filter(|name| !name.starts_with('.')) else {
+            continue;
+        };
+        let path = dir.join(name);
+        if entry.file_type()?.is_dir() {
+            files(&path, suffix, paths)?;
+            continue;
+        }
+        let name = name.strip_suffix(".gz").unwrap_or(name);
+        let name = name.strip_suffix(".zst").unwrap_or(name);
+        if name.ends_with(suffix) && path.is_file() {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether the file name `name` matches the pattern `pattern`, without `/`.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') && !pattern.starts_with("\\.") {
+        return false;
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches_chars(&pattern, &name)
+}
+
+fn matches_chars(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| matches_chars(rest, &name[skip..])),
+        Some((&first, rest)) => {
+            let Some((&c, name_rest)) = name.split_first() else {
+                return false;
+            };
+            let rest = match first {
+                '\\' => match rest.split_first() {
+                    Some((&escaped, rest)) if escaped == c => rest,
+                    Some(_) => return false,
+                    // A `\` at the end is itself.
+                    None if c == '\\' => rest,
+                    None => return false,
+                },
+                '?' => rest,
+                '[' => match class(rest, c) {
+                    Some((true, rest)) => rest,
+                    Some((false, _)) => return false,
+                    // A `[` without a `]` is itself.
+                    None if c == '[' => rest,
+                    None => return false,
+                },
+                _ if first == c => rest,
+                _ => return false,
+            };
+            matches_chars(rest, name_rest)
+        }
+    }
+}
+
+/// Whether `c` is in the character class at the start of `pattern`, after the
+/// `[`, and the pattern after the class, or `None` if the class has no `]`.
+fn class(pattern: &[char], c: char) -> Option<(bool, &[char])> {
+    let (negated, mut rest) = match pattern.split_first() {
+        Some(('!' | '^', rest)) => (true, rest),
+        _ => (false, pattern),
+    };
+    let mut found = false;
+    let mut first = true;
+    loop {
+        match rest {
+            [']', after @ ..] if !first => return Some((found != negated, after)),
+            ['\\', single, after @ ..] => {
+                found |= *single == c;
+                rest = after;
+            }
+            [low, '-', high, after @ ..] if *high != ']' => {
+                found |= (*low..=*high).contains(&c);
+                rest = after;
+            }
+            [single, after @ ..] => {
+                found |= *single == c;
+                rest = after;
+            }
+            [] => return None,
+        }
+        first = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("*.json", "skbl.json"));
+        assert!(matches("*", "skbl.json"));
+        assert!(!matches("*.json", "skbl.json.gz"));
+        assert!(matches("skbl?.json", "skbl2.json"));
+        assert!(!matches("skbl?.json", "skbl.json"));
+        assert!(matches("*a*b*", "xaybz"));
+    }
+
+    #[test]
+    fn matches_classes() {
+        assert!(matches("part[0-9].json", "part7.json"));
+        assert!(!matches("part[0-9].json", "parta.json"));
+        assert!(matches("part[abc].json", "partb.json"));
+        assert!(matches("part[!abc].json", "partd.json"));
+        assert!(!matches("part[^abc].json", "parta.json"));
+        // `]` first in a class is itself, and a `[` without a `]` too.
+        assert!(matches("[]a]", "]"));
+        assert!(matches("a[b", "a[b"));
+    }
+
+    #[test]
+    fn matches_escapes() {
+        assert!(matches(r"\*.json", "*.json"));
+        assert!(!matches(r"\*.json", "skbl.json"));
+        assert!(matches(r"\[draft\]*", "[draft] skbl.json"));
+        assert!(!matches(r"\[draft\]*", "d skbl.json"));
+        assert!(matches(r"what\?", "what?"));
+        assert!(!matches(r"what\?", "whats"));
+        assert!(matches(r"[\]x]", "]"));
+        assert!(matches(r"a\\b", r"a\b"));
+    }
+
+    #[test]
+    fn wildcards_skip_hidden_names() {
+        assert!(!matches("*", ".hidden"));
+        assert!(!matches("?hidden", ".hidden"));
+        assert!(matches(".*", ".hidden"));
+        assert!(matches(r"\.hid*", ".hidden"));
+    }
+
+    /// A directory with `a.json`, `b.txt`, `.hidden.json`, `sub/c.json`,
+    /// `sub/deeper/d.json.gz`, `.git/e.json` and `[x].json`.
+    fn tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for file in [
+            "a.json",
+            "b.txt",
+            ".hidden.json",
+            "sub/c.json",
+            "sub/deeper/d.json.gz",
+            ".git/e.json",
+            "[x].json",
+        ] {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "[]").unwrap();
+        }
+        dir
+    }
+
+    /// The paths of `expand`, relative to `dir`.
+    fn expanded(dir: &tempfile::TempDir, input: &str, suffix: &str) -> io::Result<Vec<String>> {
+        let root = dir.path().to_str().unwrap();
+        let paths = expand(&format!("{}/{}", root, input), suffix)?;
+        Ok(paths
+            .into_iter()
+            .map(|path| path[root.len() + 1..].to_string())
+            .collect())
+    }
+
+    #[test]
+    fn expands_patterns() {
+        let dir = tree();
+        assert_eq!(
+            expanded(&dir, "*.json", "").unwrap(),
+            ["[x].json", "a.json"]
+        );
+        assert_eq!(
+            expanded(&dir, "**/*.json", "").unwrap(),
+            ["[x].json", "a.json", "sub/c.json"]
+        );
+        assert_eq!(
+            expanded(&dir, "**/*.json*", "").unwrap(),
+            ["[x].json", "a.json", "sub/c.json", "sub/deeper/d.json.gz"]
+        );
+        assert_eq!(expanded(&dir, "s*/c.json", "").unwrap(), ["sub/c.json"]);
+        assert_eq!(expanded(&dir, r"\[x\].json", "").unwrap(), ["[x].json"]);
+        assert_eq!(expanded(&dir, "[[]x].json", "").unwrap(), ["[x].json"]);
+    }
+
+    #[test]
+    fn expands_directories() {
+        let dir = tree();
+        assert_eq!(
+            expanded(&dir, "", ".json").unwrap(),
+            ["[x].json", "a.json", "sub/c.json", "sub/deeper/d.json.gz"]
+        );
+        assert_eq!(
+            expanded(&dir, "sub", ".json").unwrap(),
+            ["sub/c.json", "sub/deeper/d.json.gz"]
+        );
+    }
+
+    #[test]
+    fn keeps_other_inputs() {
+        assert_eq!(expand("-", ".json").unwrap(), ["-"]);
+        assert_eq!(expand("missing.json", ".json").unwrap(), ["missing.json"]);
+    }
+
+    #[test]
+    fn fails_when_nothing_matches() {
+        let dir = tree();
+        let err = expanded(&dir, "*.yaml", "").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err
+            .to_string()
+            .ends_with("*.yaml: no files match the pattern"));
+        let err = expanded(&dir, "sub", ".yaml").unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("sub: no files in the directory end with .yaml"));
+        assert!(expanded(&dir, r"\*.json", "").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! Load and dump a whole JSON array at once, as in the tutorial.
+//!
+//! The file is read to a `String` before parsing and the output is serialized
+//! to a `String` before writing, which was the fastest variant in the tutorial.
+//! Gzip compressed files and a leading byte order mark are handled as in
+//! [`crate::io`].
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+use memmap2::Mmap;
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
+use serde_json::Value;
+
+use crate::io::{create, open, Compression, BOM, STDIO};
+use crate::json_iter::Parser;
+
+/// Read the JSON array from `reader` into memory.
+pub fn load_from_reader<R: Read>(reader: R) -> io::Result<Vec<Value>> {
+    load_from_reader_with(reader, Parser::Serde)
+}
+
+/// Read the JSON array from `reader` into memory and parse it with `parser`.
+pub fn load_from_reader_with<R: Read>(mut reader: R, parser: Parser) -> io::Result<Vec<Value>> {
+    match parser {
+        Parser::Serde => {
+            let mut content = String::new();
+            reader.read_to_string(&mut content)?;
+            let content = content.strip_prefix('\u{feff}').unwrap_or(&content);
+            Ok(serde_json::from_str(content)?)
+        }
+        #[cfg(feature = "simd")]
+        Parser::Simd => {
+            let mut content = Vec::new();
+            reader.read_to_end(&mut content)?;
+            if content.starts_with(BOM) {
+                content.drain(..BOM.len());
+            }
+            simd_from_slice(&mut content)
+        }
+    }
+}
+
+#[cfg(feature = "simd")]
+fn simd_from_slice(bytes: &mut [u8]) -> io::Result<Vec<Value>> {
+    simd_json::serde::from_slice(bytes)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Read the JSON array in `path` into memory.
+pub fn load_from_file(path: &str) -> io::Result<Vec<Value>> {
+    load_from_reader(open(path)?)
+}
+
+/// Parse the JSON array in `path` directly from a memory map of the file.
+///
+/// This avoids the copy of the whole file into a `String`, but only works for
+/// uncompressed files.
+pub fn load_from_file_mmap(path: &str) -> io::Result<Vec<Value>> {
+    load_from_file_mmap_with(path, Parser::Serde)
+}
+
+/// Parse the JSON array in `path` with `parser` from a memory map of the file.
+///
+/// simd-json parses in place, so it gets a copy-on-write map.
+pub fn load_from_file_mmap_with(path: &str, parser: Parser) -> io::Result<Vec<Value>> {
+    if path == STDIO {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "stdin can't be memory mapped",
+        ));
+    }
+    let file = File::open(path)?;
+    // SAFETY: the map is only used while parsing, the file must not be
+    // truncated by another process meanwhile.
+    let mmap = unsafe { Mmap::map(&file)? };
+    if Compression::from_magic(&mmap) != Compression::None {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "compressed files can't be memory mapped",
+        ));
+    }
+    match parser {
+        Parser::Serde => Ok(serde_json::from_slice(
+            mmap.strip_prefix(BOM).unwrap_or(&mmap),
+        )?),
+        #[cfg(feature = "simd")]
+        Parser::Simd => {
+            // SAFETY: as above, changes are private to this process.
+            let mut mmap = unsafe { memmap2::MmapOptions::new().map_copy(&file)? };
+            let bom = if mmap.starts_with(BOM) { BOM.len() } else { 0 };
+            simd_from_slice(&mut mmap[bom..])
+        }
+    }
+}
+
+/// Write `value` to `writer` as a JSON array.
+pub fn dump_to_writer<W: Write>(value: &[Value], mut writer: W) -> io::Result<W> {
+    let buffer = serde_json::to_string(value)?;
+    writer.write_all(buffer.as_bytes())?;
+    Ok(writer)
+}
+
+/// Write `value` to `writer` as a pretty printed JSON array, with `indent`
+/// spaces per level.
+pub fn dump_to_writer_pretty<W: Write>(
+    value: &[Value],
+    mut writer: W,
+    indent: usize,
+) -> io::Result<W> {
+    let indent = vec![b' '; indent];
+    let mut buffer = Vec::new();
+    let formatter = PrettyFormatter::with_indent(&indent);
+    value.serialize(&mut serde_json::Serializer::with_formatter(
+        &mut buffer,
+        formatter,
+    ))?;
+    writer.write_all(&buffer)?;
+    Ok(writer)
+}
+
+/// Write `value` to `path` as a JSON array.
+pub fn dump_to_file(value: &[Value], path: &str) -> io::Result<()> {
+    dump_to_writer(value, create(path)?)?.finish()
+}
@@ -0,0 +1,146 @@
+//! Inferring a JSON Schema from a stream of documents, to check later dumps
+//! against with [`validate`](crate::validate).
+//!
+//! The schema describes what was seen: the types of every field, the fields
+//! of objects and the elements of arrays, and which fields were present in
+//! every object. Numbers are `integer` if all of them were.
+
+use std::collections::BTreeMap;
+
+use serde_json::{json, Map, Value};
+
+/// The JSON Schema dialect of the inferred schemas.
+pub const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Builds a JSON Schema from the documents added to it.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaInference {
+    root: Shape,
+    closed: bool,
+}
+
+/// What was seen of the values at one place in the documents.
+#[derive(Debug, Clone, Default)]
+struct Shape {
+    null: bool,
+    boolean: bool,
+    integer: bool,
+    number: bool,
+    string: bool,
+    /// The number of values, for telling which fields are required.
+    count: u64,
+    /// The number of objects and the values of their fields.
+    objects: u64,
+    properties: BTreeMap<String, Shape>,
+    /// The elements of all arrays, if there were any.
+    items: Option<Box<Shape>>,
+    array: bool,
+}
+
+impl Shape {
+    fn add(&mut self, value: &Value) {
+        self.count += 1;
+        match value {
+            Value::Null => self.null = true,
+            Value::Bool(_) => self.boolean = true,
+            Value::Number(number) if number.is_f64() => self.number = true,
+            Value::Number(_) => self.integer = true,
+            Value::String(_) => self.string = true,
+            Value::Array(values) => {
+                self.array = true;
+                for value in values {
+                    self.items.get_or_insert_with(Default::default).add(value);
+                }
+            }
+            Value::Object(members) => {
+                self.objects += 1;
+                for (name, value) in members {
+                    self.properties.entry(name.clone()).or_default().add(value);
+                }
+            }
+        }
+    }
+
+    fn schema(&self, closed: bool) -> Map<String, Value> {
+        let mut types = Vec::new();
+        if self.null {
+            types.push("null");
+        }
+        if self.boolean {
+            types.push("boolean");
+        }
+        if self.number {
+            // Also covers the integers.
+            types.push("number");
+        } else if self.integer {
+            types.push("integer");
+        }
+        if self.string {
+            types.push("string");
+        }
+        if self.array {
+            types.push("array");
+        }
+        if self.objects > 0 {
+            types.push("object");
+        }
+
+        let mut schema = Map::new();
+        match types.as_slice() {
+            // Nothing was seen, e.g. the elements of arrays that were all
+            // empty, so anything goes.
+            [] => return schema,
+            [name] => schema.insert("type".into(), json!(name)),
+            names => schema.insert("type".into(), json!(names)),
+        };
+        if let Some(items) = &self.items {
+            schema.insert("items".into(), Value::Object(items.schema(closed)));
+        }
+        if self.objects > 0 {
+            let properties: Map<_, _> = self
+                .properties
+                .iter()
+                .map(|(name, shape)| (name.clone(), Value::Object(shape.schema(closed))))
+                .collect();
+            let required: Vec<_> = self
+                .properties
+                .iter()
+                .filter(|(_, shape)| shape.count == self.objects)
+                .map(|(name, _)| name.as_str())
+                .collect();
+            schema.insert("properties".into(), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert("required".into(), json!(required));
+            }
+            if closed {
+                schema.insert("additionalProperties".into(), Value::Bool(false));
+            }
+        }
+        schema
+    }
+}
+
+impl SchemaInference {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forbid the fields that weren't seen in an object, with
+    /// `additionalProperties: false`.
+    pub fn closed(mut self, closed: bool) -> Self {
+        self.closed = closed;
+        self
+    }
+
+    pub fn add(&mut self, doc: &Value) {
+        self.root.add(doc);
+    }
+
+    /// The schema of the documents added so far.
+    pub fn schema(&self) -> Value {
+        let mut schema = Map::new();
+        schema.insert("$schema".into(), json!(DIALECT));
+        schema.extend(self.root.schema(self.closed));
+        Value::Object(schema)
+    }
+}
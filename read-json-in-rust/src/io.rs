@@ -0,0 +1,755 @@
+//! Opening input and output files, with transparent compression.
+//!
+//! Input is decompressed if the path ends with `.gz` or `.zst`, or the content
+//! starts with the gzip or zstd magic bytes. Output is compressed if the path
+//! ends with `.gz` or `.zst`. Zstandard requires the `zstd` feature.
+//!
+//! The path `-` means stdin for input and stdout for output. Compressed stdin
+//! is detected by its magic bytes, stdout is never compressed.
+//!
+//! With the `http` feature, an input can also be an `http://` or `https://`
+//! URL, streamed as it is downloaded. Outputs can't be URLs, see
+//! [`upload`](crate::upload) for posting documents instead.
+//!
+//! With the `s3` feature, inputs and outputs can be `s3://bucket/key` objects,
+//! see [`s3`](crate::s3).
+//!
+//! Inputs in Latin-1 or Windows-1252 can be decoded to UTF-8 as they are
+//! read, see [`ReadOptions::encoding`].
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use flate2::bufread::MultiGzDecoder;
+use flate2::write::GzEncoder;
+
+mod encoding;
+
+pub use encoding::{Decoder, Encoding};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// A buffered, possibly decompressed, input.
+pub type Input = Box<dyn BufRead + Send>;
+
+/// Compression applied to a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// The compression implied by the extension of `path`.
+    pub fn from_path(path: &str) -> Self {
+        if path.ends_with(".gz") {
+            Compression::Gzip
+        } else if path.ends_with(".zst") {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// The compression implied by the first bytes of a file.
+    pub fn from_magic(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if bytes.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+#[cfg(not(feature = "http"))]
+fn http_unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reading URLs requires the `http` feature",
+    )
+}
+
+#[cfg(not(feature = "s3"))]
+fn s3_unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "s3:// paths require the `s3` feature",
+    )
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "zstd compression requires the `zstd` feature",
+    )
+}
+
+/// Wrap `reader` in a decoder for `compression`.
+pub fn decompress<R: BufRead + Send + 'static>(
+    reader: R,
+    compression: Compression,
+) -> io::Result<Input> {
+    decompress_with_capacity(reader, compression, BUFFER_SIZE)
+}
+
+fn decompress_with_capacity<R: BufRead + Send + 'static>(
+    reader: R,
+    compression: Compression,
+    capacity: usize,
+) -> io::Result<Input> {
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(BufReader::with_capacity(
+            capacity,
+            MultiGzDecoder::new(reader),
+        )),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Box::new(BufReader::with_capacity(
+            capacity,
+            zstd::Decoder::with_buffer(reader)?,
+        )),
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd => return Err(zstd_unsupported()),
+    })
+}
+
+/// The path meaning stdin or stdout.
+pub const STDIO: &str = "-";
+
+/// Size of the read and write buffers by default, the default of `BufReader`
+/// and `BufWriter`.
+pub const BUFFER_SIZE: usize = 8 * 1024;
+
+/// Options for opening inputs, e.g.
+///
+/// ```no_run
+/// # use read_json_in_rust::io::ReadOptions;
+/// let input = ReadOptions::new().buffer_size(1 << 20).open("data/skbl.json")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    buffer_size: usize,
+    counter: Option<ByteCounter>,
+    encoding: Encoding,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            buffer_size: BUFFER_SIZE,
+            counter: None,
+            encoding: Encoding::Utf8,
+        }
+    }
+}
+
+impl ReadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read up to `buffer_size` bytes (at least 1) at a time, larger reads
+    /// may pay off on network filesystems.
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size.max(1);
+        self
+    }
+
+    /// Count the bytes read from the file (before decompression) in
+    /// `counter`.
+    pub fn counter(mut self, counter: ByteCounter) -> Self {
+        self.counter = Some(counter);
+        self
+    }
+
+    /// Decode the input (after decompressing it) from `encoding` to UTF-8.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Open `path` for reading, decompressing it if needed.
+    pub fn open(&self, path: &str) -> io::Result<Input> {
+        let reader = self.open_compressed(path)?;
+        self.decode_text(reader)
+    }
+
+    /// Decompress `reader` if needed and decode it as [`ReadOptions::open`]
+    /// does with a file, with the compression implied by `name` or the first
+    /// bytes. This is for inputs within other inputs, such as the files of
+    /// an archive.
+    pub fn wrap(&self, name: &str, reader: Input) -> io::Result<Input> {
+        let reader = decode(name, reader, self.buffer_size)?;
+        self.decode_text(reader)
+    }
+
+    /// Open `path` and decompress it, but don't decode it, e.g. for a tar
+    /// archive.
+    pub(crate) fn open_compressed(&self, path: &str) -> io::Result<Input> {
+        let reader = self.count(open_raw(path, self.buffer_size)?);
+        decode(path, reader, self.buffer_size)
+    }
+
+    /// Count the bytes read from `reader` in the counter, if there is one.
+    pub(crate) fn count(&self, reader: Input) -> Input {
+        match &self.counter {
+            Some(counter) => Box::new(CountingReader::new(reader, counter.clone())),
+            None => reader,
+        }
+    }
+
+    fn decode_text(&self, reader: Input) -> io::Result<Input> {
+        match self.encoding {
+            Encoding::Utf8 => skip_bom(reader),
+            encoding => Ok(Box::new(Decoder::new(reader, encoding))),
+        }
+    }
+}
+
+/// The byte order mark that Windows tools often write at the start of UTF-8
+/// files.
+pub const BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Skip a [`BOM`] at the start of `reader`, if there is one.
+fn skip_bom(mut reader: Input) -> io::Result<Input> {
+    let start = reader.fill_buf()?;
+    if start.starts_with(BOM) {
+        reader.consume(BOM.len());
+        return Ok(reader);
+    }
+    if start.len() >= BOM.len() || !BOM.starts_with(start) {
+        return Ok(reader);
+    }
+    // A short read of a prefix of the mark, e.g. from a pipe.
+    let mut start = Vec::with_capacity(BOM.len());
+    reader
+        .by_ref()
+        .take(BOM.len() as u64)
+        .read_to_end(&mut start)?;
+    if start == BOM {
+        return Ok(reader);
+    }
+    Ok(Box::new(io::Cursor::new(start).chain(reader)))
+}
+
+/// Open `path` for reading, decompressing it if needed.
+pub fn open(path: &str) -> io::Result<Input> {
+    ReadOptions::new().open(path)
+}
+
+/// Open `path` like [`open`], counting the bytes read from the file (before
+/// decompression) in `counter`.
+pub fn open_counted(path: &str, counter: &ByteCounter) -> io::Result<Input> {
+    ReadOptions::new().counter(counter.clone()).open(path)
+}
+
+/// Whether `path` is an HTTP or HTTPS URL.
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Whether `path` is an `s3://` URI of an object.
+pub fn is_s3(path: &str) -> bool {
+    path.starts_with("s3://")
+}
+
+/// The size of the file at `path`, `None` for stdin, URLs and objects.
+pub fn input_size(path: &str) -> io::Result<Option<u64>> {
+    if path == STDIO || is_url(path) || is_s3(path) {
+        return Ok(None);
+    }
+    Ok(Some(std::fs::metadata(path)?.len()))
+}
+
+fn open_raw(path: &str, capacity: usize) -> io::Result<Input> {
+    if path == STDIO {
+        return Ok(Box::new(BufReader::with_capacity(capacity, io::stdin())));
+    }
+    if is_url(path) {
+        return open_url(path, capacity);
+    }
+    if is_s3(path) {
+        #[cfg(feature = "s3")]
+        return Ok(Box::new(BufReader::with_capacity(
+            capacity,
+            crate::s3::open(path)?,
+        )));
+        #[cfg(not(feature = "s3"))]
+        return Err(s3_unsupported());
+    }
+    Ok(Box::new(BufReader::with_capacity(
+        capacity,
+        File::open(path)?,
+    )))
+}
+
+/// Stream the body of a GET request, decoded if the server compressed it.
+#[cfg(feature = "http")]
+fn open_url(url: &str, capacity: usize) -> io::Result<Input> {
+    // No timeout, as the download may take long.
+    let client = reqwest::blocking::Client::builder()
+        .timeout(None)
+        .build()
+        .map_err(io::Error::other)?;
+    let response = client
+        .get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(io::Error::other)?;
+    Ok(Box::new(BufReader::with_capacity(capacity, response)))
+}
+
+#[cfg(not(feature = "http"))]
+fn open_url(_url: &str, _capacity: usize) -> io::Result<Input> {
+    Err(http_unsupported())
+}
+
+fn decode(path: &str, mut reader: Input, capacity: usize) -> io::Result<Input> {
+    let compression = match Compression::from_path(path) {
+        Compression::None => Compression::from_magic(reader.fill_buf()?),
+        compression => compression,
+    };
+    decompress_with_capacity(reader, compression, capacity)
+}
+
+/// A shared count of bytes, that can be read while the reader counting them
+/// is in use elsewhere.
+#[derive(Debug, Clone, Default)]
+pub struct ByteCounter(Arc<AtomicU64>);
+
+impl ByteCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn add(&self, bytes: usize) {
+        self.0.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+/// Counts the bytes read or consumed from the wrapped reader.
+pub struct CountingReader<R> {
+    reader: R,
+    counter: ByteCounter,
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(reader: R, counter: ByteCounter) -> Self {
+        Self { reader, counter }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.counter.add(read);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.counter.add(amt);
+        self.reader.consume(amt);
+    }
+}
+
+/// A writer that has to be finished after the last write, e.g. to complete
+/// an upload.
+pub trait Finish: Write + Send {
+    /// Flush the writer and complete the output.
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// A writer that is complete once flushed.
+struct Flushed<W>(W);
+
+impl<W: Write> Write for Flushed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<W: Write + Send> Finish for Flushed<W> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+enum Encoder {
+    Plain(Box<dyn Finish>),
+    Gzip(GzEncoder<Box<dyn Finish>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, Box<dyn Finish>>),
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(writer) => writer.write(buf),
+            Encoder::Gzip(writer) => writer.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(writer) => writer.flush(),
+            Encoder::Gzip(writer) => writer.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(writer) => writer.flush(),
+        }
+    }
+}
+
+/// A buffered, possibly compressed, output.
+///
+/// Call [`Output::finish`] when done, dropping it may lose the end of a
+/// compressed stream without reporting an error.
+pub struct Output {
+    writer: BufWriter<Encoder>,
+}
+
+impl Output {
+    /// Wrap `writer` in an encoder for `compression`.
+    ///
+    /// `level` is the compression level (0-9 for gzip, 1-22 for zstd), `None`
+    /// uses the default of the format.
+    pub fn new<W: Write + Send + 'static>(
+        writer: W,
+        compression: Compression,
+        level: Option<i32>,
+    ) -> io::Result<Self> {
+        Self::with_finish(Box::new(Flushed(writer)), compression, level)
+    }
+
+    /// Wrap `writer` like [`Output::new`], finishing it when the output is
+    /// finished.
+    pub fn with_finish(
+        writer: Box<dyn Finish>,
+        compression: Compression,
+        level: Option<i32>,
+    ) -> io::Result<Self> {
+        Self::with_capacity(writer, compression, level, BUFFER_SIZE)
+    }
+
+    fn with_capacity(
+        writer: Box<dyn Finish>,
+        compression: Compression,
+        level: Option<i32>,
+        capacity: usize,
+    ) -> io::Result<Self> {
+        let encoder = match compression {
+            Compression::None => Encoder::Plain(writer),
+            Compression::Gzip => {
+                let level = match level {
+                    Some(level) => flate2::Compression::new(level.clamp(0, 9) as u32),
+                    None => flate2::Compression::default(),
+                };
+                Encoder::Gzip(GzEncoder::new(writer, level))
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(
+                writer,
+                level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL),
+            )?),
+            #[cfg(not(feature = "zstd"))]
+            Compression::Zstd => return Err(zstd_unsupported()),
+        };
+        Ok(Self {
+            writer: BufWriter::with_capacity(capacity, encoder),
+        })
+    }
+
+    /// Flush all buffered data, end the compressed stream and finish the
+    /// underlying writer.
+    pub fn finish(self) -> io::Result<()> {
+        match self.writer.into_inner().map_err(|err| err.into_error())? {
+            Encoder::Plain(writer) => writer.finish(),
+            Encoder::Gzip(writer) => writer.finish()?.finish(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(writer) => writer.finish()?.finish(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// The path of part `number` of an output split into several files, e.g.
+/// `out_0001.json.gz` for part 1 of `out.json.gz`.
+pub fn part_path(path: &str, number: usize) -> String {
+    let path = Path::new(path);
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    let extension = name
+        .find('.')
+        .filter(|&start| start > 0)
+        .unwrap_or(name.len());
+    let (stem, extension) = name.split_at(extension);
+    path.with_file_name(format!("{}_{:04}{}", stem, number, extension))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// A temporary file in the directory of a file, that replaces the file with
+/// [`Replacement::persist`] once it's complete, e.g. to update a file in place
+/// without ever leaving it half written.
+///
+/// The temporary file has the same extension, so it's compressed the same
+/// way, and it's removed if the replacement is dropped.
+pub struct Replacement {
+    temp: tempfile::TempPath,
+    path: PathBuf,
+    backup: Option<PathBuf>,
+}
+
+impl Replacement {
+    pub fn new(path: &str) -> io::Result<Self> {
+        let path = Path::new(path);
+        let Some(name) = path.file_name() else {
+            let message = format!("{} is not a file", path.display());
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        };
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut builder = tempfile::Builder::new();
+        // As for a new file, before the umask.
+        #[cfg(unix)]
+        builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o666));
+        let temp = builder
+            .prefix(".")
+            .suffix(&format!(".{}", name.to_string_lossy()))
+            .tempfile_in(dir)?
+            .into_temp_path();
+        Ok(Self {
+            temp,
+            path: path.to_path_buf(),
+            backup: None,
+        })
+    }
+
+    /// Keep the file as `backup` when it's replaced, overwriting an older
+    /// backup.
+    pub fn backup(mut self, backup: impl Into<PathBuf>) -> Self {
+        self.backup = Some(backup.into());
+        self
+    }
+
+    /// The path of the temporary file.
+    pub fn temp_path(&self) -> &str {
+        self.temp
+            .to_str()
+            .expect("a UTF-8 path, in the directory of one")
+    }
+
+    /// Rename the temporary file over the file, keeping the permissions of
+    /// the file if it exists.
+    pub fn persist(self) -> io::Result<()> {
+        match std::fs::metadata(&self.path) {
+            Ok(metadata) => {
+                std::fs::set_permissions(&self.temp, metadata.permissions())?;
+                if let Some(backup) = &self.backup {
+                    keep_backup(&self.path, backup)?;
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        self.temp.persist(&self.path).map_err(|err| err.error)
+    }
+}
+
+/// Link `path` to `backup`, so the file is never missing. Copy it if the
+/// filesystem has no hard links.
+fn keep_backup(path: &Path, backup: &Path) -> io::Result<()> {
+    match std::fs::remove_file(backup) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+    if std::fs::hard_link(path, backup).is_err() {
+        std::fs::copy(path, backup)?;
+    }
+    Ok(())
+}
+
+/// Options for creating outputs, e.g.
+///
+/// ```no_run
+/// # use read_json_in_rust::io::WriteOptions;
+/// let output = WriteOptions::new().buffer_size(1 << 20).create("skbl2.json.gz")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    buffer_size: usize,
+    level: Option<i32>,
+    bom: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            buffer_size: BUFFER_SIZE,
+            level: None,
+            bom: false,
+        }
+    }
+}
+
+impl WriteOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write up to `buffer_size` bytes (at least 1) at a time, before
+    /// compression.
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size.max(1);
+        self
+    }
+
+    /// The compression level (0-9 for gzip, 1-22 for zstd), `None` uses the
+    /// default of the format.
+    pub fn compression_level(mut self, level: Option<i32>) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Start the output with a [`BOM`], for Windows tools such as Excel that
+    /// take UTF-8 files without one for a legacy encoding. Without this
+    /// nothing is written before the data.
+    pub fn bom(mut self, bom: bool) -> Self {
+        self.bom = bom;
+        self
+    }
+
+    /// Create `path` for writing, compressing it if the extension asks for
+    /// it.
+    ///
+    /// For `-` the output is written to stdout, uncompressed.
+    pub fn create(&self, path: &str) -> io::Result<Output> {
+        let output = |writer: Box<dyn Finish>, compression| {
+            let mut output =
+                Output::with_capacity(writer, compression, self.level, self.buffer_size)?;
+            if self.bom {
+                output.write_all(BOM)?;
+            }
+            Ok(output)
+        };
+        if path == STDIO {
+            return output(Box::new(Flushed(io::stdout())), Compression::None);
+        }
+        if is_url(path) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("can't write to the URL {}", path),
+            ));
+        }
+        let compression = Compression::from_path(path);
+        #[cfg(not(feature = "zstd"))]
+        if compression == Compression::Zstd {
+            // Fail before creating an empty file.
+            return Err(zstd_unsupported());
+        }
+        if is_s3(path) {
+            #[cfg(feature = "s3")]
+            return output(Box::new(crate::s3::create(path)?), compression);
+            #[cfg(not(feature = "s3"))]
+            return Err(s3_unsupported());
+        }
+        output(Box::new(Flushed(File::create(path)?)), compression)
+    }
+}
+
+/// Create `path` for writing, compressing it if the extension asks for it.
+///
+/// For `-` the output is written to stdout.
+pub fn create(path: &str) -> io::Result<Output> {
+    WriteOptions::new().create(path)
+}
+
+/// Create `path` for writing, compressing it with `level` if the extension
+/// asks for compression.
+pub fn create_with_level(path: &str, level: Option<i32>) -> io::Result<Output> {
+    WriteOptions::new().compression_level(level).create(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `bytes` after [`skip_bom`], with the reader returning `chunk` bytes at
+    /// a time.
+    fn without_bom(bytes: &'static [u8], chunk: usize) -> Vec<u8> {
+        let mut reader = skip_bom(Box::new(io::BufReader::with_capacity(chunk, bytes))).unwrap();
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        read
+    }
+
+    #[test]
+    fn skips_a_bom() {
+        for chunk in [1, 2, 3, 64] {
+            assert_eq!(without_bom(b"\xef\xbb\xbf[1]", chunk), b"[1]");
+            assert_eq!(without_bom(b"\xef\xbb\xbf", chunk), b"");
+        }
+    }
+
+    #[test]
+    fn keeps_input_without_a_bom() {
+        for chunk in [1, 2, 3, 64] {
+            assert_eq!(without_bom(b"[1]", chunk), b"[1]");
+            assert_eq!(without_bom(b"", chunk), b"");
+            // A prefix of the mark that isn't one.
+            assert_eq!(without_bom(b"\xef\xbb", chunk), b"\xef\xbb");
+            assert_eq!(without_bom(b"\xef\xbbx]", chunk), b"\xef\xbbx]");
+        }
+    }
+
+    #[test]
+    fn skips_a_bom_when_opening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bom.json");
+        std::fs::write(&path, b"\xef\xbb\xbf[\"h\xc3\xb6\"]").unwrap();
+        let mut text = String::new();
+        open(path.to_str().unwrap())
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "[\"hö\"]");
+    }
+}
@@ -0,0 +1,154 @@
+//! Decoding inputs in legacy single-byte encodings to UTF-8.
+//!
+//! Both encodings map every byte to one character, Latin-1 to the code point
+//! of the same value and Windows-1252 likewise but for 32 bytes, so they are
+//! decoded with the table below rather than with encoding_rs, whose decoders
+//! for multi-byte encodings aren't needed.
+
+use std::io::{self, BufRead, Read};
+
+/// The character encoding of an input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// ISO-8859-1: every byte is the code point of the same value.
+    Latin1,
+    /// Windows-1252, ISO-8859-1 with printable characters such as `€` and
+    /// `”` instead of most C1 control codes. The five unassigned bytes are
+    /// the control codes, as in the WHATWG Encoding Standard.
+    Windows1252,
+}
+
+/// The characters of the bytes 0x80 to 0x9F in Windows-1252.
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+impl Encoding {
+    fn decode(self, byte: u8) -> char {
+        match (self, byte) {
+            (Encoding::Windows1252, 0x80..=0x9f) => WINDOWS_1252[usize::from(byte - 0x80)],
+            _ => char::from(byte),
+        }
+    }
+}
+
+/// Decodes a reader in a single-byte [`Encoding`] to UTF-8, a buffer at a
+/// time.
+pub struct Decoder<R> {
+    reader: R,
+    encoding: Encoding,
+    /// The decoded bytes, from `position` on not yet consumed.
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl<R: BufRead> Decoder<R> {
+    /// Decode `reader` from `encoding`. [`Encoding::Utf8`] is passed
+    /// through as it is.
+    pub fn new(reader: R, encoding: Encoding) -> Self {
+        Self {
+            reader,
+            encoding,
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for Decoder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.encoding == Encoding::Utf8 {
+            return self.reader.fill_buf();
+        }
+        if self.position == self.buffer.len() {
+            let bytes = self.reader.fill_buf()?;
+            self.buffer.clear();
+            self.position = 0;
+            for &byte in bytes {
+                match byte {
+                    0..=0x7f => self.buffer.push(byte),
+                    _ => {
+                        let mut utf8 = [0; 4];
+                        let utf8 = self.encoding.decode(byte).encode_utf8(&mut utf8);
+                        self.buffer.extend_from_slice(utf8.as_bytes());
+                    }
+                }
+            }
+            let read = bytes.len();
+            self.reader.consume(read);
+        }
+        Ok(&self.buffer[self.position..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self.encoding {
+            Encoding::Utf8 => self.reader.consume(amt),
+            _ => self.position = (self.position + amt).min(self.buffer.len()),
+        }
+    }
+}
+
+impl<R: BufRead> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let decoded = self.fill_buf()?;
+        let read = decoded.len().min(buf.len());
+        buf[..read].copy_from_slice(&decoded[..read]);
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8], encoding: Encoding) -> String {
+        let mut decoded = String::new();
+        // A small buffer, so that characters are decoded over several reads.
+        Decoder::new(io::BufReader::with_capacity(3, bytes), encoding)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        decoded
+    }
+
+    #[test]
+    fn decodes_latin1() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let expected: String = (0..=255u8).map(char::from).collect();
+        assert_eq!(decode(&bytes, Encoding::Latin1), expected);
+        assert_eq!(decode(b"h\xf6 \xe5r", Encoding::Latin1), "hö år");
+    }
+
+    #[test]
+    fn decodes_windows_1252() {
+        assert_eq!(
+            decode(b"\x80 \x93hund\x94 \x85", Encoding::Windows1252),
+            "€ “hund” …"
+        );
+        assert_eq!(
+            decode(b"\x8a\x8e\x9a\x9e\x9f\x99", Encoding::Windows1252),
+            "ŠŽšžŸ™"
+        );
+        // The unassigned bytes are the C1 control codes.
+        for byte in [0x81, 0x8d, 0x8f, 0x90, 0x9d] {
+            assert_eq!(
+                decode(&[byte], Encoding::Windows1252),
+                char::from(byte).to_string()
+            );
+        }
+        // Outside 0x80 to 0x9F it is Latin-1.
+        let bytes: Vec<u8> = (0..0x80).chain(0xa0..=0xff).collect();
+        assert_eq!(
+            decode(&bytes, Encoding::Windows1252),
+            decode(&bytes, Encoding::Latin1)
+        );
+    }
+
+    #[test]
+    fn passes_utf8_through() {
+        assert_eq!(decode("hö “x”".as_bytes(), Encoding::Utf8), "hö “x”");
+    }
+}
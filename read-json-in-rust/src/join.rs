@@ -0,0 +1,371 @@
+//! Joining two streams of documents on a key, with a sort-merge join.
+//!
+//! Both inputs must be sorted by the key as with [`crate::sort`], e.g. with
+//! `json-tool --sort-by` or a [`crate::sort::ExternalSort`]; then only the
+//! documents of the right input that share one key are kept in memory.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Peekable;
+
+use serde_json::Value;
+
+use crate::json_iter::JsonIterError;
+use crate::path::FieldPath;
+use crate::sort::compare_keys;
+
+/// Which documents of the left input are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JoinKind {
+    /// Only those with a matching document in the right input.
+    #[default]
+    Inner,
+    /// All of them, those without a match as they are, or with `null` in the
+    /// field of [`Join::into_field`].
+    Left,
+}
+
+/// The input of a [`JoinError::Unsorted`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Left => write!(f, "left"),
+            Side::Right => write!(f, "right"),
+        }
+    }
+}
+
+/// How to join two inputs: on which key, which documents are written and
+/// how a left and a right document are combined.
+///
+/// By default the combined document is the left one with the top-level
+/// fields of the right one that it doesn't have; with
+/// [`Join::into_field`] the right document is put in a field instead.
+/// Documents without the key, or with a `null`, array or object key, don't
+/// match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Join {
+    left_on: FieldPath,
+    right_on: FieldPath,
+    kind: JoinKind,
+    into: Option<String>,
+}
+
+impl Join {
+    /// Join on the value at `on` in both inputs.
+    pub fn new(on: FieldPath) -> Self {
+        Self {
+            right_on: on.clone(),
+            left_on: on,
+            kind: JoinKind::default(),
+            into: None,
+        }
+    }
+
+    /// Take the key of the right documents from `path` instead.
+    pub fn right_on(mut self, path: FieldPath) -> Self {
+        self.right_on = path;
+        self
+    }
+
+    pub fn kind(mut self, kind: JoinKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Put the right document in this top-level field of the left one,
+    /// instead of adding its fields.
+    pub fn into_field(mut self, field: impl Into<String>) -> Self {
+        self.into = Some(field.into());
+        self
+    }
+
+    /// The combined documents of `left` and `right`, in the order of the
+    /// left input, each left document once per matching right document.
+    pub fn join<L, R>(&self, left: L, right: R) -> Joined<L::IntoIter, R::IntoIter>
+    where
+        L: IntoIterator<Item = Result<Value, JsonIterError>>,
+        R: IntoIterator<Item = Result<Value, JsonIterError>>,
+    {
+        Joined {
+            join: self.clone(),
+            left: left.into_iter(),
+            right: right.into_iter().peekable(),
+            left_index: 0,
+            right_index: 0,
+            left_key: None,
+            right_key: None,
+            group: Vec::new(),
+            group_key: None,
+            pending: Vec::new(),
+            done: false,
+        }
+    }
+
+    fn combine(&self, left: &Value, right: Option<&Value>) -> Value {
+        let mut doc = left.clone();
+        let Some(object) = doc.as_object_mut() else {
+            return doc;
+        };
+        match (&self.into, right) {
+            (Some(field), right) => {
+                object.insert(field.clone(), right.cloned().unwrap_or_default());
+            }
+            (None, Some(Value::Object(right))) => {
+                for (name, value) in right {
+                    if !object.contains_key(name) {
+                        object.insert(name.clone(), value.clone());
+                    }
+                }
+            }
+            (None, _) => {}
+        }
+        doc
+    }
+}
+
+/// The key of `doc` at `path`, if it can match.
+fn key_of(path: &FieldPath, doc: &Value) -> Option<Value> {
+    path.get(doc)
+        .filter(|key| !matches!(key, Value::Null | Value::Array(_) | Value::Object(_)))
+        .cloned()
+}
+
+/// Iterator over the documents of a [`Join`].
+///
+/// Yields `Err` at most once, after which the iterator is exhausted.
+pub struct Joined<L, R: Iterator> {
+    join: Join,
+    left: L,
+    right: Peekable<R>,
+    left_index: u64,
+    right_index: u64,
+    /// The keys of the last documents read, to check the order, also those
+    /// that can't match.
+    left_key: Option<Value>,
+    right_key: Option<Value>,
+    /// The right documents with the key `group_key`.
+    group: Vec<Value>,
+    group_key: Option<Value>,
+    /// Combined documents yet to be yielded, in reverse.
+    pending: Vec<Value>,
+    done: bool,
+}
+
+impl<L, R> Joined<L, R>
+where
+    L: Iterator<Item = Result<Value, JsonIterError>>,
+    R: Iterator<Item = Result<Value, JsonIterError>>,
+{
+    /// The next right document and its key, checking the order.
+    fn next_right(&mut self) -> Result<Option<(Option<Value>, Value)>, JoinError> {
+        let Some(doc) = self.right.next() else {
+            return Ok(None);
+        };
+        let doc = doc?;
+        let order_key = self.join.right_on.get(&doc);
+        if self.right_index > 0
+            && compare_keys(self.right_key.as_ref(), order_key) == Ordering::Greater
+        {
+            return Err(JoinError::Unsorted {
+                input: Side::Right,
+                document: self.right_index + 1,
+            });
+        }
+        self.right_index += 1;
+        self.right_key = order_key.cloned();
+        let key = key_of(&self.join.right_on, &doc);
+        Ok(Some((key, doc)))
+    }
+
+    /// Read the right documents up to `key`, keeping those with the key.
+    fn advance_right(&mut self, key: &Value) -> Result<(), JoinError> {
+        if let Some(group_key) = &self.group_key {
+            if compare_keys(Some(group_key), Some(key)) != Ordering::Less {
+                return Ok(());
+            }
+        }
+        self.group.clear();
+        self.group_key = None;
+        loop {
+            let next_key = match self.right.peek() {
+                None => return Ok(()),
+                Some(Err(_)) => None,
+                Some(Ok(doc)) => key_of(&self.join.right_on, doc),
+            };
+            let order = compare_keys(next_key.as_ref(), Some(key));
+            if next_key.is_some() && order == Ordering::Greater {
+                // Keep it for a later left document.
+                return Ok(());
+            }
+            let Some((next_key, doc)) = self.next_right()? else {
+                return Ok(());
+            };
+            if next_key.is_some() && order == Ordering::Equal {
+                self.group.push(doc);
+                self.group_key = next_key;
+            }
+        }
+    }
+
+    fn next_left(&mut self) -> Result<Option<()>, JoinError> {
+        let Some(doc) = self.left.next() else {
+            return Ok(None);
+        };
+        let doc = doc?;
+        let order_key = self.join.left_on.get(&doc);
+        if self.left_index > 0
+            && compare_keys(self.left_key.as_ref(), order_key) == Ordering::Greater
+        {
+            return Err(JoinError::Unsorted {
+                input: Side::Left,
+                document: self.left_index + 1,
+            });
+        }
+        self.left_index += 1;
+        self.left_key = order_key.cloned();
+        let key = key_of(&self.join.left_on, &doc);
+        let matches = match &key {
+            Some(key) => {
+                self.advance_right(key)?;
+                let same = self
+                    .group_key
+                    .as_ref()
+                    .map(|group_key| compare_keys(Some(group_key), Some(key)) == Ordering::Equal);
+                same == Some(true)
+            }
+            None => false,
+        };
+        if matches {
+            let combined = self
+                .group
+                .iter()
+                .rev()
+                .map(|right| self.join.combine(&doc, Some(right)));
+            self.pending = combined.collect();
+        } else if self.join.kind == JoinKind::Left {
+            self.pending = vec![self.join.combine(&doc, None)];
+        }
+        Ok(Some(()))
+    }
+}
+
+impl<L, R> Iterator for Joined<L, R>
+where
+    L: Iterator<Item = Result<Value, JsonIterError>>,
+    R: Iterator<Item = Result<Value, JsonIterError>>,
+{
+    type Item = Result<Value, JoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(doc) = self.pending.pop() {
+                return Some(Ok(doc));
+            }
+            if self.done {
+                return None;
+            }
+            match self.next_left() {
+                Ok(Some(())) => {}
+                Ok(None) => self.done = true,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Errors from joining two inputs.
+#[derive(Debug)]
+pub enum JoinError {
+    Read(JsonIterError),
+    /// The key of the document with this number (from 1) of an input is
+    /// less than that of the document before it.
+    Unsorted {
+        input: Side,
+        document: u64,
+    },
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JoinError::Read(err) => write!(f, "{}", err),
+            JoinError::Unsorted { input, document } => write!(
+                f,
+                "the {} input isn't sorted by the key at document {}",
+                input, document
+            ),
+        }
+    }
+}
+
+impl std::error::Error for JoinError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JoinError::Read(err) => Some(err),
+            JoinError::Unsorted { .. } => None,
+        }
+    }
+}
+
+impl From<JsonIterError> for JoinError {
+    fn from(err: JsonIterError) -> Self {
+        JoinError::Read(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn docs(docs: Value) -> Vec<Result<Value, JsonIterError>> {
+        serde_json::from_value::<Vec<Value>>(docs)
+            .unwrap()
+            .into_iter()
+            .map(Ok)
+            .collect()
+    }
+
+    #[test]
+    fn reports_the_unsorted_document_from_1() {
+        let join = Join::new("id".parse().unwrap());
+        let sorted = || docs(json!([{"id": 1}, {"id": 2}, {"id": 3}]));
+        let unsorted = || docs(json!([{"id": 1}, {"id": 3}, {"id": 2}]));
+
+        let err = join
+            .join(unsorted(), sorted())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            JoinError::Unsorted {
+                input: Side::Left,
+                document: 3
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "the left input isn't sorted by the key at document 3"
+        );
+
+        let err = join
+            .join(sorted(), unsorted())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the right input isn't sorted by the key at document 3"
+        );
+    }
+}
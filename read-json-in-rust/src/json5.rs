@@ -0,0 +1,495 @@
+//! [JSON5](https://json5.org), the relaxed JSON of hand-maintained files:
+//! comments, trailing commas, unquoted keys, single-quoted strings, hex
+//! numbers and numbers such as `.5`, `5.` and `+5`.
+//!
+//! The input is translated to JSON as it is read, a buffer at a time, so the
+//! documents of an array stream as with any other JSON input:
+//!
+//! ```no_run
+//! # use read_json_in_rust::{io, json5::Json5Reader, json_iter};
+//! # use serde_json::Value;
+//! let input = Json5Reader::new(io::open("lexicon.json5")?);
+//! for doc in json_iter::load_from_reader::<Value, _>(input)? {
+//!     println!("{}", doc?);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! Comments are dropped, keeping their line breaks, so lines in errors of the
+//! JSON reader are those of the input. `Infinity` and `NaN` have no JSON
+//! equivalent and are an error.
+
+use std::io::{self, BufRead, Read};
+
+/// Where the translation is in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Between tokens.
+    Between,
+    /// After a `/`, the start of a comment.
+    Slash,
+    LineComment,
+    BlockComment {
+        star: bool,
+    },
+    String {
+        quote: u8,
+        escape: Escape,
+    },
+    /// In a number, literal or unquoted key, collected in `word`.
+    Word,
+}
+
+/// Where the translation is in an escape sequence of a string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    None,
+    /// After the backslash.
+    Backslash,
+    /// In `\xHH`, after `digits` of the digits.
+    Hex {
+        digits: u8,
+        value: u8,
+    },
+    /// After a backslash and a carriage return, which may be followed by a
+    /// line feed of the same line continuation.
+    Return,
+}
+
+/// Translates JSON5 read from a reader to JSON.
+pub struct Json5Reader<R> {
+    reader: R,
+    state: State,
+    /// The open objects (`true`) and arrays (`false`).
+    containers: Vec<bool>,
+    /// Whether the next token is a key.
+    key: bool,
+    /// Whether a comma was read and not yet written, dropped if it is
+    /// followed by the end of the object or array.
+    comma: bool,
+    word: Vec<u8>,
+    line: u64,
+    column: u64,
+    /// The translated bytes, from `position` on not yet consumed.
+    buffer: Vec<u8>,
+    position: usize,
+    done: bool,
+    /// An error of the translation, returned after the bytes translated
+    /// before it, so that the JSON reader gets as far as it can.
+    error: Option<io::Error>,
+}
+
+impl<R: BufRead> Json5Reader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            state: State::Between,
+            containers: Vec::new(),
+            key: false,
+            comma: false,
+            word: Vec::new(),
+            line: 1,
+            column: 0,
+            buffer: Vec::new(),
+            position: 0,
+            done: false,
+            error: None,
+        }
+    }
+
+    fn error(&self, message: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "JSON5 syntax error at line {}, column {}: {}",
+                self.line, self.column, message
+            ),
+        )
+    }
+
+    fn translate(&mut self, byte: u8) -> io::Result<()> {
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 0;
+        } else {
+            self.column += 1;
+        }
+        match self.state {
+            State::Between => self.between(byte),
+            State::Slash => match byte {
+                b'/' => {
+                    self.state = State::LineComment;
+                    Ok(())
+                }
+                b'*' => {
+                    self.state = State::BlockComment { star: false };
+                    Ok(())
+                }
+                _ => Err(self.error("expected a comment after `/`")),
+            },
+            State::LineComment => {
+                if byte == b'\n' {
+                    self.buffer.push(byte);
+                    self.state = State::Between;
+                }
+                Ok(())
+            }
+            State::BlockComment { star } => {
+                self.state = match byte {
+                    b'/' if star => State::Between,
+                    b'*' => State::BlockComment { star: true },
+                    _ => State::BlockComment { star: false },
+                };
+                if byte == b'\n' {
+                    self.buffer.push(byte);
+                }
+                Ok(())
+            }
+            State::String { quote, escape } => self.string(quote, escape, byte),
+            State::Word if is_word(byte) => {
+                self.word.push(byte);
+                Ok(())
+            }
+            State::Word => {
+                self.end_word()?;
+                self.between(byte)
+            }
+        }
+    }
+
+    fn between(&mut self, byte: u8) -> io::Result<()> {
+        match byte {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                self.buffer.push(byte);
+                return Ok(());
+            }
+            // The other whitespace of JSON5 in ASCII, vertical tab and form
+            // feed.
+            0x0b | 0x0c => {
+                self.buffer.push(b' ');
+                return Ok(());
+            }
+            b'/' => {
+                self.state = State::Slash;
+                return Ok(());
+            }
+            _ => {}
+        }
+        if std::mem::take(&mut self.comma) && !matches!(byte, b']' | b'}') {
+            self.buffer.push(b',');
+        }
+        let key = std::mem::take(&mut self.key);
+        match byte {
+            b'{' | b'[' => {
+                self.containers.push(byte == b'{');
+                self.key = byte == b'{';
+            }
+            b'}' | b']' => {
+                self.containers.pop();
+            }
+            b',' => {
+                self.comma = true;
+                self.key = self.containers.last() == Some(&true);
+                return Ok(());
+            }
+            b'"' | b'\'' => {
+                self.state = State::String {
+                    quote: byte,
+                    escape: Escape::None,
+                };
+                self.buffer.push(b'"');
+                return Ok(());
+            }
+            _ if is_word(byte) => {
+                self.state = State::Word;
+                self.key = key;
+                self.word.push(byte);
+                return Ok(());
+            }
+            _ => {}
+        }
+        self.buffer.push(byte);
+        Ok(())
+    }
+
+    fn string(&mut self, quote: u8, escape: Escape, byte: u8) -> io::Result<()> {
+        let mut next = Escape::None;
+        match escape {
+            Escape::None => match byte {
+                _ if byte == quote => {
+                    self.buffer.push(b'"');
+                    self.state = State::Between;
+                    return Ok(());
+                }
+                b'\\' => next = Escape::Backslash,
+                b'"' => self.buffer.extend_from_slice(b"\\\""),
+                b'\n' | b'\r' => return Err(self.error("unescaped line break in a string")),
+                0..=0x1f => self.escaped(byte),
+                _ => self.buffer.push(byte),
+            },
+            Escape::Backslash => match byte {
+                b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't' | b'u' => {
+                    self.buffer.extend_from_slice(&[b'\\', byte]);
+                }
+                b'\'' => self.buffer.push(byte),
+                b'v' => self.escaped(0x0b),
+                b'0' => self.escaped(0),
+                b'x' => {
+                    next = Escape::Hex {
+                        digits: 0,
+                        value: 0,
+                    }
+                }
+                // A line continuation.
+                b'\n' => {}
+                b'\r' => next = Escape::Return,
+                b'1'..=b'9' => return Err(self.error("digits can't be escaped")),
+                // Any other character stands for itself.
+                _ => self.buffer.push(byte),
+            },
+            Escape::Hex { digits, value } => {
+                let Some(digit) = (byte as char).to_digit(16) else {
+                    return Err(self.error("expected two hex digits after `\\x`"));
+                };
+                let value = value * 16 + digit as u8;
+                if digits == 0 {
+                    next = Escape::Hex { digits: 1, value };
+                } else {
+                    self.escaped(value);
+                }
+            }
+            Escape::Return => {
+                if byte != b'\n' {
+                    return self.string(quote, Escape::None, byte);
+                }
+            }
+        }
+        self.state = State::String {
+            quote,
+            escape: next,
+        };
+        Ok(())
+    }
+
+    /// Write the character `code` as a `\u` escape.
+    fn escaped(&mut self, code: u8) {
+        self.buffer
+            .extend_from_slice(format!("\\u{:04x}", code).as_bytes());
+    }
+
+    fn end_word(&mut self) -> io::Result<()> {
+        self.state = State::Between;
+        let word = std::mem::take(&mut self.word);
+        let word = String::from_utf8(word).map_err(|_| self.error("invalid UTF-8"))?;
+        match word.as_bytes()[0] {
+            b'0'..=b'9' | b'.' | b'+' | b'-' => {
+                let number = number(&word).map_err(|message| self.error(&message))?;
+                self.buffer.extend_from_slice(number.as_bytes());
+            }
+            _ if self.key => {
+                self.key = false;
+                self.buffer.push(b'"');
+                self.buffer.extend_from_slice(word.as_bytes());
+                self.buffer.push(b'"');
+            }
+            _ => match word.as_str() {
+                "true" | "false" | "null" => self.buffer.extend_from_slice(word.as_bytes()),
+                "Infinity" | "NaN" => return Err(self.error("JSON has no Infinity or NaN")),
+                _ => return Err(self.error(&format!("unexpected `{}`", word))),
+            },
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.state {
+            State::Word => self.end_word(),
+            State::Between | State::LineComment => Ok(()),
+            State::Slash => Err(self.error("expected a comment after `/`")),
+            State::BlockComment { .. } => Err(self.error("unterminated comment")),
+            State::String { .. } => Err(self.error("unterminated string")),
+        }
+    }
+}
+
+/// Whether `byte` can be part of a number, literal or unquoted key. Bytes of
+/// non-ASCII characters are, for keys such as `böjning`.
+fn is_word(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'$' | b'+' | b'-' | b'.') || byte >= 0x80
+}
+
+/// The JSON of the JSON5 number `word`, e.g. `0.5` for `.5` and `255` for
+/// `0xff`. Other mistakes are left to the JSON reader.
+fn number(word: &str) -> Result<String, String> {
+    let (sign, digits) = match word.as_bytes()[0] {
+        b'+' => ("", &word[1..]),
+        b'-' => ("-", &word[1..]),
+        _ => ("", word),
+    };
+    if digits == "Infinity" || digits == "NaN" {
+        return Err("JSON has no Infinity or NaN".to_string());
+    }
+    if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        let value =
+            u128::from_str_radix(hex, 16).map_err(|_| format!("invalid hex number `{}`", word))?;
+        return Ok(format!("{}{}", sign, value));
+    }
+    let mut number = String::from(sign);
+    if digits.starts_with('.') {
+        number.push('0');
+    }
+    match digits.find('.') {
+        Some(point) if !digits[point + 1..].starts_with(|c: char| c.is_ascii_digit()) => {
+            number.push_str(&digits[..=point]);
+            number.push('0');
+            number.push_str(&digits[point + 1..]);
+        }
+        _ => number.push_str(digits),
+    }
+    Ok(number)
+}
+
+impl<R: BufRead> BufRead for Json5Reader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.position == self.buffer.len() && !self.done {
+            if let Some(err) = self.error.take() {
+                self.done = true;
+                return Err(err);
+            }
+            self.buffer.clear();
+            self.position = 0;
+            let bytes = self.reader.fill_buf()?;
+            if bytes.is_empty() {
+                match self.finish() {
+                    Ok(()) => self.done = true,
+                    Err(err) => self.error = Some(err),
+                }
+                continue;
+            }
+            // A copy, as translating needs `self` while the reader's buffer
+            // is borrowed.
+            let bytes = bytes.to_vec();
+            self.reader.consume(bytes.len());
+            for byte in bytes {
+                if let Err(err) = self.translate(byte) {
+                    self.error = Some(err);
+                    break;
+                }
+            }
+        }
+        Ok(&self.buffer[self.position..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position = (self.position + amt).min(self.buffer.len());
+    }
+}
+
+impl<R: BufRead> Read for Json5Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let translated = self.fill_buf()?;
+        let read = translated.len().min(buf.len());
+        buf[..read].copy_from_slice(&translated[..read]);
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// `json5` translated to JSON, read a byte at a time as well as at once
+    /// to cross every buffer boundary.
+    fn translate(json5: &str) -> io::Result<String> {
+        let mut json = String::new();
+        Json5Reader::new(json5.as_bytes()).read_to_string(&mut json)?;
+        let mut bytewise = String::new();
+        Json5Reader::new(io::BufReader::with_capacity(1, json5.as_bytes()))
+            .read_to_string(&mut bytewise)?;
+        assert_eq!(json, bytewise);
+        Ok(json)
+    }
+
+    fn parse(json5: &str) -> Value {
+        let json = translate(json5).unwrap();
+        serde_json::from_str(&json).unwrap_or_else(|err| panic!("{}: {}", err, json))
+    }
+
+    fn error(json5: &str) -> String {
+        translate(json5).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn drops_comments_keeping_lines() {
+        let json = translate("[1, // one\n/* two\nthree */ 2 /**/]").unwrap();
+        assert_eq!(json.matches('\n').count(), 2);
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), json!([1, 2]));
+        assert_eq!(parse("{/* a */ a: 1 /* b */}"), json!({ "a": 1 }));
+        assert_eq!(parse("[1, // } ] 'x\n2]"), json!([1, 2]));
+    }
+
+    #[test]
+    fn drops_trailing_commas() {
+        assert_eq!(parse("[1, 2,]"), json!([1, 2]));
+        assert_eq!(parse("{a: 1, b: [],}"), json!({ "a": 1, "b": [] }));
+        assert_eq!(parse("[[1,], {}, // last\n]"), json!([[1], {}]));
+    }
+
+    #[test]
+    fn quotes_keys_and_converts_single_quoted_strings() {
+        assert_eq!(
+            parse("{lemma: 'hund', $id: 1, _x2: \"y\"}"),
+            json!({ "lemma": "hund", "$id": 1, "_x2": "y" })
+        );
+        assert_eq!(
+            parse(r#"['it\'s', 'say "hej"', "a'b"]"#),
+            json!(["it's", "say \"hej\"", "a'b"])
+        );
+    }
+
+    #[test]
+    fn converts_hex_numbers() {
+        assert_eq!(parse("[0x1F, 0XFF, -0x10, 0x0]"), json!([31, 255, -16, 0]));
+    }
+
+    #[test]
+    fn converts_signs_and_dots() {
+        assert_eq!(parse("[+5, -5, +0.5]"), json!([5, -5, 0.5]));
+        assert_eq!(parse("[.5, -.5, +.5]"), json!([0.5, -0.5, 0.5]));
+        assert_eq!(parse("[5., -5., 5.e2]"), json!([5.0, -5.0, 500.0]));
+        assert_eq!(parse("[1e3, 1E-2]"), json!([1000.0, 0.01]));
+    }
+
+    #[test]
+    fn converts_escapes() {
+        assert_eq!(
+            parse(
+                r"['\x41å', '\0', 'a\
+b']"
+            ),
+            json!(["Aå", "\0", "ab"])
+        );
+        assert_eq!(parse(r"['\v\'\a']"), json!(["\u{b}'a"]));
+        assert_eq!(parse(r#"["\n\t\\\/"]"#), json!(["\n\t\\/"]));
+    }
+
+    #[test]
+    fn keeps_literals() {
+        assert_eq!(parse("[true, false, null]"), json!([true, false, null]));
+    }
+
+    #[test]
+    fn rejects_infinity_and_nan() {
+        for literal in ["Infinity", "-Infinity", "+Infinity", "NaN", "-NaN"] {
+            let err = error(&format!("[1,\n {}]", literal));
+            assert!(err.contains("line 2"), "{}", err);
+            assert!(err.contains("JSON has no Infinity or NaN"), "{}", err);
+        }
+    }
+}
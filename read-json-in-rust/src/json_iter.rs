@@ -0,0 +1,701 @@
+//! Stream a JSON array one document at a time.
+//!
+//! Instead of loading the whole file into memory, `load_from_reader` returns an
+//! iterator that deserializes one array element at a time, and `dump_to_writer`
+//! writes the documents of an iterator as a JSON array. `load_from_file` and
+//! `dump_to_file` do the same for buffered files.
+//!
+//! Newline-delimited JSON is handled by `load_ndjson` and `dump_ndjson` with
+//! the same iterator interface, and concatenated JSON values without an
+//! array by `load_concatenated`. [`SeqWriter`] writes JSON text sequences.
+//!
+//! [`RawDocuments`] reads the documents as raw JSON instead, and
+//! [`RawArrayWriter`] writes them back as they are, e.g. to pass most of them
+//! through unchanged and only parse the ones that are updated.
+//!
+//! `par_map` spreads the transformation of documents over a rayon thread pool,
+//! and `read_ahead` and `write_behind` move reading and writing to threads of
+//! their own.
+//!
+//! [`Loader`] configures how documents are read, e.g. with the `simd` feature
+//! each document can be parsed with simd-json instead of serde.
+//!
+//! With the `async` feature, [`aio`] provides the same for tokio.
+
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
+use struson::reader::json_path::JsonPathPiece;
+use struson::reader::{JsonReader, JsonReaderPosition, JsonStreamReader, ReaderSettings};
+use struson::writer::{JsonStreamWriter, JsonWriter};
+
+use crate::io::{self, Input};
+use crate::number::{self, NumberFormat, NumberFormatter};
+
+#[cfg(feature = "async")]
+pub mod aio;
+mod concatenated;
+mod error;
+mod lenient;
+mod loader;
+mod ndjson;
+mod par;
+mod pointer;
+mod raw;
+mod seq;
+mod stage;
+mod strict;
+
+pub use concatenated::{load_concatenated, load_concatenated_from_reader, Values};
+pub use error::JsonIterError;
+pub use loader::{Loader, Parser};
+pub use ndjson::{
+    dump_ndjson, dump_ndjson_to_writer, load_ndjson, load_ndjson_from_reader, try_dump_ndjson,
+    try_dump_ndjson_to_writer, Lines, LinesWriter,
+};
+pub use par::{par_map, par_map_window, Order, ParMap, Windowed};
+pub use raw::{RawArrayWriter, RawDocuments};
+pub use seq::{SeqWriter, RS};
+pub use stage::{read_ahead, write_behind, Behind, Received};
+
+/// Iterator over the documents of a JSON array.
+///
+/// Yields `Err` at most once, after which the iterator is exhausted, except
+/// for the [`JsonIterError::Skipped`] documents and
+/// [`JsonIterError::MissingComma`] errors of a [`Loader::lenient`] loader.
+pub struct Documents<T, R: Read> {
+    source: Option<Source<R>>,
+    parser: Parser,
+    strict: bool,
+    /// Raw bytes of the current document, for parsers other than struson
+    /// and for lenient and strict loaders.
+    buffer: Vec<u8>,
+    /// Number of documents read so far.
+    document: u64,
+    _doc: PhantomData<fn() -> T>,
+}
+
+enum Source<R: Read> {
+    Reader(JsonStreamReader<R>),
+    /// The elements of a lenient loader, and the JSON path of their array.
+    Lenient(lenient::Elements<R>, Vec<JsonPathPiece>),
+}
+
+impl<T: DeserializeOwned, R: Read> Documents<T, R> {
+    fn new(reader: R, loader: &Loader) -> Result<Self, JsonIterError> {
+        let source = if loader.lenient {
+            let mut reader = stream_reader(lenient::Tap::new(reader));
+            pointer::seek_pointer(&mut reader, &loader.pointer)?;
+            reader.begin_array()?;
+            // The path of the first element, without its index.
+            let mut path = reader.current_position(true).path.unwrap_or_default();
+            path.pop();
+            Source::Lenient(lenient::Elements::new(reader), path)
+        } else {
+            let mut reader = stream_reader(reader);
+            pointer::seek_pointer(&mut reader, &loader.pointer)?;
+            reader.begin_array()?;
+            Source::Reader(reader)
+        };
+        Ok(Self {
+            source: Some(source),
+            parser: loader.parser,
+            strict: loader.strict,
+            buffer: Vec::new(),
+            document: 0,
+            _doc: PhantomData,
+        })
+    }
+
+    /// Read the next document, with its number and position in the error.
+    fn read_document(&mut self) -> Result<T, JsonIterError> {
+        let Some(Source::Reader(reader)) = self.source.as_mut() else {
+            unreachable!("reader to be present");
+        };
+        self.document += 1;
+        let document = self.document;
+        let at_reader = |err: JsonIterError, reader: &JsonStreamReader<R>| {
+            let position = err.position().cloned();
+            err.in_document(
+                document,
+                position.unwrap_or_else(|| reader.current_position(true)),
+            )
+        };
+        let buffered = self.strict || self.parser != Parser::Serde;
+        if !buffered {
+            return reader
+                .deserialize_next()
+                .map_err(|err| at_reader(err.into(), reader));
+        }
+        // The document is parsed from the buffer, so the positions of its
+        // errors are from the start of the document.
+        let start = reader.current_position(true);
+        self.buffer.clear();
+        let mut json_writer = JsonStreamWriter::new(&mut self.buffer);
+        let copied = reader
+            .transfer_to(&mut json_writer)
+            .map_err(JsonIterError::from)
+            .and_then(|()| Ok(json_writer.finish_document()?));
+        if let Err(err) = copied {
+            return Err(at_reader(err, reader));
+        }
+        let keys = match self.strict {
+            true => strict::check_keys(&self.buffer, document),
+            false => Ok(()),
+        };
+        let doc = keys.and_then(|()| match self.parser {
+            Parser::Serde => from_slice(&self.buffer),
+            #[cfg(feature = "simd")]
+            Parser::Simd => Ok(simd_json::serde::from_slice(&mut self.buffer)?),
+        });
+        doc.map_err(|err| {
+            let position = within(&start, &err);
+            err.in_document(document, position)
+        })
+    }
+
+    /// Parse the document in the buffer, that started at byte `offset`.
+    fn parse_lenient(&mut self, offset: u64) -> Result<T, JsonIterError> {
+        self.document += 1;
+        let keys = match self.strict {
+            true => strict::check_keys(&self.buffer, self.document),
+            false => Ok(()),
+        };
+        let doc = keys.and_then(|()| match self.parser {
+            Parser::Serde => from_slice(&self.buffer),
+            // simd-json parses in place, so keep the document for the error.
+            #[cfg(feature = "simd")]
+            Parser::Simd => {
+                simd_json::serde::from_slice(&mut self.buffer.clone()).map_err(Into::into)
+            }
+        });
+        doc.map_err(|source| {
+            let Some(Source::Lenient(_, path)) = &self.source else {
+                unreachable!("a lenient loader");
+            };
+            // The position of the error in the input, not in the document.
+            let mut path = path.clone();
+            path.push(JsonPathPiece::ArrayItem(self.document as u32 - 1));
+            let start = JsonReaderPosition {
+                path: Some(path),
+                line_pos: None,
+                data_pos: Some(offset),
+            };
+            let position = within(&start, &source);
+            JsonIterError::Skipped {
+                document: self.document,
+                offset: Some(offset),
+                text: String::from_utf8_lossy(&self.buffer).into_owned(),
+                source: Box::new(source.in_document(self.document, position)),
+            }
+        })
+    }
+}
+
+/// The position of `err` from parsing a document on its own, in the input
+/// where the document starts at `start`.
+fn within(start: &JsonReaderPosition, err: &JsonIterError) -> JsonReaderPosition {
+    let (path, data_pos) = match err {
+        #[cfg(feature = "simd")]
+        JsonIterError::Simd(err) => (None, Some(err.index() as u64)),
+        err => match err.position() {
+            Some(position) => (position.path.as_deref(), position.data_pos),
+            None => (None, None),
+        },
+    };
+    JsonReaderPosition {
+        path: match (&start.path, path) {
+            (Some(start), Some(path)) => Some([start.as_slice(), path].concat()),
+            (start, _) => start.clone(),
+        },
+        line_pos: None,
+        data_pos: match (start.data_pos, data_pos) {
+            (Some(start), Some(offset)) => Some(start + offset),
+            (start, _) => start,
+        },
+    }
+}
+
+/// Create a struson reader for `reader` that accepts every JSON number.
+///
+/// By default struson rejects numbers such as `1e100` and `5e-324`, which
+/// are valid JSON and which serde reads as `f64`.
+pub fn stream_reader<R: Read>(reader: R) -> JsonStreamReader<R> {
+    JsonStreamReader::new_custom(
+        reader,
+        ReaderSettings {
+            restrict_number_values: false,
+            ..Default::default()
+        },
+    )
+}
+
+/// Deserialize the single document `json`.
+fn from_slice<T: DeserializeOwned>(json: &[u8]) -> Result<T, JsonIterError> {
+    let mut reader = stream_reader(json);
+    let doc = reader.deserialize_next()?;
+    reader.consume_trailing_whitespace()?;
+    Ok(doc)
+}
+
+fn finish_array<R: Read>(mut reader: JsonStreamReader<R>) -> Result<(), JsonIterError> {
+    reader.end_array()?;
+    reader.skip_to_top_level()?;
+    reader.consume_trailing_whitespace()?;
+    Ok(())
+}
+
+impl<T: DeserializeOwned, R: Read> Iterator for Documents<T, R> {
+    type Item = Result<T, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = match self.source.as_mut()? {
+            Source::Reader(reader) => match reader.has_next() {
+                Ok(true) => self.read_document(),
+                Ok(false) => {
+                    let Some(Source::Reader(reader)) = self.source.take() else {
+                        unreachable!("reader to be present");
+                    };
+                    return finish_array(reader).err().map(Err);
+                }
+                Err(err) => {
+                    let err = JsonIterError::from(err);
+                    let position = err.position().cloned();
+                    let position = position.unwrap_or_else(|| reader.current_position(true));
+                    Err(err.in_document(self.document + 1, position))
+                }
+            },
+            Source::Lenient(elements, _) => match elements.take_missing_comma() {
+                Some(offset) => Err(JsonIterError::MissingComma {
+                    document: self.document,
+                    offset,
+                }),
+                None => match elements.next_element(&mut self.buffer) {
+                    Ok(Some(offset)) => self.parse_lenient(offset),
+                    Ok(None) => {
+                        self.source = None;
+                        return None;
+                    }
+                    Err(err) => {
+                        let position = JsonReaderPosition {
+                            path: None,
+                            line_pos: None,
+                            data_pos: Some(elements.offset()),
+                        };
+                        Err(err.in_document(self.document + 1, position))
+                    }
+                },
+            },
+        };
+        if result.as_ref().is_err_and(|err| {
+            !matches!(
+                err,
+                JsonIterError::Skipped { .. } | JsonIterError::MissingComma { .. }
+            )
+        }) {
+            self.source = None;
+        }
+        Some(result)
+    }
+}
+
+/// Writes documents as elements of a JSON array.
+pub struct ArrayWriter<W: Write> {
+    writer: ArrayOutput<W>,
+    numbers: Option<NumberFormat>,
+}
+
+enum ArrayOutput<W: Write> {
+    Compact(JsonStreamWriter<W>),
+    Pretty {
+        writer: W,
+        indent: Vec<u8>,
+        buffer: Vec<u8>,
+        empty: bool,
+    },
+}
+
+impl<W: Write> ArrayWriter<W> {
+    pub fn new(writer: W) -> Result<Self, JsonIterError> {
+        let mut writer = JsonStreamWriter::new(writer);
+        writer.begin_array()?;
+        Ok(Self {
+            writer: ArrayOutput::Compact(writer),
+            numbers: None,
+        })
+    }
+
+    /// Writes the array pretty printed, with `indent` spaces per level.
+    ///
+    /// Documents are still written one at a time, each is formatted by
+    /// serde_json in a buffer first.
+    pub fn pretty(mut writer: W, indent: usize) -> Result<Self, JsonIterError> {
+        writer.write_all(b"[")?;
+        Ok(Self {
+            writer: ArrayOutput::Pretty {
+                writer,
+                indent: vec![b' '; indent],
+                buffer: Vec::new(),
+                empty: true,
+            },
+            numbers: None,
+        })
+    }
+
+    /// Write the floats in `format`, see [`crate::number`].
+    pub fn numbers(mut self, format: NumberFormat) -> Self {
+        self.numbers = Some(format);
+        self
+    }
+
+    pub fn write<T: Serialize>(&mut self, doc: &T) -> Result<(), JsonIterError> {
+        match &mut self.writer {
+            ArrayOutput::Compact(writer) => match self.numbers {
+                Some(format) => {
+                    let doc = serde_json::to_value(doc).map_err(std::io::Error::from)?;
+                    number::write_value(writer, &doc, format)?;
+                }
+                None => writer.serialize_value(doc)?,
+            },
+            ArrayOutput::Pretty {
+                writer,
+                indent,
+                buffer,
+                empty,
+            } => {
+                buffer.clear();
+                buffer.extend_from_slice(if *empty { b"\n" } else { b",\n" });
+                let formatter = PrettyFormatter::with_indent(indent);
+                match self.numbers {
+                    Some(format) => doc.serialize(&mut serde_json::Serializer::with_formatter(
+                        &mut *buffer,
+                        NumberFormatter::new(formatter, format),
+                    )),
+                    None => doc.serialize(&mut serde_json::Serializer::with_formatter(
+                        &mut *buffer,
+                        formatter,
+                    )),
+                }
+                .map_err(std::io::Error::from)?;
+                // Newlines in strings are escaped, so each newline is a line
+                // break in the document, that is one level deeper in the array.
+                for line in buffer.split_inclusive(|&byte| byte == b'\n') {
+                    writer.write_all(line)?;
+                    if line.ends_with(b"\n") {
+                        writer.write_all(indent)?;
+                    }
+                }
+                *empty = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Closes the array and returns the underlying writer, flushed.
+    pub fn finish(self) -> Result<W, JsonIterError> {
+        let mut writer = match self.writer {
+            ArrayOutput::Compact(mut writer) => {
+                writer.end_array()?;
+                writer.finish_document()?
+            }
+            ArrayOutput::Pretty {
+                mut writer, empty, ..
+            } => {
+                writer.write_all(if empty { b"]" } else { b"\n]" })?;
+                writer
+            }
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+/// Iterate over the documents of the JSON array read from `reader`.
+///
+/// The reader is used as is, wrap it in a `BufReader` if short reads are
+/// expensive (as for a `File`).
+pub fn load_from_reader<T: DeserializeOwned, R: Read>(
+    reader: R,
+) -> Result<Documents<T, R>, JsonIterError> {
+    Loader::new().from_reader(reader)
+}
+
+/// Iterate over the documents of the JSON array found at the JSON pointer
+/// `pointer` (e.g. `/hits/hits`) in the JSON read from `reader`.
+///
+/// Everything outside of the array is skipped without being deserialized.
+pub fn load_from_reader_at<T: DeserializeOwned, R: Read>(
+    reader: R,
+    pointer: &str,
+) -> Result<Documents<T, R>, JsonIterError> {
+    Loader::new().pointer(pointer).from_reader(reader)
+}
+
+/// Open `path` and iterate over the documents of the JSON array it contains.
+///
+/// Gzip compressed files are decompressed, see [`crate::io::open`].
+pub fn load_from_file<T: DeserializeOwned>(
+    path: &str,
+) -> Result<Documents<T, Input>, JsonIterError> {
+    Loader::new().from_file(path)
+}
+
+/// Open `path` and iterate over the documents of the JSON array found at the
+/// JSON pointer `pointer` (e.g. `/hits/hits`).
+pub fn load_from_file_at<T: DeserializeOwned>(
+    path: &str,
+    pointer: &str,
+) -> Result<Documents<T, Input>, JsonIterError> {
+    Loader::new().pointer(pointer).from_file(path)
+}
+
+/// Write all documents to `writer` as a JSON array and return the writer.
+pub fn dump_to_writer<W, T, I>(writer: W, docs: I) -> Result<W, JsonIterError>
+where
+    W: Write,
+    T: Serialize,
+    I: IntoIterator<Item = T>,
+{
+    try_dump_to_writer(writer, docs.into_iter().map(Ok))
+}
+
+/// Write all documents to `writer` as a JSON array, stopping at the first `Err`.
+///
+/// This accepts the iterator returned from `load_from_reader` directly, so a
+/// malformed input aborts the write with the reader's error.
+pub fn try_dump_to_writer<W, T, I>(writer: W, docs: I) -> Result<W, JsonIterError>
+where
+    W: Write,
+    T: Serialize,
+    I: IntoIterator<Item = Result<T, JsonIterError>>,
+{
+    let mut writer = ArrayWriter::new(writer)?;
+    for doc in docs {
+        writer.write(&doc?)?;
+    }
+    writer.finish()
+}
+
+/// Write all documents to `path` as a JSON array.
+pub fn dump_to_file<T, I>(path: &str, docs: I) -> Result<(), JsonIterError>
+where
+    T: Serialize,
+    I: IntoIterator<Item = T>,
+{
+    try_dump_to_file(path, docs.into_iter().map(Ok))
+}
+
+/// Write all documents to `path` as a JSON array, stopping at the first `Err`.
+///
+/// The output is gzip compressed if `path` ends with `.gz`.
+pub fn try_dump_to_file<T, I>(path: &str, docs: I) -> Result<(), JsonIterError>
+where
+    T: Serialize,
+    I: IntoIterator<Item = Result<T, JsonIterError>>,
+{
+    try_dump_to_writer(io::create(path)?, docs)?.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    const NUMBERS: &[u8] = b"[1e100, 1.5e300, 5e-324, -2.5E+10]";
+
+    #[test]
+    fn reads_large_and_small_numbers() {
+        let docs: Vec<f64> = load_from_reader(NUMBERS)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(docs, [1e100, 1.5e300, 5e-324, -2.5e10]);
+    }
+
+    #[test]
+    fn reads_large_and_small_numbers_leniently() {
+        let docs: Vec<f64> = Loader::new()
+            .lenient(true)
+            .from_reader(NUMBERS)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(docs, [1e100, 1.5e300, 5e-324, -2.5e10]);
+    }
+
+    #[test]
+    fn reads_large_and_small_numbers_raw() {
+        let docs: Vec<String> = Loader::new()
+            .raw_from_reader(NUMBERS)
+            .unwrap()
+            .map(|doc| doc.map(|doc| doc.get().to_owned()))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(docs, ["1e100", "1.5e300", "5e-324", "-2.5E+10"]);
+    }
+
+    #[test]
+    fn reads_integers_up_to_64_bits() {
+        let json: &[u8] = b"[18446744073709551615, -9223372036854775808, 18446744073709551616]";
+        let docs: Vec<Result<Value, _>> = load_from_reader(json).unwrap().collect();
+        assert_eq!(docs[0].as_ref().unwrap().as_u64(), Some(u64::MAX));
+        assert_eq!(docs[1].as_ref().unwrap().as_i64(), Some(i64::MIN));
+        assert!(matches!(
+            docs[2],
+            Err(JsonIterError::Document { document: 3, .. })
+        ));
+
+        // The raw documents are copied as they are, whatever their size.
+        let raw: Vec<String> = Loader::new()
+            .raw_from_reader(json)
+            .unwrap()
+            .map(|doc| doc.map(|doc| doc.get().to_owned()))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            raw,
+            [
+                "18446744073709551615",
+                "-9223372036854775808",
+                "18446744073709551616"
+            ]
+        );
+    }
+
+    fn write_numbers(pretty: bool, numbers: Option<NumberFormat>) -> String {
+        let json: &[u8] = b"[18446744073709551615, -9223372036854775808, -0, -0.0, 0e10, \
+            1E21, 1e-7, 2.5E+3, 1.0]";
+        let mut writer = match pretty {
+            true => ArrayWriter::pretty(Vec::new(), 0).unwrap(),
+            false => ArrayWriter::new(Vec::new()).unwrap(),
+        };
+        if let Some(format) = numbers {
+            writer = writer.numbers(format);
+        }
+        for doc in load_from_reader::<Value, _>(json).unwrap() {
+            writer.write(&doc.unwrap()).unwrap();
+        }
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn writes_zeros_exponents_and_large_integers() {
+        assert_eq!(
+            write_numbers(false, None),
+            "[18446744073709551615,-9223372036854775808,0,-0,0,1000000000000000000000,\
+             0.0000001,2500,1]"
+        );
+        assert_eq!(
+            write_numbers(false, Some(NumberFormat::Shortest)),
+            "[18446744073709551615,-9223372036854775808,0,-0.0,0.0,1e+21,1e-7,2500.0,1.0]"
+        );
+        assert_eq!(
+            write_numbers(false, Some(NumberFormat::Canonical)),
+            "[18446744073709551615,-9223372036854775808,0,0,0,1e+21,1e-7,2500,1]"
+        );
+        // Pretty printing writes the numbers the same way.
+        for numbers in [NumberFormat::Shortest, NumberFormat::Canonical] {
+            assert_eq!(
+                write_numbers(true, Some(numbers)).replace('\n', ""),
+                write_numbers(false, Some(numbers))
+            );
+        }
+    }
+
+    fn lenient(json: &str) -> Vec<Result<Value, JsonIterError>> {
+        Loader::new()
+            .lenient(true)
+            .from_reader(json.as_bytes())
+            .unwrap()
+            .collect()
+    }
+
+    #[test]
+    fn lenient_reads_both_sides_of_a_missing_comma() {
+        let docs = lenient(r#"[{"a":1} {"a":2}, "x""y"]"#);
+        assert_eq!(docs.len(), 6);
+        assert_eq!(docs[0].as_ref().unwrap()["a"], 1);
+        assert!(matches!(
+            docs[1],
+            Err(JsonIterError::MissingComma {
+                document: 1,
+                offset: 9
+            })
+        ));
+        assert_eq!(docs[2].as_ref().unwrap()["a"], 2);
+        assert_eq!(docs[3].as_ref().unwrap(), "x");
+        assert!(matches!(
+            docs[4],
+            Err(JsonIterError::MissingComma { document: 3, .. })
+        ));
+        assert_eq!(docs[5].as_ref().unwrap(), "y");
+    }
+
+    #[test]
+    fn lenient_reports_the_error_where_it_is_in_the_input() {
+        let docs = lenient(r#"[{"a": 1}, {"a": nul}]"#);
+        let err = docs[1].as_ref().unwrap_err();
+        assert_eq!(err.byte_offset(), Some(11));
+        assert_eq!(
+            err.position().and_then(|position| position.data_pos),
+            Some(17)
+        );
+        assert_eq!(
+            err.to_string(),
+            "skipped document 2 at byte 11: JSON syntax error InvalidLiteral at byte 17, path $[1].a"
+        );
+        let JsonIterError::Skipped { source, .. } = err else {
+            panic!("a skipped document");
+        };
+        // The same error as without `lenient`.
+        let strict: Vec<Result<Value, _>> = load_from_reader(&br#"[{"a": 1}, {"a": nul}]"#[..])
+            .unwrap()
+            .collect();
+        assert_eq!(
+            source.to_string(),
+            strict[1].as_ref().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn lenient_skips_a_mismatched_close() {
+        let docs = lenient(r#"[{"b":[1,2}, {"s":"]},"}, 3]"#);
+        assert_eq!(docs.len(), 3);
+        assert!(matches!(
+            &docs[0],
+            Err(JsonIterError::Skipped { document: 1, text, .. }) if text == r#"{"b":[1,2}"#
+        ));
+        assert_eq!(docs[1].as_ref().unwrap()["s"], "]},");
+        assert_eq!(docs[2].as_ref().unwrap(), 3);
+    }
+
+    #[test]
+    fn lenient_skips_empty_and_broken_elements() {
+        let docs = lenient("[1,,tru, 2,]");
+        assert_eq!(docs.len(), 4);
+        assert!(matches!(
+            docs[1],
+            Err(JsonIterError::Skipped { document: 2, .. })
+        ));
+        assert!(matches!(
+            docs[2],
+            Err(JsonIterError::Skipped { document: 3, .. })
+        ));
+        assert_eq!(docs[3].as_ref().unwrap(), 2);
+    }
+
+    #[test]
+    fn lenient_fails_on_an_unclosed_array() {
+        let docs = lenient(r#"[{"a":1}, {"b":"#);
+        assert_eq!(docs.len(), 2);
+        assert!(matches!(
+            docs[1],
+            Err(JsonIterError::Document { document: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+//! Async variant of `json_iter` for use inside a tokio runtime.
+//!
+//! struson only reads synchronously, so loading runs the reader on tokio's
+//! blocking thread pool and sends the documents back over a bounded channel.
+//! Writing serializes one document at a time to a buffer that is written with
+//! `AsyncWrite`.
+
+use std::io::{BufReader, Read};
+use std::pin::pin;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use struson::writer::{JsonStreamWriter, JsonWriter};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::io::SyncIoBridge;
+
+use super::JsonIterError;
+
+/// Number of parsed documents that may wait in the channel for the consumer.
+const CHANNEL_CAPACITY: usize = 64;
+
+fn spawn_reader<T, R>(reader: R) -> ReceiverStream<Result<T, JsonIterError>>
+where
+    T: DeserializeOwned + Send + 'static,
+    R: Read + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    tokio::task::spawn_blocking(move || {
+        let docs = match super::load_from_reader(BufReader::new(reader)) {
+            Ok(docs) => docs,
+            Err(err) => {
+                let _ = tx.blocking_send(Err(err));
+                return;
+            }
+        };
+        for doc in docs {
+            // The receiver is gone, so nobody wants the remaining documents.
+            if tx.blocking_send(doc).is_err() {
+                break;
+            }
+        }
+    });
+    ReceiverStream::new(rx)
+}
+
+/// Stream the documents of the JSON array read from `reader`.
+///
+/// Must be called from within a tokio runtime.
+pub fn load_from_reader<T, R>(reader: R) -> impl Stream<Item = Result<T, JsonIterError>>
+where
+    T: DeserializeOwned + Send + 'static,
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    spawn_reader(SyncIoBridge::new(reader))
+}
+
+/// Open `path` and stream the documents of the JSON array it contains.
+pub async fn load_from_file<T>(
+    path: &str,
+) -> Result<impl Stream<Item = Result<T, JsonIterError>>, JsonIterError>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let file = tokio::fs::File::open(path).await?;
+    // The blocking reader thread can read the file directly.
+    Ok(spawn_reader(file.into_std().await))
+}
+
+/// Write all documents to `writer` as a JSON array and return the writer.
+pub async fn dump_to_writer<W, T, S>(writer: W, docs: S) -> Result<W, JsonIterError>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+    S: Stream<Item = T>,
+{
+    try_dump_to_writer(writer, docs.map(Ok)).await
+}
+
+/// Write all documents to `writer` as a JSON array, stopping at the first `Err`.
+pub async fn try_dump_to_writer<W, T, S>(mut writer: W, docs: S) -> Result<W, JsonIterError>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+    S: Stream<Item = Result<T, JsonIterError>>,
+{
+    let mut docs = pin!(docs);
+    let mut buffer = Vec::new();
+    let mut first = true;
+    writer.write_all(b"[").await?;
+    while let Some(doc) = docs.next().await {
+        buffer.clear();
+        if !first {
+            buffer.push(b',');
+        }
+        first = false;
+        let mut json_writer = JsonStreamWriter::new(&mut buffer);
+        json_writer.serialize_value(&doc?)?;
+        json_writer.finish_document()?;
+        writer.write_all(&buffer).await?;
+    }
+    writer.write_all(b"]").await?;
+    writer.flush().await?;
+    Ok(writer)
+}
+
+/// Write all documents to `path` as a JSON array.
+pub async fn dump_to_file<T, S>(path: &str, docs: S) -> Result<(), JsonIterError>
+where
+    T: Serialize,
+    S: Stream<Item = T>,
+{
+    try_dump_to_file(path, docs.map(Ok)).await
+}
+
+/// Write all documents to `path` as a JSON array, stopping at the first `Err`.
+pub async fn try_dump_to_file<T, S>(path: &str, docs: S) -> Result<(), JsonIterError>
+where
+    T: Serialize,
+    S: Stream<Item = Result<T, JsonIterError>>,
+{
+    let file = tokio::fs::File::create(path).await?;
+    try_dump_to_writer(tokio::io::BufWriter::new(file), docs).await?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+//! Concatenated JSON, top-level values one after another without an
+//! enclosing array, such as `{"a":1}{"a":2}` or newline-delimited JSON.
+
+use std::io::BufRead;
+
+use serde::de::DeserializeOwned;
+use serde_json::de::IoRead;
+use serde_json::StreamDeserializer;
+
+use super::JsonIterError;
+use crate::io::{self, Input};
+
+/// Iterator over the values of a concatenated JSON stream.
+///
+/// Values may be separated by whitespace, and numbers, `true`, `false` and
+/// `null` must be. Yields `Err` at most once, after which the iterator is
+/// exhausted.
+pub struct Values<T, R: BufRead> {
+    values: Option<StreamDeserializer<'static, IoRead<R>, T>>,
+    document: u64,
+}
+
+impl<T: DeserializeOwned, R: BufRead> Iterator for Values<T, R> {
+    type Item = Result<T, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let values = self.values.as_mut()?;
+        let Some(result) = values.next() else {
+            self.values = None;
+            return None;
+        };
+        self.document += 1;
+        let result = result.map_err(|source| JsonIterError::Concatenated {
+            document: self.document,
+            source,
+        });
+        if result.is_err() {
+            self.values = None;
+        }
+        Some(result)
+    }
+}
+
+/// Iterate over the concatenated JSON values read from `reader`.
+pub fn load_concatenated_from_reader<T: DeserializeOwned, R: BufRead>(reader: R) -> Values<T, R> {
+    Values {
+        values: Some(serde_json::Deserializer::from_reader(reader).into_iter()),
+        document: 0,
+    }
+}
+
+/// Open `path` and iterate over the concatenated JSON values it contains.
+pub fn load_concatenated<T: DeserializeOwned>(
+    path: &str,
+) -> Result<Values<T, Input>, JsonIterError> {
+    Ok(load_concatenated_from_reader(io::open(path)?))
+}
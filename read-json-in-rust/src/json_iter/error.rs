@@ -0,0 +1,413 @@
+use std::fmt;
+use std::io;
+
+use struson::reader::json_path::{JsonPath, JsonPathPiece};
+use struson::reader::{JsonReaderPosition, ReaderError, TransferError};
+use struson::serde::{DeserializerError, SerializerError};
+
+/// Errors that can occur while streaming documents with `json_iter`.
+#[derive(Debug)]
+pub enum JsonIterError {
+    /// Opening, creating or writing a file failed.
+    Io(io::Error),
+    /// The JSON reader failed, e.g. on a syntax error or an unexpected value type.
+    Reader(ReaderError),
+    /// A document could not be deserialized to the requested type.
+    Deserialize(DeserializerError),
+    /// A document could not be serialized.
+    Serialize(SerializerError),
+    /// A document of a JSON array could not be read.
+    Document {
+        /// Number of the document, starting at 1.
+        document: u64,
+        /// Where in the input the error occurred, as far as it is known: the
+        /// byte offset and the JSON path, e.g. `$[2].senses[0]`. With
+        /// [`super::Loader::strict`] and simd-json the document is parsed
+        /// from a compact copy, so the offset within it is approximate.
+        position: JsonReaderPosition,
+        source: Box<JsonIterError>,
+    },
+    /// A line of newline-delimited JSON could not be parsed.
+    Ndjson {
+        /// Line number, starting at 1.
+        line: u64,
+        /// Byte offset of the start of the line.
+        offset: u64,
+        source: serde_json::Error,
+    },
+    /// A value of a concatenated JSON stream could not be parsed.
+    Concatenated {
+        /// Number of the value, starting at 1.
+        document: u64,
+        source: serde_json::Error,
+    },
+    /// An action of an Elasticsearch bulk file could not be read.
+    Bulk {
+        /// Number of the action, starting at 1.
+        action: u64,
+        message: &'static str,
+    },
+    /// A document of a MessagePack stream could not be read.
+    MessagePack {
+        /// Number of the document, starting at 1.
+        document: u64,
+        source: rmp_serde::decode::Error,
+    },
+    /// A document of a CBOR sequence could not be read.
+    Cbor {
+        /// Number of the document, starting at 1.
+        document: u64,
+        source: ciborium::de::Error<io::Error>,
+    },
+    /// A CSV or TSV table could not be read.
+    Csv(csv::Error),
+    /// A row of a table could not be turned into a document.
+    Table {
+        /// Line number of the row, starting at 1.
+        row: u64,
+        message: String,
+    },
+    /// An XML input could not be read.
+    Xml {
+        /// Byte offset of the error.
+        offset: u64,
+        source: quick_xml::Error,
+    },
+    /// A YAML input could not be read.
+    Yaml(serde_yaml::Error),
+    /// A document could not be deserialized and was skipped, see
+    /// [`super::Loader::lenient`].
+    Skipped {
+        /// Number of the document, starting at 1.
+        document: u64,
+        /// Byte offset of the start of the document, if known.
+        offset: Option<u64>,
+        /// The text of the document, with invalid UTF-8 replaced.
+        text: String,
+        /// Why, as a [`JsonIterError::Document`] with the position in the
+        /// input, or a [`JsonIterError::DuplicateKey`].
+        source: Box<JsonIterError>,
+    },
+    /// The comma after a document of a JSON array was missing, see
+    /// [`super::Loader::lenient`]. The documents on both sides are read.
+    MissingComma {
+        /// Number of the document before the comma, starting at 1.
+        document: u64,
+        /// Byte offset of the document after it.
+        offset: u64,
+    },
+    /// An object of a document had a key more than once, see
+    /// [`super::Loader::strict`].
+    DuplicateKey {
+        /// Number of the document, starting at 1.
+        document: u64,
+        /// JSON pointer of the object in the document, empty for the
+        /// document itself.
+        pointer: String,
+        key: String,
+    },
+    /// A JSON pointer did not start with `/`.
+    InvalidPointer { pointer: String },
+    /// A JSON pointer did not reference any value in the input.
+    PointerNotFound { pointer: String },
+    /// The thread pool for parallel transformation could not be created.
+    ThreadPool(rayon::ThreadPoolBuildError),
+    /// An SQLite table could not be read.
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+    /// simd-json failed to parse a document.
+    #[cfg(feature = "simd")]
+    Simd(simd_json::Error),
+}
+
+impl JsonIterError {
+    /// `self` from reading document number `document`, at `position`,
+    /// unless it already says which document.
+    pub(super) fn in_document(self, document: u64, position: JsonReaderPosition) -> Self {
+        match self {
+            JsonIterError::Document { .. }
+            | JsonIterError::Skipped { .. }
+            | JsonIterError::MissingComma { .. }
+            | JsonIterError::DuplicateKey { .. } => self,
+            source => JsonIterError::Document {
+                document,
+                position,
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// The position in the input where the error occurred, if known.
+    pub fn position(&self) -> Option<&JsonReaderPosition> {
+        let reader_error = match self {
+            JsonIterError::Document { position, .. } => return Some(position),
+            JsonIterError::Skipped { source, .. } => return source.position(),
+            JsonIterError::Reader(err) => err,
+            JsonIterError::Deserialize(DeserializerError::ReaderError(err)) => err,
+            _ => return None,
+        };
+        Some(match reader_error {
+            ReaderError::SyntaxError(err) => &err.location,
+            ReaderError::UnexpectedValueType { location, .. } => location,
+            ReaderError::UnexpectedStructure { location, .. } => location,
+            ReaderError::MaxNestingDepthExceeded { location, .. } => location,
+            ReaderError::UnsupportedNumberValue { location, .. } => location,
+            ReaderError::IoError { location, .. } => location,
+            _ => return None,
+        })
+    }
+
+    /// The byte offset in the input where the error occurred, if known.
+    pub fn byte_offset(&self) -> Option<u64> {
+        match self {
+            JsonIterError::Ndjson { offset, source, .. } => {
+                return Some(offset + source.column().saturating_sub(1) as u64)
+            }
+            JsonIterError::Skipped { offset, .. } => return *offset,
+            JsonIterError::MissingComma { offset, .. } => return Some(*offset),
+            _ => {}
+        }
+        self.position().and_then(|position| position.data_pos)
+    }
+}
+
+impl fmt::Display for JsonIterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonIterError::Io(err) => write!(f, "io error: {}", err),
+            JsonIterError::Reader(err) => write!(f, "failed to read JSON: {}", err),
+            JsonIterError::Deserialize(err) => write!(f, "failed to deserialize document: {}", err),
+            JsonIterError::Serialize(err) => write!(f, "failed to serialize document: {}", err),
+            JsonIterError::Document {
+                document,
+                position,
+                source,
+            } => {
+                write!(f, "failed to read document {}", document)?;
+                if let Some(offset) = position.data_pos {
+                    write!(f, " at byte {}", offset)?;
+                }
+                if let Some(path) = &position.path {
+                    write!(f, ", path {}", format_path(path))?;
+                }
+                write!(f, ": ")?;
+                write_cause(f, source)
+            }
+            JsonIterError::Ndjson { line, source, .. } => {
+                write!(f, "failed to parse line {}: {}", line, source)
+            }
+            JsonIterError::Concatenated { document, source } => {
+                write!(f, "failed to parse value {}: {}", document, source)
+            }
+            JsonIterError::Bulk { action, message } => {
+                write!(f, "bulk action {} {}", action, message)
+            }
+            JsonIterError::MessagePack { document, source } => {
+                write!(f, "failed to read document {}: {}", document, source)
+            }
+            JsonIterError::Cbor { document, source } => {
+                write!(f, "failed to read document {}: {}", document, source)
+            }
+            JsonIterError::Csv(err) => write!(f, "failed to read table: {}", err),
+            JsonIterError::Table { row, message } => write!(f, "row {}: {}", row, message),
+            JsonIterError::Xml { offset, source } => {
+                write!(f, "failed to read XML at byte {}: {}", offset, source)
+            }
+            JsonIterError::Yaml(err) => write!(f, "failed to read YAML: {}", err),
+            JsonIterError::Skipped {
+                document,
+                offset,
+                source,
+                ..
+            } => {
+                write!(f, "skipped document {}", document)?;
+                if let Some(offset) = offset {
+                    write!(f, " at byte {}", offset)?;
+                }
+                write!(f, ": ")?;
+                let JsonIterError::Document {
+                    position, source, ..
+                } = source.as_ref()
+                else {
+                    return write!(f, "{}", source);
+                };
+                write_cause(f, source)?;
+                if let Some(offset) = position.data_pos {
+                    write!(f, " at byte {}", offset)?;
+                }
+                if let Some(path) = &position.path {
+                    write!(f, ", path {}", format_path(path))?;
+                }
+                Ok(())
+            }
+            JsonIterError::MissingComma { document, offset } => write!(
+                f,
+                "missing comma after document {} at byte {}",
+                document, offset
+            ),
+            JsonIterError::DuplicateKey {
+                document,
+                pointer,
+                key,
+            } if pointer.is_empty() => {
+                write!(f, "document {} has the key '{}' twice", document, key)
+            }
+            JsonIterError::DuplicateKey {
+                document,
+                pointer,
+                key,
+            } => write!(
+                f,
+                "document {} has the key '{}' twice in '{}'",
+                document, key, pointer
+            ),
+            JsonIterError::InvalidPointer { pointer } => {
+                write!(f, "invalid JSON pointer '{}'", pointer)
+            }
+            JsonIterError::PointerNotFound { pointer } => {
+                write!(f, "JSON pointer '{}' not found", pointer)
+            }
+            JsonIterError::ThreadPool(err) => write!(f, "failed to create thread pool: {}", err),
+            #[cfg(feature = "sqlite")]
+            JsonIterError::Sqlite(err) => write!(f, "failed to read SQLite table: {}", err),
+            #[cfg(feature = "simd")]
+            JsonIterError::Simd(err) => write!(f, "failed to parse document: {}", err),
+        }
+    }
+}
+
+/// `path` as a JSON path like `$[2].senses[0]`, as struson writes it.
+fn format_path(path: &JsonPath) -> String {
+    let mut formatted = "$".to_string();
+    for piece in path {
+        match piece {
+            JsonPathPiece::ArrayItem(index) => formatted.push_str(&format!("[{}]", index)),
+            JsonPathPiece::ObjectMember(name) => formatted.push_str(&format!(".{}", name)),
+        }
+    }
+    formatted
+}
+
+/// Write `err` for a [`JsonIterError::Document`], without the position that
+/// struson adds to its errors.
+fn write_cause(f: &mut fmt::Formatter<'_>, err: &JsonIterError) -> fmt::Result {
+    let reader_error = match err {
+        JsonIterError::Reader(err) => err,
+        JsonIterError::Deserialize(DeserializerError::ReaderError(err)) => err,
+        JsonIterError::Deserialize(err) => return write!(f, "{}", err),
+        err => return write!(f, "{}", err),
+    };
+    match reader_error {
+        ReaderError::SyntaxError(err) => write!(f, "JSON syntax error {}", err.kind),
+        ReaderError::UnexpectedValueType {
+            expected, actual, ..
+        } => write!(f, "expected {} but got {}", expected, actual),
+        ReaderError::UnexpectedStructure { kind, .. } => {
+            write!(f, "unexpected JSON structure {}", kind)
+        }
+        ReaderError::MaxNestingDepthExceeded {
+            max_nesting_depth, ..
+        } => write!(f, "maximum nesting depth {} exceeded", max_nesting_depth),
+        ReaderError::UnsupportedNumberValue { number, .. } => {
+            write!(f, "unsupported number value '{}'", number)
+        }
+        ReaderError::IoError { error, .. } => write!(f, "{}", error),
+        err => write!(f, "{}", err),
+    }
+}
+
+impl std::error::Error for JsonIterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JsonIterError::Io(err) => Some(err),
+            JsonIterError::Reader(err) => Some(err),
+            JsonIterError::Deserialize(err) => Some(err),
+            JsonIterError::Serialize(err) => Some(err),
+            JsonIterError::Document { source, .. } => Some(source),
+            JsonIterError::Ndjson { source, .. } => Some(source),
+            JsonIterError::Concatenated { source, .. } => Some(source),
+            JsonIterError::MessagePack { source, .. } => Some(source),
+            JsonIterError::Cbor { source, .. } => Some(source),
+            JsonIterError::Csv(err) => Some(err),
+            JsonIterError::Xml { source, .. } => Some(source),
+            JsonIterError::Yaml(err) => Some(err),
+            JsonIterError::Skipped { source, .. } => Some(source),
+            JsonIterError::Bulk { .. }
+            | JsonIterError::Table { .. }
+            | JsonIterError::MissingComma { .. }
+            | JsonIterError::DuplicateKey { .. }
+            | JsonIterError::InvalidPointer { .. }
+            | JsonIterError::PointerNotFound { .. } => None,
+            JsonIterError::ThreadPool(err) => Some(err),
+            #[cfg(feature = "sqlite")]
+            JsonIterError::Sqlite(err) => Some(err),
+            #[cfg(feature = "simd")]
+            JsonIterError::Simd(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for JsonIterError {
+    fn from(err: io::Error) -> Self {
+        JsonIterError::Io(err)
+    }
+}
+
+impl From<csv::Error> for JsonIterError {
+    fn from(err: csv::Error) -> Self {
+        JsonIterError::Csv(err)
+    }
+}
+
+impl From<serde_yaml::Error> for JsonIterError {
+    fn from(err: serde_yaml::Error) -> Self {
+        JsonIterError::Yaml(err)
+    }
+}
+
+impl From<rayon::ThreadPoolBuildError> for JsonIterError {
+    fn from(err: rayon::ThreadPoolBuildError) -> Self {
+        JsonIterError::ThreadPool(err)
+    }
+}
+
+impl From<ReaderError> for JsonIterError {
+    fn from(err: ReaderError) -> Self {
+        JsonIterError::Reader(err)
+    }
+}
+
+impl From<TransferError> for JsonIterError {
+    fn from(err: TransferError) -> Self {
+        match err {
+            TransferError::ReaderError(err) => JsonIterError::Reader(err),
+            TransferError::WriterError(err) => JsonIterError::Io(err),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for JsonIterError {
+    fn from(err: rusqlite::Error) -> Self {
+        JsonIterError::Sqlite(err)
+    }
+}
+
+#[cfg(feature = "simd")]
+impl From<simd_json::Error> for JsonIterError {
+    fn from(err: simd_json::Error) -> Self {
+        JsonIterError::Simd(err)
+    }
+}
+
+impl From<DeserializerError> for JsonIterError {
+    fn from(err: DeserializerError) -> Self {
+        JsonIterError::Deserialize(err)
+    }
+}
+
+impl From<SerializerError> for JsonIterError {
+    fn from(err: SerializerError) -> Self {
+        JsonIterError::Serialize(err)
+    }
+}
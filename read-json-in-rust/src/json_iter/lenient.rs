@@ -0,0 +1,208 @@
+//! Reading the elements of an array without a JSON reader, for
+//! [`super::Loader::lenient`].
+//!
+//! A JSON reader can't go on after a syntax error, so once struson has found
+//! the array, its elements are split on the commas outside of strings and
+//! nested values, and each is parsed on its own. An element also ends where
+//! a value is complete but another one follows without a comma.
+
+use std::io::{self, Read};
+
+use struson::reader::{JsonReader, JsonStreamReader};
+
+use super::JsonIterError;
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Bytes kept by [`Tap`], more than the JSON reader buffers.
+const KEEP: usize = 16 * 1024;
+
+/// Keeps a copy of the last bytes read while `recording`, to take over from
+/// the JSON reader where it stopped.
+pub(super) struct Tap<R> {
+    reader: R,
+    recorded: Vec<u8>,
+    /// Byte offset of `recorded[0]` in the input.
+    start: u64,
+    recording: bool,
+}
+
+impl<R: Read> Tap<R> {
+    pub(super) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            recorded: Vec::new(),
+            start: 0,
+            recording: true,
+        }
+    }
+}
+
+impl<R: Read> Read for Tap<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        if self.recording {
+            self.recorded.extend_from_slice(&buf[..read]);
+            if self.recorded.len() > 2 * KEEP {
+                let dropped = self.recorded.len() - KEEP;
+                self.recorded.drain(..dropped);
+                self.start += dropped as u64;
+            }
+        }
+        Ok(read)
+    }
+}
+
+/// The raw elements of the array a JSON reader has begun.
+pub(super) struct Elements<R: Read> {
+    /// Owns the input, read through [`JsonStreamReader::reader_mut`].
+    json: JsonStreamReader<Tap<R>>,
+    buffer: Vec<u8>,
+    position: usize,
+    /// Byte offset of `buffer[position]` in the input.
+    offset: u64,
+    done: bool,
+    /// Byte offset of the element after the last one, which wasn't
+    /// separated from it by a comma.
+    missing_comma: Option<u64>,
+}
+
+impl<R: Read> Elements<R> {
+    /// Take over from `json` just after the `[` of the array.
+    pub(super) fn new(mut json: JsonStreamReader<Tap<R>>) -> Self {
+        let offset = json.current_position(false).data_pos.unwrap_or(0);
+        let tap = json.reader_mut();
+        tap.recording = false;
+        // The bytes the JSON reader has buffered but not consumed.
+        let buffer = std::mem::take(&mut tap.recorded);
+        let position = (offset - tap.start) as usize;
+        Self {
+            json,
+            buffer,
+            position,
+            offset,
+            done: false,
+            missing_comma: None,
+        }
+    }
+
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        if self.position == self.buffer.len() {
+            self.buffer.resize(CHUNK_SIZE, 0);
+            let read = self.json.reader_mut().read(&mut self.buffer)?;
+            self.buffer.truncate(read);
+            self.position = 0;
+        }
+        Ok(self.buffer.get(self.position).copied())
+    }
+
+    fn advance(&mut self) {
+        self.position += 1;
+        self.offset += 1;
+    }
+
+    /// The byte offset of the next byte to read in the input.
+    pub(super) fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The byte offset of the element after the last one returned by
+    /// [`Self::next_element`], if there was no comma between them.
+    pub(super) fn take_missing_comma(&mut self) -> Option<u64> {
+        self.missing_comma.take()
+    }
+
+    /// Copy the next element to `element` and return its byte offset, or
+    /// `None` at the end of the array.
+    ///
+    /// An empty element, as in `[1,,2]`, is returned as such, but a trailing
+    /// comma is not an element. A closing bracket that doesn't match the
+    /// innermost open one closes the ones up to its match, or is kept as part
+    /// of the element if it has none, so that `{"a":[1}` ends at the `}`.
+    pub(super) fn next_element(
+        &mut self,
+        element: &mut Vec<u8>,
+    ) -> Result<Option<u64>, JsonIterError> {
+        element.clear();
+        if self.done {
+            return Ok(None);
+        }
+        while self.peek()?.is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.advance();
+        }
+        let start = self.offset;
+        // The closing brackets of the values that are open.
+        let mut closing = Vec::new();
+        let (mut in_string, mut escaped) = (false, false);
+        // Whether the element is a whole value, so that anything but a comma
+        // or the end of the array starts the next one.
+        let mut complete = false;
+        loop {
+            let Some(byte) = self.peek()? else {
+                let message = "the array isn't closed";
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, message).into());
+            };
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => {
+                        in_string = false;
+                        complete = closing.is_empty();
+                    }
+                    _ => {}
+                }
+            } else if closing.is_empty() {
+                match byte {
+                    b',' => {
+                        self.advance();
+                        break;
+                    }
+                    b']' => {
+                        self.advance();
+                        self.done = true;
+                        if element.is_empty() {
+                            return Ok(None);
+                        }
+                        break;
+                    }
+                    _ if byte.is_ascii_whitespace() => complete = !element.is_empty(),
+                    _ if complete => {
+                        self.missing_comma = Some(self.offset);
+                        break;
+                    }
+                    _ => Self::open(byte, &mut closing, &mut in_string),
+                }
+            } else {
+                match byte {
+                    b'}' | b']' => {
+                        if let Some(index) = closing.iter().rposition(|&close| close == byte) {
+                            closing.truncate(index);
+                            complete = closing.is_empty();
+                        }
+                    }
+                    _ => Self::open(byte, &mut closing, &mut in_string),
+                }
+            }
+            self.advance();
+            element.push(byte);
+        }
+        while element
+            .last()
+            .is_some_and(|byte| byte.is_ascii_whitespace())
+        {
+            element.pop();
+        }
+        Ok(Some(start))
+    }
+
+    /// Start a string or a nested value at `byte`, if it does.
+    fn open(byte: u8, closing: &mut Vec<u8>, in_string: &mut bool) {
+        match byte {
+            b'"' => *in_string = true,
+            b'{' => closing.push(b'}'),
+            b'[' => closing.push(b']'),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::Read;
+
+use serde::de::DeserializeOwned;
+
+use super::{Documents, JsonIterError, RawDocuments};
+use crate::io::{Encoding, Input, ReadOptions};
+
+/// The parser used to deserialize each document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Parser {
+    /// Deserialize directly from the struson reader.
+    #[default]
+    Serde,
+    /// Copy the raw bytes of each document and parse them with simd-json.
+    #[cfg(feature = "simd")]
+    Simd,
+}
+
+/// Options for reading documents, e.g.
+///
+/// ```no_run
+/// # use read_json_in_rust::json_iter::Loader;
+/// # use serde_json::Value;
+/// let docs = Loader::new().pointer("/hits/hits").from_file::<Value>("data/dump.json")?;
+/// # Ok::<(), read_json_in_rust::json_iter::JsonIterError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Loader {
+    pub(super) pointer: String,
+    pub(super) parser: Parser,
+    pub(super) lenient: bool,
+    pub(super) strict: bool,
+    read: ReadOptions,
+}
+
+impl Loader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the array at the JSON pointer `pointer` (e.g. `/hits/hits`)
+    /// instead of the top-level array.
+    pub fn pointer(mut self, pointer: &str) -> Self {
+        self.pointer = pointer.to_string();
+        self
+    }
+
+    pub fn parser(mut self, parser: Parser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// Skip the documents that can't be parsed or deserialized: each is
+    /// yielded as a [`JsonIterError::Skipped`] error and reading continues
+    /// with the next one.
+    ///
+    /// The elements of the array are then found by the commas between them,
+    /// so a document with an unclosed bracket or quote takes the following
+    /// ones with it, and anything after the array is not read. A document
+    /// that closes a bracket it didn't open, as in `{"a":[1}`, ends there,
+    /// and a missing comma between two documents is yielded as a
+    /// [`JsonIterError::MissingComma`] error between them.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Fail on a document with a key more than once in an object, with a
+    /// [`JsonIterError::DuplicateKey`], instead of keeping the last value.
+    /// With [`Loader::lenient`] such documents are skipped.
+    ///
+    /// Each document is then copied and read twice, once for the keys and
+    /// once to deserialize it.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Read files `buffer_size` bytes at a time, see
+    /// [`ReadOptions::buffer_size`].
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.read = self.read.buffer_size(buffer_size);
+        self
+    }
+
+    /// Decode files from `encoding`, see [`ReadOptions::encoding`].
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.read = self.read.encoding(encoding);
+        self
+    }
+
+    /// Iterate over the documents read from `reader`.
+    pub fn from_reader<T: DeserializeOwned, R: Read>(
+        &self,
+        reader: R,
+    ) -> Result<Documents<T, R>, JsonIterError> {
+        Documents::new(reader, self)
+    }
+
+    /// Open `path` and iterate over the documents it contains.
+    ///
+    /// Compressed files are decompressed, see [`crate::io::open`].
+    pub fn from_file<T: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<Documents<T, Input>, JsonIterError> {
+        self.from_reader(self.read.open(path)?)
+    }
+
+    /// Iterate over the documents read from `reader` as raw JSON.
+    pub fn raw_from_reader<R: Read>(&self, reader: R) -> Result<RawDocuments<R>, JsonIterError> {
+        RawDocuments::new(reader, self)
+    }
+
+    /// Open `path` and iterate over the documents it contains as raw JSON.
+    pub fn raw_from_file(&self, path: &str) -> Result<RawDocuments<Input>, JsonIterError> {
+        self.raw_from_reader(self.read.open(path)?)
+    }
+}
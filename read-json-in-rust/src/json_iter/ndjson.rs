@@ -0,0 +1,167 @@
+//! Newline-delimited JSON (JSON Lines), one document per line.
+
+use std::io::{BufRead, Write};
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use struson::writer::{JsonStreamWriter, JsonWriter};
+
+use super::JsonIterError;
+use crate::io::{self, Input};
+use crate::number::{self, NumberFormat};
+
+/// Iterator over the documents of a newline-delimited JSON stream.
+///
+/// Blank lines are skipped. Yields `Err` at most once, after which the
+/// iterator is exhausted.
+pub struct Lines<T, R: BufRead> {
+    reader: Option<R>,
+    buffer: String,
+    line: u64,
+    offset: u64,
+    _doc: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned, R: BufRead> Lines<T, R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader: Some(reader),
+            buffer: String::new(),
+            line: 0,
+            offset: 0,
+            _doc: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned, R: BufRead> Iterator for Lines<T, R> {
+    type Item = Result<T, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reader = self.reader.as_mut()?;
+        loop {
+            self.buffer.clear();
+            let read = match reader.read_line(&mut self.buffer) {
+                Ok(0) => {
+                    self.reader = None;
+                    return None;
+                }
+                Ok(read) => read,
+                Err(err) => {
+                    self.reader = None;
+                    return Some(Err(err.into()));
+                }
+            };
+            let offset = self.offset;
+            self.line += 1;
+            self.offset += read as u64;
+            if self.buffer.trim().is_empty() {
+                continue;
+            }
+            let result =
+                serde_json::from_str(&self.buffer).map_err(|source| JsonIterError::Ndjson {
+                    line: self.line,
+                    offset,
+                    source,
+                });
+            if result.is_err() {
+                self.reader = None;
+            }
+            return Some(result);
+        }
+    }
+}
+
+/// Writes documents as newline-delimited JSON.
+pub struct LinesWriter<W: Write> {
+    writer: W,
+    numbers: Option<NumberFormat>,
+}
+
+impl<W: Write> LinesWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            numbers: None,
+        }
+    }
+
+    /// Write the floats in `format`, see [`crate::number`].
+    pub fn numbers(mut self, format: NumberFormat) -> Self {
+        self.numbers = Some(format);
+        self
+    }
+
+    pub fn write<T: Serialize>(&mut self, doc: &T) -> Result<(), JsonIterError> {
+        if let Some(format) = self.numbers {
+            number::to_writer(&mut self.writer, doc, format).map_err(std::io::Error::from)?;
+            self.writer.write_all(b"\n")?;
+            return Ok(());
+        }
+        let mut json_writer = JsonStreamWriter::new(&mut self.writer);
+        json_writer.serialize_value(doc)?;
+        json_writer.finish_document()?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Returns the underlying writer, flushed.
+    pub fn finish(mut self) -> Result<W, JsonIterError> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Iterate over the newline-delimited documents read from `reader`.
+pub fn load_ndjson_from_reader<T: DeserializeOwned, R: BufRead>(reader: R) -> Lines<T, R> {
+    Lines::new(reader)
+}
+
+/// Open `path` and iterate over the newline-delimited documents it contains.
+pub fn load_ndjson<T: DeserializeOwned>(path: &str) -> Result<Lines<T, Input>, JsonIterError> {
+    Ok(load_ndjson_from_reader(io::open(path)?))
+}
+
+/// Write all documents to `writer` as newline-delimited JSON and return the writer.
+pub fn dump_ndjson_to_writer<W, T, I>(writer: W, docs: I) -> Result<W, JsonIterError>
+where
+    W: Write,
+    T: Serialize,
+    I: IntoIterator<Item = T>,
+{
+    try_dump_ndjson_to_writer(writer, docs.into_iter().map(Ok))
+}
+
+/// Write all documents to `writer` as newline-delimited JSON, stopping at the first `Err`.
+pub fn try_dump_ndjson_to_writer<W, T, I>(writer: W, docs: I) -> Result<W, JsonIterError>
+where
+    W: Write,
+    T: Serialize,
+    I: IntoIterator<Item = Result<T, JsonIterError>>,
+{
+    let mut writer = LinesWriter::new(writer);
+    for doc in docs {
+        writer.write(&doc?)?;
+    }
+    writer.finish()
+}
+
+/// Write all documents to `path` as newline-delimited JSON.
+pub fn dump_ndjson<T, I>(path: &str, docs: I) -> Result<(), JsonIterError>
+where
+    T: Serialize,
+    I: IntoIterator<Item = T>,
+{
+    try_dump_ndjson(path, docs.into_iter().map(Ok))
+}
+
+/// Write all documents to `path` as newline-delimited JSON, stopping at the first `Err`.
+pub fn try_dump_ndjson<T, I>(path: &str, docs: I) -> Result<(), JsonIterError>
+where
+    T: Serialize,
+    I: IntoIterator<Item = Result<T, JsonIterError>>,
+{
+    try_dump_ndjson_to_writer(io::create(path)?, docs)?.finish()?;
+    Ok(())
+}
@@ -0,0 +1,263 @@
+//! Parallel transformation of streamed documents.
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, Scope};
+
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use super::JsonIterError;
+
+/// Number of documents read before a batch is handed to the thread pool.
+const BATCH_SIZE: usize = 1024;
+
+/// Iterator returned by [`par_map`].
+pub struct ParMap<I, F, U> {
+    docs: I,
+    f: F,
+    pool: ThreadPool,
+    mapped: std::vec::IntoIter<U>,
+    error: Option<JsonIterError>,
+    done: bool,
+}
+
+impl<T, U, I, F> ParMap<I, F, U>
+where
+    T: Send,
+    U: Send,
+    I: Iterator<Item = Result<T, JsonIterError>>,
+    F: Fn(T) -> U + Sync,
+{
+    fn fill(&mut self) {
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        for doc in self.docs.by_ref().take(BATCH_SIZE) {
+            match doc {
+                Ok(doc) => batch.push(doc),
+                Err(err) => {
+                    self.error = Some(err);
+                    break;
+                }
+            }
+        }
+        if batch.len() < BATCH_SIZE {
+            self.done = true;
+        }
+        let f = &self.f;
+        let mapped: Vec<U> = self.pool.install(|| batch.into_par_iter().map(f).collect());
+        self.mapped = mapped.into_iter();
+    }
+}
+
+impl<T, U, I, F> Iterator for ParMap<I, F, U>
+where
+    T: Send,
+    U: Send,
+    I: Iterator<Item = Result<T, JsonIterError>>,
+    F: Fn(T) -> U + Sync,
+{
+    type Item = Result<U, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(doc) = self.mapped.next() {
+                return Some(Ok(doc));
+            }
+            if let Some(err) = self.error.take() {
+                return Some(Err(err));
+            }
+            if self.done {
+                return None;
+            }
+            self.fill();
+        }
+    }
+}
+
+/// Apply `f` to the documents on a rayon pool of `threads` threads, yielding
+/// the results in input order.
+///
+/// Documents are read in batches, so at most one batch is held in memory.
+/// With `threads == 0` rayon picks the number of threads (one per CPU).
+/// A reader error is yielded after the documents preceding it.
+pub fn par_map<T, U, I, F>(
+    docs: I,
+    threads: usize,
+    f: F,
+) -> Result<ParMap<I::IntoIter, F, U>, JsonIterError>
+where
+    T: Send,
+    U: Send,
+    I: IntoIterator<Item = Result<T, JsonIterError>>,
+    F: Fn(T) -> U + Sync,
+{
+    let pool = ThreadPoolBuilder::new().num_threads(threads).build()?;
+    Ok(ParMap {
+        docs: docs.into_iter(),
+        f,
+        pool,
+        mapped: Vec::new().into_iter(),
+        error: None,
+        done: false,
+    })
+}
+
+/// Number of documents a thread of [`par_map_window`] takes at once.
+const CHUNK_SIZE: usize = 64;
+
+/// The order of the results of [`par_map_window`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// The order of the documents, holding back the results that are done
+    /// before the ones preceding them.
+    Input,
+    /// The order the results are done in.
+    Completion,
+}
+
+type Chunk<U> = Vec<Result<U, JsonIterError>>;
+
+/// The documents not yet taken by a thread of [`par_map_window`].
+struct Remaining<I> {
+    docs: I,
+    /// Index of the next chunk.
+    next: usize,
+    done: bool,
+}
+
+/// Iterator returned by [`par_map_window`].
+pub struct Windowed<U> {
+    results: Receiver<(usize, Chunk<U>)>,
+    /// A slot in the window for each chunk taken off it.
+    slots: SyncSender<()>,
+    order: Order,
+    /// The chunks done before the next one, with [`Order::Input`].
+    pending: BTreeMap<usize, Chunk<U>>,
+    next: usize,
+    chunk: std::vec::IntoIter<Result<U, JsonIterError>>,
+    failed: bool,
+}
+
+impl<U> Iterator for Windowed<U> {
+    type Item = Result<U, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.chunk.next() {
+                self.failed = result.is_err();
+                return Some(result);
+            }
+            if self.failed {
+                return None;
+            }
+            let chunk = match self.order {
+                Order::Input => match self.pending.remove(&self.next) {
+                    Some(chunk) => chunk,
+                    None => {
+                        let (index, chunk) = self.results.recv().ok()?;
+                        self.pending.insert(index, chunk);
+                        continue;
+                    }
+                },
+                Order::Completion => self.results.recv().ok()?.1,
+            };
+            self.next += 1;
+            self.slots.send(()).ok();
+            self.chunk = chunk.into_iter();
+        }
+    }
+}
+
+/// Apply `f` to the documents on `threads` threads of `scope`, yielding the
+/// results in `order`.
+///
+/// At most about `window` documents are taken from `docs` and not yet
+/// yielded, so a slow document holds up the others by no more than that.
+/// With `threads == 0` there is one thread per CPU. A reader error stops the
+/// results; with [`Order::Input`] it is yielded after the documents
+/// preceding it.
+pub fn par_map_window<'scope, T, U, I, F>(
+    scope: &'scope Scope<'scope, '_>,
+    docs: I,
+    threads: usize,
+    window: usize,
+    order: Order,
+    f: F,
+) -> Windowed<U>
+where
+    T: Send,
+    U: Send + 'scope,
+    I: IntoIterator<Item = Result<T, JsonIterError>>,
+    I::IntoIter: Send + 'scope,
+    F: Fn(T) -> U + Send + Sync + 'scope,
+{
+    let threads = match threads {
+        0 => thread::available_parallelism().map_or(1, |cpus| cpus.get()),
+        threads => threads,
+    };
+    let chunks = window.div_ceil(CHUNK_SIZE).max(1);
+    let (slots, free) = mpsc::sync_channel(chunks);
+    for _ in 0..chunks {
+        slots.send(()).expect("a slot for each chunk");
+    }
+    let free = Arc::new(Mutex::new(free));
+    let remaining = Arc::new(Mutex::new(Remaining {
+        docs: docs.into_iter(),
+        next: 0,
+        done: false,
+    }));
+    let f = Arc::new(f);
+    let (done, results) = mpsc::channel();
+    for _ in 0..threads {
+        let (free, remaining, f, done) = (free.clone(), remaining.clone(), f.clone(), done.clone());
+        scope.spawn(move || loop {
+            // Stops when the `Windowed` is dropped.
+            if free
+                .lock()
+                .expect("no panic holding the lock")
+                .recv()
+                .is_err()
+            {
+                break;
+            }
+            let (index, docs) = {
+                let mut remaining = remaining.lock().expect("no panic holding the lock");
+                if remaining.done {
+                    break;
+                }
+                let docs: Vec<_> = remaining.docs.by_ref().take(CHUNK_SIZE).collect();
+                if docs.len() < CHUNK_SIZE || docs.iter().any(Result::is_err) {
+                    remaining.done = true;
+                }
+                if docs.is_empty() {
+                    break;
+                }
+                remaining.next += 1;
+                (remaining.next - 1, docs)
+            };
+            let mut chunk = Vec::with_capacity(docs.len());
+            for doc in docs {
+                match doc {
+                    Ok(doc) => chunk.push(Ok(f(doc))),
+                    Err(err) => {
+                        chunk.push(Err(err));
+                        break;
+                    }
+                }
+            }
+            if done.send((index, chunk)).is_err() {
+                break;
+            }
+        });
+    }
+    Windowed {
+        results,
+        slots,
+        order,
+        pending: BTreeMap::new(),
+        next: 0,
+        chunk: Vec::new().into_iter(),
+        failed: false,
+    }
+}
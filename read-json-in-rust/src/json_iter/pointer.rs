@@ -0,0 +1,65 @@
+//! Navigation to a JSON pointer (RFC 6901) inside a streamed document.
+
+use std::io::Read;
+
+use struson::reader::{JsonReader, JsonStreamReader, ValueType};
+
+use super::JsonIterError;
+
+/// Splits a JSON pointer like `/hits/hits` into its unescaped reference tokens.
+fn reference_tokens(pointer: &str) -> Result<Vec<String>, JsonIterError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(JsonIterError::InvalidPointer {
+            pointer: pointer.to_string(),
+        });
+    };
+    Ok(rest
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+/// Advances `reader` to the value referenced by `pointer`, skipping every
+/// sibling on the way there.
+pub(super) fn seek_pointer<R: Read>(
+    reader: &mut JsonStreamReader<R>,
+    pointer: &str,
+) -> Result<(), JsonIterError> {
+    let not_found = || JsonIterError::PointerNotFound {
+        pointer: pointer.to_string(),
+    };
+    for token in reference_tokens(pointer)? {
+        match reader.peek()? {
+            ValueType::Object => {
+                reader.begin_object()?;
+                loop {
+                    if !reader.has_next()? {
+                        return Err(not_found());
+                    }
+                    if reader.next_name()? == token {
+                        break;
+                    }
+                    reader.skip_value()?;
+                }
+            }
+            ValueType::Array => {
+                let index: usize = token.parse().map_err(|_| not_found())?;
+                reader.begin_array()?;
+                for _ in 0..index {
+                    if !reader.has_next()? {
+                        return Err(not_found());
+                    }
+                    reader.skip_value()?;
+                }
+                if !reader.has_next()? {
+                    return Err(not_found());
+                }
+            }
+            _ => return Err(not_found()),
+        }
+    }
+    Ok(())
+}
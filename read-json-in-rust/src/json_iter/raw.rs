@@ -0,0 +1,174 @@
+use std::io::{Read, Write};
+
+use serde::Serialize;
+use serde_json::value::RawValue;
+use struson::reader::{JsonReader, JsonStreamReader};
+use struson::writer::{JsonStreamWriter, JsonWriter};
+
+use super::{finish_array, pointer, stream_reader, JsonIterError, Loader};
+
+/// Iterator over the documents of a JSON array as raw JSON, without
+/// deserializing them.
+///
+/// Every document is checked by struson and written compactly, so the raw
+/// JSON has no whitespace and only the escapes JSON requires.
+///
+/// Yields `Err` at most once, after which the iterator is exhausted.
+pub struct RawDocuments<R: Read> {
+    reader: Option<JsonStreamReader<R>>,
+    /// Number of documents read so far.
+    document: u64,
+}
+
+impl<R: Read> RawDocuments<R> {
+    pub(super) fn new(reader: R, loader: &Loader) -> Result<Self, JsonIterError> {
+        let mut reader = stream_reader(reader);
+        pointer::seek_pointer(&mut reader, &loader.pointer)?;
+        reader.begin_array()?;
+        Ok(Self {
+            reader: Some(reader),
+            document: 0,
+        })
+    }
+
+    fn read_document(&mut self) -> Result<Box<RawValue>, JsonIterError> {
+        let reader = self.reader.as_mut().expect("reader to be present");
+        let mut buffer = Vec::new();
+        let mut json_writer = JsonStreamWriter::new(&mut buffer);
+        reader.transfer_to(&mut json_writer)?;
+        json_writer.finish_document()?;
+        let json = String::from_utf8(buffer).expect("struson to write UTF-8");
+        Ok(RawValue::from_string(json).expect("struson to write valid JSON"))
+    }
+}
+
+impl<R: Read> Iterator for RawDocuments<R> {
+    type Item = Result<Box<RawValue>, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reader = self.reader.as_mut()?;
+        let result = match reader.has_next() {
+            Ok(true) => self.read_document(),
+            Ok(false) => return finish_array(self.reader.take()?).err().map(Err),
+            Err(err) => Err(err.into()),
+        };
+        if let Err(err) = result {
+            let reader = self.reader.take()?;
+            let position = err.position().cloned();
+            let position = position.unwrap_or_else(|| reader.current_position(true));
+            return Some(Err(err.in_document(self.document + 1, position)));
+        }
+        self.document += 1;
+        Some(result)
+    }
+}
+
+/// Writes documents as elements of a JSON array, copying raw documents as
+/// they are.
+pub struct RawArrayWriter<W: Write> {
+    writer: W,
+    empty: bool,
+}
+
+impl<W: Write> RawArrayWriter<W> {
+    pub fn new(mut writer: W) -> Result<Self, JsonIterError> {
+        writer.write_all(b"[")?;
+        Ok(Self {
+            writer,
+            empty: true,
+        })
+    }
+
+    /// Continue an array that `writer` has begun, e.g. in a file reopened
+    /// for appending, that has the `[` and `empty` if no documents yet.
+    pub fn resume(writer: W, empty: bool) -> Self {
+        Self { writer, empty }
+    }
+
+    /// The underlying writer, to flush it or find its position.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    fn separate(&mut self) -> Result<(), JsonIterError> {
+        if !self.empty {
+            self.writer.write_all(b",")?;
+        }
+        self.empty = false;
+        Ok(())
+    }
+
+    pub fn write_raw(&mut self, doc: &RawValue) -> Result<(), JsonIterError> {
+        self.separate()?;
+        self.writer.write_all(doc.get().as_bytes())?;
+        Ok(())
+    }
+
+    pub fn write<T: Serialize>(&mut self, doc: &T) -> Result<(), JsonIterError> {
+        self.separate()?;
+        serde_json::to_writer(&mut self.writer, doc).map_err(std::io::Error::from)?;
+        Ok(())
+    }
+
+    /// Closes the array and returns the underlying writer, flushed.
+    pub fn finish(mut self) -> Result<W, JsonIterError> {
+        self.writer.write_all(b"]")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::transform::{AffectedKeys, DocumentTransform, Remove, Rename};
+
+    /// Copy the documents of `input` as `--raw` does, parsing and updating
+    /// only those that may have the field of `removal` or `rename`.
+    fn pass_raw(input: &str, removal: &str, rename: &str) -> String {
+        let removal: Remove = removal.parse().unwrap();
+        let rename: Rename = rename.parse().unwrap();
+        let keys = AffectedKeys::new([&removal.path, &rename.from]).unwrap();
+        let mut writer = RawArrayWriter::new(Vec::new()).unwrap();
+        for raw in Loader::new().raw_from_reader(input.as_bytes()).unwrap() {
+            let raw = raw.unwrap();
+            if keys.may_affect(raw.get()) {
+                let mut doc: Value = serde_json::from_str(raw.get()).unwrap();
+                removal.apply(&mut doc).unwrap();
+                rename.apply(&mut doc).unwrap();
+                writer.write(&doc).unwrap();
+            } else {
+                writer.write_raw(&raw).unwrap();
+            }
+        }
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn copies_untouched_documents_as_they_are() {
+        // Key order, number formats and doubled keys that parsing would lose.
+        let input = r#"[{"z":1.50,"a":[1E3,-0,12345678901234567890123],"é":"\"x\""},{"k":1,"k":2},7,"s",null]"#;
+        assert_eq!(pass_raw(input, "_internal", "pos=partOfSpeech"), input);
+        assert_eq!(pass_raw("[]", "_internal", "pos=partOfSpeech"), "[]");
+    }
+
+    #[test]
+    fn updates_documents_with_the_fields() {
+        let input = r#"[{"z":1.50,"_internal":{"a":1}},{"b":"pos"},{"entry":{"pos":"nn"},"pos":"vb"},{"y":2}]"#;
+        let output = pass_raw(input, "_internal", "pos=partOfSpeech");
+        let docs: Vec<Value> = serde_json::from_str(&output).unwrap();
+        assert_eq!(
+            docs,
+            [
+                serde_json::json!({ "z": 1.5 }),
+                serde_json::json!({ "b": "pos" }),
+                serde_json::json!({ "entry": { "pos": "nn" }, "partOfSpeech": "vb" }),
+                serde_json::json!({ "y": 2 }),
+            ]
+        );
+        // The last document doesn't have the keys and is copied.
+        assert!(output.ends_with(r#",{"y":2}]"#), "{}", output);
+    }
+}
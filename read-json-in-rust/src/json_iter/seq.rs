@@ -0,0 +1,57 @@
+//! JSON text sequences (RFC 7464, `application/json-seq`): every document is
+//! preceded by a record separator (0x1E) and followed by a line feed.
+//!
+//! A reader can start at any record separator, e.g. after a truncated
+//! document or when it resumes a stream, and tell a complete document from a
+//! cut-off one by the line feed.
+
+use std::io::Write;
+
+use serde::Serialize;
+use struson::writer::{JsonStreamWriter, JsonWriter};
+
+use super::JsonIterError;
+use crate::number::{self, NumberFormat};
+
+/// The record separator before every document.
+pub const RS: u8 = 0x1e;
+
+/// Writes documents as a JSON text sequence.
+pub struct SeqWriter<W: Write> {
+    writer: W,
+    numbers: Option<NumberFormat>,
+}
+
+impl<W: Write> SeqWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            numbers: None,
+        }
+    }
+
+    /// Write the floats in `format`, see [`crate::number`].
+    pub fn numbers(mut self, format: NumberFormat) -> Self {
+        self.numbers = Some(format);
+        self
+    }
+
+    pub fn write<T: Serialize>(&mut self, doc: &T) -> Result<(), JsonIterError> {
+        self.writer.write_all(&[RS])?;
+        if let Some(format) = self.numbers {
+            number::to_writer(&mut self.writer, doc, format).map_err(std::io::Error::from)?;
+        } else {
+            let mut json_writer = JsonStreamWriter::new(&mut self.writer);
+            json_writer.serialize_value(doc)?;
+            json_writer.finish_document()?;
+        }
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Returns the underlying writer, flushed.
+    pub fn finish(mut self) -> Result<W, JsonIterError> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
@@ -0,0 +1,134 @@
+//! Reading and writing on threads of their own, connected to the rest of
+//! the conversion by bounded channels.
+//!
+//! With [`read_ahead`] the next documents are parsed while the current ones
+//! are updated, and with [`write_behind`] the updated ones are serialized
+//! meanwhile. At most about `capacity` documents wait in each channel, so
+//! the memory use stays the same however fast each side is.
+//!
+//! The items are sent in batches, as a thread switch per document costs more
+//! than most updates.
+
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{Scope, ScopedJoinHandle};
+
+/// Number of items sent at once.
+const BATCH_SIZE: usize = 256;
+
+/// A channel holding about `capacity` items in batches.
+fn channel<T>(capacity: usize) -> (SyncSender<Vec<T>>, Receiver<Vec<T>>) {
+    mpsc::sync_channel(capacity.div_ceil(BATCH_SIZE).max(1))
+}
+
+/// Iterator over the items sent from another thread, see [`read_ahead`].
+pub struct Received<T> {
+    receiver: Receiver<Vec<T>>,
+    batch: std::vec::IntoIter<T>,
+}
+
+impl<T> Received<T> {
+    fn new(receiver: Receiver<Vec<T>>) -> Self {
+        Self {
+            receiver,
+            batch: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<T> Iterator for Received<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            if let Some(item) = self.batch.next() {
+                return Some(item);
+            }
+            self.batch = self.receiver.recv().ok()?.into_iter();
+        }
+    }
+}
+
+/// Iterate on a thread of `scope` over the items of the iterator returned by
+/// `produce`, and receive them in order through a channel of about
+/// `capacity` items.
+///
+/// The thread stops when the iterator ends or the returned [`Received`] is
+/// dropped.
+pub fn read_ahead<'scope, I, F>(
+    scope: &'scope Scope<'scope, '_>,
+    capacity: usize,
+    produce: F,
+) -> Received<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Send + 'scope,
+    F: FnOnce() -> I + Send + 'scope,
+{
+    let (sender, receiver) = channel(capacity);
+    scope.spawn(move || {
+        let mut items = produce().into_iter().peekable();
+        while items.peek().is_some() {
+            let batch = items.by_ref().take(BATCH_SIZE).collect();
+            if sender.send(batch).is_err() {
+                break;
+            }
+        }
+    });
+    Received::new(receiver)
+}
+
+/// The sending side of [`write_behind`].
+pub struct Behind<'scope, T, R> {
+    sender: SyncSender<Vec<T>>,
+    batch: Vec<T>,
+    consumer: ScopedJoinHandle<'scope, R>,
+}
+
+impl<T, R> Behind<'_, T, R> {
+    /// Send `item` to the consumer, or return `Err` if the consumer has
+    /// stopped, e.g. on a write error that [`Behind::finish`] returns.
+    pub fn send(&mut self, item: T) -> Result<(), T> {
+        self.batch.push(item);
+        if self.batch.len() < BATCH_SIZE {
+            return Ok(());
+        }
+        let batch = std::mem::replace(&mut self.batch, Vec::with_capacity(BATCH_SIZE));
+        self.sender
+            .send(batch)
+            .map_err(|err| err.0.into_iter().last().expect("a full batch"))
+    }
+
+    /// End the items and wait for the consumer to return.
+    pub fn finish(self) -> R {
+        if !self.batch.is_empty() {
+            // A consumer that stopped early returns why.
+            self.sender.send(self.batch).ok();
+        }
+        drop(self.sender);
+        match self.consumer.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+/// Run `consume` on a thread of `scope` with the items sent through the
+/// returned [`Behind`], over a channel of about `capacity` items.
+pub fn write_behind<'scope, T, R, F>(
+    scope: &'scope Scope<'scope, '_>,
+    capacity: usize,
+    consume: F,
+) -> Behind<'scope, T, R>
+where
+    T: Send + 'scope,
+    R: Send + 'scope,
+    F: FnOnce(Received<T>) -> R + Send + 'scope,
+{
+    let (sender, receiver) = channel(capacity);
+    let consumer = scope.spawn(move || consume(Received::new(receiver)));
+    Behind {
+        sender,
+        batch: Vec::with_capacity(BATCH_SIZE),
+        consumer,
+    }
+}
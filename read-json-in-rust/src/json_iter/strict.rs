@@ -0,0 +1,165 @@
+//! Finding the keys that are in an object more than once, which serde
+//! silently collapses to the last value, for [`super::Loader::strict`].
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use super::JsonIterError;
+use crate::path::push_token;
+
+/// Fail with [`JsonIterError::DuplicateKey`] if an object of the JSON
+/// document `json`, the `document`th one read, has a key more than once.
+///
+/// Nothing but the keys is kept, and those only while their object is read.
+pub(super) fn check_keys(json: &[u8], document: u64) -> Result<(), JsonIterError> {
+    let mut duplicate = None;
+    let mut deserializer = serde_json::Deserializer::from_slice(json);
+    let checked = Keys {
+        duplicate: &mut duplicate,
+    }
+    .deserialize(&mut deserializer);
+    let Some(Duplicate { mut path, key }) = duplicate else {
+        // Syntax errors are left to the parser of the document.
+        return checked.or(Ok(()));
+    };
+    let mut pointer = String::new();
+    for segment in path.drain(..).rev() {
+        push_token(&mut pointer, &segment);
+    }
+    Err(JsonIterError::DuplicateKey {
+        document,
+        pointer,
+        key,
+    })
+}
+
+struct Duplicate {
+    /// The keys and indexes from the object with the key up to the document.
+    path: Vec<String>,
+    key: String,
+}
+
+/// Reads a value, stopping at the first duplicate key.
+struct Keys<'a> {
+    duplicate: &'a mut Option<Duplicate>,
+}
+
+/// Adds `segment` to the path of the duplicate key found in its value.
+fn within<E>(duplicate: &mut Option<Duplicate>, segment: impl FnOnce() -> String) -> E
+where
+    E: de::Error,
+{
+    if let Some(duplicate) = duplicate {
+        duplicate.path.push(segment());
+    }
+    E::custom("duplicate key")
+}
+
+impl<'de> DeserializeSeed<'de> for Keys<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Keys<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E>(self) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut index = 0;
+        loop {
+            let element = Keys {
+                duplicate: &mut *self.duplicate,
+            };
+            match seq.next_element_seed(element) {
+                Ok(Some(())) => index += 1,
+                Ok(None) => return Ok(()),
+                Err(err) if self.duplicate.is_none() => return Err(err),
+                Err(_) => return Err(within(self.duplicate, || index.to_string())),
+            }
+        }
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut keys = HashSet::new();
+        while let Some(Key(key)) = map.next_key()? {
+            if keys.contains(&key) {
+                *self.duplicate = Some(Duplicate {
+                    path: Vec::new(),
+                    key: key.into_owned(),
+                });
+                return Err(de::Error::custom("duplicate key"));
+            }
+            let value = Keys {
+                duplicate: &mut *self.duplicate,
+            };
+            if let Err(err) = map.next_value_seed(value) {
+                return match self.duplicate.is_none() {
+                    true => Err(err),
+                    false => Err(within(self.duplicate, || key.into_owned())),
+                };
+            }
+            keys.insert(key);
+        }
+        Ok(())
+    }
+}
+
+/// A key, borrowed from the document unless it has escapes.
+struct Key<'de>(Cow<'de, str>);
+
+impl<'de> de::Deserialize<'de> for Key<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeyVisitor;
+
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = Key<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a key")
+            }
+
+            fn visit_borrowed_str<E>(self, key: &'de str) -> Result<Key<'de>, E> {
+                Ok(Key(Cow::Borrowed(key)))
+            }
+
+            fn visit_str<E>(self, key: &str) -> Result<Key<'de>, E> {
+                Ok(Key(Cow::Owned(key.to_string())))
+            }
+        }
+
+        deserializer.deserialize_str(KeyVisitor)
+    }
+}
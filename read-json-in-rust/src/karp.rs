@@ -0,0 +1,186 @@
+//! Karp exports, the entries of a resource in an envelope with its metadata:
+//!
+//! ```json
+//! {"resource_id": "skbl", "version": 3, "entries": [{...}, {...}]}
+//! ```
+//!
+//! The entries are streamed like the documents of a bare array, all other
+//! members of the envelope are kept as metadata.
+
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use struson::reader::{JsonReader, JsonStreamReader};
+use struson::writer::{JsonStreamWriter, JsonWriter};
+
+use crate::io::{self, Input};
+use crate::json_iter::{self, JsonIterError};
+use crate::number::{self, NumberFormat};
+
+/// The member of the envelope with the entries.
+pub const ENTRIES: &str = "entries";
+
+/// The members of the envelope other than the entries.
+pub type Metadata = Map<String, Value>;
+
+/// Iterator over the entries of a Karp export.
+///
+/// Yields `Err` at most once, after which the iterator is exhausted.
+pub struct Entries<T, R: Read> {
+    reader: Option<JsonStreamReader<R>>,
+    metadata: Metadata,
+    _entry: PhantomData<fn() -> T>,
+}
+
+/// Read members into `metadata` until the entries or the end of the envelope,
+/// returns whether the entries were found.
+fn read_metadata<R: Read>(
+    reader: &mut JsonStreamReader<R>,
+    metadata: &mut Metadata,
+) -> Result<bool, JsonIterError> {
+    while reader.has_next()? {
+        let name = reader.next_name_owned()?;
+        if name == ENTRIES {
+            return Ok(true);
+        }
+        metadata.insert(name, reader.deserialize_next()?);
+    }
+    Ok(false)
+}
+
+impl<T: DeserializeOwned, R: Read> Entries<T, R> {
+    fn new(reader: R) -> Result<Self, JsonIterError> {
+        let mut reader = json_iter::stream_reader(reader);
+        let mut metadata = Metadata::new();
+        reader.begin_object()?;
+        if !read_metadata(&mut reader, &mut metadata)? {
+            return Err(JsonIterError::PointerNotFound {
+                pointer: format!("/{}", ENTRIES),
+            });
+        }
+        reader.begin_array()?;
+        Ok(Self {
+            reader: Some(reader),
+            metadata,
+            _entry: PhantomData,
+        })
+    }
+
+    /// The metadata of the envelope. Members after the entries are only
+    /// included once all entries have been read.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn finish(&mut self, mut reader: JsonStreamReader<R>) -> Result<(), JsonIterError> {
+        reader.end_array()?;
+        read_metadata(&mut reader, &mut self.metadata)?;
+        reader.end_object()?;
+        reader.consume_trailing_whitespace()?;
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned, R: Read> Iterator for Entries<T, R> {
+    type Item = Result<T, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reader = self.reader.as_mut()?;
+        let result = match reader.has_next() {
+            Ok(true) => reader.deserialize_next().map_err(JsonIterError::from),
+            Ok(false) => {
+                let reader = self.reader.take()?;
+                return self.finish(reader).err().map(Err);
+            }
+            Err(err) => Err(err.into()),
+        };
+        if result.is_err() {
+            self.reader = None;
+        }
+        Some(result)
+    }
+}
+
+/// Iterate over the entries of the Karp export read from `reader`.
+pub fn load_from_reader<T: DeserializeOwned, R: Read>(
+    reader: R,
+) -> Result<Entries<T, R>, JsonIterError> {
+    Entries::new(reader)
+}
+
+/// Open `path` and iterate over the entries of the Karp export it contains.
+pub fn load_from_file<T: DeserializeOwned>(path: &str) -> Result<Entries<T, Input>, JsonIterError> {
+    load_from_reader(io::open(path)?)
+}
+
+/// Read all metadata of the Karp export read from `reader`, skipping the
+/// entries without deserializing them.
+pub fn load_metadata_from_reader<R: Read>(reader: R) -> Result<Metadata, JsonIterError> {
+    let mut reader = json_iter::stream_reader(reader);
+    let mut metadata = Metadata::new();
+    reader.begin_object()?;
+    if read_metadata(&mut reader, &mut metadata)? {
+        reader.skip_value()?;
+        read_metadata(&mut reader, &mut metadata)?;
+    }
+    reader.end_object()?;
+    reader.consume_trailing_whitespace()?;
+    Ok(metadata)
+}
+
+/// Open `path` and read all metadata of the Karp export it contains.
+pub fn load_metadata(path: &str) -> Result<Metadata, JsonIterError> {
+    load_metadata_from_reader(io::open(path)?)
+}
+
+/// Writes entries in an envelope with metadata.
+pub struct ExportWriter<W: Write> {
+    writer: JsonStreamWriter<W>,
+    numbers: Option<NumberFormat>,
+}
+
+impl<W: Write> ExportWriter<W> {
+    /// Writes the metadata, an `entries` member in it is left out.
+    pub fn new(writer: W, metadata: &Metadata) -> Result<Self, JsonIterError> {
+        let mut writer = JsonStreamWriter::new(writer);
+        writer.begin_object()?;
+        for (name, value) in metadata {
+            if name != ENTRIES {
+                writer.name(name)?;
+                writer.serialize_value(value)?;
+            }
+        }
+        writer.name(ENTRIES)?;
+        writer.begin_array()?;
+        Ok(Self {
+            writer,
+            numbers: None,
+        })
+    }
+
+    /// Write the floats of the entries in `format`, see [`crate::number`].
+    pub fn numbers(mut self, format: NumberFormat) -> Self {
+        self.numbers = Some(format);
+        self
+    }
+
+    pub fn write<T: Serialize>(&mut self, entry: &T) -> Result<(), JsonIterError> {
+        let Some(format) = self.numbers else {
+            return Ok(self.writer.serialize_value(entry)?);
+        };
+        let entry = serde_json::to_value(entry).map_err(std::io::Error::from)?;
+        Ok(number::write_value(&mut self.writer, &entry, format)?)
+    }
+
+    /// Closes the envelope and returns the underlying writer, flushed.
+    pub fn finish(mut self) -> Result<W, JsonIterError> {
+        self.writer.end_array()?;
+        self.writer.end_object()?;
+        let mut writer = self.writer.finish_document()?;
+        writer.flush()?;
+        Ok(writer)
+    }
+}
@@ -0,0 +1,47 @@
+pub mod aggregate;
+pub mod archive;
+pub mod bulk;
+pub mod cbor;
+#[cfg(feature = "arrow")]
+pub mod columnar;
+pub mod copy;
+pub mod dedup;
+pub mod diff;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod filter;
+pub mod glob;
+pub mod in_memory;
+pub mod infer;
+pub mod io;
+pub mod join;
+pub mod json5;
+pub mod json_iter;
+pub mod karp;
+pub mod lmf;
+pub mod lookup;
+pub mod msgpack;
+pub mod number;
+pub mod path;
+pub mod pipeline;
+pub mod provenance;
+pub mod report;
+pub mod rules;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod sample;
+pub mod scroll;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod shuffle;
+pub mod sort;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+pub mod table;
+pub mod transcode;
+pub mod transform;
+#[cfg(feature = "http")]
+pub mod upload;
+pub mod validate;
+pub mod yaml;
@@ -0,0 +1,223 @@
+//! Lexicons in LMF (Lexical Markup Framework) XML, read with
+//! [quick-xml](https://crates.io/crates/quick-xml): every `LexicalEntry`
+//! element is a document, e.g.
+//!
+//! ```xml
+//! <LexicalEntry id="hund..nn.1">
+//!   <feat att="partOfSpeech" val="nn"/>
+//!   <Lemma>
+//!     <FormRepresentation><feat att="writtenForm" val="hund"/></FormRepresentation>
+//!   </Lemma>
+//! </LexicalEntry>
+//! ```
+//!
+//! becomes
+//!
+//! ```json
+//! {"id": "hund..nn.1", "partOfSpeech": "nn",
+//!  "Lemma": [{"FormRepresentation": [{"writtenForm": "hund"}]}]}
+//! ```
+//!
+//! Attributes and `feat` elements are string fields (an array if there are
+//! several with the same name), other child elements are arrays of objects
+//! under their name and text is kept in `#text`.
+
+use std::io::BufRead;
+
+use quick_xml::errors::IllFormedError;
+use quick_xml::escape::{resolve_predefined_entity, EscapeError};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, XmlVersion};
+use serde_json::{Map, Value};
+
+use crate::io::{self, Input};
+use crate::json_iter::JsonIterError;
+
+/// The elements that are read as documents.
+pub const LEXICAL_ENTRY: &str = "LexicalEntry";
+
+/// The elements that are a field of their parent.
+const FEAT: &str = "feat";
+
+/// The field with the text of an element.
+pub const TEXT: &str = "#text";
+
+/// An element that hasn't ended yet.
+struct Element {
+    name: String,
+    fields: Map<String, Value>,
+    text: String,
+}
+
+impl Element {
+    fn new(start: &BytesStart) -> Result<Self, quick_xml::Error> {
+        let mut fields = Map::new();
+        for attribute in start.attributes() {
+            let attribute = attribute?;
+            let value = attribute.normalized_value(XmlVersion::Implicit1_0)?;
+            add_field(&mut fields, attribute.key.0, value.into());
+        }
+        Ok(Self {
+            name: start.name().0.to_string(),
+            fields,
+            text: String::new(),
+        })
+    }
+
+    /// Add the ended element `child` to the fields.
+    fn push(&mut self, child: Element) {
+        if child.name == FEAT {
+            if let (Some(Value::String(att)), Some(val), 2) = (
+                child.fields.get("att"),
+                child.fields.get("val"),
+                child.fields.len(),
+            ) {
+                add_field(&mut self.fields, att, val.clone());
+                return;
+            }
+        }
+        let name = child.name.clone();
+        let child = child.into_value();
+        match self.fields.get_mut(&name) {
+            Some(Value::Array(children)) => children.push(child),
+            _ => {
+                self.fields.insert(name, Value::Array(vec![child]));
+            }
+        }
+    }
+
+    fn into_value(mut self) -> Value {
+        let text = self.text.trim();
+        if !text.is_empty() {
+            self.fields.insert(TEXT.to_string(), text.into());
+        }
+        Value::Object(self.fields)
+    }
+}
+
+/// Set the field `name`, or add `value` to it if it is already set.
+fn add_field(fields: &mut Map<String, Value>, name: &str, value: Value) {
+    match fields.get_mut(name) {
+        Some(Value::Array(values)) => values.push(value),
+        Some(first) => *first = Value::Array(vec![first.take(), value]),
+        None => {
+            fields.insert(name.to_string(), value);
+        }
+    }
+}
+
+/// Iterator over the lexical entries of an LMF file as documents.
+///
+/// Yields `Err` at most once, after which the iterator is exhausted.
+pub struct LexicalEntries<R: BufRead> {
+    reader: Option<Reader<R>>,
+    buffer: Vec<u8>,
+    /// The entry being read and the elements in it.
+    stack: Vec<Element>,
+}
+
+impl<R: BufRead> LexicalEntries<R> {
+    fn read_entry(&mut self) -> Result<Option<Value>, quick_xml::Error> {
+        let reader = self.reader.as_mut().expect("reader to be present");
+        loop {
+            self.buffer.clear();
+            match reader.read_event_into(&mut self.buffer)? {
+                Event::Start(start)
+                    if !self.stack.is_empty() || start.name().0 == LEXICAL_ENTRY =>
+                {
+                    self.stack.push(Element::new(&start)?);
+                }
+                Event::Empty(start) => {
+                    let element = Element::new(&start)?;
+                    match self.stack.last_mut() {
+                        Some(parent) => parent.push(element),
+                        None if element.name == LEXICAL_ENTRY => {
+                            return Ok(Some(element.into_value()))
+                        }
+                        None => {}
+                    }
+                }
+                Event::End(_) => {
+                    if let Some(element) = self.stack.pop() {
+                        match self.stack.last_mut() {
+                            Some(parent) => parent.push(element),
+                            None => return Ok(Some(element.into_value())),
+                        }
+                    }
+                }
+                Event::Text(text) => {
+                    if let Some(element) = self.stack.last_mut() {
+                        element.text.push_str(&text.xml10_content());
+                    }
+                }
+                Event::CData(text) => {
+                    if let Some(element) = self.stack.last_mut() {
+                        element.text.push_str(&text.xml10_content());
+                    }
+                }
+                Event::GeneralRef(reference) => {
+                    if let Some(element) = self.stack.last_mut() {
+                        match reference.resolve_char_ref()? {
+                            Some(c) => element.text.push(c),
+                            None => {
+                                let text =
+                                    resolve_predefined_entity(&reference).ok_or_else(|| {
+                                        EscapeError::UnrecognizedEntity(
+                                            0..reference.len(),
+                                            reference.to_string(),
+                                        )
+                                    })?;
+                                element.text.push_str(text);
+                            }
+                        }
+                    }
+                }
+                Event::Eof => {
+                    return match self.stack.pop() {
+                        Some(element) => Err(IllFormedError::MissingEndTag(element.name).into()),
+                        None => Ok(None),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LexicalEntries<R> {
+    type Item = Result<Value, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.as_ref()?;
+        let result = self.read_entry().map_err(|source| JsonIterError::Xml {
+            offset: self.reader.as_ref().map_or(0, Reader::error_position),
+            source,
+        });
+        match result {
+            Ok(Some(doc)) => Some(Ok(doc)),
+            Ok(None) => {
+                self.reader = None;
+                None
+            }
+            Err(err) => {
+                self.reader = None;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Iterate over the lexical entries of the LMF file read from `reader`.
+pub fn load_from_reader<R: BufRead>(reader: R) -> LexicalEntries<R> {
+    LexicalEntries {
+        reader: Some(Reader::from_reader(reader)),
+        buffer: Vec::new(),
+        stack: Vec::new(),
+    }
+}
+
+/// Open `path` and iterate over the lexical entries of the LMF file it
+/// contains.
+pub fn load_from_file(path: &str) -> Result<LexicalEntries<Input>, JsonIterError> {
+    Ok(load_from_reader(io::open(path)?))
+}
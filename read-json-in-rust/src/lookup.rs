@@ -0,0 +1,219 @@
+//! An in-memory index of the entries of a lexicon by a key such as their
+//! word form, built as the lexicon is streamed:
+//!
+//! ```no_run
+//! # use read_json_in_rust::lookup::Index;
+//! # use serde_json::Value;
+//! let index = Index::<Value>::from_file("saldom.json", |entry| {
+//!     entry["word"].as_str().unwrap_or_default()
+//! })?;
+//! for entry in index.lookup("hundarna") {
+//!     println!("{}", entry);
+//! }
+//! # Ok::<(), read_json_in_rust::json_iter::JsonIterError>(())
+//! ```
+//!
+//! Only the entries and their keys are held in memory, not the file. The
+//! entries of a key are kept next to each other in one vector, with a hash
+//! table of the range of every key, so that an index of millions of entries
+//! costs a string per key and eight bytes for its range rather than a
+//! vector per key. Building needs a few more bytes per entry while the entries
+//! are put in order.
+
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+
+use crate::json_iter::{self, JsonIterError};
+
+/// Values by key, in the order they were inserted.
+#[derive(Debug, Clone)]
+pub struct Index<T> {
+    /// The values, grouped by key.
+    values: Vec<T>,
+    /// The start and the number of the values of every key.
+    keys: HashMap<Box<str>, (u32, u32)>,
+}
+
+impl<T> Index<T> {
+    /// Index the documents of `docs` by `key`, stopping at the first `Err`.
+    pub fn from_documents<E>(
+        docs: impl IntoIterator<Item = Result<T, E>>,
+        key: impl Fn(&T) -> &str,
+    ) -> Result<Self, E> {
+        let mut builder = IndexBuilder::new();
+        for doc in docs {
+            let doc = doc?;
+            builder.insert_with(doc, &key);
+        }
+        Ok(builder.build())
+    }
+
+    /// The values of `key`, empty for an unknown key.
+    pub fn lookup(&self, key: &str) -> &[T] {
+        match self.keys.get(key) {
+            Some(&(start, len)) => &self.values[start as usize..(start + len) as usize],
+            None => &[],
+        }
+    }
+
+    /// All values, grouped by key.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// The number of values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The keys, in no particular order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.keys.keys().map(|key| &**key)
+    }
+}
+
+impl<T: DeserializeOwned> Index<T> {
+    /// Stream the JSON array of documents at `path` into an index by `key`.
+    pub fn from_file(path: &str, key: impl Fn(&T) -> &str) -> Result<Self, JsonIterError> {
+        Self::from_documents(json_iter::load_from_file(path)?, key)
+    }
+}
+
+/// Collects the values of an [`Index`].
+#[derive(Debug)]
+pub struct IndexBuilder<T> {
+    /// The values and the number of their key, in the order they came.
+    values: Vec<(u32, T)>,
+    /// The number of every key, in the order they were first seen.
+    keys: HashMap<Box<str>, u32>,
+}
+
+impl<T> Default for IndexBuilder<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            keys: HashMap::new(),
+        }
+    }
+}
+
+impl<T> IndexBuilder<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn key_number(&mut self, key: &str) -> u32 {
+        if let Some(&number) = self.keys.get(key) {
+            return number;
+        }
+        let number = u32::try_from(self.keys.len()).expect("fewer than 2³² keys");
+        // A key is only copied the first time it's seen.
+        self.keys.insert(key.into(), number);
+        number
+    }
+
+    /// Add `value` under `key`.
+    pub fn insert(&mut self, key: &str, value: T) {
+        let number = self.key_number(key);
+        self.values.push((number, value));
+    }
+
+    /// Add `value` under the key that `key` returns for it.
+    pub fn insert_with(&mut self, value: T, key: impl Fn(&T) -> &str) {
+        let number = self.key_number(key(&value));
+        self.values.push((number, value));
+    }
+
+    /// Group the values by key, keeping the order of the values of a key.
+    pub fn build(self) -> Index<T> {
+        assert!(
+            u32::try_from(self.values.len()).is_ok(),
+            "fewer than 2³² values"
+        );
+        // The start of the values of every key, by counting them.
+        let mut starts = vec![0u32; self.keys.len()];
+        for &(number, _) in &self.values {
+            starts[number as usize] += 1;
+        }
+        let mut start = 0;
+        for count in &mut starts {
+            start += std::mem::replace(count, start);
+        }
+        // Where every value goes, then swapped into place in cycles, so that
+        // the values are moved without a second vector of them.
+        let mut next = starts.clone();
+        let mut targets: Vec<u32> = self
+            .values
+            .iter()
+            .map(|&(number, _)| {
+                let target = next[number as usize];
+                next[number as usize] += 1;
+                target
+            })
+            .collect();
+        let mut values = self.values;
+        for i in 0..values.len() {
+            while targets[i] as usize != i {
+                let target = targets[i] as usize;
+                values.swap(i, target);
+                targets.swap(i, target);
+            }
+        }
+        drop(targets);
+        let values: Vec<T> = values.into_iter().map(|(_, value)| value).collect();
+        let mut keys: HashMap<Box<str>, (u32, u32)> = self
+            .keys
+            .into_iter()
+            .map(|(key, number)| {
+                let start = starts[number as usize];
+                let end = next[number as usize];
+                (key, (start, end - start))
+            })
+            .collect();
+        keys.shrink_to_fit();
+        Index { values, keys }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn looks_up_every_key_of_unsorted_duplicates() {
+        // Keys in no order, each seen many times but one.
+        let keys: Vec<String> = (0..500u32)
+            .map(|i| match i {
+                250 => "once".to_string(),
+                i => format!("k{}", (i * 7919) % 37),
+            })
+            .collect();
+        let mut builder = IndexBuilder::new();
+        let mut expected: BTreeMap<&str, Vec<u32>> = BTreeMap::new();
+        for (value, key) in keys.iter().enumerate() {
+            builder.insert(key, value as u32);
+            expected.entry(key).or_default().push(value as u32);
+        }
+        let index = builder.build();
+
+        assert_eq!(index.len(), keys.len());
+        let mut index_keys: Vec<&str> = index.keys().collect();
+        index_keys.sort_unstable();
+        assert_eq!(index_keys, expected.keys().copied().collect::<Vec<_>>());
+        for (key, values) in &expected {
+            assert_eq!(index.lookup(key), values.as_slice(), "key {}", key);
+        }
+        assert!(index.lookup("k99").is_empty());
+        // The values of a key are next to each other.
+        let mut grouped = index.values().to_vec();
+        grouped.dedup_by_key(|value| &keys[*value as usize]);
+        assert_eq!(grouped.len(), expected.len());
+    }
+}
@@ -1,5 +1,4 @@
 use std::fs::File;
-use std::io::BufWriter;
 
 use std::io::Read;
 use std::io::Write;
@@ -0,0 +1,82 @@
+//! MessagePack, with [rmp-serde](https://crates.io/crates/rmp-serde): the
+//! documents are written one value after another, without an enclosing
+//! array, so they can be streamed like newline-delimited JSON.
+
+use std::io::{BufRead, Write};
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::io::{self, Input};
+use crate::json_iter::JsonIterError;
+
+/// Iterator over the documents of a MessagePack stream.
+///
+/// Yields `Err` at most once, after which the iterator is exhausted.
+pub struct Values<T, R: BufRead> {
+    reader: Option<R>,
+    document: u64,
+    _doc: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned, R: BufRead> Iterator for Values<T, R> {
+    type Item = Result<T, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reader = self.reader.as_mut()?;
+        let result = match reader.fill_buf() {
+            Ok([]) => {
+                self.reader = None;
+                return None;
+            }
+            Ok(_) => {
+                self.document += 1;
+                rmp_serde::from_read(&mut *reader).map_err(|source| JsonIterError::MessagePack {
+                    document: self.document,
+                    source,
+                })
+            }
+            Err(err) => Err(err.into()),
+        };
+        if result.is_err() {
+            self.reader = None;
+        }
+        Some(result)
+    }
+}
+
+/// Iterate over the MessagePack documents read from `reader`.
+pub fn load_from_reader<T: DeserializeOwned, R: BufRead>(reader: R) -> Values<T, R> {
+    Values {
+        reader: Some(reader),
+        document: 0,
+        _doc: PhantomData,
+    }
+}
+
+/// Open `path` and iterate over the MessagePack documents it contains.
+pub fn load_from_file<T: DeserializeOwned>(path: &str) -> Result<Values<T, Input>, JsonIterError> {
+    Ok(load_from_reader(io::open(path)?))
+}
+
+/// Writes documents as MessagePack, structs as maps with their field names.
+pub struct MessagePackWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> MessagePackWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write<T: Serialize>(&mut self, doc: &T) -> Result<(), rmp_serde::encode::Error> {
+        rmp_serde::encode::write_named(&mut self.writer, doc)
+    }
+
+    /// Returns the underlying writer, flushed.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
@@ -0,0 +1,193 @@
+//! Writing floating point numbers the same way whatever writes them.
+//!
+//! serde_json writes `1.0` and `1e-7` where struson writes `1` and
+//! `0.0000001`, so the same document comes out differently compact and
+//! pretty printed, and a number that went through an update can change form.
+//! With a [`NumberFormat`] the writers of this crate all write the floats in
+//! that format: serde_json through a [`NumberFormatter`], struson through
+//! [`write_value`]. Integers are written as they are.
+
+use std::io::{self, Write};
+
+use serde::Serialize;
+use serde_json::ser::{CompactFormatter, Formatter};
+use serde_json::{Serializer, Value};
+use struson::writer::JsonWriter;
+
+/// How floating point numbers are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberFormat {
+    /// The shortest digits that read back as the same number, with a `.0` for
+    /// whole numbers and an exponent for very large and small ones, as
+    /// serde_json writes them, e.g. `1.0`, `0.1` and `1e-7`.
+    Shortest,
+    /// The shortest digits as in JavaScript and the JSON Canonicalization
+    /// Scheme (RFC 8785), without `.0` and with an exponent only from `1e21`
+    /// and below `1e-6`, e.g. `1`, `0.1` and `1e-7`.
+    Canonical,
+}
+
+impl NumberFormat {
+    /// `value` in this format, which must be finite.
+    pub fn format(self, value: f64) -> String {
+        match self {
+            NumberFormat::Shortest => Value::from(value).to_string(),
+            NumberFormat::Canonical => canonical(value),
+        }
+    }
+}
+
+/// `value` as in RFC 8785, section 3.2.2.3.
+fn canonical(value: f64) -> String {
+    if value == 0.0 {
+        // Also for -0.
+        return "0".to_string();
+    }
+    // `{:e}` writes the shortest digits, e.g. `1.5e-7`.
+    let exponential = format!("{:e}", value.abs());
+    let (mantissa, exponent) = exponential.split_once('e').expect("an exponent");
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    // The position of the decimal point after the first digit.
+    let n = exponent.parse::<i32>().expect("an integer exponent") + 1;
+    let mut s = String::new();
+    if value < 0.0 {
+        s.push('-');
+    }
+    if k <= n && n <= 21 {
+        s.push_str(&digits);
+        s.extend(std::iter::repeat_n('0', (n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        s.push_str(&digits[..n as usize]);
+        s.push('.');
+        s.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        s.push_str("0.");
+        s.extend(std::iter::repeat_n('0', -n as usize));
+        s.push_str(&digits);
+    } else {
+        s.push_str(&digits[..1]);
+        if k > 1 {
+            s.push('.');
+            s.push_str(&digits[1..]);
+        }
+        s.push('e');
+        s.push(if n > 0 { '+' } else { '-' });
+        s.push_str(&(n - 1).abs().to_string());
+    }
+    s
+}
+
+/// A serde_json formatter writing the floats in a [`NumberFormat`], and
+/// everything else like `inner`, e.g. a `PrettyFormatter`.
+#[derive(Debug, Clone)]
+pub struct NumberFormatter<F = CompactFormatter> {
+    inner: F,
+    format: NumberFormat,
+}
+
+impl<F: Formatter> NumberFormatter<F> {
+    pub fn new(inner: F, format: NumberFormat) -> Self {
+        Self { inner, format }
+    }
+}
+
+impl<F: Formatter> Formatter for NumberFormatter<F> {
+    fn write_f32<W: ?Sized + Write>(&mut self, writer: &mut W, value: f32) -> io::Result<()> {
+        // Through the shortest digits of the `f32`, not of the `f64`.
+        let value = value.to_string().parse().expect("a float");
+        self.write_f64(writer, value)
+    }
+
+    fn write_f64<W: ?Sized + Write>(&mut self, writer: &mut W, value: f64) -> io::Result<()> {
+        writer.write_all(self.format.format(value).as_bytes())
+    }
+
+    fn begin_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_array(writer)
+    }
+
+    fn end_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_array(writer)
+    }
+
+    fn begin_array_value<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.inner.begin_array_value(writer, first)
+    }
+
+    fn end_array_value<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_array_value(writer)
+    }
+
+    fn begin_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_object(writer)
+    }
+
+    fn end_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_object(writer)
+    }
+
+    fn begin_object_key<W: ?Sized + Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.inner.begin_object_key(writer, first)
+    }
+
+    fn begin_object_value<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_object_value(writer)
+    }
+
+    fn end_object_value<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_object_value(writer)
+    }
+}
+
+/// Write `doc` compactly to `writer` with the floats in `format`.
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(
+    writer: W,
+    doc: &T,
+    format: NumberFormat,
+) -> serde_json::Result<()> {
+    let formatter = NumberFormatter::new(CompactFormatter, format);
+    doc.serialize(&mut Serializer::with_formatter(writer, formatter))
+}
+
+/// Write `value` with a struson writer, with the floats in `format`.
+pub fn write_value<J: JsonWriter>(
+    writer: &mut J,
+    value: &Value,
+    format: NumberFormat,
+) -> io::Result<()> {
+    match value {
+        Value::Number(number) if number.is_f64() => {
+            let value = number.as_f64().expect("a float");
+            writer
+                .number_value_from_string(&format.format(value))
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        }
+        Value::Array(values) => {
+            writer.begin_array()?;
+            for value in values {
+                write_value(writer, value, format)?;
+            }
+            writer.end_array()
+        }
+        Value::Object(members) => {
+            writer.begin_object()?;
+            for (name, value) in members {
+                writer.name(name)?;
+                write_value(writer, value, format)?;
+            }
+            writer.end_object()
+        }
+        value => writer
+            .serialize_value(value)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+    }
+}
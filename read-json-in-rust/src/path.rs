@@ -0,0 +1,185 @@
+//! Paths to fields inside a document.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+/// A path to a field, written either as a JSON pointer (`/entry/pos`) or with
+/// dots (`entry.pos`). Array elements are selected by index (`senses.0`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldPath {
+    path: String,
+    segments: Vec<String>,
+}
+
+impl FieldPath {
+    /// The path of `segments`, as a JSON pointer.
+    pub(crate) fn from_segments(segments: Vec<String>) -> Self {
+        let mut path = String::new();
+        for segment in &segments {
+            push_token(&mut path, segment);
+        }
+        Self { path, segments }
+    }
+
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    pub fn get<'a>(&self, doc: &'a Value) -> Option<&'a Value> {
+        self.segments
+            .iter()
+            .try_fold(doc, |value, segment| match value {
+                Value::Object(object) => object.get(segment),
+                Value::Array(array) => array.get(segment.parse::<usize>().ok()?),
+                _ => None,
+            })
+    }
+
+    pub fn get_mut<'a>(&self, doc: &'a mut Value) -> Option<&'a mut Value> {
+        get_mut(&self.segments, doc)
+    }
+
+    /// Remove the field from `doc` and return its value.
+    pub fn remove(&self, doc: &mut Value) -> Option<Value> {
+        remove(&self.segments, doc)
+    }
+
+    /// Set the field in `doc` to `value`, creating missing objects on the way.
+    ///
+    /// Gives `value` back if the path goes through a value that is neither an
+    /// object nor an array, or through an index that is out of bounds.
+    pub fn set(&self, doc: &mut Value, value: Value) -> Result<(), Value> {
+        put(&self.segments, doc, value, false)
+    }
+
+    /// Like [`FieldPath::set`], but an array element is inserted at the index
+    /// instead of replacing the element there.
+    pub fn insert(&self, doc: &mut Value, value: Value) -> Result<(), Value> {
+        put(&self.segments, doc, value, true)
+    }
+}
+
+/// Append `key` to the JSON pointer `path`, escaping `~` and `/`.
+pub(crate) fn push_token(path: &mut String, key: &str) {
+    path.push('/');
+    path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+}
+
+/// Remove `key` from `object`, keeping the order of the other keys with the
+/// `preserve_order` feature.
+pub(crate) fn remove_key(object: &mut Map<String, Value>, key: &str) -> Option<Value> {
+    #[cfg(feature = "preserve_order")]
+    return object.shift_remove(key);
+    #[cfg(not(feature = "preserve_order"))]
+    object.remove(key)
+}
+
+/// Call `f` with every value that `segments` selects in `doc`, where a `*`
+/// segment selects every element of an array or value of an object.
+pub(crate) fn for_each_match(segments: &[String], doc: &mut Value, f: &mut dyn FnMut(&mut Value)) {
+    let Some((first, rest)) = segments.split_first() else {
+        return f(doc);
+    };
+    match doc {
+        Value::Object(object) if first == "*" => object
+            .values_mut()
+            .for_each(|value| for_each_match(rest, value, f)),
+        Value::Array(array) if first == "*" => array
+            .iter_mut()
+            .for_each(|value| for_each_match(rest, value, f)),
+        _ => {
+            if let Some(value) = get_mut(std::slice::from_ref(first), doc) {
+                for_each_match(rest, value, f);
+            }
+        }
+    }
+}
+
+/// Remove the field at `segments` from `doc` and return its value.
+pub(crate) fn remove(segments: &[String], doc: &mut Value) -> Option<Value> {
+    let (last, parent) = segments.split_last()?;
+    match get_mut(parent, doc)? {
+        Value::Object(object) => remove_key(object, last),
+        Value::Array(array) => {
+            let index = last.parse::<usize>().ok()?;
+            (index < array.len()).then(|| array.remove(index))
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn get_mut<'a>(segments: &[String], doc: &'a mut Value) -> Option<&'a mut Value> {
+    segments.iter().try_fold(doc, |value, segment| match value {
+        Value::Object(object) => object.get_mut(segment),
+        Value::Array(array) => array.get_mut(segment.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// Set the field at `segments`, see [`FieldPath::set`] and [`FieldPath::insert`].
+pub(crate) fn put(
+    segments: &[String],
+    doc: &mut Value,
+    value: Value,
+    insert: bool,
+) -> Result<(), Value> {
+    let Some((last, parent)) = segments.split_last() else {
+        return Err(value);
+    };
+    let mut current = doc;
+    for segment in parent {
+        current = match current {
+            Value::Object(object) => object
+                .entry(segment.clone())
+                .or_insert_with(|| Value::Object(Default::default())),
+            Value::Array(array) => match segment.parse().ok().and_then(|i: usize| array.get_mut(i))
+            {
+                Some(element) => element,
+                None => return Err(value),
+            },
+            _ => return Err(value),
+        };
+    }
+    match current {
+        Value::Object(object) => {
+            object.insert(last.clone(), value);
+        }
+        Value::Array(array) => match last.parse::<usize>() {
+            Ok(index) if insert && index <= array.len() => array.insert(index, value),
+            Ok(index) if index < array.len() => array[index] = value,
+            _ => return Err(value),
+        },
+        _ => return Err(value),
+    }
+    Ok(())
+}
+
+impl FromStr for FieldPath {
+    type Err = String;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let segments: Vec<String> = if let Some(pointer) = path.strip_prefix('/') {
+            pointer
+                .split('/')
+                .map(|token| token.replace("~1", "/").replace("~0", "~"))
+                .collect()
+        } else {
+            path.split('.').map(str::to_string).collect()
+        };
+        if path.is_empty() || (!path.starts_with('/') && segments.iter().any(String::is_empty)) {
+            return Err(format!("invalid field path '{}'", path));
+        }
+        Ok(Self {
+            path: path.to_string(),
+            segments,
+        })
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.path)
+    }
+}
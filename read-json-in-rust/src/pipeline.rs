@@ -0,0 +1,223 @@
+//! Stages of filters and updates chained with a builder, e.g.
+//! `Pipeline::new().filter(..).map(..).tap(..)`, where the time spent in
+//! each stage is added up as the documents pass through.
+//!
+//! A pipeline is `Sync`, so [`Pipeline::run`] can be called from the threads
+//! of [`crate::json_iter::par_map`].
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::transform::{DocumentTransform, TransformError};
+
+/// Filters and updates applied to each document in the order they were
+/// added.
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+struct Stage {
+    name: String,
+    kind: Kind,
+    documents: AtomicU64,
+    nanos: AtomicU64,
+}
+
+enum Kind {
+    Filter(Box<dyn Fn(&Value) -> bool + Send + Sync>),
+    Map(Box<dyn DocumentTransform>),
+    Tap(Box<dyn Fn(&Value) + Send + Sync>),
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn stage(mut self, label: &str, kind: Kind) -> Self {
+        self.stages.push(Stage {
+            name: format!("{} {}", label, self.stages.len() + 1),
+            kind,
+            documents: AtomicU64::new(0),
+            nanos: AtomicU64::new(0),
+        });
+        self
+    }
+
+    /// Drop the documents that `keep` returns `false` for.
+    pub fn filter(self, keep: impl Fn(&Value) -> bool + Send + Sync + 'static) -> Self {
+        self.stage("filter", Kind::Filter(Box::new(keep)))
+    }
+
+    /// Update the documents with `transform`, e.g. a
+    /// [`crate::transform::SetField`] or a closure.
+    pub fn map(self, transform: impl DocumentTransform + 'static) -> Self {
+        self.stage("map", Kind::Map(Box::new(transform)))
+    }
+
+    /// Look at the documents without changing them, e.g. to count them.
+    pub fn tap(self, inspect: impl Fn(&Value) + Send + Sync + 'static) -> Self {
+        self.stage("tap", Kind::Tap(Box::new(inspect)))
+    }
+
+    /// Name the last stage in [`Pipeline::timings`], instead of its kind and
+    /// number, like `map 2`.
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        if let Some(stage) = self.stages.last_mut() {
+            stage.name = name.into();
+        }
+        self
+    }
+
+    /// Pass `doc` through the stages, `None` if a filter drops it.
+    pub fn run(&self, mut doc: Value) -> Result<Option<Value>, TransformError> {
+        for stage in &self.stages {
+            let start = Instant::now();
+            let kept = match &stage.kind {
+                Kind::Filter(keep) => keep(&doc),
+                Kind::Map(transform) => {
+                    transform.apply(&mut doc)?;
+                    true
+                }
+                Kind::Tap(inspect) => {
+                    inspect(&doc);
+                    true
+                }
+            };
+            let nanos = start.elapsed().as_nanos() as u64;
+            stage.nanos.fetch_add(nanos, Ordering::Relaxed);
+            stage.documents.fetch_add(1, Ordering::Relaxed);
+            if !kept {
+                return Ok(None);
+            }
+        }
+        Ok(Some(doc))
+    }
+
+    /// Pass all documents through the stages, leaving out the dropped ones.
+    pub fn run_all<'a, I>(
+        &'a self,
+        docs: I,
+    ) -> impl Iterator<Item = Result<Value, TransformError>> + 'a
+    where
+        I: IntoIterator<Item = Value>,
+        I::IntoIter: 'a,
+    {
+        docs.into_iter().filter_map(|doc| self.run(doc).transpose())
+    }
+
+    /// The documents that entered each stage and the time spent in it so
+    /// far, in the order of the stages.
+    pub fn timings(&self) -> Vec<StageTiming> {
+        self.stages
+            .iter()
+            .map(|stage| StageTiming {
+                name: stage.name.clone(),
+                documents: stage.documents.load(Ordering::Relaxed),
+                elapsed: Duration::from_nanos(stage.nanos.load(Ordering::Relaxed)),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self.stages.iter().map(|stage| &stage.name).collect();
+        f.debug_struct("Pipeline").field("stages", &names).finish()
+    }
+}
+
+/// The time spent in a stage of a [`Pipeline`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageTiming {
+    pub name: String,
+    /// Number of documents that entered the stage.
+    pub documents: u64,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for StageTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} documents in {:?}",
+            self.name, self.documents, self.elapsed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::transform::{Derive, SetField};
+
+    #[test]
+    fn runs_the_stages_in_order() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let tapped = Arc::clone(&seen);
+        let pipeline = Pipeline::new()
+            .map("n=1".parse::<SetField>().unwrap())
+            .filter(|doc| doc["keep"] == true)
+            .tap(move |doc| tapped.lock().unwrap().push(doc.clone()))
+            .map(|doc: &mut Value| {
+                doc["n"] = (doc["n"].as_i64().unwrap() + 1).into();
+                Ok(())
+            })
+            .named("increment");
+
+        assert_eq!(
+            pipeline.run(json!({ "keep": true })).unwrap(),
+            Some(json!({ "keep": true, "n": 2 }))
+        );
+        assert_eq!(pipeline.run(json!({ "keep": false })).unwrap(), None);
+        // The filter saw the document as the map before it left it.
+        assert_eq!(*seen.lock().unwrap(), [json!({ "keep": true, "n": 1 })]);
+
+        let names: Vec<_> = pipeline
+            .timings()
+            .into_iter()
+            .map(|timing| timing.name)
+            .collect();
+        assert_eq!(names, ["map 1", "filter 2", "tap 3", "increment"]);
+    }
+
+    #[test]
+    fn counts_the_documents_of_each_stage() {
+        let pipeline = Pipeline::new()
+            .filter(|doc| doc["n"].as_i64().unwrap() % 2 == 0)
+            .named("even")
+            .map("even=true".parse::<SetField>().unwrap());
+        let docs = (0..5).map(|n| json!({ "n": n }));
+        assert_eq!(pipeline.run_all(docs).count(), 3);
+        let timings = pipeline.timings();
+        let documents: Vec<_> = timings.iter().map(|timing| timing.documents).collect();
+        assert_eq!(documents, [5, 3]);
+        assert!(
+            timings[0].to_string().starts_with("even: 5 documents in "),
+            "{}",
+            timings[0]
+        );
+    }
+
+    #[test]
+    fn run_all_yields_the_errors_and_goes_on() {
+        let pipeline = Pipeline::new()
+            .map("next=.n + 1".parse::<Derive>().unwrap())
+            .filter(|doc| doc["next"] != 3);
+        let docs = [json!({ "n": 2 }), json!({ "n": "two" }), json!({ "n": 4 })];
+        let results: Vec<_> = pipeline.run_all(docs).collect();
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0], Err(TransformError::Jq(_))));
+        assert_eq!(results[1].as_ref().unwrap(), &json!({ "n": 4, "next": 5 }));
+        // The failed document didn't reach the filter.
+        assert_eq!(pipeline.timings()[1].documents, 2);
+    }
+}
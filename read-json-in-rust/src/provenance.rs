@@ -0,0 +1,74 @@
+//! Stamping the output with how it was produced, so the consumers of a
+//! lexicon file can trace it back.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// When, by what and how the output was produced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Provenance {
+    /// The start of the run, in UTC as in RFC 3339.
+    pub processed_at: String,
+    pub tool: String,
+    /// The version of this crate.
+    pub version: String,
+    /// The arguments of the tool, with the updates and the rules file given.
+    pub arguments: Vec<String>,
+    /// The rules applied from the rules file, by lexicon.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rules: Option<Value>,
+}
+
+impl Provenance {
+    /// The provenance of a run of `tool` with `arguments` starting now.
+    pub fn new(tool: impl Into<String>, arguments: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            processed_at: rfc3339(SystemTime::now()),
+            tool: tool.into(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            arguments: arguments.into_iter().collect(),
+            rules: None,
+        }
+    }
+
+    /// Record the rules applied from a rules file, as JSON by lexicon.
+    pub fn rules(mut self, rules: Value) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).expect("a provenance to serialize")
+    }
+}
+
+/// `time` in UTC as `2026-10-14T05:39:00Z`.
+pub fn rfc3339(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let (days, seconds) = ((seconds / 86_400) as i64, seconds % 86_400);
+    // The civil date of a day since 1970-01-01, from
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
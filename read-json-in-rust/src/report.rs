@@ -0,0 +1,55 @@
+//! The elapsed time and the peak memory use of a run, to compare the ways of
+//! processing the documents.
+
+use std::fmt;
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// The peak resident set size of this process in bytes, where the platform
+/// reports it (`VmHWM` in `/proc/self/status` on Linux).
+pub fn peak_rss() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?;
+    let kilobytes: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// The resources used by a run, displayed as a line for people and
+/// serialized for scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Report {
+    pub elapsed_seconds: f64,
+    /// `None` if the platform doesn't report it.
+    pub peak_rss_bytes: Option<u64>,
+}
+
+impl Report {
+    /// Report `elapsed` and the peak memory use so far.
+    pub fn new(elapsed: Duration) -> Self {
+        Self {
+            elapsed_seconds: elapsed.as_secs_f64(),
+            peak_rss_bytes: peak_rss(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a report to serialize")
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Elapsed time {:?}",
+            Duration::from_secs_f64(self.elapsed_seconds)
+        )?;
+        if let Some(bytes) = self.peak_rss_bytes {
+            write!(f, ", peak memory {:.1} MB", bytes as f64 / 1_000_000.0)?;
+        }
+        Ok(())
+    }
+}
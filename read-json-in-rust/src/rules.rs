@@ -0,0 +1,417 @@
+//! Update rules for many lexicons, read from a TOML or YAML file.
+//!
+//! ```toml
+//! [lexicons.skbl]
+//! remove = ["_id", "_score"]
+//! rename = { "entry.pos" = "entry.partOfSpeech" }
+//! set = { lexiconName = "skbl2", lexiconOrder = 48 }
+//!
+//! [[lexicons.skbl.cases]]
+//! when = '.entry.pos == "nn"'
+//! set = { kind = "noun" }
+//! ```
+//!
+//! Each document is updated with the rules of the lexicon named by its
+//! `lexiconName` field, or with the rules of one lexicon from [`Rules::get`].
+//! Rules with a `when` clause, a jq condition, only update the documents it
+//! matches, and `cases` are more rules applied after the others, typically
+//! with a `when` each, so one pass can migrate documents of different shapes.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::transform::{DocumentTransform, Jq, Remove, Rename, SetField, TransformResult};
+
+/// The field naming the lexicon of a document.
+pub const LEXICON_FIELD: &str = "lexiconName";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    lexicons: BTreeMap<String, LexiconRulesFile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LexiconRulesFile {
+    when: Option<String>,
+    remove: Vec<String>,
+    #[serde(deserialize_with = "in_order")]
+    rename: Vec<(String, String)>,
+    #[serde(deserialize_with = "in_order")]
+    set: Vec<(String, Value)>,
+    cases: Vec<LexiconRulesFile>,
+}
+
+/// The entries of a table or mapping in the order of the file, since a
+/// rename can move a field that an earlier one has renamed, and the fields
+/// that are set are added in that order.
+fn in_order<'de, D, V>(deserializer: D) -> Result<Vec<(String, V)>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    struct Entries<V>(std::marker::PhantomData<V>);
+
+    impl<'de, V: Deserialize<'de>> Visitor<'de> for Entries<V> {
+        type Value = Vec<(String, V)>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a table of fields")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+            while let Some(entry) = map.next_entry()? {
+                entries.push(entry);
+            }
+            Ok(entries)
+        }
+    }
+
+    deserializer.deserialize_map(Entries(std::marker::PhantomData))
+}
+
+/// The updates for one lexicon, applied in the order removals, renames and
+/// then sets, as on the command line, and then the cases.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LexiconRules {
+    /// Only update the documents this jq condition matches.
+    pub when: Option<Jq>,
+    pub remove: Vec<Remove>,
+    pub rename: Vec<Rename>,
+    pub set: Vec<SetField>,
+    /// More rules, each checking its `when` against the document as updated
+    /// so far.
+    pub cases: Vec<LexiconRules>,
+}
+
+impl LexiconRules {
+    /// The rules as JSON, in the shape of the rules file but with the renames
+    /// and sets as `old=new` and `field=value` in the order they are applied,
+    /// leaving out what is empty.
+    pub fn to_value(&self) -> Value {
+        let mut rules = serde_json::Map::new();
+        if let Some(when) = &self.when {
+            rules.insert("when".to_string(), when.code().into());
+        }
+        let mut insert = |key: &str, values: Vec<Value>| {
+            if !values.is_empty() {
+                rules.insert(key.to_string(), values.into());
+            }
+        };
+        insert(
+            "remove",
+            self.remove
+                .iter()
+                .map(|removal| removal.path.to_string().into())
+                .collect(),
+        );
+        insert(
+            "rename",
+            self.rename
+                .iter()
+                .map(|rename| format!("{}={}", rename.from, rename.to).into())
+                .collect(),
+        );
+        insert(
+            "set",
+            self.set
+                .iter()
+                .map(|set| format!("{}={}", set.field, set.value).into())
+                .collect(),
+        );
+        insert(
+            "cases",
+            self.cases.iter().map(LexiconRules::to_value).collect(),
+        );
+        Value::Object(rules)
+    }
+}
+
+impl DocumentTransform for LexiconRules {
+    /// Update `doc`, failing only if a `when` clause fails on it.
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        if let Some(when) = &self.when {
+            if !when.matches(doc)? {
+                return Ok(());
+            }
+        }
+        for removal in &self.remove {
+            removal.apply(doc)?;
+        }
+        for rename in &self.rename {
+            rename.apply(doc)?;
+        }
+        for set_field in &self.set {
+            set_field.apply(doc)?;
+        }
+        for case in &self.cases {
+            case.apply(doc)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<LexiconRulesFile> for LexiconRules {
+    type Error = String;
+
+    fn try_from(rules: LexiconRulesFile) -> Result<Self, Self::Error> {
+        let when = rules
+            .when
+            .map(|code| Jq::new(&code).map_err(|err| err.to_string()))
+            .transpose()?;
+        let remove = rules
+            .remove
+            .iter()
+            .map(|path| path.parse())
+            .collect::<Result<_, _>>()?;
+        let rename = rules
+            .rename
+            .iter()
+            .map(|(from, to)| Rename::new(from.parse()?, to.parse()?))
+            .collect::<Result<_, String>>()?;
+        let set = rules
+            .set
+            .into_iter()
+            .map(|(field, value)| SetField { field, value })
+            .collect();
+        let cases = rules
+            .cases
+            .into_iter()
+            .map(LexiconRules::try_from)
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            when,
+            remove,
+            rename,
+            set,
+            cases,
+        })
+    }
+}
+
+/// Update rules by lexicon name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rules {
+    lexicons: BTreeMap<String, LexiconRules>,
+}
+
+impl Rules {
+    /// Read the rules from `path`, as YAML if it ends with `.yaml` or `.yml`
+    /// and as TOML otherwise.
+    pub fn from_file(path: &str) -> Result<Self, RulesError> {
+        let text = std::fs::read_to_string(path)?;
+        let yaml = matches!(
+            Path::new(path).extension().and_then(|ext| ext.to_str()),
+            Some("yaml" | "yml")
+        );
+        Self::parse(&text, yaml)
+    }
+
+    fn parse(text: &str, yaml: bool) -> Result<Self, RulesError> {
+        let file: RulesFile = match yaml {
+            true => serde_yaml::from_str(text)?,
+            false => toml::from_str(text)?,
+        };
+        let lexicons = file
+            .lexicons
+            .into_iter()
+            .map(|(name, rules)| match LexiconRules::try_from(rules) {
+                Ok(rules) => Ok((name, rules)),
+                Err(err) => Err(RulesError::Invalid { lexicon: name, err }),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { lexicons })
+    }
+
+    /// The names of the lexicons, in order.
+    pub fn lexicons(&self) -> impl Iterator<Item = &str> {
+        self.lexicons.keys().map(String::as_str)
+    }
+
+    /// The rules of every lexicon as JSON, by name, see
+    /// [`LexiconRules::to_value`].
+    pub fn to_value(&self) -> Value {
+        self.lexicons
+            .iter()
+            .map(|(name, rules)| (name.clone(), rules.to_value()))
+            .collect()
+    }
+
+    /// The rules of lexicon `name`.
+    pub fn get(&self, name: &str) -> Result<&LexiconRules, RulesError> {
+        self.lexicons
+            .get(name)
+            .ok_or_else(|| RulesError::UnknownLexicon(name.to_string()))
+    }
+
+    /// The rules of the lexicon named by the `lexiconName` of `doc`, if any.
+    pub fn rules_for(&self, doc: &Value) -> Option<&LexiconRules> {
+        self.lexicons.get(doc.get(LEXICON_FIELD)?.as_str()?)
+    }
+}
+
+impl DocumentTransform for Rules {
+    /// Update `doc` with the rules of its lexicon, documents of other
+    /// lexicons are left as is.
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        match self.rules_for(doc) {
+            Some(rules) => rules.apply(doc),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Errors from reading a rules file or looking up a lexicon.
+#[derive(Debug)]
+pub enum RulesError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    Yaml(serde_yaml::Error),
+    /// A path or a `when` clause in the rules of `lexicon` is invalid.
+    Invalid {
+        lexicon: String,
+        err: String,
+    },
+    UnknownLexicon(String),
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulesError::Io(err) => write!(f, "{}", err),
+            RulesError::Toml(err) => write!(f, "invalid TOML: {}", err),
+            RulesError::Yaml(err) => write!(f, "invalid YAML: {}", err),
+            RulesError::Invalid { lexicon, err } => {
+                write!(f, "in the rules for '{}': {}", lexicon, err)
+            }
+            RulesError::UnknownLexicon(name) => write!(f, "no rules for lexicon '{}'", name),
+        }
+    }
+}
+
+impl std::error::Error for RulesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RulesError::Io(err) => Some(err),
+            RulesError::Toml(err) => Some(err),
+            RulesError::Yaml(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for RulesError {
+    fn from(err: std::io::Error) -> Self {
+        RulesError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for RulesError {
+    fn from(err: toml::de::Error) -> Self {
+        RulesError::Toml(err)
+    }
+}
+
+impl From<serde_yaml::Error> for RulesError {
+    fn from(err: serde_yaml::Error) -> Self {
+        RulesError::Yaml(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Renames chained so that alphabetical order gives another result.
+    const TOML: &str = r#"
+[lexicons.skbl]
+rename = { "z" = "a", "a" = "b" }
+"#;
+
+    const YAML: &str = "
+lexicons:
+  skbl:
+    rename:
+      z: a
+      a: b
+";
+
+    #[test]
+    fn applies_renames_in_file_order() {
+        for (text, yaml) in [(TOML, false), (YAML, true)] {
+            let rules = Rules::parse(text, yaml).unwrap();
+            let mut doc = json!({ "lexiconName": "skbl", "z": 1 });
+            rules.apply(&mut doc).unwrap();
+            assert_eq!(
+                doc,
+                json!({ "lexiconName": "skbl", "b": 1 }),
+                "yaml: {}",
+                yaml
+            );
+        }
+    }
+
+    #[test]
+    fn sets_fields_in_file_order() {
+        let text = "[lexicons.skbl]\nset = { lexiconOrder = 48, kind = \"noun\" }\n";
+        let rules = Rules::parse(text, false).unwrap();
+        let set = &rules.get("skbl").unwrap().set;
+        assert_eq!(
+            set,
+            &[
+                SetField {
+                    field: "lexiconOrder".into(),
+                    value: json!(48),
+                },
+                SetField {
+                    field: "kind".into(),
+                    value: json!("noun"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let err = Rules::parse("[lexicons.skbl]\nrenames = {}\n", false).unwrap_err();
+        assert!(matches!(err, RulesError::Toml(_)));
+    }
+
+    #[test]
+    fn converts_the_rules_to_json_in_order() {
+        let text = r#"
+[lexicons.skbl]
+remove = ["_id"]
+rename = { "z" = "a", "a" = "b" }
+set = { lexiconOrder = 48, lexiconName = "skbl2" }
+
+[[lexicons.skbl.cases]]
+when = '.pos == "nn"'
+set = { kind = "noun" }
+
+[lexicons.saldo]
+"#;
+        let rules = Rules::parse(text, false).unwrap();
+        assert_eq!(
+            rules.to_value(),
+            json!({
+                "saldo": {},
+                "skbl": {
+                    "remove": ["_id"],
+                    "rename": ["z=a", "a=b"],
+                    "set": ["lexiconOrder=48", "lexiconName=\"skbl2\""],
+                    "cases": [{ "when": ".pos == \"nn\"", "set": ["kind=\"noun\""] }],
+                },
+            })
+        );
+    }
+}
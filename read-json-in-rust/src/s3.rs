@@ -0,0 +1,142 @@
+//! Objects in an S3-compatible store as inputs and outputs, with the `s3`
+//! feature ([rust-s3](https://crates.io/crates/rust-s3)).
+//!
+//! An object is named `s3://bucket/key`. The region and credentials are read
+//! from the environment (`AWS_REGION`, `AWS_ACCESS_KEY_ID`,
+//! `AWS_SECRET_ACCESS_KEY`, or the AWS profile), and `AWS_ENDPOINT` selects
+//! another store than AWS, addressed with path-style requests.
+//!
+//! Objects are streamed through a pipe by a thread doing the request, so
+//! downloads and multipart uploads run while the documents are converted.
+
+use std::io::{self, PipeReader, PipeWriter, Read, Write};
+use std::thread::{self, JoinHandle};
+
+use s3::creds::Credentials;
+use s3::error::S3Error;
+use s3::{Bucket, Region};
+
+use crate::io::Finish;
+
+/// The scheme of object URIs.
+const SCHEME: &str = "s3://";
+
+/// The bucket and the key of `uri`.
+fn parse(uri: &str) -> io::Result<(&str, &str)> {
+    uri.strip_prefix(SCHEME)
+        .and_then(|path| path.split_once('/'))
+        .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("expected s3://bucket/key, got '{}'", uri),
+            )
+        })
+}
+
+fn bucket(name: &str) -> io::Result<Box<Bucket>> {
+    let region = Region::from_default_env().map_err(io::Error::other)?;
+    let path_style = matches!(region, Region::Custom { .. });
+    let credentials = Credentials::default().map_err(io::Error::other)?;
+    let mut bucket = Bucket::new(name, region, credentials).map_err(io::Error::other)?;
+    if path_style {
+        bucket = bucket.with_path_style();
+    }
+    // No timeout, as streaming an object may take long.
+    bucket.set_request_timeout(None);
+    Ok(bucket)
+}
+
+fn join(request: JoinHandle<Result<u16, S3Error>>) -> io::Result<()> {
+    match request.join() {
+        Ok(result) => result.map(|_| ()).map_err(io::Error::other),
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+/// The content of an object, read as it is downloaded.
+pub struct Download {
+    pipe: PipeReader,
+    request: Option<JoinHandle<Result<u16, S3Error>>>,
+}
+
+impl Read for Download {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.pipe.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            // The end of the pipe is only the end of the object if the
+            // request succeeded.
+            if let Some(request) = self.request.take() {
+                join(request)?;
+            }
+        }
+        Ok(read)
+    }
+}
+
+/// Start downloading the object at `uri`.
+pub fn open(uri: &str) -> io::Result<Download> {
+    let (bucket_name, key) = parse(uri)?;
+    let bucket = bucket(bucket_name)?;
+    let key = key.to_string();
+    let (pipe, mut writer) = io::pipe()?;
+    let request = thread::spawn(move || bucket.get_object_to_writer(key, &mut writer));
+    Ok(Download {
+        pipe,
+        request: Some(request),
+    })
+}
+
+/// Writes an object, uploaded in parts as it is written.
+pub struct Upload {
+    pipe: PipeWriter,
+    request: Option<JoinHandle<Result<u16, S3Error>>>,
+}
+
+impl Upload {
+    /// The error of the failed request instead of the broken pipe `err`.
+    fn request_error(&mut self, err: io::Error) -> io::Error {
+        if err.kind() != io::ErrorKind::BrokenPipe {
+            return err;
+        }
+        match self.request.take() {
+            Some(request) => join(request).err().unwrap_or(err),
+            None => err,
+        }
+    }
+}
+
+impl Write for Upload {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pipe.write(buf).map_err(|err| self.request_error(err))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.pipe.flush().map_err(|err| self.request_error(err))
+    }
+}
+
+impl Finish for Upload {
+    /// Ends the object and waits for the upload to complete.
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        let Upload { pipe, request } = *self;
+        drop(pipe);
+        match request {
+            Some(request) => join(request),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Start uploading the object at `uri`.
+pub fn create(uri: &str) -> io::Result<Upload> {
+    let (bucket_name, key) = parse(uri)?;
+    let bucket = bucket(bucket_name)?;
+    let key = key.to_string();
+    let (mut reader, pipe) = io::pipe()?;
+    let request = thread::spawn(move || bucket.put_object_stream(&mut reader, key));
+    Ok(Upload {
+        pipe,
+        request: Some(request),
+    })
+}
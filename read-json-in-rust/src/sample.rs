@@ -0,0 +1,141 @@
+//! Picking a few documents out of a stream: the last ones, those with the
+//! largest values of a field, or a random sample.
+//!
+//! Only the picked documents are kept in memory, so they work on inputs of
+//! any size. For the first documents use [`Iterator::take`].
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, VecDeque};
+
+use rand::rngs::ChaCha8Rng;
+use rand::{Rng, RngExt, SeedableRng};
+use serde_json::Value;
+
+use crate::path::FieldPath;
+use crate::sort::compare_keys;
+
+/// Which documents [`top`] picks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Extreme {
+    #[default]
+    Largest,
+    Smallest,
+}
+
+/// The last `n` documents, in input order, stopping at the first `Err`.
+pub fn tail<T, E, I>(docs: I, n: usize) -> Result<Vec<T>, E>
+where
+    I: IntoIterator<Item = Result<T, E>>,
+{
+    let mut last = VecDeque::new();
+    for doc in docs {
+        last.push_back(doc?);
+        if last.len() > n {
+            last.pop_front();
+        }
+    }
+    Ok(last.into())
+}
+
+/// The random number generator for [`sample`]: seeded with `seed`, so the
+/// same seed picks the same documents of the same input, or with a random
+/// seed if there is none.
+pub fn rng(seed: Option<u64>) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed.unwrap_or_else(rand::random))
+}
+
+/// A uniform random sample of `n` documents (all of them if there are fewer),
+/// in input order, stopping at the first `Err`.
+///
+/// Uses reservoir sampling, so the number of documents needn't be known.
+pub fn sample<T, E, I, R>(docs: I, n: usize, rng: &mut R) -> Result<Vec<T>, E>
+where
+    I: IntoIterator<Item = Result<T, E>>,
+    R: Rng + ?Sized,
+{
+    let mut reservoir = Vec::new();
+    for (index, doc) in docs.into_iter().enumerate() {
+        let doc = doc?;
+        if reservoir.len() < n {
+            reservoir.push((index, doc));
+            continue;
+        }
+        let slot = rng.random_range(0..=index);
+        if slot < n {
+            reservoir[slot] = (index, doc);
+        }
+    }
+    reservoir.sort_unstable_by_key(|&(index, _)| index);
+    Ok(reservoir.into_iter().map(|(_, doc)| doc).collect())
+}
+
+/// A document in the heap of [`top`], ordered from worst to best.
+struct Ranked {
+    key: Value,
+    index: usize,
+    extreme: Extreme,
+    doc: Value,
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let order = compare_keys(Some(&self.key), Some(&other.key));
+        let order = match self.extreme {
+            Extreme::Largest => order,
+            Extreme::Smallest => order.reverse(),
+        };
+        // Of equal values the earlier document is better.
+        order.then(other.index.cmp(&self.index))
+    }
+}
+
+/// The `n` documents with the largest (or smallest) numbers at `path`, best
+/// first, stopping at the first `Err`. Of documents with equal numbers the
+/// earlier ones are picked, and documents without a number are left out.
+///
+/// The picked documents are kept in a heap, so the others are dropped after
+/// one comparison.
+pub fn top<E, I>(docs: I, n: usize, path: &FieldPath, extreme: Extreme) -> Result<Vec<Value>, E>
+where
+    I: IntoIterator<Item = Result<Value, E>>,
+{
+    let mut heap = BinaryHeap::with_capacity(n);
+    for (index, doc) in docs.into_iter().enumerate() {
+        let doc = doc?;
+        let Some(key) = path.get(&doc).filter(|key| key.is_number()).cloned() else {
+            continue;
+        };
+        let ranked = Reverse(Ranked {
+            key,
+            index,
+            extreme,
+            doc,
+        });
+        if heap.len() < n {
+            heap.push(ranked);
+        } else if heap.peek().is_some_and(|worst| ranked < *worst) {
+            heap.pop();
+            heap.push(ranked);
+        }
+    }
+    // Ascending order of `Reverse` is from best to worst.
+    Ok(heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(ranked)| ranked.doc)
+        .collect())
+}
@@ -0,0 +1,46 @@
+//! Documents from Elasticsearch search and scroll responses.
+//!
+//! A response has its documents in the `_source` of every hit:
+//! `{"hits": {"hits": [{"_index": ..., "_id": ..., "_source": {...}}, ...]}}`.
+//! Only the hits are deserialized, the rest of the response is skipped.
+
+use std::io::Read;
+use std::iter::Map;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::json_iter::{Documents, JsonIterError, Loader};
+
+/// The JSON pointer to the hits of a response.
+pub const HITS_POINTER: &str = "/hits/hits";
+
+/// A hit of a response, other fields than these are ignored.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Hit<T> {
+    #[serde(rename = "_id")]
+    pub id: Option<String>,
+    #[serde(rename = "_source")]
+    pub source: T,
+}
+
+/// Iterator over the sources of the hits of a response.
+pub type Sources<T, R> =
+    Map<Documents<Hit<T>, R>, fn(Result<Hit<T>, JsonIterError>) -> Result<T, JsonIterError>>;
+
+/// Iterate over the `_source` of every hit of the response read from `reader`,
+/// with the options of `loader` except for its pointer.
+///
+/// A hit without `_source` (e.g. from a search with `"_source": false`) is an
+/// error.
+pub fn load_sources_from_reader<T: DeserializeOwned, R: Read>(
+    loader: &Loader,
+    reader: R,
+) -> Result<Sources<T, R>, JsonIterError> {
+    let hits = loader.clone().pointer(HITS_POINTER).from_reader(reader)?;
+    Ok(hits.map(source as fn(_) -> _))
+}
+
+fn source<T>(hit: Result<Hit<T>, JsonIterError>) -> Result<T, JsonIterError> {
+    hit.map(|hit| hit.source)
+}
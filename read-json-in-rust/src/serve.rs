@@ -0,0 +1,165 @@
+//! Updating documents over HTTP with the `serve` feature, for those who'd
+//! rather use curl or a web page than the command line.
+//!
+//! The pipelines are the lexicons of a [`Rules`] file, by name:
+//!
+//! - `GET /` is a web page for choosing a pipeline and a file to update and
+//!   downloading the result.
+//! - `GET /pipelines` lists their names as a JSON array.
+//! - `POST /pipelines/NAME` updates the JSON array of documents in the
+//!   request body with the rules of lexicon NAME and streams the updated
+//!   array back, a document at a time, so neither the upload nor the result
+//!   has to fit in memory. A gzip or zstd compressed body is decompressed.
+//!   `PUT` does the same, for `curl -T`.
+//!
+//! An input that can't be read or updated is a `400 Bad Request` with the
+//! error if it happens before the first 64 KiB of the result are sent, and
+//! otherwise ends the response without its last chunk, which clients report
+//! as an incomplete transfer.
+//!
+//! ```no_run
+//! # use std::net::TcpListener;
+//! # use read_json_in_rust::{rules::Rules, serve::{http::HttpOptions, Server}};
+//! let rules = Rules::from_file("rules.toml")?;
+//! Server::new(rules, HttpOptions::new()).serve(TcpListener::bind("127.0.0.1:8080")?)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! The server speaks HTTP/1.1 with [`http`], a worker thread per connection
+//! up to a limit, and closes every connection after its response. Put a
+//! reverse proxy in front of it for TLS or authentication.
+
+use std::error::Error;
+use std::io;
+use std::net::TcpListener;
+
+use serde_json::Value;
+
+use crate::io::{Input, ReadOptions};
+use crate::json_iter::{self, ArrayWriter};
+use crate::rules::{LexiconRules, Rules};
+use crate::transform::DocumentTransform;
+
+pub mod http;
+
+use http::{ChunkedResponse, HttpOptions, Request};
+
+/// The web page at `/`.
+const INDEX: &str = include_str!("serve/index.html");
+
+/// Serves the pipelines of a [`Rules`] file.
+pub struct Server {
+    rules: Rules,
+    options: HttpOptions,
+}
+
+impl Server {
+    pub fn new(rules: Rules, options: HttpOptions) -> Self {
+        Self { rules, options }
+    }
+
+    /// Answer the requests of the connections to `listener`, until accepting
+    /// a connection fails.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        http::serve(listener, &self.options, |request| {
+            handle(&self.rules, request)
+        })
+    }
+}
+
+fn handle(rules: &Rules, request: &mut Request) -> io::Result<()> {
+    let path = request.path().to_string();
+    match (request.method(), path.as_str()) {
+        ("GET", "/") => request.respond("200 OK", "text/html; charset=utf-8", INDEX.as_bytes()),
+        ("GET", "/pipelines") => {
+            let names: Vec<&str> = rules.lexicons().collect();
+            let body = serde_json::to_vec(&names)?;
+            request.respond("200 OK", "application/json", &body)
+        }
+        ("POST" | "PUT", _) if path.starts_with("/pipelines/") => {
+            let Some(name) = http::percent_decode(&path["/pipelines/".len()..]) else {
+                return request.respond_error("400 Bad Request", "invalid percent-encoding");
+            };
+            let Ok(lexicon) = rules.get(&name) else {
+                let message = format!("no pipeline {}", name);
+                return request.respond_error("404 Not Found", &message);
+            };
+            let body = request.body()?;
+            let mut response = request.chunked(
+                "application/json",
+                &[("Content-Disposition", &content_disposition(&name))],
+            )?;
+            match update(lexicon, body, &mut response) {
+                Ok(()) => response.finish(),
+                Err(err) if !response.started() => {
+                    request.respond_error("400 Bad Request", &err.to_string())
+                }
+                // The status has been sent, so all that is left is to not
+                // end the chunked body.
+                Err(_) => Ok(()),
+            }
+        }
+        (_, "/" | "/pipelines") => request.respond_error("405 Method Not Allowed", "use GET"),
+        (_, _) if path.starts_with("/pipelines/") => {
+            request.respond_error("405 Method Not Allowed", "use POST")
+        }
+        _ => request.respond_error("404 Not Found", "not found"),
+    }
+}
+
+/// The `Content-Disposition` of the result of pipeline `name`, a download
+/// named `NAME.json`. Browsers take the name from `filename*`, which can
+/// have any characters, others from the ASCII `filename`.
+fn content_disposition(name: &str) -> String {
+    let ascii = name.replace(
+        |c: char| !c.is_ascii() || c.is_ascii_control() || c == '"' || c == '\\',
+        "_",
+    );
+    let mut encoded = String::new();
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    format!(
+        "attachment; filename=\"{}.json\"; filename*=UTF-8''{}.json",
+        ascii, encoded
+    )
+}
+
+/// Update the documents read from `body` with `lexicon` and write them to
+/// `response`.
+fn update(
+    lexicon: &LexiconRules,
+    body: Input,
+    response: &mut ChunkedResponse,
+) -> Result<(), Box<dyn Error>> {
+    let input = ReadOptions::new().wrap("", body)?;
+    let mut writer = ArrayWriter::new(response)?;
+    for doc in json_iter::load_from_reader::<Value, _>(input)? {
+        let mut doc = doc?;
+        lexicon.apply(&mut doc)?;
+        writer.write(&doc)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_the_download() {
+        assert_eq!(
+            content_disposition("skbl"),
+            "attachment; filename=\"skbl.json\"; filename*=UTF-8''skbl.json"
+        );
+        assert_eq!(
+            content_disposition("hö \"x\"\r\n"),
+            "attachment; filename=\"h_ _x___.json\"; filename*=UTF-8''h%C3%B6%20%22x%22%0D%0A.json"
+        );
+    }
+}
@@ -0,0 +1,723 @@
+//! The HTTP/1.1 server under [`Server`](super::Server) and fm-server, on top
+//! of httparse.
+//!
+//! hyper, or axum on top of it, would be the usual choice, but the server of
+//! hyper needs httpdate, which isn't among the crates this workspace can be
+//! built with. What is needed here is small: one request per connection,
+//! bodies with a length or chunked, and responses with a length or chunked.
+//!
+//! A fixed number of worker threads serve the connections, the next
+//! connection waits in the queue while they are all busy. Reads and writes
+//! time out, so a client that stops sending or reading doesn't keep a worker
+//! forever, and a body longer than the limit is an error.
+//!
+//! ```no_run
+//! # use std::net::TcpListener;
+//! # use read_json_in_rust::serve::http::{self, HttpOptions};
+//! let listener = TcpListener::bind("127.0.0.1:8080")?;
+//! http::serve(listener, &HttpOptions::new(), |request| {
+//!     let body = format!("you asked for {}\n", request.path());
+//!     request.respond("200 OK", "text/plain", body.as_bytes())
+//! })?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::io::Input;
+
+/// The longest request line and headers that are read.
+const MAX_HEAD: usize = 64 * 1024;
+
+/// Bytes of a chunked response sent in one chunk.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The longest request body by default, 1 GiB.
+pub const MAX_BODY: u64 = 1 << 30;
+
+/// How long the rest of a request is read and dropped after an error
+/// response, so that the client gets to read the response.
+const LINGER: Duration = Duration::from_secs(5);
+
+/// How the connections of a server are served.
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    workers: usize,
+    read_timeout: Duration,
+    write_timeout: Duration,
+    max_body: u64,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            workers: 8,
+            read_timeout: Duration::from_secs(30),
+            write_timeout: Duration::from_secs(30),
+            max_body: MAX_BODY,
+        }
+    }
+}
+
+impl HttpOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of connections served at a time (at least 1), 8 by default.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// How long to wait for the next bytes of a request, 30 seconds by
+    /// default.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// How long to wait for the client to take the next bytes of a
+    /// response, 30 seconds by default.
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// The longest request body in bytes, [`MAX_BODY`] by default.
+    pub fn max_body(mut self, max_body: u64) -> Self {
+        self.max_body = max_body;
+        self
+    }
+
+    /// Read the head of a request from `stream` and pass it to `handler`,
+    /// or answer with an error if it's not a request that can be handled.
+    fn handle<H>(&self, mut stream: TcpStream, handler: &H) -> io::Result<()>
+    where
+        H: Fn(&mut Request) -> io::Result<()>,
+    {
+        stream.set_read_timeout(Some(self.read_timeout))?;
+        stream.set_write_timeout(Some(self.write_timeout))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let head = match read_head(&mut reader) {
+            Ok(head) => head,
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                return respond_error(&mut stream, "400 Bad Request", &err.to_string());
+            }
+            Err(err) => return Err(err),
+        };
+        if head.content_length > self.max_body {
+            let message = too_long(self.max_body).to_string();
+            return respond_error(&mut stream, "413 Content Too Large", &message);
+        }
+        let mut request = Request {
+            head,
+            reader: Some(reader),
+            stream,
+            max_body: self.max_body,
+        };
+        handler(&mut request)
+    }
+}
+
+/// Answer the requests of the connections to `listener` with `handler`,
+/// until accepting a connection fails.
+pub fn serve<H>(listener: TcpListener, options: &HttpOptions, handler: H) -> io::Result<()>
+where
+    H: Fn(&mut Request) -> io::Result<()> + Sync,
+{
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(options.workers);
+    let receiver = Mutex::new(receiver);
+    thread::scope(|scope| {
+        for _ in 0..options.workers {
+            scope.spawn(|| {
+                while let Ok(stream) = receiver.lock().expect("no worker to panic").recv() {
+                    // The client has gone away or sent nonsense, there's no
+                    // one to report the error to.
+                    options.handle(stream, &handler).ok();
+                }
+            });
+        }
+        let result = listener
+            .incoming()
+            .try_for_each(|stream| sender.send(stream?).map_err(io::Error::other));
+        // Let the workers finish the connections they have.
+        drop(sender);
+        result
+    })
+}
+
+/// The parts of a request head that the server looks at.
+#[derive(Debug)]
+struct Head {
+    method: String,
+    /// The path, without the query.
+    path: String,
+    content_length: u64,
+    chunked: bool,
+    expect_continue: bool,
+}
+
+/// A request, to be answered with one of [`respond`](Self::respond),
+/// [`respond_error`](Self::respond_error) or [`chunked`](Self::chunked).
+/// The connection is closed after the response.
+pub struct Request {
+    head: Head,
+    /// The body, until it's taken.
+    reader: Option<BufReader<TcpStream>>,
+    stream: TcpStream,
+    max_body: u64,
+}
+
+impl Request {
+    pub fn method(&self) -> &str {
+        &self.head.method
+    }
+
+    /// The path, without the query, still percent-encoded.
+    pub fn path(&self) -> &str {
+        &self.head.path
+    }
+
+    /// The body of the request, or an empty one if it has been taken.
+    /// Reading more than the limit of the server fails. A client waiting
+    /// for `100 Continue` gets it.
+    pub fn body(&mut self) -> io::Result<Input> {
+        let Some(reader) = self.reader.take() else {
+            return Ok(Box::new(io::empty()));
+        };
+        if self.head.expect_continue {
+            self.stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+        }
+        Ok(if self.head.chunked {
+            let body = Limited::new(ChunkedBody::new(reader), self.max_body);
+            Box::new(BufReader::new(body))
+        } else {
+            Box::new(reader.take(self.head.content_length))
+        })
+    }
+
+    /// Answer with `body`.
+    pub fn respond(&mut self, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+        respond(&mut self.stream, status, content_type, body)
+    }
+
+    /// Answer with the error `message` as plain text.
+    pub fn respond_error(&mut self, status: &str, message: &str) -> io::Result<()> {
+        respond_error(&mut self.stream, status, message)
+    }
+
+    /// Answer with `200 OK` and a chunked body of `content_type`, with the
+    /// other `headers`.
+    pub fn chunked(
+        &self,
+        content_type: &str,
+        headers: &[(&str, &str)],
+    ) -> io::Result<ChunkedResponse> {
+        let mut head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nTransfer-Encoding: chunked\r\n\
+             Connection: close\r\n",
+            content_type
+        );
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        Ok(ChunkedResponse {
+            stream: self.stream.try_clone()?,
+            head: Some(head),
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        })
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn too_long(max_body: u64) -> io::Error {
+    invalid(format!(
+        "the request body is longer than {} bytes",
+        max_body
+    ))
+}
+
+/// Read the request line and the headers, up to the empty line before the
+/// body.
+fn read_head(reader: &mut impl BufRead) -> io::Result<Head> {
+    let mut bytes = Vec::new();
+    loop {
+        if reader.read_until(b'\n', &mut bytes)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if bytes.len() > MAX_HEAD {
+            return Err(invalid("the request headers are too long"));
+        }
+        let mut headers = [httparse::EMPTY_HEADER; 64];
+        let mut request = httparse::Request::new(&mut headers);
+        match request.parse(&bytes) {
+            Ok(httparse::Status::Complete(_)) => {}
+            Ok(httparse::Status::Partial) => continue,
+            Err(err) => return Err(invalid(format!("invalid request: {}", err))),
+        }
+        let path = request.path.unwrap_or_default();
+        let mut head = Head {
+            method: request.method.unwrap_or_default().to_string(),
+            path: path.split('?').next().unwrap_or_default().to_string(),
+            content_length: 0,
+            chunked: false,
+            expect_continue: false,
+        };
+        let mut content_length = None;
+        for header in request.headers.iter() {
+            let value = String::from_utf8_lossy(header.value);
+            let value = value.trim();
+            if header.name.eq_ignore_ascii_case("content-length") {
+                let length = value
+                    .parse()
+                    .map_err(|_| invalid("invalid Content-Length"))?;
+                if content_length.is_some_and(|other| other != length) {
+                    return Err(invalid("conflicting Content-Length headers"));
+                }
+                content_length = Some(length);
+            } else if header.name.eq_ignore_ascii_case("transfer-encoding") {
+                head.chunked = value.eq_ignore_ascii_case("chunked");
+                if !head.chunked {
+                    return Err(invalid(format!("unsupported Transfer-Encoding {}", value)));
+                }
+            } else if header.name.eq_ignore_ascii_case("expect") {
+                head.expect_continue = value.eq_ignore_ascii_case("100-continue");
+            }
+        }
+        // Either could be how a proxy in front of the server reads the body,
+        // so a request with both is refused.
+        if head.chunked && content_length.is_some() {
+            return Err(invalid(
+                "a request can't have both Content-Length and Transfer-Encoding",
+            ));
+        }
+        head.content_length = content_length.unwrap_or(0);
+        return Ok(head);
+    }
+}
+
+/// Answer with `body` and close the connection.
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+/// Answer with the error `message`. The rest of the request is read and
+/// dropped for a while, as closing a connection with unread data can reset
+/// it before the client has read the response.
+fn respond_error(stream: &mut TcpStream, status: &str, message: &str) -> io::Result<()> {
+    let body = format!("{}\n", message);
+    respond(stream, status, "text/plain; charset=utf-8", body.as_bytes())?;
+    stream.shutdown(Shutdown::Write)?;
+    stream.set_read_timeout(Some(LINGER))?;
+    io::copy(stream, &mut io::sink()).ok();
+    Ok(())
+}
+
+/// A response with a chunked body. The status line and the headers are sent
+/// with the first chunk, so an error before it can still be answered with
+/// an error status.
+pub struct ChunkedResponse {
+    stream: TcpStream,
+    head: Option<String>,
+    buffer: Vec<u8>,
+}
+
+impl ChunkedResponse {
+    /// Whether the status has been sent.
+    pub fn started(&self) -> bool {
+        self.head.is_none()
+    }
+
+    fn send(&mut self) -> io::Result<()> {
+        if let Some(head) = self.head.take() {
+            self.stream.write_all(head.as_bytes())?;
+        }
+        if !self.buffer.is_empty() {
+            write!(self.stream, "{:x}\r\n", self.buffer.len())?;
+            self.stream.write_all(&self.buffer)?;
+            self.stream.write_all(b"\r\n")?;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+
+    /// Send the rest of the body and its end.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.send()?;
+        self.stream.write_all(b"0\r\n\r\n")?;
+        self.stream.flush()
+    }
+}
+
+impl Write for ChunkedResponse {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.send()?;
+        }
+        Ok(buf.len())
+    }
+
+    /// Only sends a full chunk, so that a writer flushing when it's done
+    /// doesn't send the status before the end is known to be fine.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A request body sent with `Transfer-Encoding: chunked`, e.g. by
+/// `curl -T -` from stdin.
+struct ChunkedBody<R> {
+    reader: R,
+    /// Bytes left of the current chunk.
+    remaining: u64,
+    done: bool,
+}
+
+impl<R: BufRead> ChunkedBody<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            remaining: 0,
+            done: false,
+        }
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(line)
+    }
+}
+
+impl<R: BufRead> Read for ChunkedBody<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            let line = self.read_line()?;
+            // The size may be followed by extensions after a `;`.
+            let size = line.split(';').next().unwrap_or_default().trim();
+            self.remaining =
+                u64::from_str_radix(size, 16).map_err(|_| invalid("invalid chunk size"))?;
+            if self.remaining == 0 {
+                // Trailers, up to an empty line.
+                while !self.read_line()?.trim().is_empty() {}
+                self.done = true;
+                return Ok(0);
+            }
+        }
+        let max = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let read = self.reader.read(&mut buf[..max])?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= read as u64;
+        if self.remaining == 0 && !self.read_line()?.trim().is_empty() {
+            return Err(invalid("a chunk is longer than its size"));
+        }
+        Ok(read)
+    }
+}
+
+/// A body that fails when it's longer than `max` bytes.
+struct Limited<R> {
+    reader: R,
+    max: u64,
+    remaining: u64,
+}
+
+impl<R> Limited<R> {
+    fn new(reader: R, max: u64) -> Self {
+        Self {
+            reader,
+            max,
+            remaining: max,
+        }
+    }
+}
+
+impl<R: Read> Read for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // A byte more than is left tells a body at the limit from a longer
+        // one.
+        let max = buf.len().min(
+            self.remaining
+                .saturating_add(1)
+                .try_into()
+                .unwrap_or(usize::MAX),
+        );
+        let read = self.reader.read(&mut buf[..max])?;
+        if read as u64 > self.remaining {
+            return Err(too_long(self.max));
+        }
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Decode the `%XX` escapes of `s`, such as `h%C3%B6` for `hö`, or `None` if
+/// they are not valid UTF-8.
+pub fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(after.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &after[2..];
+        } else {
+            bytes.push(byte);
+            rest = after;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(request: &str) -> io::Result<Head> {
+        read_head(&mut request.as_bytes())
+    }
+
+    fn chunked(body: &str) -> io::Result<String> {
+        let mut decoded = String::new();
+        ChunkedBody::new(body.as_bytes()).read_to_string(&mut decoded)?;
+        Ok(decoded)
+    }
+
+    /// Send `request` to a server on a free port and return the response.
+    fn exchange(options: HttpOptions, request: &[u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            http_echo(listener, &options).ok();
+        });
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    /// Answer with the method, the path and the body of every request.
+    fn http_echo(listener: TcpListener, options: &HttpOptions) -> io::Result<()> {
+        serve(listener, options, |request| {
+            let mut body = String::new();
+            match request.body()?.read_to_string(&mut body) {
+                Ok(_) => {
+                    let echo = format!("{} {} {}", request.method(), request.path(), body);
+                    request.respond("200 OK", "text/plain", echo.as_bytes())
+                }
+                Err(err) => request.respond_error("400 Bad Request", &err.to_string()),
+            }
+        })
+    }
+
+    #[test]
+    fn reads_the_head() {
+        let head = parse(
+            "POST /pipelines/skbl?x=1 HTTP/1.1\r\nHost: localhost\r\n\
+             content-length: 12\r\nExpect: 100-continue\r\n\r\nbody",
+        )
+        .unwrap();
+        assert_eq!(head.method, "POST");
+        assert_eq!(head.path, "/pipelines/skbl");
+        assert_eq!(head.content_length, 12);
+        assert!(!head.chunked);
+        assert!(head.expect_continue);
+
+        let head = parse("PUT / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n").unwrap();
+        assert!(head.chunked);
+        assert_eq!(head.content_length, 0);
+    }
+
+    #[test]
+    fn rejects_invalid_heads() {
+        for (request, message) in [
+            (
+                "POST / HTTP/1.1\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n",
+                "a request can't have both Content-Length and Transfer-Encoding",
+            ),
+            (
+                "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nContent-Length: 3\r\n\r\n",
+                "a request can't have both Content-Length and Transfer-Encoding",
+            ),
+            (
+                "POST / HTTP/1.1\r\nContent-Length: 3\r\nContent-Length: 4\r\n\r\n",
+                "conflicting Content-Length headers",
+            ),
+            (
+                "POST / HTTP/1.1\r\nContent-Length: -1\r\n\r\n",
+                "invalid Content-Length",
+            ),
+            (
+                "POST / HTTP/1.1\r\nTransfer-Encoding: gzip\r\n\r\n",
+                "unsupported Transfer-Encoding gzip",
+            ),
+            ("GET\r\n\r\n", "invalid request: invalid token"),
+        ] {
+            let err = parse(request).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{}", request);
+            assert_eq!(err.to_string(), message, "{}", request);
+        }
+        let long = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "x".repeat(MAX_HEAD));
+        assert_eq!(
+            parse(&long).unwrap_err().to_string(),
+            "the request headers are too long"
+        );
+        assert_eq!(
+            parse("GET / HTTP/1.1\r\nHost:").unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn decodes_chunked_bodies() {
+        assert_eq!(chunked("5\r\nhello\r\n0\r\n\r\n").unwrap(), "hello");
+        assert_eq!(
+            chunked("3;name=value\r\n[1,\r\nA\r\n2,3,4,5,6]\r\n0\r\nX-Trailer: 1\r\n\r\n").unwrap(),
+            "[1,2,3,4,5,6]"
+        );
+        assert_eq!(chunked("0\r\n\r\n").unwrap(), "");
+
+        let err = chunked("2\r\nhello\r\n0\r\n\r\n").unwrap_err();
+        assert_eq!(err.to_string(), "a chunk is longer than its size");
+        let err = chunked("x\r\nhello\r\n").unwrap_err();
+        assert_eq!(err.to_string(), "invalid chunk size");
+        let err = chunked("5\r\nhel").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = chunked("5\r\nhello\r\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn limits_bodies() {
+        let mut body = String::new();
+        Limited::new("hello".as_bytes(), 5)
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(body, "hello");
+        let err = Limited::new("hello".as_bytes(), 4)
+            .read_to_string(&mut String::new())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "the request body is longer than 4 bytes");
+    }
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(percent_decode("h%C3%B6..nn.1").unwrap(), "hö..nn.1");
+        assert_eq!(percent_decode("a%20b%2fc").unwrap(), "a b/c");
+        assert_eq!(percent_decode("plain").unwrap(), "plain");
+        assert_eq!(percent_decode("%C3"), None);
+        assert_eq!(percent_decode("%G1"), None);
+        assert_eq!(percent_decode("%4"), None);
+    }
+
+    #[test]
+    fn serves_requests() {
+        let response = exchange(
+            HttpOptions::new(),
+            b"POST /echo?q HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello",
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(
+            response.ends_with("\r\n\r\nPOST /echo hello"),
+            "{}",
+            response
+        );
+
+        let response = exchange(
+            HttpOptions::new(),
+            b"PUT / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhe\r\n3\r\nllo\r\n0\r\n\r\n",
+        );
+        assert!(response.ends_with("\r\n\r\nPUT / hello"), "{}", response);
+    }
+
+    #[test]
+    fn refuses_requests_it_cannot_read() {
+        let response = exchange(
+            HttpOptions::new(),
+            b"POST / HTTP/1.1\r\nContent-Length: 5\r\nTransfer-Encoding: chunked\r\n\r\n",
+        );
+        assert!(
+            response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+            "{}",
+            response
+        );
+
+        let options = HttpOptions::new().max_body(4);
+        let response = exchange(
+            options.clone(),
+            b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello",
+        );
+        assert!(
+            response.starts_with("HTTP/1.1 413 Content Too Large\r\n"),
+            "{}",
+            response
+        );
+        let response = exchange(
+            options,
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n",
+        );
+        assert!(
+            response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+            "{}",
+            response
+        );
+        assert!(
+            response.ends_with("the request body is longer than 4 bytes\n"),
+            "{}",
+            response
+        );
+    }
+
+    #[test]
+    fn times_out_idle_clients() {
+        let options = HttpOptions::new()
+            .workers(1)
+            .read_timeout(Duration::from_millis(100));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || http_echo(listener, &options).ok());
+        // The only worker is taken by a client that never sends its headers,
+        // until the read times out.
+        let mut idle = TcpStream::connect(address).unwrap();
+        idle.write_all(b"GET / HTTP/1.1\r\n").unwrap();
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(b"GET /next HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.ends_with("GET /next "), "{}", response);
+    }
+}
@@ -0,0 +1,247 @@
+//! Shuffling documents into a random order, for training and evaluation
+//! splits, also of inputs larger than the memory.
+//!
+//! Inputs that fit in the memory budget are shuffled in memory. Larger ones
+//! are scattered into [`BUCKETS`] temporary files, each document into a
+//! random one, and then every file is shuffled in memory in turn, or
+//! scattered again if it is still too large. This gives every order the same
+//! chance.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use rand::seq::SliceRandom;
+use rand::{Rng, RngExt};
+use serde_json::Value;
+
+use crate::json_iter::{self, JsonIterError, LinesWriter};
+use crate::sort::{approximate_size, DEFAULT_MEMORY};
+
+/// Number of temporary files the documents are scattered into.
+pub const BUCKETS: usize = 64;
+
+/// A shuffle with a memory budget, as for [`crate::sort::ExternalSort`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shuffle {
+    memory: usize,
+    temp_dir: Option<PathBuf>,
+}
+
+impl Default for Shuffle {
+    fn default() -> Self {
+        Self {
+            memory: DEFAULT_MEMORY,
+            temp_dir: None,
+        }
+    }
+}
+
+impl Shuffle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shuffle at most about this many bytes of documents in memory.
+    pub fn memory(mut self, bytes: usize) -> Self {
+        self.memory = bytes;
+        self
+    }
+
+    /// Write the temporary files to this directory instead of the default
+    /// one of the system, `$TMPDIR` or `/tmp`.
+    pub fn temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = Some(dir.into());
+        self
+    }
+
+    /// The documents in a random order from `rng`. The input is read before
+    /// this returns, and its first error is returned then.
+    pub fn shuffle<I, E, R>(&self, docs: I, mut rng: R) -> Result<Shuffled<R>, E>
+    where
+        I: IntoIterator<Item = Result<Value, E>>,
+        E: From<JsonIterError>,
+        R: Rng,
+    {
+        let mut docs = docs.into_iter();
+        let mut buffered = Vec::new();
+        let mut size = 0;
+        for doc in docs.by_ref() {
+            let doc = doc?;
+            size += approximate_size(&doc);
+            buffered.push(doc);
+            if size > self.memory {
+                break;
+            }
+        }
+        if size <= self.memory {
+            buffered.shuffle(&mut rng);
+            return Ok(Shuffled {
+                shuffle: self.clone(),
+                rng,
+                current: buffered.into_iter(),
+                buckets: Vec::new(),
+            });
+        }
+        let mut scatter = Scatter::new(self)?;
+        for doc in buffered.into_iter().map(Ok).chain(docs) {
+            scatter.write(&doc?, &mut rng)?;
+        }
+        Ok(Shuffled {
+            shuffle: self.clone(),
+            rng,
+            current: Vec::new().into_iter(),
+            buckets: scatter.finish()?,
+        })
+    }
+
+    fn tempfile(&self) -> Result<File, JsonIterError> {
+        Ok(match &self.temp_dir {
+            Some(dir) => tempfile::tempfile_in(dir)?,
+            None => tempfile::tempfile()?,
+        })
+    }
+}
+
+/// The temporary files that documents are scattered into.
+struct Scatter {
+    buckets: Vec<LinesWriter<BufWriter<File>>>,
+}
+
+impl Scatter {
+    fn new(shuffle: &Shuffle) -> Result<Self, JsonIterError> {
+        let buckets = (0..BUCKETS)
+            .map(|_| Ok(LinesWriter::new(BufWriter::new(shuffle.tempfile()?))))
+            .collect::<Result<_, JsonIterError>>()?;
+        Ok(Self { buckets })
+    }
+
+    fn write<R: Rng>(&mut self, doc: &Value, rng: &mut R) -> Result<(), JsonIterError> {
+        let bucket = rng.random_range(0..self.buckets.len());
+        self.buckets[bucket].write(doc)
+    }
+
+    /// The files, rewound for reading.
+    fn finish(self) -> Result<Vec<File>, JsonIterError> {
+        self.buckets
+            .into_iter()
+            .map(|writer| {
+                let writer = writer.finish()?;
+                let mut file = writer.into_inner().map_err(|err| err.into_error())?;
+                file.seek(SeekFrom::Start(0))?;
+                Ok(file)
+            })
+            .collect()
+    }
+}
+
+/// Iterator over the documents shuffled by a [`Shuffle`].
+///
+/// Yields `Err` at most once, after which the iterator is exhausted.
+pub struct Shuffled<R> {
+    shuffle: Shuffle,
+    rng: R,
+    /// The shuffled documents of the current bucket.
+    current: std::vec::IntoIter<Value>,
+    /// The buckets yet to be shuffled, the next one last.
+    buckets: Vec<File>,
+}
+
+impl<R: Rng> Shuffled<R> {
+    /// Shuffle the next bucket into `current`, or scatter it into new
+    /// buckets if it doesn't fit in memory.
+    fn next_bucket(&mut self, file: File) -> Result<(), JsonIterError> {
+        let mut docs = json_iter::load_ndjson_from_reader::<Value, _>(BufReader::new(file));
+        let mut buffered = Vec::new();
+        let mut size = 0;
+        for doc in docs.by_ref() {
+            let doc = doc?;
+            size += approximate_size(&doc);
+            buffered.push(doc);
+            if size > self.shuffle.memory && buffered.len() > 1 {
+                break;
+            }
+        }
+        if size <= self.shuffle.memory || buffered.len() == 1 {
+            buffered.shuffle(&mut self.rng);
+            self.current = buffered.into_iter();
+            return Ok(());
+        }
+        let mut scatter = Scatter::new(&self.shuffle)?;
+        for doc in buffered.into_iter().map(Ok).chain(docs) {
+            scatter.write(&doc?, &mut self.rng)?;
+        }
+        self.buckets.extend(scatter.finish()?);
+        Ok(())
+    }
+}
+
+impl<R: Rng> Iterator for Shuffled<R> {
+    type Item = Result<Value, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(doc) = self.current.next() {
+                return Some(Ok(doc));
+            }
+            let file = self.buckets.pop()?;
+            if let Err(err) = self.next_bucket(file) {
+                self.buckets.clear();
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::sample;
+
+    fn docs(count: u64) -> Vec<Value> {
+        (0..count).map(|i| json!({ "i": i % 300 })).collect()
+    }
+
+    fn shuffled(shuffle: &Shuffle, docs: &[Value], seed: u64) -> Vec<Value> {
+        shuffle
+            .shuffle(
+                docs.iter().cloned().map(Ok::<_, JsonIterError>),
+                sample::rng(Some(seed)),
+            )
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn sorted(mut docs: Vec<Value>) -> Vec<Value> {
+        docs.sort_by_key(|doc| doc["i"].as_u64());
+        docs
+    }
+
+    #[test]
+    fn shuffles_the_same_with_the_same_seed() {
+        let docs = docs(1000);
+        // In memory, scattered into buckets and scattered again.
+        for memory in [DEFAULT_MEMORY, 2000, 100] {
+            let shuffle = Shuffle::new().memory(memory);
+            let first = shuffled(&shuffle, &docs, 7);
+            assert_eq!(first, shuffled(&shuffle, &docs, 7), "memory {}", memory);
+            assert_ne!(first, shuffled(&shuffle, &docs, 8), "memory {}", memory);
+            assert_ne!(first, docs, "memory {}", memory);
+        }
+    }
+
+    #[test]
+    fn keeps_every_document() {
+        // With duplicates, each as often as in the input.
+        let docs = docs(1000);
+        for memory in [DEFAULT_MEMORY, 2000, 100] {
+            let shuffle = Shuffle::new().memory(memory);
+            let shuffled = shuffled(&shuffle, &docs, 7);
+            assert_eq!(sorted(shuffled), sorted(docs.clone()), "memory {}", memory);
+        }
+        assert!(shuffled(&Shuffle::new().memory(100), &[], 7).is_empty());
+    }
+}
@@ -0,0 +1,396 @@
+//! Sorting documents by a field, in memory or with an external merge sort.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Seek, SeekFrom};
+use std::mem::size_of;
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use crate::json_iter::{self, JsonIterError, Lines};
+use crate::path::FieldPath;
+
+/// Number of documents sorted in memory before they are spilled to a
+/// temporary file by [`external_sort`].
+pub const CHUNK_SIZE: usize = 100_000;
+
+/// The default memory budget of an [`ExternalSort`], 256 MiB.
+pub const DEFAULT_MEMORY: usize = 256 << 20;
+
+/// Number of spilled chunks merged at once, so the open temporary files stay
+/// bounded however large the input is.
+pub const MERGE_WIDTH: usize = 64;
+
+/// Compare two sort keys: missing and `null` first, then booleans, numbers,
+/// strings, arrays and objects. Numbers are compared by value, strings by
+/// their UTF-8 bytes, and arrays and objects are considered equal.
+pub fn compare_keys(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    fn rank(value: Option<&Value>) -> u8 {
+        match value {
+            None | Some(Value::Null) => 0,
+            Some(Value::Bool(_)) => 1,
+            Some(Value::Number(_)) => 2,
+            Some(Value::String(_)) => 3,
+            Some(Value::Array(_)) => 4,
+            Some(Value::Object(_)) => 5,
+        }
+    }
+    match (a, b) {
+        (Some(Value::Bool(a)), Some(Value::Bool(b))) => a.cmp(b),
+        (Some(Value::Number(a)), Some(Value::Number(b))) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a
+                .as_f64()
+                .unwrap_or(f64::NAN)
+                .total_cmp(&b.as_f64().unwrap_or(f64::NAN)),
+        },
+        (Some(Value::String(a)), Some(Value::String(b))) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Sort `docs` by the value at `path`, keeping the order of documents with
+/// equal keys.
+pub fn sort_by(docs: &mut [Value], path: &FieldPath) {
+    docs.sort_by(|a, b| compare_keys(path.get(a), path.get(b)));
+}
+
+/// Iterator over the documents sorted by an [`ExternalSort`].
+pub struct Sorted {
+    path: FieldPath,
+    sorted: std::vec::IntoIter<Value>,
+    chunks: Vec<Lines<Value, BufReader<File>>>,
+    heads: BinaryHeap<Reverse<Head>>,
+}
+
+/// The next document of a spilled chunk, ordered by key and then chunk, so
+/// documents with equal keys keep their input order.
+struct Head {
+    key: Option<Value>,
+    chunk: usize,
+    doc: Value,
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_keys(self.key.as_ref(), other.key.as_ref()).then(self.chunk.cmp(&other.chunk))
+    }
+}
+
+impl Sorted {
+    /// Merge the sorted `chunks`, in input order, or iterate over `sorted`
+    /// if there are none.
+    fn new(path: &FieldPath, sorted: Vec<Value>, chunks: Vec<File>) -> Result<Self, JsonIterError> {
+        let mut sorted = Sorted {
+            path: path.clone(),
+            sorted: sorted.into_iter(),
+            chunks: chunks
+                .into_iter()
+                .map(|file| json_iter::load_ndjson_from_reader(BufReader::new(file)))
+                .collect(),
+            heads: BinaryHeap::new(),
+        };
+        for chunk in 0..sorted.chunks.len() {
+            sorted.push_next(chunk)?;
+        }
+        Ok(sorted)
+    }
+
+    fn push_next(&mut self, chunk: usize) -> Result<(), JsonIterError> {
+        if let Some(doc) = self.chunks[chunk].next() {
+            let doc = doc?;
+            self.heads.push(Reverse(Head {
+                key: self.path.get(&doc).cloned(),
+                chunk,
+                doc,
+            }));
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Sorted {
+    type Item = Result<Value, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.chunks.is_empty() {
+            return self.sorted.next().map(Ok);
+        }
+        let Reverse(head) = self.heads.pop()?;
+        if let Err(err) = self.push_next(head.chunk) {
+            self.heads.clear();
+            return Some(Err(err));
+        }
+        Some(Ok(head.doc))
+    }
+}
+
+/// An external merge sort, for inputs larger than the memory.
+///
+/// Documents are sorted in memory until their estimated size exceeds the
+/// memory budget, or their number the chunk size. Then they are written to
+/// an anonymous temporary file as newline-delimited JSON, and the files are
+/// merged while iterating; every [`MERGE_WIDTH`] files are merged into one
+/// as they are written. The estimate is of the parsed documents, so the
+/// memory used is somewhat larger than the budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalSort {
+    memory: usize,
+    chunk_size: usize,
+    temp_dir: Option<PathBuf>,
+}
+
+impl Default for ExternalSort {
+    fn default() -> Self {
+        Self {
+            memory: DEFAULT_MEMORY,
+            chunk_size: usize::MAX,
+            temp_dir: None,
+        }
+    }
+}
+
+impl ExternalSort {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sort at most about this many bytes of documents in memory.
+    pub fn memory(mut self, bytes: usize) -> Self {
+        self.memory = bytes;
+        self
+    }
+
+    /// Sort at most this many documents in memory.
+    pub fn chunk_size(mut self, documents: usize) -> Self {
+        self.chunk_size = documents.max(1);
+        self
+    }
+
+    /// Write the temporary files to this directory instead of the default
+    /// one of the system, `$TMPDIR` or `/tmp`.
+    pub fn temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = Some(dir.into());
+        self
+    }
+
+    /// Sort the documents by the value at `path`, keeping the order of
+    /// documents with equal keys. The first error from `docs` is returned
+    /// before anything is sorted.
+    pub fn sort<I, E>(&self, docs: I, path: &FieldPath) -> Result<Sorted, E>
+    where
+        I: IntoIterator<Item = Result<Value, E>>,
+        E: From<JsonIterError>,
+    {
+        let mut chunk = Vec::new();
+        let mut size = 0;
+        // The spilled chunks in input order, with how many merges made them.
+        let mut spilled = Vec::new();
+        for doc in docs {
+            let doc = doc?;
+            size += approximate_size(&doc);
+            chunk.push(doc);
+            if size > self.memory || chunk.len() >= self.chunk_size {
+                sort_by(&mut chunk, path);
+                let file = self.spill(chunk.drain(..).map(Ok))?;
+                self.push_spilled(&mut spilled, file, path)?;
+                size = 0;
+            }
+        }
+        sort_by(&mut chunk, path);
+        if spilled.is_empty() {
+            return Ok(Sorted::new(path, chunk, Vec::new())?);
+        }
+        if !chunk.is_empty() {
+            spilled.push((self.spill(chunk.into_iter().map(Ok))?, 0));
+        }
+        let files = spilled.into_iter().map(|(file, _)| file).collect();
+        Ok(Sorted::new(path, Vec::new(), files)?)
+    }
+
+    /// Add a spilled chunk after the others, merging the last
+    /// [`MERGE_WIDTH`] chunks into one while they were merged as often.
+    fn push_spilled(
+        &self,
+        spilled: &mut Vec<(File, u32)>,
+        file: File,
+        path: &FieldPath,
+    ) -> Result<(), JsonIterError> {
+        spilled.push((file, 0));
+        while let Some(&(_, level)) = spilled.last() {
+            let start = match spilled.len().checked_sub(MERGE_WIDTH) {
+                Some(start) if spilled[start..].iter().all(|&(_, l)| l == level) => start,
+                _ => break,
+            };
+            let files = spilled.drain(start..).map(|(file, _)| file).collect();
+            let merged = self.spill(Sorted::new(path, Vec::new(), files)?)?;
+            spilled.push((merged, level + 1));
+        }
+        Ok(())
+    }
+
+    /// Write sorted documents to an anonymous temporary file, which is
+    /// removed when it is closed.
+    fn spill<I>(&self, docs: I) -> Result<File, JsonIterError>
+    where
+        I: IntoIterator<Item = Result<Value, JsonIterError>>,
+    {
+        let file = match &self.temp_dir {
+            Some(dir) => tempfile::tempfile_in(dir)?,
+            None => tempfile::tempfile()?,
+        };
+        let writer = json_iter::try_dump_ndjson_to_writer(BufWriter::new(file), docs)?;
+        let mut file = writer.into_inner().map_err(|err| err.into_error())?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(file)
+    }
+}
+
+/// Sort the documents by the value at `path`, keeping the order of
+/// documents with equal keys.
+///
+/// Up to `chunk_size` documents are sorted in memory. Larger inputs are
+/// sorted with an [`ExternalSort`] in chunks of that many documents.
+pub fn external_sort<I, E>(docs: I, path: &FieldPath, chunk_size: usize) -> Result<Sorted, E>
+where
+    I: IntoIterator<Item = Result<Value, E>>,
+    E: From<JsonIterError>,
+{
+    ExternalSort::new()
+        .memory(usize::MAX)
+        .chunk_size(chunk_size)
+        .sort(docs, path)
+}
+
+/// The approximate number of bytes `value` takes in memory.
+pub(crate) fn approximate_size(value: &Value) -> usize {
+    size_of::<Value>()
+        + match value {
+            Value::String(string) => string.len(),
+            Value::Array(values) => values.iter().map(approximate_size).sum(),
+            Value::Object(members) => members
+                .iter()
+                // The key and some overhead of the map per member.
+                .map(|(name, value)| {
+                    size_of::<String>() + name.len() + 16 + approximate_size(value)
+                })
+                .sum(),
+            _ => 0,
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Documents with the keys `keys` at `n`, or none for `None`, numbered
+    /// in input order at `i`.
+    fn docs(keys: &[Option<i64>]) -> Vec<Value> {
+        keys.iter()
+            .enumerate()
+            .map(|(i, key)| match key {
+                Some(key) => json!({ "n": key, "i": i }),
+                None => json!({ "i": i }),
+            })
+            .collect()
+    }
+
+    fn sorted(sorted: Sorted) -> Vec<Value> {
+        sorted.collect::<Result<_, JsonIterError>>().unwrap()
+    }
+
+    #[test]
+    fn sorts_stably_with_missing_keys_first() {
+        let keys = [
+            Some(2),
+            None,
+            Some(1),
+            Some(2),
+            None,
+            Some(1),
+            Some(0),
+            Some(2),
+        ];
+        let path: FieldPath = "n".parse().unwrap();
+        let mut expected = docs(&keys);
+        sort_by(&mut expected, &path);
+        let order: Vec<_> = expected
+            .iter()
+            .map(|doc| doc["i"].as_u64().unwrap())
+            .collect();
+        assert_eq!(order, [1, 4, 6, 2, 5, 0, 3, 7]);
+
+        // In one chunk and spilled in several, merged from files.
+        for chunk_size in [100, 1, 3] {
+            let docs = docs(&keys).into_iter().map(Ok::<_, JsonIterError>);
+            let sorted = sorted(external_sort(docs, &path, chunk_size).unwrap());
+            assert_eq!(sorted, expected, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn spills_to_the_temp_dir_past_the_memory_budget() {
+        let keys: Vec<_> = (0..10).map(|n| Some(n % 3)).collect();
+        let path: FieldPath = "n".parse().unwrap();
+        let doc_size = approximate_size(&docs(&keys)[0]);
+        let missing = std::env::temp_dir().join("no such directory of json-tool");
+        let sort = |memory, dir: &std::path::Path| {
+            let docs = docs(&keys).into_iter().map(Ok::<_, JsonIterError>);
+            ExternalSort::new()
+                .memory(memory)
+                .temp_dir(dir)
+                .sort(docs, &path)
+        };
+
+        // Within the budget nothing is written.
+        assert!(sort(10 * doc_size, &missing).is_ok());
+        assert!(matches!(
+            sort(2 * doc_size, &missing),
+            Err(JsonIterError::Io(_))
+        ));
+
+        let mut expected = docs(&keys);
+        sort_by(&mut expected, &path);
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(sorted(sort(2 * doc_size, dir.path()).unwrap()), expected);
+    }
+
+    #[test]
+    fn merges_more_chunks_than_the_merge_width() {
+        let keys: Vec<_> = (0..MERGE_WIDTH as i64 * 3 + 5)
+            .map(|n| Some(n % 7))
+            .collect();
+        let path: FieldPath = "n".parse().unwrap();
+        let mut expected = docs(&keys);
+        sort_by(&mut expected, &path);
+        let docs = docs(&keys).into_iter().map(Ok::<_, JsonIterError>);
+        let sort = ExternalSort::new().chunk_size(2).sort(docs, &path).unwrap();
+        assert_eq!(sorted(sort), expected);
+    }
+
+    #[test]
+    fn returns_the_first_error_of_the_input() {
+        let broken = JsonIterError::Io(std::io::Error::other("broken"));
+        let docs = vec![Ok(json!({ "n": 1 })), Err(broken), Ok(json!({ "n": 0 }))];
+        let result = external_sort(docs, &"n".parse().unwrap(), 1);
+        assert!(matches!(result, Err(JsonIterError::Io(err)) if err.to_string() == "broken"));
+    }
+}
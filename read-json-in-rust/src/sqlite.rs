@@ -0,0 +1,166 @@
+//! Documents in an SQLite table, with the `sqlite` feature
+//! ([rusqlite](https://crates.io/crates/rusqlite)).
+//!
+//! Every document is a row with the JSON of the document in the column
+//! `doc`, and optionally columns with the values of some of its fields, so
+//! the table can be queried without JSON functions:
+//!
+//! ```sql
+//! SELECT doc FROM documents WHERE pos = 'nn' ORDER BY lemma;
+//! ```
+
+use std::collections::VecDeque;
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, Connection};
+use serde_json::Value;
+
+use crate::json_iter::JsonIterError;
+use crate::table::Column;
+
+/// The table the documents are written to and read from by default.
+pub const TABLE: &str = "documents";
+
+/// The column with the JSON of the documents.
+pub const DOC_COLUMN: &str = "doc";
+
+/// Number of rows read at a time.
+const PAGE_SIZE: i64 = 1000;
+
+/// Quote `name` as an SQL identifier.
+fn identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The value of a column for the value of a field.
+fn column_value(value: Option<&Value>) -> SqlValue {
+    match value {
+        None | Some(Value::Null) => SqlValue::Null,
+        Some(Value::Bool(value)) => SqlValue::Integer(i64::from(*value)),
+        Some(Value::Number(number)) => match number.as_i64() {
+            Some(number) => SqlValue::Integer(number),
+            None => SqlValue::Real(number.as_f64().unwrap_or(f64::NAN)),
+        },
+        Some(Value::String(value)) => SqlValue::Text(value.clone()),
+        Some(value) => SqlValue::Text(value.to_string()),
+    }
+}
+
+/// Writes documents as rows of a table, in a single transaction.
+pub struct SqliteWriter {
+    connection: Connection,
+    insert: String,
+    columns: Vec<Column>,
+}
+
+impl SqliteWriter {
+    /// Open the database at `path` and create `table` if it doesn't exist,
+    /// with a column for the value of each of `columns` (strings, numbers
+    /// and booleans as they are, other values as JSON).
+    pub fn create(path: &str, table: &str, columns: Vec<Column>) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+        let names: Vec<_> = std::iter::once(DOC_COLUMN)
+            .chain(columns.iter().map(|column| column.name.as_str()))
+            .map(identifier)
+            .collect();
+        let mut definitions = vec![format!("{} TEXT NOT NULL", names[0])];
+        definitions.extend_from_slice(&names[1..]);
+        connection.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {} ({}); BEGIN",
+            identifier(table),
+            definitions.join(", ")
+        ))?;
+        let placeholders: Vec<_> = (1..=names.len()).map(|n| format!("?{}", n)).collect();
+        let insert = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            identifier(table),
+            names.join(", "),
+            placeholders.join(", ")
+        );
+        Ok(Self {
+            connection,
+            insert,
+            columns,
+        })
+    }
+
+    pub fn write(&mut self, doc: &Value) -> rusqlite::Result<()> {
+        let mut insert = self.connection.prepare_cached(&self.insert)?;
+        let mut values = vec![SqlValue::Text(doc.to_string())];
+        values.extend(
+            self.columns
+                .iter()
+                .map(|column| column_value(column.path.get(doc))),
+        );
+        insert.execute(rusqlite::params_from_iter(values))?;
+        Ok(())
+    }
+
+    /// Commits the transaction and closes the database.
+    pub fn finish(self) -> rusqlite::Result<()> {
+        self.connection.execute_batch("COMMIT")?;
+        self.connection.close().map_err(|(_, err)| err)
+    }
+}
+
+/// Iterator over the documents in the rows of a table, in the order they
+/// were inserted.
+///
+/// Yields `Err` at most once, after which the iterator is exhausted.
+pub struct Rows {
+    connection: Option<Connection>,
+    select: String,
+    /// The rowid of the last row read.
+    last: i64,
+    page: VecDeque<Value>,
+}
+
+impl Rows {
+    fn read_page(&mut self) -> rusqlite::Result<()> {
+        let connection = self.connection.as_ref().expect("connection to be open");
+        let mut select = connection.prepare_cached(&self.select)?;
+        let mut rows = select.query(params![self.last, PAGE_SIZE])?;
+        while let Some(row) = rows.next()? {
+            self.last = row.get(0)?;
+            self.page.push_back(row.get(1)?);
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Rows {
+    type Item = Result<Value, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.connection.as_ref()?;
+        if self.page.is_empty() {
+            if let Err(err) = self.read_page() {
+                self.connection = None;
+                return Some(Err(err.into()));
+            }
+        }
+        let doc = self.page.pop_front();
+        if doc.is_none() {
+            self.connection = None;
+        }
+        doc.map(Ok)
+    }
+}
+
+/// Open the database at `path` and iterate over the documents in `table`.
+pub fn load(path: &str, table: &str) -> Result<Rows, JsonIterError> {
+    let connection = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let select = format!(
+        "SELECT rowid, {} FROM {} WHERE rowid > ?1 ORDER BY rowid LIMIT ?2",
+        identifier(DOC_COLUMN),
+        identifier(table)
+    );
+    // Fail early for a missing table.
+    connection.prepare(&select)?;
+    Ok(Rows {
+        connection: Some(connection),
+        select,
+        last: i64::MIN,
+        page: VecDeque::new(),
+    })
+}
@@ -0,0 +1,339 @@
+//! Statistics over a stream of documents, for sanity-checking a dump.
+//!
+//! Fields are named by JSON pointers, with `*` for the elements of an array,
+//! e.g. `/entry/senses/*/gloss`.
+//!
+//! [`Stats`] counts the types of the values of every field, [`Profiles`]
+//! also sums up the values themselves, for data-quality monitoring.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use serde::ser::SerializeSeq;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+use crate::path::push_token;
+
+/// How many values of each JSON type were seen.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TypeCounts {
+    pub null: u64,
+    pub bool: u64,
+    pub number: u64,
+    pub string: u64,
+    pub array: u64,
+    pub object: u64,
+}
+
+impl TypeCounts {
+    fn add(&mut self, value: &Value) {
+        let count = match value {
+            Value::Null => &mut self.null,
+            Value::Bool(_) => &mut self.bool,
+            Value::Number(_) => &mut self.number,
+            Value::String(_) => &mut self.string,
+            Value::Array(_) => &mut self.array,
+            Value::Object(_) => &mut self.object,
+        };
+        *count += 1;
+    }
+
+    /// The number of values of all types.
+    pub fn total(&self) -> u64 {
+        self.null + self.bool + self.number + self.string + self.array + self.object
+    }
+
+    /// The types that were seen, with their counts.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, u64)> {
+        [
+            ("null", self.null),
+            ("bool", self.bool),
+            ("number", self.number),
+            ("string", self.string),
+            ("array", self.array),
+            ("object", self.object),
+        ]
+        .into_iter()
+        .filter(|&(_, count)| count > 0)
+    }
+}
+
+/// Counts of the documents, their size and the values of every field.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    pub documents: u64,
+    /// Total size of the documents serialized as compact JSON, in bytes.
+    pub bytes: u64,
+    /// The types of the values of every field, by path. The count of a field
+    /// is the number of documents (or array elements) it is present in.
+    pub fields: BTreeMap<String, TypeCounts>,
+}
+
+/// Counts the bytes written to it.
+struct ByteCount(u64);
+
+impl Write for ByteCount {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, doc: &Value) {
+        self.documents += 1;
+        let mut size = ByteCount(0);
+        serde_json::to_writer(&mut size, doc).expect("a value to serialize");
+        self.bytes += size.0;
+        for_each_field(
+            &mut String::new(),
+            doc,
+            &mut |path, value| match self.fields.get_mut(path) {
+                Some(types) => types.add(value),
+                None => {
+                    let mut types = TypeCounts::default();
+                    types.add(value);
+                    self.fields.insert(path.to_string(), types);
+                }
+            },
+        );
+    }
+}
+
+/// Call `f` with the path and value of every field in `value`, at `path`,
+/// parents before the fields in them.
+fn for_each_field(path: &mut String, value: &Value, f: &mut impl FnMut(&str, &Value)) {
+    let len = path.len();
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                push_token(path, key);
+                f(path, value);
+                for_each_field(path, value, f);
+                path.truncate(len);
+            }
+        }
+        Value::Array(array) => {
+            path.push_str("/*");
+            for value in array {
+                f(path, value);
+                for_each_field(path, value, f);
+            }
+            path.truncate(len);
+        }
+        _ => {}
+    }
+}
+
+/// Profiles of the values of every field, by path, e.g. for comparing the
+/// releases of a lexicon.
+#[derive(Debug, Clone, Serialize)]
+pub struct Profiles {
+    pub documents: u64,
+    pub fields: BTreeMap<String, FieldProfile>,
+    #[serde(skip)]
+    top: usize,
+}
+
+impl Profiles {
+    /// Profiles with the `top` most common strings of each field.
+    pub fn new(top: usize) -> Self {
+        Self {
+            documents: 0,
+            fields: BTreeMap::new(),
+            top,
+        }
+    }
+
+    pub fn add(&mut self, doc: &Value) {
+        self.documents += 1;
+        for_each_field(
+            &mut String::new(),
+            doc,
+            &mut |path, value| match self.fields.get_mut(path) {
+                Some(profile) => profile.add(value),
+                None => {
+                    let mut profile = FieldProfile::new(self.top);
+                    profile.add(value);
+                    self.fields.insert(path.to_string(), profile);
+                }
+            },
+        );
+    }
+}
+
+/// The values of a field, see [`Profiles`].
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldProfile {
+    pub types: TypeCounts,
+    /// The share of the values that are `null`.
+    pub null_rate: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub numbers: Option<NumberProfile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strings: Option<StringProfile>,
+    #[serde(skip)]
+    top: usize,
+}
+
+impl FieldProfile {
+    fn new(top: usize) -> Self {
+        Self {
+            types: TypeCounts::default(),
+            null_rate: 0.0,
+            numbers: None,
+            strings: None,
+            top,
+        }
+    }
+
+    fn add(&mut self, value: &Value) {
+        self.types.add(value);
+        self.null_rate = self.types.null as f64 / self.types.total() as f64;
+        match value {
+            Value::Number(number) => {
+                let number = number.as_f64().unwrap_or(f64::NAN);
+                let count = self.types.number;
+                let numbers = self.numbers.get_or_insert(NumberProfile {
+                    min: number,
+                    max: number,
+                    mean: 0.0,
+                });
+                numbers.min = numbers.min.min(number);
+                numbers.max = numbers.max.max(number);
+                numbers.mean += (number - numbers.mean) / count as f64;
+            }
+            Value::String(text) => {
+                let length = text.chars().count();
+                let top = self.top;
+                let strings = self.strings.get_or_insert_with(|| StringProfile {
+                    min_length: length,
+                    max_length: length,
+                    top: TopValues::new(top),
+                });
+                strings.min_length = strings.min_length.min(length);
+                strings.max_length = strings.max_length.max(length);
+                strings.top.add(text);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The numbers of a field.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NumberProfile {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+/// The strings of a field, with their lengths in characters.
+#[derive(Debug, Clone, Serialize)]
+pub struct StringProfile {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub top: TopValues,
+}
+
+/// Strings counted with a fixed number of counters per field.
+const COUNTERS_PER_VALUE: usize = 10;
+
+/// The most common strings of a field, counted with the Space-Saving
+/// algorithm: there are 10 counters for each of the `k` values, and when they
+/// are all taken a new string takes over the counter of the least common one.
+///
+/// The counts are exact as long as there are no more different strings than
+/// counters. After that a count may be too high, by at most its error: the
+/// count of the string whose counter was taken over. It serializes as the `k`
+/// most common strings with their counts and errors.
+#[derive(Debug, Clone)]
+pub struct TopValues {
+    k: usize,
+    /// The count and error of each string.
+    counts: HashMap<String, (u64, u64)>,
+}
+
+/// A string of [`TopValues::top`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TopValue<'a> {
+    pub value: &'a str,
+    pub count: u64,
+    /// How much too high the count may be, so the string was seen at least
+    /// `count - error` times.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub error: u64,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+impl TopValues {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            counts: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, value: &str) {
+        if let Some((count, _)) = self.counts.get_mut(value) {
+            *count += 1;
+            return;
+        }
+        if self.counts.len() < self.k * COUNTERS_PER_VALUE {
+            self.counts.insert(value.to_string(), (1, 0));
+            return;
+        }
+        let least = self.counts.iter().min_by_key(|&(_, &(count, _))| count);
+        let Some((least, &(count, _))) = least else {
+            // No counters, for `k == 0`.
+            return;
+        };
+        let least = least.clone();
+        self.counts.remove(&least);
+        self.counts.insert(value.to_string(), (count + 1, count));
+    }
+
+    /// The `k` most common strings, most common first.
+    pub fn top(&self) -> Vec<TopValue<'_>> {
+        let mut top: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(value, &(count, error))| TopValue {
+                value,
+                count,
+                error,
+            })
+            .collect();
+        top.sort_unstable_by(|a, b| {
+            let guaranteed = |top: &TopValue| top.count - top.error;
+            (guaranteed(b), b.count)
+                .cmp(&(guaranteed(a), a.count))
+                .then(a.value.cmp(b.value))
+        });
+        top.truncate(self.k);
+        top
+    }
+}
+
+impl Serialize for TopValues {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let top = self.top();
+        let mut seq = serializer.serialize_seq(Some(top.len()))?;
+        for value in top {
+            seq.serialize_element(&value)?;
+        }
+        seq.end()
+    }
+}
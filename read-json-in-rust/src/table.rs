@@ -0,0 +1,329 @@
+//! Documents as rows of a CSV or TSV table, with
+//! [csv](https://crates.io/crates/csv).
+//!
+//! Every column is a field of the documents. Strings are written as they are,
+//! missing fields and `null` as empty cells and other values as JSON.
+//!
+//! Read back, every row is a document with a field per column, named by the
+//! header or a [`TableSchema`]. Empty cells are left out.
+
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde_json::{Map, Number, Value};
+
+use crate::json_iter::JsonIterError;
+use crate::path::FieldPath;
+use crate::transform::parse_value;
+
+/// A column of a table: a header and the field with the values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub name: String,
+    pub path: FieldPath,
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    /// Parses `NAME=FIELD`, or `FIELD` for a column named like the field.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, path) = s.split_once('=').unwrap_or((s, s));
+        Ok(Self {
+            name: name.to_string(),
+            path: path.parse()?,
+        })
+    }
+}
+
+/// When to quote a cell.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Quote {
+    /// Only cells with the delimiter, a quote or a line break.
+    #[default]
+    Necessary,
+    Always,
+    /// All cells that aren't a number.
+    NonNumeric,
+    /// Never, which may not be read back correctly.
+    Never,
+}
+
+impl From<Quote> for csv::QuoteStyle {
+    fn from(quote: Quote) -> Self {
+        match quote {
+            Quote::Necessary => csv::QuoteStyle::Necessary,
+            Quote::Always => csv::QuoteStyle::Always,
+            Quote::NonNumeric => csv::QuoteStyle::NonNumeric,
+            Quote::Never => csv::QuoteStyle::Never,
+        }
+    }
+}
+
+/// The text of the cell for `value`.
+pub fn cell(value: Option<&Value>) -> Cow<'_, str> {
+    match value {
+        None | Some(Value::Null) => Cow::Borrowed(""),
+        Some(Value::String(value)) => Cow::Borrowed(value),
+        Some(value) => Cow::Owned(value.to_string()),
+    }
+}
+
+/// Options for the format of a table, e.g.
+///
+/// ```no_run
+/// # use read_json_in_rust::table::TableOptions;
+/// let columns = vec!["id".parse()?, "pos=entry.pos".parse()?];
+/// let writer = TableOptions::new().delimiter(b'\t').writer(std::io::stdout(), columns)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct TableOptions {
+    delimiter: u8,
+    quote: Quote,
+    header: bool,
+    schema: Option<TableSchema>,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: Quote::default(),
+            header: true,
+            schema: None,
+        }
+    }
+}
+
+impl TableOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The delimiter between cells, `,` by default.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn quote(mut self, quote: Quote) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// Whether the first row has the names of the columns, `true` by default.
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Read the columns with the names and types of `schema` instead of the
+    /// names in the header (a header is still skipped).
+    pub fn schema(mut self, schema: TableSchema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Iterate over the rows read from `reader` as documents.
+    pub fn reader<R: Read>(&self, reader: R) -> Result<TableReader<R>, JsonIterError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.header)
+            .from_reader(reader);
+        let columns = match &self.schema {
+            Some(schema) => schema
+                .columns
+                .iter()
+                .map(|column| Ok((schema_path(&column.name)?, column.kind)))
+                .collect::<Result<_, JsonIterError>>()?,
+            None if self.header => reader
+                .headers()?
+                .iter()
+                .map(|name| Ok((schema_path(name)?, CellType::String)))
+                .collect::<Result<_, JsonIterError>>()?,
+            None => {
+                return Err(JsonIterError::Table {
+                    row: 1,
+                    message: "a table without header needs a schema".to_string(),
+                })
+            }
+        };
+        Ok(TableReader {
+            records: Some(reader.into_records()),
+            columns,
+        })
+    }
+
+    /// Write rows of `columns` to `writer`, starting with the header.
+    pub fn writer<W: Write>(
+        &self,
+        writer: W,
+        columns: Vec<Column>,
+    ) -> Result<TableWriter<W>, csv::Error> {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .quote_style(self.quote.into())
+            .from_writer(writer);
+        if self.header {
+            writer.write_record(columns.iter().map(|column| &column.name))?;
+        }
+        Ok(TableWriter { writer, columns })
+    }
+}
+
+/// Writes documents as rows of a table.
+pub struct TableWriter<W: Write> {
+    writer: csv::Writer<W>,
+    columns: Vec<Column>,
+}
+
+impl<W: Write> TableWriter<W> {
+    pub fn write(&mut self, doc: &Value) -> Result<(), csv::Error> {
+        for column in &self.columns {
+            self.writer
+                .write_field(cell(column.path.get(doc)).as_bytes())?;
+        }
+        // An empty record ends the current one.
+        self.writer.write_record(None::<&[u8]>)
+    }
+
+    /// Returns the underlying writer, flushed.
+    pub fn finish(self) -> Result<W, csv::Error> {
+        self.writer
+            .into_inner()
+            .map_err(|err| err.into_error().into())
+    }
+}
+
+/// How the text of a cell is turned into a value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CellType {
+    #[default]
+    String,
+    Integer,
+    Number,
+    Boolean,
+    /// Parsed as JSON, e.g. for arrays written by [`TableWriter`].
+    Json,
+    /// Parsed as JSON if possible, and used as a string otherwise.
+    Auto,
+}
+
+impl CellType {
+    fn parse(self, text: &str) -> Option<Value> {
+        Some(match self {
+            CellType::String => text.into(),
+            CellType::Integer => match text.parse::<i64>() {
+                Ok(value) => value.into(),
+                Err(_) => text.parse::<u64>().ok()?.into(),
+            },
+            CellType::Number => Number::from_f64(text.parse().ok()?)?.into(),
+            CellType::Boolean => text.parse::<bool>().ok()?.into(),
+            CellType::Json => serde_json::from_str(text).ok()?,
+            CellType::Auto => parse_value(text),
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            CellType::String => "string",
+            CellType::Integer => "integer",
+            CellType::Number => "number",
+            CellType::Boolean => "boolean",
+            CellType::Json => "json",
+            CellType::Auto => "auto",
+        }
+    }
+}
+
+/// The columns of a table, in order, with the type of their cells:
+///
+/// ```toml
+/// [[columns]]
+/// name = "_id"
+///
+/// [[columns]]
+/// name = "entry.senses"
+/// type = "json"
+/// ```
+///
+/// Names are dotted paths or JSON pointers, so a column can be a nested field.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TableSchema {
+    pub columns: Vec<ColumnSchema>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnSchema {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub kind: CellType,
+}
+
+impl TableSchema {
+    /// Read the schema from a TOML file.
+    pub fn from_file(path: &str) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        toml::from_str(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+fn schema_path(name: &str) -> Result<FieldPath, JsonIterError> {
+    name.parse()
+        .map_err(|message| JsonIterError::Table { row: 1, message })
+}
+
+/// Iterator over the rows of a table as documents.
+///
+/// Yields `Err` at most once, after which the iterator is exhausted.
+pub struct TableReader<R: Read> {
+    records: Option<csv::StringRecordsIntoIter<R>>,
+    columns: Vec<(FieldPath, CellType)>,
+}
+
+impl<R: Read> TableReader<R> {
+    fn document(&self, record: &csv::StringRecord) -> Result<Value, JsonIterError> {
+        let row = record.position().map_or(0, |position| position.line());
+        if record.len() != self.columns.len() {
+            return Err(JsonIterError::Table {
+                row,
+                message: format!("{} cells for {} columns", record.len(), self.columns.len()),
+            });
+        }
+        let mut doc = Value::Object(Map::new());
+        for ((path, kind), text) in self.columns.iter().zip(record) {
+            if text.is_empty() {
+                continue;
+            }
+            let message = match kind.parse(text).map(|value| path.set(&mut doc, value)) {
+                Some(Ok(())) => continue,
+                Some(Err(_)) => format!("column {} conflicts with another column", path),
+                None => format!("column {}: '{}' is not a valid {}", path, text, kind.name()),
+            };
+            return Err(JsonIterError::Table { row, message });
+        }
+        Ok(doc)
+    }
+}
+
+impl<R: Read> Iterator for TableReader<R> {
+    type Item = Result<Value, JsonIterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = match self.records.as_mut()?.next()? {
+            Ok(record) => self.document(&record),
+            Err(err) => Err(err.into()),
+        };
+        if result.is_err() {
+            self.records = None;
+        }
+        Some(result)
+    }
+}
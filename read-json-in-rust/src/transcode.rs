@@ -0,0 +1,338 @@
+//! Updating documents token by token, without parsing them into a `Value`.
+//!
+//! A [`Transcoder`] copies the tokens of each document from a struson reader
+//! to a writer, skipping removed fields and replacing set ones as it goes.
+//! Only the objects that a rename moves fields within are read into memory,
+//! e.g. `name` for `name.lastname=name.surname`.
+
+use std::io::{Read, Write};
+
+use serde_json::Value;
+use struson::reader::{JsonReader, JsonStreamReader, ValueType};
+use struson::writer::{JsonStreamWriter, JsonWriter};
+
+use crate::json_iter::{self, JsonIterError};
+use crate::path::FieldPath;
+use crate::transform::{DocumentTransform, Remove, Rename, SetField};
+
+/// The updates of a transcoder, applied in the same order as by `json-tool`:
+/// removals, renames and then set fields.
+#[derive(Debug, Clone, Default)]
+pub struct Transcoder {
+    removals: Vec<Remove>,
+    renames: Vec<(Vec<String>, Rename)>,
+    sets: Vec<SetField>,
+}
+
+/// Whether the segment `pattern` of a path selects `segment`.
+fn selects(pattern: &str, segment: &str) -> bool {
+    pattern == "*" || pattern == segment
+}
+
+/// Whether `path` selects the field at `segments` or one inside it.
+fn reaches(path: &[String], segments: &[String]) -> bool {
+    path.len() >= segments.len()
+        && path
+            .iter()
+            .zip(segments)
+            .all(|(pattern, segment)| selects(pattern, segment))
+}
+
+impl Transcoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn remove(mut self, removal: Remove) -> Self {
+        self.removals.push(removal);
+        self
+    }
+
+    /// Add a rename, the object that holds both paths is read into memory.
+    pub fn rename(mut self, rename: Rename) -> Self {
+        let from = rename.from.segments();
+        let to = rename.to.segments();
+        let parents = (&from[..from.len() - 1], &to[..to.len() - 1]);
+        let common = parents
+            .0
+            .iter()
+            .zip(parents.1)
+            .take_while(|(from, to)| from == to)
+            .count();
+        self.renames.push((from[..common].to_vec(), rename));
+        self
+    }
+
+    pub fn set(mut self, set_field: SetField) -> Self {
+        self.sets.push(set_field);
+        self
+    }
+
+    fn removes(&self, segments: &[String]) -> bool {
+        self.removals.iter().any(|removal| {
+            let path = removal.path.segments();
+            path.len() == segments.len() && reaches(path, segments)
+        })
+    }
+
+    /// Whether an update changes something inside the value at `segments`.
+    fn changes_inside(&self, segments: &[String]) -> bool {
+        (segments.is_empty() && !self.sets.is_empty())
+            || self.removals.iter().any(|removal| {
+                let path = removal.path.segments();
+                path.len() > segments.len() && reaches(path, segments)
+            })
+            || self
+                .renames
+                .iter()
+                .any(|(parent, _)| reaches(parent, segments))
+    }
+
+    /// Copy the next value of `reader` to `writer` with the updates.
+    pub fn transcode<R: Read, W: Write>(
+        &self,
+        reader: &mut JsonStreamReader<R>,
+        writer: &mut JsonStreamWriter<W>,
+    ) -> Result<(), JsonIterError> {
+        self.copy(reader, writer, &mut Vec::new())
+    }
+
+    fn copy<R: Read, W: Write>(
+        &self,
+        reader: &mut JsonStreamReader<R>,
+        writer: &mut JsonStreamWriter<W>,
+        segments: &mut Vec<String>,
+    ) -> Result<(), JsonIterError> {
+        if self
+            .renames
+            .iter()
+            .any(|(parent, _)| parent.len() == segments.len() && reaches(parent, segments))
+        {
+            let mut value = reader.deserialize_next()?;
+            self.update(segments, &mut value);
+            writer.serialize_value(&value)?;
+            return Ok(());
+        }
+        if !self.changes_inside(segments) {
+            reader.transfer_to(writer)?;
+            return Ok(());
+        }
+        match reader.peek()? {
+            ValueType::Object => self.copy_object(reader, writer, segments),
+            ValueType::Array => {
+                reader.begin_array()?;
+                writer.begin_array()?;
+                let mut index = 0;
+                while reader.has_next()? {
+                    segments.push(index.to_string());
+                    if self.removes(segments) {
+                        reader.skip_value()?;
+                    } else {
+                        self.copy(reader, writer, segments)?;
+                    }
+                    segments.pop();
+                    index += 1;
+                }
+                reader.end_array()?;
+                writer.end_array()?;
+                Ok(())
+            }
+            _ => Ok(reader.transfer_to(writer)?),
+        }
+    }
+
+    fn copy_object<R: Read, W: Write>(
+        &self,
+        reader: &mut JsonStreamReader<R>,
+        writer: &mut JsonStreamWriter<W>,
+        segments: &mut Vec<String>,
+    ) -> Result<(), JsonIterError> {
+        // Only top-level fields are set.
+        let sets: &[SetField] = if segments.is_empty() { &self.sets } else { &[] };
+        let mut written = vec![false; sets.len()];
+        reader.begin_object()?;
+        writer.begin_object()?;
+        while reader.has_next()? {
+            segments.push(reader.next_name_owned()?);
+            let name = segments.last().expect("the name just pushed");
+            let set = sets.iter().position(|set_field| set_field.field == *name);
+            if self.removes(segments) {
+                reader.skip_value()?;
+            } else if let Some(index) = set {
+                // The value is replaced in place, as with `preserve_order`.
+                reader.skip_value()?;
+                if !written[index] {
+                    writer.name(name)?;
+                    writer.serialize_value(&sets[index].value)?;
+                    written[index] = true;
+                }
+            } else {
+                writer.name(name)?;
+                self.copy(reader, writer, segments)?;
+            }
+            segments.pop();
+        }
+        for (set_field, written) in sets.iter().zip(written) {
+            if !written {
+                writer.name(&set_field.field)?;
+                writer.serialize_value(&set_field.value)?;
+            }
+        }
+        reader.end_object()?;
+        writer.end_object()?;
+        Ok(())
+    }
+
+    /// Apply the updates inside the value at `segments` to `value`, none of
+    /// which can fail.
+    fn update(&self, segments: &[String], value: &mut Value) {
+        let relative = |path: &FieldPath| {
+            let path = path.segments();
+            (path.len() > segments.len() && reaches(path, segments))
+                .then(|| FieldPath::from_segments(path[segments.len()..].to_vec()))
+        };
+        for removal in &self.removals {
+            if let Some(path) = relative(&removal.path) {
+                Remove { path }.apply(value).ok();
+            }
+        }
+        for (_, rename) in &self.renames {
+            if let (Some(from), Some(to)) = (relative(&rename.from), relative(&rename.to)) {
+                Rename { from, to }.apply(value).ok();
+            }
+        }
+        if segments.is_empty() {
+            for set_field in &self.sets {
+                set_field.apply(value).ok();
+            }
+        }
+    }
+
+    /// Copy the documents of the JSON array read from `reader` to a JSON
+    /// array written to `writer` with the updates, and return the writer.
+    pub fn transcode_array<R: Read, W: Write>(
+        &self,
+        reader: R,
+        writer: W,
+    ) -> Result<W, JsonIterError> {
+        let mut reader = json_iter::stream_reader(reader);
+        let mut writer = JsonStreamWriter::new(writer);
+        reader.begin_array()?;
+        writer.begin_array()?;
+        while reader.has_next()? {
+            self.transcode(&mut reader, &mut writer)?;
+        }
+        reader.end_array()?;
+        reader.consume_trailing_whitespace()?;
+        writer.end_array()?;
+        let mut writer = writer.finish_document()?;
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCS: &str = r#"[
+        {"_id": 1, "name": {"first": "Ada", "last": "L"}, "senses": [{"id": "a", "gloss": "x"}, {"gloss": "y"}, 3]},
+        {"name": "plain", "lexiconName": "old", "x": 5, "entry": {"pos": "nn", "inner": {"pos": 1}}},
+        {"senses": {"k": {"id": 1, "gloss": "z"}}, "entry": {"pos": "vb"}, "x": {"y": 0}},
+        [{"_id": 2}], 2.5, "a string", null, true, {}
+    ]"#;
+
+    /// Check that `transcoder` gives the same documents as the updates on
+    /// parsed values, in the same order as it applies them.
+    fn assert_same_as_values(transcoder: &Transcoder) {
+        let output = transcoder
+            .transcode_array(DOCS.as_bytes(), Vec::new())
+            .unwrap();
+        let read = |json: &[u8]| {
+            json_iter::load_from_reader(json)
+                .unwrap()
+                .collect::<Result<Vec<Value>, _>>()
+                .unwrap()
+        };
+        let mut docs = read(DOCS.as_bytes());
+        for doc in &mut docs {
+            transcoder
+                .removals
+                .iter()
+                .for_each(|removal| removal.apply(doc).unwrap());
+            transcoder
+                .renames
+                .iter()
+                .for_each(|(_, rename)| rename.apply(doc).unwrap());
+            transcoder
+                .sets
+                .iter()
+                .for_each(|set_field| set_field.apply(doc).unwrap());
+        }
+        // Written and read again as when streaming, the keys may be in
+        // another order.
+        let streamed = json_iter::dump_to_writer(Vec::new(), docs).unwrap();
+        assert_eq!(read(&output), read(&streamed), "{:?}", transcoder);
+    }
+
+    #[test]
+    fn removes_as_on_values() {
+        let transcoder = Transcoder::new()
+            .remove("_id".parse().unwrap())
+            .remove("senses.*.id".parse().unwrap())
+            .remove("senses.1".parse().unwrap())
+            .remove("name.first".parse().unwrap());
+        assert_same_as_values(&transcoder);
+    }
+
+    #[test]
+    fn renames_as_on_values() {
+        for renames in [
+            &["name.last=name.surname"][..],
+            &["entry.pos=partOfSpeech", "x=entry.x"],
+            &["entry.inner.pos=entry.pos"],
+            &[
+                "senses.*.gloss=senses.*.definition",
+                "name.first=name.given.first",
+            ],
+            // The new path can't be created where `x` is a number.
+            &["entry.pos=x.pos"],
+        ] {
+            let transcoder = renames
+                .iter()
+                .fold(Transcoder::new(), |transcoder, rename| {
+                    transcoder.rename(rename.parse().unwrap())
+                });
+            assert_same_as_values(&transcoder);
+        }
+    }
+
+    #[test]
+    fn sets_as_on_values() {
+        let transcoder = Transcoder::new()
+            .remove("x".parse().unwrap())
+            .rename("name=label".parse().unwrap())
+            .set("lexiconName=skbl2".parse().unwrap())
+            .set("lexiconOrder=48".parse().unwrap())
+            .set("x=[1]".parse().unwrap());
+        assert_same_as_values(&transcoder);
+    }
+
+    #[test]
+    fn keeps_the_order_of_the_keys_and_numbers() {
+        let transcoder = Transcoder::new()
+            .remove("senses.*.id".parse().unwrap())
+            .set("lexiconName=skbl2".parse().unwrap())
+            .set("lexiconOrder=48".parse().unwrap());
+        let input =
+            r#"[{"z": 1.5e3, "lexiconName": "old", "senses": [{"id": 1, "b": -0}]}, 1E400]"#;
+        let output = transcoder
+            .transcode_array(input.as_bytes(), Vec::new())
+            .unwrap();
+        // Set fields are replaced in place and added at the end.
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            r#"[{"z":1.5e3,"lexiconName":"skbl2","senses":[{"b":-0}],"lexiconOrder":48},1E400]"#
+        );
+    }
+}
@@ -0,0 +1,555 @@
+//! Updates applied to each document.
+
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::path::{self, remove_key, FieldPath};
+
+mod enrich;
+mod flatten;
+mod id;
+mod jq;
+mod normalize;
+mod select;
+
+pub use enrich::Enrich;
+pub use flatten::{Flatten, FlattenArrays, Unflatten};
+pub use id::{AssignId, IdKind};
+pub use jq::{Jq, JqError};
+pub use normalize::{NormalizationForm, Normalize};
+pub use select::Select;
+
+/// The result of [`DocumentTransform::apply`].
+pub type TransformResult = Result<(), TransformError>;
+
+/// An update applied to each document in place.
+///
+/// The updates of this module implement it, as do closures taking a
+/// `&mut Value`, so custom updates can be run in [`Transforms`] next to the
+/// built-in ones.
+pub trait DocumentTransform: Send + Sync {
+    fn apply(&self, doc: &mut Value) -> TransformResult;
+}
+
+impl<F> DocumentTransform for F
+where
+    F: Fn(&mut Value) -> TransformResult + Send + Sync,
+{
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        self(doc)
+    }
+}
+
+/// Updates applied one after the other, in the order they were added.
+#[derive(Default)]
+pub struct Transforms {
+    transforms: Vec<Box<dyn DocumentTransform>>,
+}
+
+impl Transforms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `transform` after the updates added so far.
+    pub fn with(mut self, transform: impl DocumentTransform + 'static) -> Self {
+        self.push(transform);
+        self
+    }
+
+    pub fn push(&mut self, transform: impl DocumentTransform + 'static) {
+        self.transforms.push(Box::new(transform));
+    }
+
+    pub fn len(&self) -> usize {
+        self.transforms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+}
+
+impl<T: DocumentTransform + 'static> Extend<T> for Transforms {
+    /// Add the updates of `iter` after the updates added so far.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|transform| self.push(transform));
+    }
+}
+
+impl DocumentTransform for Transforms {
+    /// Apply the updates in order, stopping at the first that fails.
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        self.transforms
+            .iter()
+            .try_for_each(|transform| transform.apply(doc))
+    }
+}
+
+impl fmt::Debug for Transforms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transforms")
+            .field("len", &self.transforms.len())
+            .finish()
+    }
+}
+
+/// Sets a top-level field to a value, as in `lexiconName=skbl2`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetField {
+    pub field: String,
+    pub value: Value,
+}
+
+impl DocumentTransform for SetField {
+    /// Set the field if `doc` is an object, other documents are left as is.
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        if let Some(object) = doc.as_object_mut() {
+            object.insert(self.field.clone(), self.value.clone());
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SetField {
+    type Err = String;
+
+    /// Parses `field=value`, where `value` is parsed with [`parse_value`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (field, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected field=value, got '{}'", s))?;
+        if field.is_empty() {
+            return Err(format!("missing field name in '{}'", s));
+        }
+        Ok(SetField {
+            field: field.to_string(),
+            value: parse_value(value),
+        })
+    }
+}
+
+/// Sets a field to the value of a jq expression over the document, as in
+/// `displayName=.name.firstname + " " + .name.lastname`, with string
+/// concatenation, arithmetic and `if ... then ... else ... end`.
+///
+/// The field is set to the first value the expression yields, and left as it
+/// is if the expression yields none, e.g. for `empty`. Missing objects on the
+/// path are created.
+#[derive(Debug, Clone)]
+pub struct Derive {
+    pub field: FieldPath,
+    pub expr: Jq,
+}
+
+impl DocumentTransform for Derive {
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        if let Some(value) = self.expr.first(doc)? {
+            // Like `SetField`, paths through other values are skipped.
+            let _ = self.field.set(doc, value);
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Derive {
+    type Err = String;
+
+    /// Parses `field=expr`, where `field` is a dotted path or a JSON pointer.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (field, expr) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected field=expression, got '{}'", s))?;
+        Ok(Derive {
+            field: field.parse()?,
+            expr: expr.parse().map_err(|err: JqError| err.to_string())?,
+        })
+    }
+}
+
+/// Moves a field to a new path, as in `entry.pos=entry.partOfSpeech`. A `*`
+/// segment stands for every element of an array (or every value of an
+/// object), as for [`Remove`], and must be at the same place in both paths:
+/// `senses.*.gloss=senses.*.definition` renames the field in every sense.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    pub from: FieldPath,
+    pub to: FieldPath,
+}
+
+/// The number of segments up to and including the last `*` of `path`.
+fn wildcard_prefix(path: &FieldPath) -> usize {
+    path.segments()
+        .iter()
+        .rposition(|segment| segment == "*")
+        .map_or(0, |last| last + 1)
+}
+
+impl Rename {
+    /// The rename of `from` to `to`, or an error if their `*`s are not at
+    /// the same places, or one is last, which is no field to move.
+    pub fn new(from: FieldPath, to: FieldPath) -> Result<Self, String> {
+        let prefix = wildcard_prefix(&from).max(wildcard_prefix(&to));
+        let (old, new) = (from.segments(), to.segments());
+        if prefix > 0
+            && (prefix >= old.len() || prefix >= new.len() || old[..prefix] != new[..prefix])
+        {
+            return Err(format!(
+                "a * must be at the same place in both paths of '{}={}', and not last",
+                from, to
+            ));
+        }
+        Ok(Self { from, to })
+    }
+}
+
+impl DocumentTransform for Rename {
+    /// Move the field if `doc` has it, creating missing objects on the new
+    /// path. If the new path can't be created, the field is left in place.
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        let prefix = wildcard_prefix(&self.from);
+        let from = &self.from.segments()[prefix..];
+        let to = &self.to.segments()[prefix..];
+        path::for_each_match(&self.from.segments()[..prefix], doc, &mut |value| {
+            rename(from, to, value)
+        });
+        Ok(())
+    }
+}
+
+/// Move the field at `from` in `doc` to `to`.
+fn rename(from: &[String], to: &[String], doc: &mut Value) {
+    #[cfg(feature = "preserve_order")]
+    if rename_key(from, to, doc) {
+        return;
+    }
+    let Some(value) = path::remove(from, doc) else {
+        return;
+    };
+    if let Err(value) = path::put(to, doc, value, false) {
+        // Put the field back where it was.
+        path::put(from, doc, value, true).ok();
+    }
+}
+
+/// Rename the key in place if both paths are in the same object, so the
+/// field keeps its position.
+#[cfg(feature = "preserve_order")]
+fn rename_key(from: &[String], to: &[String], doc: &mut Value) -> bool {
+    let (Some((from, parent)), Some((to, to_parent))) = (from.split_last(), to.split_last()) else {
+        return false;
+    };
+    if parent != to_parent {
+        return false;
+    }
+    let Some(Value::Object(object)) = path::get_mut(parent, doc) else {
+        return false;
+    };
+    if !object.contains_key(from) || object.contains_key(to) {
+        return false;
+    }
+    *object = std::mem::take(object)
+        .into_iter()
+        .map(|(key, value)| match key == *from {
+            true => (to.clone(), value),
+            false => (key, value),
+        })
+        .collect();
+    true
+}
+
+impl FromStr for Rename {
+    type Err = String;
+
+    /// Parses `old=new`, where both are dotted paths or JSON pointers.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected old=new, got '{}'", s))?;
+        Rename::new(from.parse()?, to.parse()?)
+    }
+}
+
+/// Deletes a field, as in `_source._id`. A `*` segment stands for every
+/// element of an array (or every value of an object), so `senses.*.id`
+/// deletes `id` from all senses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remove {
+    pub path: FieldPath,
+}
+
+impl DocumentTransform for Remove {
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        remove(self.path.segments(), doc);
+        Ok(())
+    }
+}
+
+fn remove(segments: &[String], value: &mut Value) {
+    let Some((first, rest)) = segments.split_first() else {
+        return;
+    };
+    if rest.is_empty() {
+        match value {
+            Value::Object(object) if first == "*" => object.clear(),
+            Value::Object(object) => {
+                remove_key(object, first);
+            }
+            Value::Array(array) if first == "*" => array.clear(),
+            Value::Array(array) => {
+                if let Some(index) = first.parse().ok().filter(|i| *i < array.len()) {
+                    array.remove(index);
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+    match value {
+        Value::Object(object) if first == "*" => {
+            object.values_mut().for_each(|value| remove(rest, value))
+        }
+        Value::Array(array) if first == "*" => {
+            array.iter_mut().for_each(|value| remove(rest, value))
+        }
+        Value::Object(object) => {
+            if let Some(value) = object.get_mut(first) {
+                remove(rest, value);
+            }
+        }
+        Value::Array(array) => {
+            if let Some(value) = first.parse().ok().and_then(|i: usize| array.get_mut(i)) {
+                remove(rest, value);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl FromStr for Remove {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Remove { path: s.parse()? })
+    }
+}
+
+/// Keys of the fields that renames and removals may change, to find the
+/// documents they leave as they are without parsing them: a document can only
+/// be changed if its JSON contains one of the keys as a string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AffectedKeys {
+    /// The keys as JSON strings, with the quotes.
+    keys: Vec<String>,
+}
+
+impl AffectedKeys {
+    /// The keys of `paths`, or `None` if a path has no key that must be in
+    /// every document it changes.
+    pub fn new<'a>(paths: impl IntoIterator<Item = &'a FieldPath>) -> Option<Self> {
+        let keys = paths
+            .into_iter()
+            .map(|path| {
+                // Indices and `*` may select array elements, and keys with
+                // escapes may be written in several ways.
+                let key = path.segments().iter().rev().find(|segment| {
+                    *segment != "*"
+                        && segment.parse::<usize>().is_err()
+                        && !segment
+                            .chars()
+                            .any(|c| c == '"' || c == '\\' || c.is_control())
+                })?;
+                Some(format!("\"{}\"", key))
+            })
+            .collect::<Option<_>>()?;
+        Some(Self { keys })
+    }
+
+    /// Whether the document `json` may have one of the fields.
+    pub fn may_affect(&self, json: &str) -> bool {
+        self.keys.iter().any(|key| json.contains(key.as_str()))
+    }
+}
+
+/// A JSON Patch (RFC 6902), as in
+/// `[{"op": "replace", "path": "/lexiconName", "value": "skbl2"}]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Patch {
+    pub patch: json_patch::Patch,
+}
+
+impl Patch {
+    /// Read the patch from the JSON file `path`.
+    pub fn from_file(path: &str) -> io::Result<Self> {
+        let patch = serde_json::from_reader(io::BufReader::new(std::fs::File::open(path)?))?;
+        Ok(Self { patch })
+    }
+}
+
+impl DocumentTransform for Patch {
+    /// Apply all operations to `doc`, or none of them if one fails.
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        Ok(json_patch::patch(doc, &self.patch)?)
+    }
+}
+
+/// A JSON Merge Patch (RFC 7386), an object deep-merged into each document,
+/// where `null` removes a field, as in `{"lexiconName": "skbl2", "_id": null}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergePatch {
+    pub patch: Value,
+}
+
+impl DocumentTransform for MergePatch {
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        json_patch::merge(doc, &self.patch);
+        Ok(())
+    }
+}
+
+impl FromStr for MergePatch {
+    type Err = String;
+
+    /// Parses the patch as JSON, or reads it from the file `path` for
+    /// `@path`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let patch = match s.strip_prefix('@') {
+            Some(path) => {
+                let text =
+                    std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+                serde_json::from_str(&text)
+            }
+            None => serde_json::from_str(s),
+        }
+        .map_err(|err| format!("invalid merge patch: {}", err))?;
+        Ok(Self { patch })
+    }
+}
+
+/// Errors from the updates that can fail on a document.
+#[derive(Debug)]
+pub enum TransformError {
+    Jq(JqError),
+    Patch(json_patch::PatchError),
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransformError::Jq(err) => write!(f, "{}", err),
+            TransformError::Patch(err) => write!(f, "JSON Patch failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for TransformError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TransformError::Jq(err) => Some(err),
+            TransformError::Patch(err) => Some(err),
+        }
+    }
+}
+
+impl From<JqError> for TransformError {
+    fn from(err: JqError) -> Self {
+        TransformError::Jq(err)
+    }
+}
+
+impl From<json_patch::PatchError> for TransformError {
+    fn from(err: json_patch::PatchError) -> Self {
+        TransformError::Patch(err)
+    }
+}
+
+/// Parses a value given on the command line: as JSON if it is valid JSON
+/// (`48`, `true`, `"48"`) and as a string otherwise (`skbl2`).
+pub fn parse_value(value: &str) -> Value {
+    serde_json::from_str(value).unwrap_or_else(|_| value.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn renames_every_match_of_a_wildcard() {
+        let rename: Rename = "senses.*.gloss=senses.*.definition".parse().unwrap();
+        let mut doc = json!({
+            "senses": [{ "gloss": "a" }, { "id": 2 }, { "gloss": "c", "definition": "d" }],
+        });
+        rename.apply(&mut doc).unwrap();
+        assert_eq!(
+            doc,
+            json!({
+                "senses": [{ "definition": "a" }, { "id": 2 }, { "definition": "c" }],
+            })
+        );
+
+        let rename: Rename = "s.*.g=s.*.h.i".parse().unwrap();
+        let mut doc = json!({ "s": { "x": { "g": 1 }, "y": { "g": 2 } } });
+        rename.apply(&mut doc).unwrap();
+        assert_eq!(
+            doc,
+            json!({ "s": { "x": { "h": { "i": 1 } }, "y": { "h": { "i": 2 } } } })
+        );
+    }
+
+    #[test]
+    fn applies_transforms_in_order() {
+        // Each update sees the document as the ones before it left it.
+        let transforms = Transforms::new()
+            .with("a.x".parse::<Remove>().unwrap())
+            .with("a=moved".parse::<Rename>().unwrap())
+            .with("a=1".parse::<SetField>().unwrap())
+            .with(|doc: &mut Value| {
+                let a = doc["a"].as_i64().unwrap_or(0);
+                doc["b"] = (a + 1).into();
+                Ok(())
+            })
+            .with("b=c".parse::<Rename>().unwrap());
+        assert_eq!(transforms.len(), 5);
+        let mut doc = json!({ "a": { "x": 1, "y": 2 } });
+        transforms.apply(&mut doc).unwrap();
+        assert_eq!(doc, json!({ "moved": { "y": 2 }, "a": 1, "c": 2 }));
+    }
+
+    #[test]
+    fn stops_at_the_first_failing_transform() {
+        let mut transforms: Transforms = ["x=1", "y=2"]
+            .iter()
+            .map(|set_field| set_field.parse::<SetField>().unwrap())
+            .fold(Transforms::new(), Transforms::with);
+        transforms.push("z=.a.b".parse::<Derive>().unwrap());
+        transforms.extend(["z=3".parse::<SetField>().unwrap()]);
+        let mut doc = json!({ "a": 1 });
+        let err = transforms.apply(&mut doc).unwrap_err();
+        assert!(matches!(err, TransformError::Jq(_)), "{}", err);
+        assert_eq!(doc, json!({ "a": 1, "x": 1, "y": 2 }));
+    }
+
+    #[test]
+    fn rejects_wildcards_at_different_places() {
+        for rename in [
+            "s.*.g=t.*.g",
+            "s.*.g=s.h",
+            "s.g=s.*.g",
+            "s.*=t.*",
+            "s.*.*.g=s.*.g",
+        ] {
+            let err = rename.parse::<Rename>().unwrap_err();
+            assert!(
+                err.contains("a * must be at the same place"),
+                "{}: {}",
+                rename,
+                err
+            );
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Joining fields from a lookup table into the documents.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::{DocumentTransform, TransformResult};
+use crate::json_iter::{self, JsonIterError};
+use crate::path::FieldPath;
+
+/// Copies fields of the lookup record with the same key into each document,
+/// e.g. authority-record links from a file keyed by the `_id` of the entries.
+///
+/// The lookup records are kept in memory, with only the copied fields. Keys
+/// are matched by their JSON value, so `"48"` doesn't match `48`; records
+/// and documents without the key, or with a `null` key, don't match. If
+/// several records have the same key, the first one is used.
+#[derive(Debug, Clone)]
+pub struct Enrich {
+    on: FieldPath,
+    lookup_on: FieldPath,
+    fields: Vec<FieldPath>,
+    /// The copied fields of each record, by the key as JSON.
+    records: HashMap<String, Vec<(usize, Value)>>,
+}
+
+impl Enrich {
+    /// Copy `fields`, dotted paths or JSON pointers, from the records with
+    /// the same value at `on` as the document to the same paths in the
+    /// document.
+    pub fn new(on: FieldPath, fields: Vec<FieldPath>) -> Self {
+        Self {
+            lookup_on: on.clone(),
+            on,
+            fields,
+            records: HashMap::new(),
+        }
+    }
+
+    /// Take the key of the records from `path` instead of the path of the
+    /// documents.
+    pub fn lookup_on(mut self, path: FieldPath) -> Self {
+        self.lookup_on = path;
+        self
+    }
+
+    /// Add the records of the JSON array of documents at `path`.
+    pub fn load(mut self, path: &str) -> Result<Self, JsonIterError> {
+        for record in json_iter::load_from_file::<Value>(path)? {
+            self.add(&record?);
+        }
+        Ok(self)
+    }
+
+    pub fn add(&mut self, record: &Value) {
+        let Some(key) = key(&self.lookup_on, record) else {
+            return;
+        };
+        if self.records.contains_key(&key) {
+            return;
+        }
+        let values = self
+            .fields
+            .iter()
+            .enumerate()
+            .filter_map(|(index, field)| Some((index, field.get(record)?.clone())))
+            .collect();
+        self.records.insert(key, values);
+    }
+
+    /// The number of distinct keys of the records.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+impl DocumentTransform for Enrich {
+    /// Copy the fields of the matching record, if any, into `doc`. Fields
+    /// that the record doesn't have are left as they are.
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        let Some(values) = key(&self.on, doc).and_then(|key| self.records.get(&key)) else {
+            return Ok(());
+        };
+        for (index, value) in values {
+            // Like `SetField`, paths through other values are skipped.
+            let _ = self.fields[*index].set(doc, value.clone());
+        }
+        Ok(())
+    }
+}
+
+fn key(path: &FieldPath, doc: &Value) -> Option<String> {
+    path.get(doc)
+        .filter(|key| !key.is_null())
+        .map(Value::to_string)
+}
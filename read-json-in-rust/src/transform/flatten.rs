@@ -0,0 +1,196 @@
+//! Flattening nested documents to a single object with dotted keys, for
+//! tables and tools that don't take nested JSON, and back.
+
+use serde_json::{Map, Value};
+
+use super::{DocumentTransform, TransformResult};
+
+/// What [`Flatten`] and [`Unflatten`] do with arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlattenArrays {
+    /// Flatten the elements too, with their index as the key, e.g.
+    /// `senses.0.gloss`, and unflatten objects keyed `0`, `1`, ... into
+    /// arrays.
+    #[default]
+    Index,
+    /// Keep arrays as they are, as values of the flattened object, and
+    /// numbers as object keys when unflattening.
+    Keep,
+}
+
+/// Flattens the nested objects of a document into one object, joining the
+/// keys of the path to every value with a separator, e.g.
+/// `{"entry": {"pos": "nn"}}` into `{"entry.pos": "nn"}`.
+///
+/// Empty objects and arrays are kept as values. Documents that aren't
+/// objects are left as they are. If two paths join to the same key, e.g.
+/// `a.b` and `a` → `b`, the last one wins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Flatten {
+    separator: String,
+    arrays: FlattenArrays,
+}
+
+impl Default for Flatten {
+    fn default() -> Self {
+        Self {
+            separator: ".".to_string(),
+            arrays: FlattenArrays::default(),
+        }
+    }
+}
+
+impl Flatten {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Join the keys with `separator` instead of `.`.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    pub fn arrays(mut self, arrays: FlattenArrays) -> Self {
+        self.arrays = arrays;
+        self
+    }
+
+    fn flatten_into(&self, flat: &mut Map<String, Value>, key: String, value: Value) {
+        match value {
+            Value::Object(object) if !object.is_empty() => {
+                for (name, value) in object {
+                    self.flatten_into(flat, self.join(&key, &name), value);
+                }
+            }
+            Value::Array(array) if !array.is_empty() && self.arrays == FlattenArrays::Index => {
+                for (index, value) in array.into_iter().enumerate() {
+                    self.flatten_into(flat, self.join(&key, &index.to_string()), value);
+                }
+            }
+            value => {
+                flat.insert(key, value);
+            }
+        }
+    }
+
+    fn join(&self, key: &str, name: &str) -> String {
+        let mut joined = String::with_capacity(key.len() + self.separator.len() + name.len());
+        joined.push_str(key);
+        joined.push_str(&self.separator);
+        joined.push_str(name);
+        joined
+    }
+}
+
+impl DocumentTransform for Flatten {
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        let Value::Object(object) = doc else {
+            return Ok(());
+        };
+        let mut flat = Map::new();
+        for (name, value) in std::mem::take(object) {
+            self.flatten_into(&mut flat, name, value);
+        }
+        *object = flat;
+        Ok(())
+    }
+}
+
+/// Splits the keys of a flat document with a separator into nested objects,
+/// the inverse of [`Flatten`], e.g. `{"entry.pos": "nn"}` into
+/// `{"entry": {"pos": "nn"}}`.
+///
+/// With [`FlattenArrays::Index`] an object keyed by all the indices from `0`
+/// up is turned into an array. If a key is both a value and a prefix of other
+/// keys, e.g. `a` and `a.b`, the last one wins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unflatten {
+    separator: String,
+    arrays: FlattenArrays,
+}
+
+impl Default for Unflatten {
+    fn default() -> Self {
+        Self {
+            separator: ".".to_string(),
+            arrays: FlattenArrays::default(),
+        }
+    }
+}
+
+impl Unflatten {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Split the keys at `separator` instead of `.`, which must not be empty.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        assert!(
+            !self.separator.is_empty(),
+            "the separator must not be empty"
+        );
+        self
+    }
+
+    pub fn arrays(mut self, arrays: FlattenArrays) -> Self {
+        self.arrays = arrays;
+        self
+    }
+}
+
+impl DocumentTransform for Unflatten {
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        let Value::Object(object) = doc else {
+            return Ok(());
+        };
+        let mut nested = Map::new();
+        for (key, value) in std::mem::take(object) {
+            let mut names = key.split(self.separator.as_str()).peekable();
+            let mut members = &mut nested;
+            while let Some(name) = names.next() {
+                if names.peek().is_none() {
+                    members.insert(name.to_string(), value);
+                    break;
+                }
+                let member = members
+                    .entry(name)
+                    .or_insert_with(|| Value::Object(Map::new()));
+                if !member.is_object() {
+                    *member = Value::Object(Map::new());
+                }
+                members = member.as_object_mut().expect("an object");
+            }
+        }
+        if self.arrays == FlattenArrays::Index {
+            nested.values_mut().for_each(to_arrays);
+        }
+        *object = nested;
+        Ok(())
+    }
+}
+
+/// Turn the objects in `value` keyed by all the indices from `0` up into
+/// arrays.
+fn to_arrays(value: &mut Value) {
+    let Value::Object(object) = value else {
+        return;
+    };
+    object.values_mut().for_each(to_arrays);
+    // No leading zeros or signs, as `Flatten` writes the indices.
+    let is_index = |name: &String| match name.parse::<usize>() {
+        Ok(index) => index < object.len() && index.to_string() == *name,
+        Err(_) => false,
+    };
+    // The keys are distinct, so if they are all indices below the length
+    // they are all of them.
+    if object.is_empty() || !object.keys().all(is_index) {
+        return;
+    }
+    let mut elements = vec![Value::Null; object.len()];
+    for (name, value) in std::mem::take(object) {
+        elements[name.parse::<usize>().expect("an index")] = value;
+    }
+    *value = Value::Array(elements);
+}
@@ -0,0 +1,109 @@
+//! Assigning ids to documents, for systems that require unique ids.
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::{DocumentTransform, TransformResult};
+use crate::number::NumberFormat;
+use crate::path::FieldPath;
+
+/// How [`AssignId`] makes the ids.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdKind {
+    /// A random UUID (version 4), different on every run.
+    Random,
+    /// A UUID (version 8) from the SHA-256 hash of the values of these
+    /// fields, so the same values always give the same id.
+    Hash(Vec<FieldPath>),
+}
+
+/// Sets a field of every document to an id, as a UUID string like
+/// `"3f2b1c9e-…"`.
+///
+/// The hash of [`IdKind::Hash`] is over the values as canonical JSON, with
+/// missing fields as `null`, sorted keys and numbers as in RFC 8785, so it
+/// doesn't depend on how the document was written. Documents that aren't
+/// objects are left as they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssignId {
+    field: FieldPath,
+    kind: IdKind,
+}
+
+impl AssignId {
+    pub fn new(field: FieldPath, kind: IdKind) -> Self {
+        Self { field, kind }
+    }
+}
+
+impl DocumentTransform for AssignId {
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        if !doc.is_object() {
+            return Ok(());
+        }
+        let id = match &self.kind {
+            IdKind::Random => uuid(rand::random(), 4),
+            IdKind::Hash(fields) => {
+                let mut values = String::new();
+                for field in fields {
+                    write_canonical(&mut values, field.get(doc).unwrap_or(&Value::Null));
+                    values.push('\n');
+                }
+                let hash = Sha256::digest(values.as_bytes());
+                uuid(hash[..16].try_into().expect("16 bytes"), 8)
+            }
+        };
+        // Like `SetField`, paths through other values are skipped.
+        let _ = self.field.set(doc, id.into());
+        Ok(())
+    }
+}
+
+/// `bytes` as a UUID of `version`, with the RFC 9562 variant.
+fn uuid(mut bytes: [u8; 16], version: u8) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | (version << 4);
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let mut uuid = String::with_capacity(36);
+    for (i, byte) in bytes.iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            uuid.push('-');
+        }
+        uuid.push_str(&format!("{:02x}", byte));
+    }
+    uuid
+}
+
+/// Write `value` to `out` as compact JSON with sorted keys and canonical
+/// numbers.
+fn write_canonical(out: &mut String, value: &Value) {
+    match value {
+        Value::Number(number) if number.is_f64() => {
+            out.push_str(&NumberFormat::Canonical.format(number.as_f64().expect("a float")))
+        }
+        Value::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(out, value);
+            }
+            out.push(']');
+        }
+        Value::Object(members) => {
+            let mut members: Vec<_> = members.iter().collect();
+            members.sort_by_key(|&(name, _)| name);
+            out.push('{');
+            for (i, (name, value)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::from(name.as_str()).to_string());
+                out.push(':');
+                write_canonical(out, value);
+            }
+            out.push('}');
+        }
+        value => out.push_str(&value.to_string()),
+    }
+}
@@ -0,0 +1,138 @@
+//! jq filters, run with [jaq](https://github.com/01mf02/jaq).
+
+use std::fmt;
+use std::str::FromStr;
+
+use jaq_core::load::{Arena, File, Loader};
+use jaq_core::{data, unwrap_valr, Compiler, Ctx, Vars};
+use jaq_json::Val;
+use serde::Deserialize;
+use serde_json::Value;
+
+type Filter = jaq_core::Filter<data::JustLut<Val>>;
+
+/// A compiled jq filter, as in `.lexiconName = "skbl2" | .lexiconOrder = 48`.
+///
+/// Like `jq`, the filter may yield any number of documents for each input
+/// document, e.g. none for `select(.lexiconOrder > 48)`.
+pub struct Jq {
+    code: String,
+    filter: Filter,
+}
+
+impl Jq {
+    /// Compile the jq code `code`, with the jq standard library available.
+    pub fn new(code: &str) -> Result<Self, JqError> {
+        let defs = jaq_core::defs()
+            .chain(jaq_std::defs())
+            .chain(jaq_json::defs());
+        let funs = jaq_core::funs()
+            .chain(jaq_std::funs())
+            .chain(jaq_json::funs());
+        let arena = Arena::default();
+        let invalid = |errors: String| JqError::Invalid {
+            code: code.to_string(),
+            errors,
+        };
+        let modules = Loader::new(defs)
+            .load(&arena, File { code, path: () })
+            .map_err(|errors| invalid(format!("{:?}", errors)))?;
+        let filter = Compiler::default()
+            .with_funs(funs)
+            .compile(modules)
+            .map_err(|errors| invalid(format!("{:?}", errors)))?;
+        Ok(Self {
+            code: code.to_string(),
+            filter,
+        })
+    }
+
+    /// The code of the filter.
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Run the filter on `doc` and collect the documents it yields.
+    pub fn apply(&self, doc: Value) -> Result<Vec<Value>, JqError> {
+        self.run(doc)?.collect()
+    }
+
+    /// Run the filter on `doc` as an expression: the first value it yields,
+    /// if any, without running it further.
+    pub fn first(&self, doc: &Value) -> Result<Option<Value>, JqError> {
+        self.run(doc.clone())?.next().transpose()
+    }
+
+    /// Run the filter on `doc` as a condition: whether the first value it
+    /// yields is neither `false` nor `null`, as for jq's `select`. A filter
+    /// that yields nothing doesn't match.
+    pub fn matches(&self, doc: &Value) -> Result<bool, JqError> {
+        Ok(!matches!(
+            self.first(doc)?,
+            None | Some(Value::Null | Value::Bool(false))
+        ))
+    }
+
+    fn run(
+        &self,
+        doc: Value,
+    ) -> Result<impl Iterator<Item = Result<Value, JqError>> + '_, JqError> {
+        let input = Val::deserialize(doc).map_err(|err| JqError::Run(err.to_string()))?;
+        let ctx = Ctx::<data::JustLut<Val>>::new(&self.filter.lut, Vars::new([]));
+        let outputs = self.filter.id.run((ctx, input)).map(unwrap_valr);
+        Ok(outputs.map(|output| {
+            let output = output.map_err(|err| JqError::Run(err.to_string()))?;
+            serde_json::from_str(&output.to_string())
+                .map_err(|err| JqError::Run(format!("filter yielded invalid JSON: {}", err)))
+        }))
+    }
+}
+
+impl fmt::Debug for Jq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Jq").field(&self.code).finish()
+    }
+}
+
+impl Clone for Jq {
+    fn clone(&self) -> Self {
+        Self::new(&self.code).expect("code compiled before")
+    }
+}
+
+/// Filters are equal if their code is.
+impl PartialEq for Jq {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code
+    }
+}
+
+impl FromStr for Jq {
+    type Err = JqError;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        Self::new(code)
+    }
+}
+
+/// Errors from compiling or running a jq filter.
+#[derive(Debug)]
+pub enum JqError {
+    /// The code could not be parsed or refers to undefined names.
+    Invalid { code: String, errors: String },
+    /// The filter failed on a document, e.g. `.a` on an array.
+    Run(String),
+}
+
+impl fmt::Display for JqError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JqError::Invalid { code, errors } => {
+                write!(f, "invalid jq filter '{}': {}", code, errors)
+            }
+            JqError::Run(err) => write!(f, "jq filter failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for JqError {}
@@ -0,0 +1,124 @@
+//! Unicode normalization of strings, with
+//! [icu_normalizer](https://crates.io/crates/icu_normalizer).
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use icu_normalizer::{ComposingNormalizerBorrowed, DecomposingNormalizerBorrowed};
+use serde_json::Value;
+
+use super::{DocumentTransform, TransformResult};
+use crate::path::FieldPath;
+
+/// A Unicode normalization form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizationForm {
+    /// Composed characters, e.g. `å` as one code point.
+    Nfc,
+    /// Decomposed characters, e.g. `å` as `a` and a combining ring.
+    Nfd,
+}
+
+impl FromStr for NormalizationForm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "nfc" => Ok(NormalizationForm::Nfc),
+            "nfd" => Ok(NormalizationForm::Nfd),
+            _ => Err(format!("expected nfc or nfd, got '{}'", s)),
+        }
+    }
+}
+
+/// Normalizes the strings of the documents to a [`NormalizationForm`],
+/// either all of them or those in the fields added with
+/// [`Normalize::field`]. Keys are left as they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Normalize {
+    form: NormalizationForm,
+    fields: Vec<FieldPath>,
+}
+
+impl Normalize {
+    pub fn new(form: NormalizationForm) -> Self {
+        Self {
+            form,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Only normalize the strings in this field (and the values in it). A
+    /// `*` segment stands for every element of an array or value of an
+    /// object, as in `senses.*.gloss`.
+    pub fn field(mut self, path: FieldPath) -> Self {
+        self.fields.push(path);
+        self
+    }
+
+    /// Normalize the strings in the field at `path` of `doc`.
+    pub fn apply_at(&self, doc: &mut Value, path: &FieldPath) {
+        self.normalize_at(path.segments(), doc);
+    }
+
+    /// `text` in the normalization form, borrowed if it already is.
+    pub fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self.form {
+            NormalizationForm::Nfc => ComposingNormalizerBorrowed::new_nfc().normalize(text),
+            NormalizationForm::Nfd => DecomposingNormalizerBorrowed::new_nfd().normalize(text),
+        }
+    }
+
+    fn normalize_at(&self, segments: &[String], value: &mut Value) {
+        let Some((first, rest)) = segments.split_first() else {
+            self.normalize_all(value);
+            return;
+        };
+        match value {
+            Value::Object(object) if first == "*" => object
+                .values_mut()
+                .for_each(|value| self.normalize_at(rest, value)),
+            Value::Array(array) if first == "*" => array
+                .iter_mut()
+                .for_each(|value| self.normalize_at(rest, value)),
+            Value::Object(object) => {
+                if let Some(value) = object.get_mut(first) {
+                    self.normalize_at(rest, value);
+                }
+            }
+            Value::Array(array) => {
+                if let Some(value) = first.parse().ok().and_then(|i: usize| array.get_mut(i)) {
+                    self.normalize_at(rest, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn normalize_all(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => {
+                if let Cow::Owned(normalized) = self.normalize(text) {
+                    *text = normalized;
+                }
+            }
+            Value::Array(array) => array.iter_mut().for_each(|value| self.normalize_all(value)),
+            Value::Object(object) => object
+                .values_mut()
+                .for_each(|value| self.normalize_all(value)),
+            _ => {}
+        }
+    }
+}
+
+impl DocumentTransform for Normalize {
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        if self.fields.is_empty() {
+            self.normalize_all(doc);
+        }
+        for path in &self.fields {
+            self.apply_at(doc, path);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+//! Keeping only some fields of the documents.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use super::{DocumentTransform, TransformResult};
+use crate::path::FieldPath;
+
+/// Keeps only the given fields of a document, e.g. `_id` and `entry.pos`,
+/// with the objects and arrays on the way to them, and drops everything else.
+/// A `*` segment stands for every element of an array or value of an object,
+/// as in `senses.*.gloss`.
+///
+/// Objects and arrays left without any selected field are dropped too.
+/// Documents that aren't objects are left as they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Select {
+    paths: Vec<FieldPath>,
+    root: Fields,
+}
+
+/// The selected fields inside a value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Fields {
+    /// The whole value is selected.
+    all: bool,
+    children: BTreeMap<String, Fields>,
+}
+
+impl Select {
+    pub fn new(paths: impl IntoIterator<Item = FieldPath>) -> Self {
+        let paths: Vec<_> = paths.into_iter().collect();
+        let mut root = Fields::default();
+        for path in &paths {
+            let fields = path.segments().iter().fold(&mut root, |fields, segment| {
+                fields.children.entry(segment.clone()).or_default()
+            });
+            fields.all = true;
+        }
+        Self { paths, root }
+    }
+
+    /// The selected fields, in the order given.
+    pub fn paths(&self) -> &[FieldPath] {
+        &self.paths
+    }
+}
+
+impl DocumentTransform for Select {
+    fn apply(&self, doc: &mut Value) -> TransformResult {
+        if doc.is_object() {
+            select(doc, &[&self.root]);
+        }
+        Ok(())
+    }
+}
+
+/// Drop everything in `value` that isn't in any of `fields`, and whether
+/// anything is left.
+fn select(value: &mut Value, fields: &[&Fields]) -> bool {
+    if fields.iter().any(|fields| fields.all) {
+        return true;
+    }
+    let children = |key: &str| -> Vec<&Fields> {
+        fields
+            .iter()
+            .flat_map(|fields| [fields.children.get(key), fields.children.get("*")])
+            .flatten()
+            .collect()
+    };
+    match value {
+        Value::Object(object) => {
+            object.retain(|key, value| {
+                let fields = children(key);
+                !fields.is_empty() && select(value, &fields)
+            });
+            !object.is_empty()
+        }
+        Value::Array(array) => {
+            let mut index = 0;
+            array.retain_mut(|value| {
+                let fields = children(&index.to_string());
+                index += 1;
+                !fields.is_empty() && select(value, &fields)
+            });
+            !array.is_empty()
+        }
+        _ => false,
+    }
+}
+
+impl FromStr for Select {
+    type Err = String;
+
+    /// Fields separated by commas, e.g. `_id,entry.pos`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let paths = s
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Select::new(paths))
+    }
+}
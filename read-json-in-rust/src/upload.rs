@@ -0,0 +1,376 @@
+//! Posting the output in batches to an HTTP endpoint, with the `http`
+//! feature, e.g. to the `_bulk` API of Elasticsearch or an import API.
+//!
+//! Every batch is written to a [`Batch`] like to a file, and posted when it
+//! is dropped after being flushed, so a batch that failed half-way isn't.
+//! Up to `concurrency` requests are sent at a time by worker threads, the
+//! next batch waits while they are all busy. A [`RateLimit`] spaces out the
+//! requests so they don't take up all of what they are sent to.
+//!
+//! A request fails on an error status, and on a response of the `_bulk` API
+//! with `"errors": true`, which Elasticsearch sends with status 200 when
+//! only some of the documents could not be indexed.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use reqwest::blocking::Client;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::Value;
+
+/// Time to wait for a connection by default.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time to wait for a response by default, including posting the body.
+pub const TIMEOUT: Duration = Duration::from_secs(300);
+
+/// An error posting a batch.
+#[derive(Debug)]
+pub enum UploadError {
+    /// The request failed, timed out or got an error status.
+    Http(reqwest::Error),
+    /// A `_bulk` response had `"errors": true`: `failed` of its items have an
+    /// error, and `error` describes the first one.
+    Bulk { failed: usize, error: String },
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadError::Http(err) => write!(f, "{}", err),
+            UploadError::Bulk { failed, error } => write!(
+                f,
+                "{} documents of a bulk request failed, the first with {}",
+                failed, error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UploadError::Http(err) => Some(err),
+            UploadError::Bulk { .. } => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for UploadError {
+    fn from(err: reqwest::Error) -> Self {
+        UploadError::Http(err)
+    }
+}
+
+/// The error of a response of the `_bulk` API with `"errors": true`, or
+/// `None` for other responses.
+fn bulk_error(body: &[u8]) -> Option<UploadError> {
+    let response: Value = serde_json::from_slice(body).ok()?;
+    if response.get("errors") != Some(&Value::Bool(true)) {
+        return None;
+    }
+    // Every item is the result of one action, e.g. {"index": {...}}.
+    let failed: Vec<&Value> = response
+        .get("items")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item.as_object()?.values().next())
+        .filter(|result| result.get("error").is_some())
+        .collect();
+    let error = match failed.first() {
+        Some(result) => format!(
+            "status {} for the document with _id {}: {}",
+            result["status"], result["_id"], result["error"]
+        ),
+        None => "no error in the items".to_string(),
+    };
+    Some(UploadError::Bulk {
+        failed: failed.len(),
+        error,
+    })
+}
+
+/// Parse an HTTP header written as `NAME: VALUE`.
+pub fn parse_header(s: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected NAME: VALUE, got '{}'", s))?;
+    let name = HeaderName::try_from(name.trim()).map_err(|err| err.to_string())?;
+    let value = HeaderValue::try_from(value.trim()).map_err(|err| err.to_string())?;
+    Ok((name, value))
+}
+
+/// Options for the requests of an [`Uploader`].
+#[derive(Debug, Clone)]
+pub struct UploadOptions {
+    headers: HeaderMap,
+    concurrency: usize,
+    bytes_per_second: Option<f64>,
+    connect_timeout: Duration,
+    timeout: Duration,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            headers: HeaderMap::new(),
+            concurrency: 1,
+            bytes_per_second: None,
+            connect_timeout: CONNECT_TIMEOUT,
+            timeout: TIMEOUT,
+        }
+    }
+}
+
+impl UploadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send this header with every request, e.g. `Authorization`.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Send `Content-Type: content_type` unless a header sets it.
+    pub fn default_content_type(mut self, content_type: &'static str) -> Self {
+        self.headers
+            .entry(reqwest::header::CONTENT_TYPE)
+            .or_insert(HeaderValue::from_static(content_type));
+        self
+    }
+
+    /// Number of requests sent at a time (at least 1), 1 by default.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Post at most about `rate` bytes a second, counting the bodies of all
+    /// requests.
+    pub fn bytes_per_second(mut self, rate: f64) -> Self {
+        self.bytes_per_second = Some(rate);
+        self
+    }
+
+    /// Give up connecting after this long, [`CONNECT_TIMEOUT`] by default.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Give up a request that takes longer than this, from connecting to
+    /// reading the response, [`TIMEOUT`] by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Start the workers posting to `url`.
+    pub fn start(&self, url: &str) -> Result<Uploader, reqwest::Error> {
+        let client = Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout)
+            .build()?;
+        let (sender, receiver) = mpsc::sync_channel(self.concurrency);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let rate = self
+            .bytes_per_second
+            .map(|rate| Arc::new(Mutex::new(RateLimit::new(rate))));
+        let workers = (0..self.concurrency)
+            .map(|_| {
+                let request = Request {
+                    client: client.clone(),
+                    url: url.to_string(),
+                    headers: self.headers.clone(),
+                    rate: rate.clone(),
+                };
+                let receiver = Arc::clone(&receiver);
+                thread::spawn(move || request.post_all(&receiver))
+            })
+            .collect();
+        Ok(Uploader {
+            sender: Some(sender),
+            workers,
+        })
+    }
+}
+
+struct Request {
+    client: Client,
+    url: String,
+    headers: HeaderMap,
+    rate: Option<Arc<Mutex<RateLimit>>>,
+}
+
+impl Request {
+    /// Post the batches received until the channel is closed or a request
+    /// fails.
+    fn post_all(&self, receiver: &Mutex<Receiver<Vec<u8>>>) -> Result<(), UploadError> {
+        loop {
+            let body = match receiver.lock().expect("no worker to panic").recv() {
+                Ok(body) => body,
+                Err(_) => return Ok(()),
+            };
+            if let Some(rate) = &self.rate {
+                // Waiting without the lock lets the others take their turns.
+                let delay = rate
+                    .lock()
+                    .expect("no worker to panic")
+                    .delay(body.len() as u64);
+                thread::sleep(delay);
+            }
+            let response = self
+                .client
+                .post(&self.url)
+                .headers(self.headers.clone())
+                .body(body)
+                .send()?
+                .error_for_status()?;
+            if let Some(err) = bulk_error(&response.bytes()?) {
+                return Err(err);
+            }
+        }
+    }
+}
+
+/// Posts batches to a URL.
+pub struct Uploader {
+    sender: Option<SyncSender<Vec<u8>>>,
+    workers: Vec<JoinHandle<Result<(), UploadError>>>,
+}
+
+impl Uploader {
+    /// A new batch to write to, or the error of a failed request.
+    pub fn batch(&mut self) -> Result<Batch, UploadError> {
+        // Workers only stop early when a request failed.
+        if let Some(index) = self.workers.iter().position(JoinHandle::is_finished) {
+            join(self.workers.swap_remove(index))?;
+        }
+        Ok(Batch {
+            body: Vec::new(),
+            flushed: false,
+            sender: self.sender.clone().expect("uploader to be open"),
+        })
+    }
+
+    /// Wait for all batches to be posted.
+    pub fn finish(mut self) -> Result<(), UploadError> {
+        self.sender = None;
+        for worker in self.workers {
+            join(worker)?;
+        }
+        Ok(())
+    }
+}
+
+fn join(worker: JoinHandle<Result<(), UploadError>>) -> Result<(), UploadError> {
+    match worker.join() {
+        Ok(result) => result,
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+/// The body of a request, posted when dropped if nothing was written since
+/// the last flush.
+pub struct Batch {
+    body: Vec<u8>,
+    flushed: bool,
+    sender: SyncSender<Vec<u8>>,
+}
+
+impl Write for Batch {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.flushed = false;
+        self.body.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flushed = true;
+        Ok(())
+    }
+}
+
+impl Drop for Batch {
+    fn drop(&mut self) {
+        if self.flushed {
+            // If all workers failed, the error is returned by the uploader.
+            let _ = self.sender.send(std::mem::take(&mut self.body));
+        }
+    }
+}
+
+/// Spaces out what is done to `per_second` units a second, e.g. documents or
+/// bytes.
+///
+/// What falls behind, e.g. while waiting for a slow request, is caught up by
+/// at most a second's worth.
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    per_second: f64,
+    /// When the next units may be done.
+    next: Option<Instant>,
+}
+
+impl RateLimit {
+    /// A limit of `per_second` units a second, which must be positive.
+    pub fn new(per_second: f64) -> Self {
+        assert!(per_second > 0.0, "the rate must be positive");
+        Self {
+            per_second,
+            next: None,
+        }
+    }
+
+    /// How long to wait before doing `amount` units, counted as done after
+    /// that.
+    pub fn delay(&mut self, amount: u64) -> Duration {
+        let now = Instant::now();
+        let next = match self.next {
+            Some(next) => next.max(now.checked_sub(Duration::from_secs(1)).unwrap_or(now)),
+            None => now,
+        };
+        self.next = Some(next + Duration::from_secs_f64(amount as f64 / self.per_second));
+        next.saturating_duration_since(now)
+    }
+
+    /// Wait until `amount` units can be done, see [`RateLimit::delay`].
+    pub fn wait(&mut self, amount: u64) {
+        let delay = self.delay(amount);
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_failed_bulk_items() {
+        let body = br#"{"took": 3, "errors": true, "items": [
+            {"index": {"_id": "1", "status": 201, "result": "created"}},
+            {"index": {"_id": "2", "status": 400, "error": {"type": "mapper_parsing_exception"}}},
+            {"delete": {"_id": "3", "status": 404, "error": {"type": "not_found"}}}
+        ]}"#;
+        let err = bulk_error(body).unwrap();
+        assert!(matches!(err, UploadError::Bulk { failed: 2, .. }));
+        assert_eq!(
+            err.to_string(),
+            "2 documents of a bulk request failed, the first with status 400 for the document \
+             with _id \"2\": {\"type\":\"mapper_parsing_exception\"}"
+        );
+
+        let body = br#"{"took": 3, "errors": false, "items": [{"index": {"status": 201}}]}"#;
+        assert!(bulk_error(body).is_none());
+        assert!(bulk_error(b"imported").is_none());
+        assert!(bulk_error(b"").is_none());
+    }
+}
@@ -0,0 +1,105 @@
+//! Checking documents against a JSON Schema, with
+//! [jsonschema](https://crates.io/crates/jsonschema).
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader};
+
+use serde_json::Value;
+
+/// A compiled JSON Schema.
+///
+/// References to other files are resolved relative to the working directory,
+/// remote references are not supported.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    validator: jsonschema::Validator,
+}
+
+/// A part of a document that doesn't match the schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// JSON pointer to the value in the document, empty for the document.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl Schema {
+    pub fn new(schema: &Value) -> Result<Self, SchemaError> {
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|err| SchemaError::Invalid(err.to_string()))?;
+        Ok(Self { validator })
+    }
+
+    /// Read the schema from the JSON file `path`.
+    pub fn from_file(path: &str) -> Result<Self, SchemaError> {
+        let schema: Value = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        Self::new(&schema)
+    }
+
+    pub fn is_valid(&self, doc: &Value) -> bool {
+        self.validator.is_valid(doc)
+    }
+
+    /// Everything in `doc` that doesn't match the schema.
+    pub fn violations(&self, doc: &Value) -> Vec<Violation> {
+        self.validator
+            .iter_errors(doc)
+            .map(|err| Violation {
+                path: err.instance_path().as_str().to_string(),
+                message: err.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Errors from reading or compiling a schema.
+#[derive(Debug)]
+pub enum SchemaError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// The schema is not a valid JSON Schema.
+    Invalid(String),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Io(err) => write!(f, "{}", err),
+            SchemaError::Json(err) => write!(f, "invalid JSON: {}", err),
+            SchemaError::Invalid(err) => write!(f, "invalid schema: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SchemaError::Io(err) => Some(err),
+            SchemaError::Json(err) => Some(err),
+            SchemaError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SchemaError {
+    fn from(err: io::Error) -> Self {
+        SchemaError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SchemaError {
+    fn from(err: serde_json::Error) -> Self {
+        SchemaError::Json(err)
+    }
+}
@@ -0,0 +1,56 @@
+//! YAML, with [serde_yaml](https://crates.io/crates/serde_yaml): the
+//! documents are the items of a sequence of mappings.
+//!
+//! serde_yaml can't stream the items of a sequence, so the whole input is
+//! read before the first document. The output is written one document at a
+//! time.
+
+use std::io::{Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::io;
+use crate::json_iter::JsonIterError;
+
+/// Read the documents of the YAML sequence read from `reader`.
+pub fn load_from_reader<T: DeserializeOwned, R: Read>(reader: R) -> Result<Vec<T>, JsonIterError> {
+    Ok(serde_yaml::from_reader(reader)?)
+}
+
+/// Open `path` and read the documents of the YAML sequence it contains.
+pub fn load_from_file<T: DeserializeOwned>(path: &str) -> Result<Vec<T>, JsonIterError> {
+    load_from_reader(io::open(path)?)
+}
+
+/// Writes documents as the items of a YAML sequence.
+pub struct YamlWriter<W: Write> {
+    writer: W,
+    empty: bool,
+}
+
+impl<W: Write> YamlWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            empty: true,
+        }
+    }
+
+    pub fn write<T: Serialize>(&mut self, doc: &T) -> Result<(), serde_yaml::Error> {
+        // Sequences of one item each add up to a single sequence.
+        serde_yaml::to_writer(&mut self.writer, &[doc])?;
+        self.empty = false;
+        Ok(())
+    }
+
+    /// Writes an empty sequence if there were no documents, and returns the
+    /// underlying writer, flushed.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        if self.empty {
+            self.writer.write_all(b"[]\n")?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}